@@ -0,0 +1,192 @@
+//! Benchmarks comparing the resampler types, parameters and channel counts,
+//! on a reproducible test signal.
+//! The scalar and SIMD interpolators are compared in the `resamplers` benchmark.
+
+use criterion::{criterion_group, criterion_main, BenchmarkId, Criterion, Throughput};
+extern crate rubato;
+
+use rand::rngs::StdRng;
+use rand::{Rng, SeedableRng};
+use rubato::{
+    FftFixedIn, FftFixedInOut, FftFixedOut, InterpolationParameters, InterpolationType, Resampler,
+    Sample, SincFixedIn, SincFixedOut, WindowFunction,
+};
+
+/// Seed for the noise in the test signal, so that every run processes the same data.
+const SEED: u64 = 0x5eed_1234;
+
+/// Generate a reproducible test signal: a few sines at different frequencies
+/// in each channel, with a little noise from a seeded generator.
+fn make_signal<T: Sample>(nbr_channels: usize, nbr_frames: usize) -> Vec<Vec<T>> {
+    let mut rng = StdRng::seed_from_u64(SEED);
+    (0..nbr_channels)
+        .map(|chan| {
+            (0..nbr_frames)
+                .map(|n| {
+                    let t = n as f64 / 44100.0;
+                    let freq = 440.0 * (chan + 1) as f64;
+                    let value = 0.5 * (2.0 * std::f64::consts::PI * freq * t).sin()
+                        + 0.2 * (2.0 * std::f64::consts::PI * 5.3 * freq * t).sin()
+                        + 0.01 * rng.gen_range(-1.0..1.0);
+                    T::coerce(value)
+                })
+                .collect()
+        })
+        .collect()
+}
+
+fn params(sinc_len: usize, interpolation: InterpolationType) -> InterpolationParameters {
+    InterpolationParameters {
+        sinc_len,
+        f_cutoff: 0.95,
+        interpolation,
+        oversampling_factor: 256,
+        window: WindowFunction::BlackmanHarris2,
+    }
+}
+
+/// Process chunks from a long signal, feeding each call with the number of frames it needs.
+fn run<T: Sample>(resampler: &mut dyn Resampler<T>, signal: &[Vec<T>], position: &mut usize) {
+    let nbr_frames = resampler.nbr_frames_needed();
+    if *position + nbr_frames > signal[0].len() {
+        *position = 0;
+    }
+    resampler
+        .process_range(signal, *position, nbr_frames)
+        .unwrap();
+    *position += nbr_frames;
+}
+
+/// Compare all resampler types at a typical conversion from 44.1 to 48 kHz.
+fn bench_engines(c: &mut Criterion) {
+    let chunksize = 1024;
+    let nbr_channels = 2;
+    let mut group = c.benchmark_group("engines 44100 to 48000");
+    group.throughput(Throughput::Elements((chunksize * nbr_channels) as u64));
+    let ratio = 48000.0 / 44100.0;
+    let engines: Vec<(&str, Box<dyn Resampler<f64>>)> = vec![
+        (
+            "SincFixedIn",
+            Box::new(
+                SincFixedIn::new(
+                    ratio,
+                    params(256, InterpolationType::Cubic),
+                    chunksize,
+                    nbr_channels,
+                )
+                .unwrap(),
+            ),
+        ),
+        (
+            "SincFixedOut",
+            Box::new(
+                SincFixedOut::new(
+                    ratio,
+                    params(256, InterpolationType::Cubic),
+                    chunksize,
+                    nbr_channels,
+                )
+                .unwrap(),
+            ),
+        ),
+        (
+            "FftFixedIn",
+            Box::new(FftFixedIn::new(44100, 48000, chunksize, 2, nbr_channels).unwrap()),
+        ),
+        (
+            "FftFixedOut",
+            Box::new(FftFixedOut::new(44100, 48000, chunksize, 2, nbr_channels).unwrap()),
+        ),
+        (
+            "FftFixedInOut",
+            Box::new(FftFixedInOut::new(44100, 48000, chunksize, nbr_channels).unwrap()),
+        ),
+    ];
+    let signal = make_signal::<f64>(nbr_channels, 16 * chunksize);
+    for (name, mut resampler) in engines {
+        let mut position = 0;
+        group.bench_function(name, |b| {
+            b.iter(|| run(resampler.as_mut(), &signal, &mut position))
+        });
+    }
+    group.finish();
+}
+
+/// Compare the interpolation types and sinc lengths of the asynchronous resampler,
+/// using the fastest interpolator available on the machine.
+fn bench_parameters<T: Sample>(c: &mut Criterion, name: &str) {
+    let chunksize = 1024;
+    let mut group = c.benchmark_group(name);
+    group.throughput(Throughput::Elements(chunksize as u64));
+    let signal = make_signal::<T>(1, 16 * chunksize);
+    let interpolations = [
+        ("nearest", InterpolationType::Nearest),
+        ("linear", InterpolationType::Linear),
+        ("cubic", InterpolationType::Cubic),
+    ];
+    for (label, interpolation) in interpolations.iter() {
+        for sinc_len in [64, 128, 256].iter() {
+            let mut resampler = SincFixedIn::<T>::new(
+                192000.0 / 44100.0,
+                params(*sinc_len, *interpolation),
+                chunksize,
+                1,
+            )
+            .unwrap();
+            let mut position = 0;
+            group.bench_with_input(BenchmarkId::new(*label, sinc_len), sinc_len, |b, _| {
+                b.iter(|| run(&mut resampler, &signal, &mut position))
+            });
+        }
+    }
+    group.finish();
+}
+
+fn bench_parameters_32(c: &mut Criterion) {
+    bench_parameters::<f32>(c, "sinc parameters 32");
+}
+
+fn bench_parameters_64(c: &mut Criterion) {
+    bench_parameters::<f64>(c, "sinc parameters 64");
+}
+
+/// Measure how the cost scales with the number of channels.
+fn bench_channels(c: &mut Criterion) {
+    let chunksize = 1024;
+    let mut group = c.benchmark_group("channels");
+    for nbr_channels in [1, 2, 8].iter() {
+        group.throughput(Throughput::Elements((chunksize * nbr_channels) as u64));
+        let signal = make_signal::<f32>(*nbr_channels, 16 * chunksize);
+        let mut sinc = SincFixedIn::<f32>::new(
+            48000.0 / 44100.0,
+            params(256, InterpolationType::Linear),
+            chunksize,
+            *nbr_channels,
+        )
+        .unwrap();
+        let mut position = 0;
+        group.bench_with_input(
+            BenchmarkId::new("SincFixedIn", nbr_channels),
+            nbr_channels,
+            |b, _| b.iter(|| run(&mut sinc, &signal, &mut position)),
+        );
+        let mut fft = FftFixedIn::<f32>::new(44100, 48000, chunksize, 2, *nbr_channels).unwrap();
+        let mut position = 0;
+        group.bench_with_input(
+            BenchmarkId::new("FftFixedIn", nbr_channels),
+            nbr_channels,
+            |b, _| b.iter(|| run(&mut fft, &signal, &mut position)),
+        );
+    }
+    group.finish();
+}
+
+criterion_group!(
+    benches,
+    bench_engines,
+    bench_parameters_32,
+    bench_parameters_64,
+    bench_channels,
+);
+
+criterion_main!(benches);
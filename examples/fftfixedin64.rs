@@ -95,7 +95,7 @@ fn main() {
     let mut f_in = Cursor::new(&f_in_ram);
     let mut f_out = Cursor::new(&mut f_out_ram);
 
-    let mut resampler = FftFixedIn::<f64>::new(fs_in, fs_out, 1024, 2, channels);
+    let mut resampler = FftFixedIn::<f64>::new(fs_in, fs_out, 1024, 2, channels).unwrap();
     let chunksize = resampler.nbr_frames_needed();
 
     let num_chunks = f_in_ram.len() / (8 * channels * chunksize);
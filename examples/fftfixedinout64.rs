@@ -96,7 +96,7 @@ fn main() {
     let mut f_in = Cursor::new(&f_in_ram);
     let mut f_out = Cursor::new(&mut f_out_ram);
 
-    let mut resampler = FftFixedInOut::<f64>::new(fs_in, fs_out, 1024, channels);
+    let mut resampler = FftFixedInOut::<f64>::new(fs_in, fs_out, 1024, channels).unwrap();
     let chunksize = resampler.nbr_frames_needed();
 
     let num_chunks = f_in_ram.len() / (8 * channels * chunksize);
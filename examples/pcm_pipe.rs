@@ -0,0 +1,213 @@
+extern crate rubato;
+use rubato::convert::{from_i16, to_i16, Dither};
+use rubato::{suggest_parameters, FftFixedIn, Quality, Resampler, SincFixedIn};
+use std::convert::TryInto;
+use std::env;
+use std::io::{self, Read, Write};
+
+extern crate env_logger;
+extern crate log;
+use env_logger::Builder;
+use log::LevelFilter;
+
+///! A streaming resampler that reads raw interleaved PCM from stdin and writes the result to stdout,
+///! for use in shell pipelines.
+///! The command line arguments are sample format, number of channels, input samplerate,
+///! output samplerate, and engine.
+///! The sample format is `f32` for little-endian 32 bit floats, or `s16` for little-endian 16 bit integers.
+///! The engine is `sinc` for the asynchronous SincFixedIn, or `fft` for the synchronous FftFixedIn.
+///! To resample a stereo 16 bit stream from 44.1kHz to 48kHz, the command is:
+///! ```
+///! cat input.raw | cargo run --release --example pcm_pipe s16 2 44100 48000 sinc > output.raw
+///! ```
+///! The output is dithered when writing 16 bit integers.
+///! Log messages are written to stderr.
+
+const CHUNK_SIZE: usize = 1024;
+
+#[derive(Clone, Copy, PartialEq)]
+enum Format {
+    F32,
+    S16,
+}
+
+impl Format {
+    fn bytes_per_sample(self) -> usize {
+        match self {
+            Format::F32 => 4,
+            Format::S16 => 2,
+        }
+    }
+}
+
+/// Read up to `buffer.len()` bytes, and return the number read. Stops early only at the end of the stream.
+fn read_bytes<R: Read>(input: &mut R, buffer: &mut [u8]) -> usize {
+    let mut nbr_read = 0;
+    while nbr_read < buffer.len() {
+        match input.read(&mut buffer[nbr_read..]) {
+            Ok(0) => break,
+            Ok(n) => nbr_read += n,
+            Err(ref err) if err.kind() == io::ErrorKind::Interrupted => {}
+            Err(err) => panic!("Can't read input: {}", err),
+        }
+    }
+    nbr_read
+}
+
+/// Decode interleaved samples from bytes.
+fn decode(format: Format, bytes: &[u8], samples: &mut [f32]) {
+    match format {
+        Format::F32 => {
+            for (sample, value) in samples.iter_mut().zip(bytes.chunks(4)) {
+                *sample = f32::from_le_bytes(value.try_into().unwrap());
+            }
+        }
+        Format::S16 => {
+            let ints: Vec<i16> = bytes
+                .chunks(2)
+                .map(|value| i16::from_le_bytes(value.try_into().unwrap()))
+                .collect();
+            from_i16(&ints, samples);
+        }
+    }
+}
+
+/// Encode interleaved samples to bytes.
+fn encode(format: Format, samples: &[f32], dither: &mut Dither, bytes: &mut Vec<u8>) {
+    bytes.clear();
+    match format {
+        Format::F32 => {
+            for sample in samples.iter() {
+                bytes.extend_from_slice(&sample.to_le_bytes());
+            }
+        }
+        Format::S16 => {
+            let mut ints = vec![0i16; samples.len()];
+            to_i16(samples, &mut ints, Some(dither));
+            for value in ints.iter() {
+                bytes.extend_from_slice(&value.to_le_bytes());
+            }
+        }
+    }
+}
+
+fn main() {
+    // init logger, env_logger writes to stderr
+    let mut builder = Builder::from_default_env();
+    builder.filter(None, LevelFilter::Info).init();
+
+    let format = match env::args()
+        .nth(1)
+        .expect("Please specify a sample format, f32 or s16")
+        .as_str()
+    {
+        "f32" => Format::F32,
+        "s16" => Format::S16,
+        other => panic!("Unknown sample format {}, use f32 or s16", other),
+    };
+    let channels = env::args()
+        .nth(2)
+        .expect("Please specify number of channels")
+        .parse::<usize>()
+        .unwrap();
+    let fs_in = env::args()
+        .nth(3)
+        .expect("Please specify an input sample rate")
+        .parse::<usize>()
+        .unwrap();
+    let fs_out = env::args()
+        .nth(4)
+        .expect("Please specify an output sample rate")
+        .parse::<usize>()
+        .unwrap();
+    let engine = env::args().nth(5).unwrap_or_else(|| "sinc".to_string());
+
+    let ratio = fs_out as f64 / fs_in as f64;
+    let (mut resampler, latency): (Box<dyn Resampler<f32>>, _) = match engine.as_str() {
+        "sinc" => {
+            let params = suggest_parameters(fs_in, fs_out, Quality::High);
+            let resampler = SincFixedIn::<f32>::new(ratio, params, CHUNK_SIZE, channels).unwrap();
+            let latency = resampler.latency();
+            (Box::new(resampler), latency)
+        }
+        "fft" => {
+            let resampler = FftFixedIn::<f32>::new(fs_in, fs_out, CHUNK_SIZE, 2, channels).unwrap();
+            let latency = resampler.latency();
+            (Box::new(resampler), latency)
+        }
+        other => panic!("Unknown engine {}, use sinc or fft", other),
+    };
+    log::info!(
+        "Resampling {} channels from {} to {} with {}, latency {:.1} frames",
+        channels,
+        fs_in,
+        fs_out,
+        engine,
+        latency.output_frames
+    );
+
+    let stdin = io::stdin();
+    let mut input = stdin.lock();
+    let stdout = io::stdout();
+    let mut output = stdout.lock();
+    let sample_bytes = format.bytes_per_sample();
+    let mut dither = Dither::new(1);
+    let mut in_bytes = Vec::new();
+    let mut in_samples = Vec::new();
+    let mut out_samples = Vec::new();
+    let mut out_bytes = Vec::new();
+
+    // the first frames only hold the delay of the resampler, and are skipped
+    let mut skip = latency.output_frames.round().max(0.0) as usize;
+    let mut frames_in = 0;
+    let mut frames_out = 0;
+    let mut end_of_input = false;
+    loop {
+        let frames_needed = resampler.nbr_frames_needed();
+        in_bytes.resize(frames_needed * channels * sample_bytes, 0);
+        in_samples.resize(frames_needed * channels, 0.0);
+        let nbr_read = if end_of_input {
+            0
+        } else {
+            read_bytes(&mut input, &mut in_bytes)
+        };
+        if nbr_read < in_bytes.len() {
+            end_of_input = true;
+        }
+        let frames_read = nbr_read / (channels * sample_bytes);
+        frames_in += frames_read;
+        // pad a partial chunk, and flush the resampler with silence at the end
+        for value in in_bytes[frames_read * channels * sample_bytes..].iter_mut() {
+            *value = 0;
+        }
+        decode(format, &in_bytes, &mut in_samples);
+
+        out_samples.resize(resampler.nbr_output_frames_next() * channels, 0.0);
+        let nbr_written = resampler
+            .process_strided(channels, &in_samples, channels, &mut out_samples, channels)
+            .unwrap();
+
+        // stop when the output covers all of the input
+        let frames_wanted = (frames_in as f64 * ratio).round() as usize;
+        let first = skip.min(nbr_written);
+        skip -= first;
+        let last = if end_of_input {
+            nbr_written.min(first + frames_wanted.saturating_sub(frames_out))
+        } else {
+            nbr_written
+        };
+        encode(
+            format,
+            &out_samples[first * channels..last * channels],
+            &mut dither,
+            &mut out_bytes,
+        );
+        output.write_all(&out_bytes).unwrap();
+        frames_out += last - first;
+        if end_of_input && frames_out >= frames_wanted {
+            break;
+        }
+    }
+    output.flush().unwrap();
+    log::info!("Read {} frames, wrote {} frames", frames_in, frames_out);
+}
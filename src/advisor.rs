@@ -0,0 +1,311 @@
+use crate::error::{ResampleError, ResampleResult};
+use crate::{FftFixedIn, Resampler, Sample, SincFixedIn};
+use crate::{InterpolationParameters, InterpolationType, WindowFunction};
+use num_integer as integer;
+
+/// Largest oversampling factor that `suggest_parameters` uses for exact rational resampling.
+/// With a sinc length of 256 this needs up to 1 MB for the sinc filters of 64-bit data.
+const MAX_RATIONAL_FACTOR: usize = 512;
+
+/// Quality levels for choosing the interpolation parameters.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum Quality {
+    /// Short filters with a lower cutoff, for when cpu usage matters most.
+    Low,
+    /// A balance between quality and cpu usage.
+    Medium,
+    /// Long filters with a high cutoff, for the best quality.
+    High,
+}
+
+impl Quality {
+    /// Get the sinc length and cutoff for this quality.
+    /// The longer filters have a narrower transition band, which allows a higher cutoff.
+    fn filter(self) -> (usize, f32) {
+        match self {
+            Quality::Low => (64, 0.85),
+            Quality::Medium => (128, 0.91),
+            Quality::High => (256, 0.95),
+        }
+    }
+}
+
+/// Suggest interpolation parameters for resampling from `fs_in` to `fs_out`.
+///
+/// When the ratio between the rates is a fraction L/M with a small enough numerator,
+/// the suggestion is `Nearest` interpolation with an oversampling factor of L,
+/// which resamples exactly at the cost of storing L sinc filters.
+/// For 44.1 kHz to 48 kHz this gives an oversampling factor of 160.
+/// Otherwise it is the same as `suggest_variable_parameters`, with `Cubic` interpolation.
+///
+/// The `Nearest` suggestion is only exact at the given ratio.
+/// If the ratio is going to be changed while running, for example to follow clock drift,
+/// use `suggest_variable_parameters` instead.
+pub fn suggest_parameters(
+    fs_in: usize,
+    fs_out: usize,
+    quality: Quality,
+) -> InterpolationParameters {
+    let gcd = integer::gcd(fs_in, fs_out);
+    let numerator = fs_out.checked_div(gcd).unwrap_or(0);
+    if numerator == 0 || numerator > MAX_RATIONAL_FACTOR {
+        debug!(
+            "No exact oversampling factor for {} to {}, suggesting cubic interpolation",
+            fs_in, fs_out
+        );
+        return suggest_variable_parameters(quality);
+    }
+    let (sinc_len, f_cutoff) = quality.filter();
+    debug!(
+        "Suggesting nearest interpolation with oversampling factor {} for {} to {}",
+        numerator, fs_in, fs_out
+    );
+    InterpolationParameters {
+        sinc_len,
+        f_cutoff,
+        interpolation: InterpolationType::Nearest,
+        oversampling_factor: numerator,
+        window: WindowFunction::BlackmanHarris2,
+    }
+}
+
+/// Suggest interpolation parameters for a ratio that is irrational, or that will be changed while running.
+///
+/// This uses `Cubic` interpolation, which needs far fewer intermediate points than `Linear`
+/// for the same quality, and an oversampling factor that grows with the quality.
+pub fn suggest_variable_parameters(quality: Quality) -> InterpolationParameters {
+    let (sinc_len, f_cutoff) = quality.filter();
+    let oversampling_factor = match quality {
+        Quality::Low => 64,
+        Quality::Medium => 128,
+        Quality::High => 256,
+    };
+    InterpolationParameters {
+        sinc_len,
+        f_cutoff,
+        interpolation: InterpolationType::Cubic,
+        oversampling_factor,
+        window: WindowFunction::BlackmanHarris2,
+    }
+}
+
+/// The resampler types that `best_resampler` chooses between, as returned by `choose_engine`.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Engine {
+    /// `SincFixedIn` with `Nearest` interpolation, used as a polyphase filter
+    /// with one sinc per output phase, for integer resampling factors.
+    Polyphase,
+    /// `FftFixedIn`, for rational ratios where the FFT fits in a chunk.
+    Fft,
+    /// `SincFixedIn` with the parameters from `suggest_parameters`, for everything else.
+    Sinc,
+}
+
+/// Choose the resampler type for a fixed conversion from `fs_in` to `fs_out`,
+/// with chunks of `chunk_size` input frames.
+///
+/// An integer upsampling or downsampling factor gives `Polyphase`, which resamples exactly with few sincs.
+/// Other ratios give `Fft` when the smallest FFT, of `fs_in / gcd(fs_in, fs_out)` frames,
+/// fits in a chunk, and otherwise `Sinc`.
+pub fn choose_engine(fs_in: usize, fs_out: usize, chunk_size: usize) -> Engine {
+    let gcd = integer::gcd(fs_in, fs_out);
+    let min_chunk_in = fs_in.checked_div(gcd).unwrap_or(0);
+    let min_chunk_out = fs_out.checked_div(gcd).unwrap_or(0);
+    if min_chunk_in == 1 || min_chunk_out == 1 {
+        Engine::Polyphase
+    } else if min_chunk_in > 0 && min_chunk_in <= chunk_size {
+        Engine::Fft
+    } else {
+        Engine::Sinc
+    }
+}
+
+/// Create the best suited resampler for a fixed conversion from `fs_in` to `fs_out`,
+/// as chosen by `choose_engine`.
+/// The resampler takes `chunk_size` input frames per call to "process".
+/// The quality sets the filter parameters of the sinc based engines,
+/// while the FFT engine always uses its own fixed filter.
+///
+/// # Errors
+///
+/// The function returns `ResampleError::InvalidParameter` if a sample rate, the chunk size
+/// or the number of channels is zero.
+pub fn best_resampler<T>(
+    fs_in: usize,
+    fs_out: usize,
+    chunk_size: usize,
+    nbr_channels: usize,
+    quality: Quality,
+) -> ResampleResult<Box<dyn Resampler<T>>>
+where
+    T: Sample,
+{
+    ResampleError::check(fs_in > 0, "fs_in", fs_in, "must be larger than zero")?;
+    ResampleError::check(fs_out > 0, "fs_out", fs_out, "must be larger than zero")?;
+    let engine = choose_engine(fs_in, fs_out, chunk_size);
+    debug!(
+        "Choosing {:?} for {} to {}, chunk size {}",
+        engine, fs_in, fs_out, chunk_size
+    );
+    match engine {
+        Engine::Fft => Ok(Box::new(FftFixedIn::new(
+            fs_in,
+            fs_out,
+            chunk_size,
+            1,
+            nbr_channels,
+        )?)),
+        Engine::Polyphase | Engine::Sinc => Ok(Box::new(SincFixedIn::new(
+            fs_out as f64 / fs_in as f64,
+            suggest_parameters(fs_in, fs_out, quality),
+            chunk_size,
+            nbr_channels,
+        )?)),
+    }
+}
+
+/// Approximate cost of resampling with a set of parameters, as returned by `estimate_cost`.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct CostReport {
+    /// Number of multiply-adds per output frame, for all channels.
+    pub macs_per_output_frame: f64,
+    /// Number of multiply-adds per input frame, for all channels.
+    pub macs_per_input_frame: f64,
+    /// Size of the sinc tables in bytes.
+    pub table_bytes: usize,
+    /// Size of the input history kept between chunks, in bytes.
+    /// The buffers that scale with the chunk size are not included.
+    pub history_bytes: usize,
+}
+
+impl CostReport {
+    /// Get the number of multiply-adds per second, for the given output sample rate.
+    pub fn macs_per_second(&self, fs_out: f64) -> f64 {
+        self.macs_per_output_frame * fs_out
+    }
+
+    /// Get the total memory footprint in bytes, excluding the buffers that scale with the chunk size.
+    pub fn total_bytes(&self) -> usize {
+        self.table_bytes + self.history_bytes
+    }
+}
+
+/// Estimate the cost of an asynchronous resampler with the given parameters,
+/// for samples of type `T`.
+///
+/// Each output sample needs one, two or four sinc evaluations for `Nearest`, `Linear`
+/// and `Cubic` interpolation, each with one multiply-add per sinc tap,
+/// followed by the polynomial interpolation between the sinc results.
+/// The estimate ignores the overhead of the loops and the memory access,
+/// and is meant for comparing parameter choices before benchmarking on the target hardware.
+pub fn estimate_cost<T>(
+    parameters: &InterpolationParameters,
+    resample_ratio: f64,
+    nbr_channels: usize,
+) -> CostReport {
+    let sinc_len = 8 * (((parameters.sinc_len as f32) / 8.0).ceil() as usize);
+    let (nbr_sincs, interpolation_macs) = match parameters.interpolation {
+        InterpolationType::Nearest => (1, 0),
+        InterpolationType::Linear => (2, 2),
+        InterpolationType::Cubic => (4, 12),
+    };
+    let macs_per_output_frame = ((nbr_sincs * sinc_len + interpolation_macs) * nbr_channels) as f64;
+    let sample_bytes = std::mem::size_of::<T>();
+    CostReport {
+        macs_per_output_frame,
+        macs_per_input_frame: macs_per_output_frame * resample_ratio,
+        table_bytes: sinc_len * parameters.oversampling_factor * sample_bytes,
+        history_bytes: 2 * sinc_len * nbr_channels * sample_bytes,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::advisor::{
+        best_resampler, choose_engine, estimate_cost, suggest_parameters,
+        suggest_variable_parameters, Engine, Quality,
+    };
+    use crate::{InterpolationType, Resampler, SincFixedIn};
+
+    #[test]
+    fn suggestions() {
+        let params = suggest_parameters(44100, 48000, Quality::High);
+        assert_eq!(params.interpolation, InterpolationType::Nearest);
+        assert_eq!(params.oversampling_factor, 160);
+        assert_eq!(params.sinc_len, 256);
+
+        let params = suggest_parameters(48000, 96000, Quality::Low);
+        assert_eq!(params.interpolation, InterpolationType::Nearest);
+        assert_eq!(params.oversampling_factor, 2);
+        assert_eq!(params.sinc_len, 64);
+
+        // 44101 is prime, so the numerator is too large
+        let params = suggest_parameters(48000, 44101, Quality::Medium);
+        assert_eq!(params, suggest_variable_parameters(Quality::Medium));
+        assert_eq!(params.interpolation, InterpolationType::Cubic);
+        assert_eq!(
+            suggest_parameters(0, 0, Quality::Low).interpolation,
+            InterpolationType::Cubic
+        );
+
+        // the suggestion resamples exactly, so it matches the rational mode
+        let params = suggest_parameters(44100, 48000, Quality::Low);
+        let mut resampler =
+            SincFixedIn::<f64>::new(48000.0 / 44100.0, params.clone(), 1024, 1).unwrap();
+        assert!(resampler.rational_ratio().is_some());
+        let mut reference =
+            SincFixedIn::<f64>::new_rational(48000.0 / 44100.0, params, 160, 1.0e-9, 1024, 1)
+                .unwrap();
+        let waves = vec![(0..1024)
+            .map(|n| (n as f64 * 0.05).sin())
+            .collect::<Vec<f64>>()];
+        assert_eq!(
+            resampler.process(&waves).unwrap(),
+            reference.process(&waves).unwrap()
+        );
+    }
+
+    #[test]
+    fn cost() {
+        let params = suggest_variable_parameters(Quality::High);
+        let cost = estimate_cost::<f32>(&params, 2.0, 2);
+        assert_eq!(cost.macs_per_output_frame, 2.0 * (4.0 * 256.0 + 12.0));
+        assert_eq!(cost.macs_per_input_frame, 2.0 * cost.macs_per_output_frame);
+        assert_eq!(
+            cost.macs_per_second(48000.0),
+            48000.0 * cost.macs_per_output_frame
+        );
+        assert_eq!(cost.table_bytes, 256 * 256 * 4);
+        assert_eq!(cost.history_bytes, 2 * 256 * 2 * 4);
+
+        // nearest needs a larger table but a quarter of the work
+        let params = suggest_parameters(44100, 48000, Quality::High);
+        let nearest = estimate_cost::<f64>(&params, 48000.0 / 44100.0, 2);
+        assert!(nearest.macs_per_output_frame < cost.macs_per_output_frame / 4.0);
+        assert_eq!(nearest.total_bytes(), (256 * 160 + 2 * 256 * 2) * 8);
+    }
+
+    #[test]
+    fn engines() {
+        assert_eq!(choose_engine(48000, 96000, 1024), Engine::Polyphase);
+        assert_eq!(choose_engine(192000, 48000, 1024), Engine::Polyphase);
+        assert_eq!(choose_engine(44100, 48000, 1024), Engine::Fft);
+        assert_eq!(choose_engine(44100, 48000, 100), Engine::Sinc);
+        assert_eq!(choose_engine(48000, 44101, 1024), Engine::Sinc);
+
+        for (fs_in, fs_out) in [(48000, 96000), (44100, 48000), (48000, 44101)].iter() {
+            let mut resampler =
+                best_resampler::<f32>(*fs_in, *fs_out, 1024, 2, Quality::Low).unwrap();
+            assert_eq!(resampler.nbr_frames_needed(), 1024);
+            let mut nbr_frames_out = 0;
+            for _ in 0..10 {
+                nbr_frames_out += resampler
+                    .process(&[vec![0.0; 1024], vec![0.0; 1024]])
+                    .unwrap()[0]
+                    .len();
+            }
+            let expected = 10240.0 * *fs_out as f64 / *fs_in as f64;
+            assert!((nbr_frames_out as f64 - expected).abs() < 2048.0);
+        }
+    }
+}
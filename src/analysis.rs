@@ -0,0 +1,589 @@
+use crate::error::ResampleResult;
+use crate::offline::process_all;
+use crate::windows::{make_window, WindowFunction};
+use crate::{Resampler, Sample};
+use num_complex::Complex;
+use num_traits::Zero;
+use realfft::RealFftPlanner;
+
+/// Zero-padding factor used when calculating the spectrum of a window.
+const WINDOW_PADDING: usize = 16;
+
+/// Number of input frames of each test tone in `measure_resampler_snr` and `measure_resampler_rejection`.
+const SNR_INPUT_FRAMES: usize = 1 << 16;
+
+/// Largest FFT length used by `measure_resampler_snr`.
+const SNR_MAX_FFT_LEN: usize = 1 << 15;
+
+/// Number of bins on each side of the peak that belong to the test tone
+/// in `measure_resampler_snr`. This covers the mainlobe of the squared Blackman-Harris window.
+const SNR_SIGNAL_BINS: usize = 12;
+
+/// Number of output frames at the start and end of each test tone
+/// that are skipped by `measure_resampler_rejection`, to avoid the transients.
+const REJECTION_SKIP_FRAMES: usize = 4096;
+
+/// Lowest level used in the spectrograms of `compare_resamplers`, in dB relative to the peak.
+/// This keeps silent bins from giving infinite differences.
+const LEVEL_FLOOR_DB: f64 = -150.0;
+
+/// Properties of a window function, as calculated by `analyze_window`.
+#[derive(Debug, Clone, Copy)]
+pub struct WindowAnalysis {
+    /// Level of the highest sidelobe relative to the mainlobe peak, in dB.
+    pub peak_sidelobe_db: f64,
+    /// Equivalent noise bandwidth, in DFT bins.
+    pub enbw: f64,
+    /// Coherent gain, the mean value of the window. This is 1.0 for a rectangular window.
+    pub coherent_gain: f64,
+    /// Full width of the mainlobe, measured between the first nulls, in DFT bins.
+    pub mainlobe_width: f64,
+}
+
+/// Analyze a window function with the given number of points.
+///
+/// The spectrum of the window is calculated with zero-padding,
+/// and the mainlobe is taken to end at the first local minimum of the spectrum.
+/// A few hundred points is enough to get accurate values.
+pub fn analyze_window(windowfunc: WindowFunction, npoints: usize) -> WindowAnalysis {
+    trace!("Analyzing window {:?}", windowfunc);
+    let window = make_window::<f64>(npoints, windowfunc);
+    let sum: f64 = window.iter().sum();
+    let sum_sq: f64 = window.iter().map(|w| w * w).sum();
+    let coherent_gain = sum / npoints as f64;
+    let enbw = npoints as f64 * sum_sq / (sum * sum);
+
+    let fft_len = WINDOW_PADDING * npoints;
+    let mut padded = vec![0.0; fft_len];
+    padded[0..npoints].copy_from_slice(&window);
+    let mut spectrum = vec![Complex::zero(); fft_len / 2 + 1];
+    let mut planner = RealFftPlanner::<f64>::new();
+    let fft = planner.plan_fft_forward(fft_len);
+    fft.process(&mut padded, &mut spectrum).unwrap();
+    let magnitudes: Vec<f64> = spectrum.iter().map(|c| c.norm()).collect();
+
+    let first_null = magnitudes
+        .windows(2)
+        .position(|pair| pair[1] > pair[0])
+        .unwrap_or(magnitudes.len() - 1);
+    let peak_sidelobe = magnitudes[first_null..].iter().cloned().fold(0.0, f64::max);
+    let peak_sidelobe_db = 20.0 * (peak_sidelobe / magnitudes[0]).log10();
+    let mainlobe_width = 2.0 * first_null as f64 / WINDOW_PADDING as f64;
+    debug!(
+        "Analyzed window, sidelobes: {} dB, enbw: {}, coherent gain: {}, mainlobe width: {}",
+        peak_sidelobe_db, enbw, coherent_gain, mainlobe_width
+    );
+    WindowAnalysis {
+        peak_sidelobe_db,
+        enbw,
+        coherent_gain,
+        mainlobe_width,
+    }
+}
+
+/// Measure the impulse response of a resampler, by feeding it a unit impulse.
+///
+/// The first channel is given `nbr_frames` input frames, with the impulse in the middle,
+/// and the other channels are left empty.
+/// Placing the impulse in the middle means that any pre-ringing is included in the response,
+/// also for resamplers that compensate for their delay.
+/// The returned response is the output of the first channel, and is at the output sample rate.
+/// It includes the effects of the interpolation, and its sum is approximately equal
+/// to the resample ratio.
+/// The resampler should be a fresh instance, since any previous input affects the result.
+///
+/// # Errors
+///
+/// The function returns an error if `nbr_channels` doesn't match the resampler,
+/// or if the resampler returns an error.
+pub fn impulse_response<T, R>(
+    resampler: &mut R,
+    nbr_channels: usize,
+    nbr_frames: usize,
+) -> ResampleResult<Vec<T>>
+where
+    T: Sample,
+    R: Resampler<T> + ?Sized,
+{
+    let mut wave_in = vec![Vec::new(); nbr_channels];
+    if nbr_channels > 0 && nbr_frames > 0 {
+        wave_in[0] = vec![T::zero(); nbr_frames];
+        wave_in[0][nbr_frames / 2] = T::one();
+    }
+    let mut wave_out = process_all(resampler, &wave_in)?;
+    Ok(if wave_out.is_empty() {
+        Vec::new()
+    } else {
+        wave_out.swap_remove(0)
+    })
+}
+
+/// Measure the signal to noise ratio of a resampler, using sine test tones.
+///
+/// Each frequency in `test_freqs` is given relative to the input Nyquist frequency,
+/// and should be within the passband of the resampler.
+/// For each frequency, a sine is resampled, and the spectrum of a segment from the middle
+/// of the output is calculated using a squared Blackman-Harris window.
+/// The power within a few bins of the peak is counted as signal,
+/// and everything else, like aliasing, imaging and interpolation errors, as noise.
+/// The first channel of the resampler is given the test tones, and the other channels are left empty.
+/// The result is the lowest SNR of all the test tones, in dB.
+/// The window limits the measurable SNR to about 150 dB.
+///
+/// # Errors
+///
+/// The function returns an error if `nbr_channels` doesn't match the resampler,
+/// or if the resampler returns an error.
+///
+/// # Panics
+///
+/// Panics if `test_freqs` is empty, or if the output is too short to analyze,
+/// which happens for resample ratios below about 0.01.
+pub fn measure_resampler_snr<T, R>(
+    resampler: &mut R,
+    nbr_channels: usize,
+    test_freqs: &[f64],
+) -> ResampleResult<f64>
+where
+    T: Sample,
+    R: Resampler<T> + ?Sized,
+{
+    assert!(
+        !test_freqs.is_empty(),
+        "At least one test frequency is needed"
+    );
+    let mut planner = RealFftPlanner::<f64>::new();
+    let mut lowest = f64::INFINITY;
+    for freq in test_freqs.iter() {
+        let wave_out = resample_tone(resampler, nbr_channels, *freq)?;
+        let mut fft_len = SNR_MAX_FFT_LEN;
+        while fft_len > wave_out.len() / 2 {
+            fft_len /= 2;
+        }
+        assert!(
+            fft_len > 8 * SNR_SIGNAL_BINS,
+            "The output is too short to analyze"
+        );
+        let window = make_window::<f64>(fft_len, WindowFunction::BlackmanHarris2);
+        let start = (wave_out.len() - fft_len) / 2;
+        let mut buffer: Vec<f64> = wave_out[start..start + fft_len]
+            .iter()
+            .zip(window.iter())
+            .map(|(value, w)| value.to_f64().unwrap_or(0.0) * w)
+            .collect();
+        let mut spectrum = vec![Complex::zero(); fft_len / 2 + 1];
+        let fft = planner.plan_fft_forward(fft_len);
+        fft.process(&mut buffer, &mut spectrum).unwrap();
+        let powers: Vec<f64> = spectrum.iter().map(|value| value.norm_sqr()).collect();
+        let peak = (0..powers.len())
+            .max_by(|a, b| powers[*a].partial_cmp(&powers[*b]).unwrap())
+            .unwrap_or(0);
+        let first = peak.saturating_sub(SNR_SIGNAL_BINS);
+        let last = (peak + SNR_SIGNAL_BINS).min(powers.len() - 1);
+        let signal: f64 = powers[first..=last].iter().sum();
+        let noise: f64 =
+            powers[..first].iter().sum::<f64>() + powers[last + 1..].iter().sum::<f64>();
+        let snr = 10.0 * (signal / noise).log10();
+        debug!("SNR at frequency {}: {} dB", freq, snr);
+        if snr < lowest {
+            lowest = snr;
+        }
+    }
+    Ok(lowest)
+}
+
+/// Measure how well a resampler removes frequencies that can't be represented at the output
+/// sample rate, using sine test tones.
+///
+/// Each frequency in `test_freqs` is given relative to the input Nyquist frequency,
+/// and should be in the stopband of the resampler, like above the output Nyquist frequency
+/// when downsampling. Tones there must be removed, since they would otherwise alias
+/// to lower frequencies. For each frequency, a sine is resampled, and the power of the output,
+/// which is whatever remains of the tone and its aliases, is compared to the power of the input.
+/// The start and end of the output are skipped, to leave out the transients.
+/// The first channel of the resampler is given the test tones, and the other channels are left empty.
+/// The result is the lowest rejection of all the test tones, in dB.
+///
+/// # Errors
+///
+/// The function returns an error if `nbr_channels` doesn't match the resampler,
+/// or if the resampler returns an error.
+///
+/// # Panics
+///
+/// Panics if `test_freqs` is empty, or if the output is too short to analyze,
+/// which happens for resample ratios below about 0.2.
+pub fn measure_resampler_rejection<T, R>(
+    resampler: &mut R,
+    nbr_channels: usize,
+    test_freqs: &[f64],
+) -> ResampleResult<f64>
+where
+    T: Sample,
+    R: Resampler<T> + ?Sized,
+{
+    assert!(
+        !test_freqs.is_empty(),
+        "At least one test frequency is needed"
+    );
+    let mut lowest = f64::INFINITY;
+    for freq in test_freqs.iter() {
+        let wave_out = resample_tone(resampler, nbr_channels, *freq)?;
+        assert!(
+            wave_out.len() > 4 * REJECTION_SKIP_FRAMES,
+            "The output is too short to analyze"
+        );
+        let segment = &wave_out[REJECTION_SKIP_FRAMES..wave_out.len() - REJECTION_SKIP_FRAMES];
+        let power_out = segment
+            .iter()
+            .map(|value| value.to_f64().unwrap_or(0.0).powi(2))
+            .sum::<f64>()
+            / segment.len() as f64;
+        // the power of a sine with an amplitude of 0.5
+        let power_in = 0.125;
+        let rejection = 10.0 * (power_in / power_out).log10();
+        debug!("Rejection at frequency {}: {} dB", freq, rejection);
+        if rejection < lowest {
+            lowest = rejection;
+        }
+    }
+    Ok(lowest)
+}
+
+/// Resample a sine with an amplitude of 0.5 at the frequency `freq`, relative to the input
+/// Nyquist frequency, in the first channel, and return the output of the first channel.
+fn resample_tone<T, R>(resampler: &mut R, nbr_channels: usize, freq: f64) -> ResampleResult<Vec<T>>
+where
+    T: Sample,
+    R: Resampler<T> + ?Sized,
+{
+    let mut wave_in = vec![Vec::new(); nbr_channels];
+    if nbr_channels > 0 {
+        wave_in[0] = (0..SNR_INPUT_FRAMES)
+            .map(|n| T::coerce(0.5 * (std::f64::consts::PI * freq * n as f64).sin()))
+            .collect();
+    }
+    let mut wave_out = process_all(resampler, &wave_in)?;
+    Ok(if wave_out.is_empty() {
+        Vec::new()
+    } else {
+        wave_out.swap_remove(0)
+    })
+}
+
+/// Summary of the level difference within one frequency band,
+/// as part of the result of `compare_resamplers`.
+#[derive(Debug, Clone, Copy)]
+pub struct BandError {
+    /// Lower edge of the band, as a fraction of the output Nyquist frequency.
+    pub low: f64,
+    /// Upper edge of the band, as a fraction of the output Nyquist frequency.
+    pub high: f64,
+    /// Root mean square of the level difference over the band and all analysis frames, in dB.
+    pub rms_db: f64,
+    /// Largest absolute level difference within the band, in dB.
+    pub max_db: f64,
+}
+
+/// The result of `compare_resamplers`.
+#[derive(Debug, Clone)]
+pub struct ResamplerComparison {
+    /// The difference spectrogram, the level of the output of the first resampler
+    /// minus the level of the output of the second, in dB.
+    /// There is one vector per analysis frame, each with `fft_len/2 + 1` bins.
+    pub difference_db: Vec<Vec<f64>>,
+    /// The difference summarized per frequency band, from low to high frequency.
+    pub bands: Vec<BandError>,
+}
+
+/// Compare two resampler configurations, by resampling the same signal with both
+/// and calculating the difference between the spectrograms of the outputs.
+///
+/// The first channel of each resampler is given `signal`, and the other channels are left empty.
+/// The spectrograms are calculated from the output of the first channel, using a Hann window
+/// of `fft_len` points with 50% overlap, and are compared over the length of the shorter output.
+/// The levels are limited to 150 dB below the peak level, so that silent parts don't dominate
+/// the result. The difference is then summarized in `nbr_bands` bands of equal width.
+/// Since only the magnitudes are compared, a small difference in delay between the resamplers
+/// has little effect, as long as the signal is reasonably stationary.
+/// A large difference, like between a sinc and an FFT resampler, shows up as a difference
+/// in the first and last analysis frames, where only one of the outputs has started or ended.
+/// Both resamplers should use the same resample ratio, and should be fresh instances.
+///
+/// # Errors
+///
+/// The function returns an error if `nbr_channels` doesn't match the resamplers,
+/// or if a resampler returns an error.
+///
+/// # Panics
+///
+/// Panics if `fft_len` is less than 2, or if `nbr_bands` is zero
+/// or larger than the number of bins.
+pub fn compare_resamplers<T, A, B>(
+    first: &mut A,
+    second: &mut B,
+    nbr_channels: usize,
+    signal: &[T],
+    fft_len: usize,
+    nbr_bands: usize,
+) -> ResampleResult<ResamplerComparison>
+where
+    T: Sample,
+    A: Resampler<T> + ?Sized,
+    B: Resampler<T> + ?Sized,
+{
+    let nbr_bins = fft_len / 2 + 1;
+    assert!(fft_len >= 2, "The FFT length must be at least 2");
+    assert!(
+        nbr_bands > 0 && nbr_bands <= nbr_bins,
+        "The number of bands must be between 1 and the number of bins"
+    );
+    let mut wave_in = vec![Vec::new(); nbr_channels];
+    if nbr_channels > 0 {
+        wave_in[0] = signal.to_vec();
+    }
+    let first_out = process_all(first, &wave_in)?;
+    let second_out = process_all(second, &wave_in)?;
+    let empty = Vec::new();
+    let first_out = first_out.first().unwrap_or(&empty);
+    let second_out = second_out.first().unwrap_or(&empty);
+    let nbr_frames = first_out.len().min(second_out.len());
+
+    let first_levels = spectrogram(&first_out[0..nbr_frames], fft_len);
+    let second_levels = spectrogram(&second_out[0..nbr_frames], fft_len);
+    let peak = first_levels
+        .iter()
+        .chain(second_levels.iter())
+        .flat_map(|frame| frame.iter())
+        .cloned()
+        .fold(f64::NEG_INFINITY, f64::max);
+    let floor = peak + LEVEL_FLOOR_DB;
+    let difference_db: Vec<Vec<f64>> = first_levels
+        .iter()
+        .zip(second_levels.iter())
+        .map(|(first_frame, second_frame)| {
+            first_frame
+                .iter()
+                .zip(second_frame.iter())
+                .map(|(a, b)| a.max(floor) - b.max(floor))
+                .collect()
+        })
+        .collect();
+
+    let bands = (0..nbr_bands)
+        .map(|band| {
+            let start = band * nbr_bins / nbr_bands;
+            let end = (band + 1) * nbr_bins / nbr_bands;
+            let mut sum_sq = 0.0;
+            let mut max_db = 0.0;
+            let mut count = 0;
+            for frame in difference_db.iter() {
+                for diff in frame[start..end].iter() {
+                    sum_sq += diff * diff;
+                    max_db = f64::max(max_db, diff.abs());
+                    count += 1;
+                }
+            }
+            let rms_db = if count > 0 {
+                (sum_sq / count as f64).sqrt()
+            } else {
+                0.0
+            };
+            BandError {
+                low: start as f64 / (nbr_bins - 1) as f64,
+                high: (end - 1) as f64 / (nbr_bins - 1) as f64,
+                rms_db,
+                max_db,
+            }
+        })
+        .collect();
+    debug!(
+        "Compared resamplers over {} frames, with {} analysis frames",
+        nbr_frames,
+        difference_db.len()
+    );
+    Ok(ResamplerComparison {
+        difference_db,
+        bands,
+    })
+}
+
+/// Calculate the spectrogram of a signal in dB, using a Hann window with 50% overlap.
+fn spectrogram<T: Sample>(signal: &[T], fft_len: usize) -> Vec<Vec<f64>> {
+    let window = make_window::<f64>(fft_len, WindowFunction::Hann);
+    let mut planner = RealFftPlanner::<f64>::new();
+    let fft = planner.plan_fft_forward(fft_len);
+    let mut buffer = vec![0.0; fft_len];
+    let mut spectrum = vec![Complex::zero(); fft_len / 2 + 1];
+    let hop = fft_len / 2;
+    let mut levels = Vec::new();
+    let mut start = 0;
+    while start + fft_len <= signal.len() {
+        for (n, value) in buffer.iter_mut().enumerate() {
+            *value = signal[start + n].to_f64().unwrap() * window[n];
+        }
+        fft.process(&mut buffer, &mut spectrum).unwrap();
+        levels.push(
+            spectrum
+                .iter()
+                .map(|value| 20.0 * value.norm().log10())
+                .collect(),
+        );
+        start += hop;
+    }
+    levels
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::analysis::{
+        analyze_window, compare_resamplers, impulse_response, measure_resampler_rejection,
+        measure_resampler_snr,
+    };
+    use crate::{FftFixedInOut, SincFixedIn, WindowFunction};
+    use crate::{InterpolationParameters, InterpolationType};
+
+    #[test]
+    fn analyze_hann() {
+        let props = analyze_window(WindowFunction::Hann, 256);
+        assert!((props.coherent_gain - 0.5).abs() < 1.0e-6);
+        assert!((props.enbw - 1.5).abs() < 1.0e-6);
+        assert!((props.mainlobe_width - 4.0).abs() < 0.2);
+        assert!((props.peak_sidelobe_db + 31.5).abs() < 0.5);
+    }
+
+    #[test]
+    fn analyze_blackman() {
+        let props = analyze_window(WindowFunction::Blackman, 256);
+        assert!((props.coherent_gain - 0.42).abs() < 1.0e-6);
+        assert!((props.enbw - 1.727).abs() < 0.01);
+        assert!((props.mainlobe_width - 6.0).abs() < 0.2);
+        assert!((props.peak_sidelobe_db + 58.1).abs() < 1.0);
+    }
+
+    #[test]
+    fn analyze_custom() {
+        let taps = crate::windows::hann::<f64>(256);
+        let props = analyze_window(WindowFunction::CustomTaps(taps), 256);
+        assert!((props.enbw - 1.5).abs() < 1.0e-6);
+    }
+
+    #[test]
+    fn compare_windows() {
+        let bh = analyze_window(WindowFunction::BlackmanHarris, 256);
+        let bh2 = analyze_window(WindowFunction::BlackmanHarris2, 256);
+        assert!(bh2.peak_sidelobe_db < bh.peak_sidelobe_db);
+        assert!(bh2.mainlobe_width > bh.mainlobe_width);
+    }
+
+    #[test]
+    fn impulse_responses() {
+        let params = InterpolationParameters {
+            sinc_len: 64,
+            f_cutoff: 0.95,
+            interpolation: InterpolationType::Cubic,
+            oversampling_factor: 64,
+            window: WindowFunction::BlackmanHarris2,
+        };
+        let mut resampler = SincFixedIn::<f64>::new(2.0, params, 256, 2).unwrap();
+        let response = impulse_response(&mut resampler, 2, 256).unwrap();
+        assert!(response.len() > 400);
+        let sum: f64 = response.iter().sum();
+        assert!((sum - 2.0).abs() < 0.01, "{}", sum);
+        let peak_index = (0..response.len())
+            .max_by(|a, b| response[*a].partial_cmp(&response[*b]).unwrap())
+            .unwrap();
+        assert!(peak_index > 248 && peak_index < 264, "{}", peak_index);
+
+        let mut resampler = FftFixedInOut::<f64>::new(44100, 88200, 1024, 1).unwrap();
+        let response = impulse_response(&mut resampler, 1, 1000).unwrap();
+        let sum: f64 = response.iter().sum();
+        assert!((sum - 2.0).abs() < 0.01, "{}", sum);
+        assert!(impulse_response(&mut resampler, 3, 1000).is_err());
+    }
+
+    #[test]
+    fn compare_configurations() {
+        let params = |sinc_len, f_cutoff| InterpolationParameters {
+            sinc_len,
+            f_cutoff,
+            interpolation: InterpolationType::Cubic,
+            oversampling_factor: 128,
+            window: WindowFunction::BlackmanHarris2,
+        };
+        // white noise from a simple linear congruential generator
+        let mut state = 12345u32;
+        let signal: Vec<f64> = (0..20000)
+            .map(|_| {
+                state = state.wrapping_mul(1_103_515_245).wrapping_add(12345);
+                (state >> 16) as f64 / 32768.0 - 1.0
+            })
+            .collect();
+
+        let mut first = SincFixedIn::<f64>::new(2.0, params(128, 0.95), 1024, 1).unwrap();
+        let mut second = SincFixedIn::<f64>::new(2.0, params(128, 0.95), 1024, 1).unwrap();
+        let result = compare_resamplers(&mut first, &mut second, 1, &signal, 512, 8).unwrap();
+        assert!(result.difference_db.len() > 100);
+        assert_eq!(result.difference_db[0].len(), 257);
+        assert_eq!(result.bands.len(), 8);
+        assert_eq!(result.bands[0].low, 0.0);
+        assert_eq!(result.bands[7].high, 1.0);
+        assert!(result.bands.iter().all(|band| band.max_db == 0.0));
+
+        // a lower cutoff only makes a difference near the input Nyquist frequency
+        let mut first = SincFixedIn::<f64>::new(2.0, params(128, 0.95), 1024, 1).unwrap();
+        let mut second = SincFixedIn::<f64>::new(2.0, params(128, 0.8), 1024, 1).unwrap();
+        let result = compare_resamplers(&mut first, &mut second, 1, &signal, 512, 8).unwrap();
+        assert!(result.bands[0].rms_db < 0.01, "{:?}", result.bands[0]);
+        assert!(result.bands[3].rms_db > 20.0, "{:?}", result.bands[3]);
+        assert!(compare_resamplers(&mut first, &mut second, 2, &signal, 512, 8).is_err());
+    }
+
+    #[test]
+    fn snr() {
+        let params = |interpolation| InterpolationParameters {
+            sinc_len: 128,
+            f_cutoff: 0.95,
+            interpolation,
+            oversampling_factor: 128,
+            window: WindowFunction::BlackmanHarris2,
+        };
+        let freqs = [0.01, 0.2, 0.7];
+        let mut cubic =
+            SincFixedIn::<f64>::new(1.1, params(InterpolationType::Cubic), 1024, 1).unwrap();
+        let cubic_snr = measure_resampler_snr(&mut cubic, 1, &freqs).unwrap();
+        let mut linear =
+            SincFixedIn::<f64>::new(1.1, params(InterpolationType::Linear), 1024, 1).unwrap();
+        let linear_snr = measure_resampler_snr(&mut linear, 1, &freqs).unwrap();
+        assert!(cubic_snr > 140.0, "{}", cubic_snr);
+        assert!(linear_snr > 90.0, "{}", linear_snr);
+        assert!(cubic_snr > linear_snr + 40.0);
+        let mut fft = FftFixedInOut::<f64>::new(44100, 48000, 1024, 1).unwrap();
+        let fft_snr = measure_resampler_snr(&mut fft, 1, &freqs).unwrap();
+        assert!(fft_snr > 140.0, "{}", fft_snr);
+    }
+
+    #[test]
+    fn rejection() {
+        let params = |sinc_len| InterpolationParameters {
+            sinc_len,
+            f_cutoff: 0.9,
+            interpolation: InterpolationType::Cubic,
+            oversampling_factor: 128,
+            window: WindowFunction::BlackmanHarris2,
+        };
+        // the output Nyquist frequency is at 0.5 of the input Nyquist frequency
+        let freqs = [0.55, 0.7, 0.9];
+        let mut short = SincFixedIn::<f64>::new(0.5, params(32), 1024, 1).unwrap();
+        let short_rejection = measure_resampler_rejection(&mut short, 1, &freqs).unwrap();
+        let mut long = SincFixedIn::<f64>::new(0.5, params(256), 1024, 1).unwrap();
+        let long_rejection = measure_resampler_rejection(&mut long, 1, &freqs).unwrap();
+        assert!(short_rejection > 10.0, "{}", short_rejection);
+        assert!(long_rejection > 150.0, "{}", long_rejection);
+        assert!(long_rejection > short_rejection + 100.0);
+        // a tone in the passband is not rejected
+        let mut long = SincFixedIn::<f64>::new(0.5, params(256), 1024, 1).unwrap();
+        let passband = measure_resampler_rejection(&mut long, 1, &[0.2]).unwrap();
+        assert!(passband.abs() < 0.1, "{}", passband);
+    }
+}
@@ -1,18 +1,235 @@
+use crate::convert::{self, Dither};
+use crate::convolution::{FftConvolution, DEFAULT_CONVOLUTION_THRESHOLD};
 use crate::error::{ResampleError, ResampleResult};
 use crate::interpolation::*;
 #[cfg(all(target_arch = "x86_64", feature = "avx"))]
-use crate::interpolator_avx::AvxInterpolator;
+use crate::interpolator_avx::{self, AvxInterpolator};
+#[cfg(all(target_arch = "x86_64", feature = "avx512"))]
+use crate::interpolator_avx512::{self, Avx512Interpolator};
 #[cfg(all(target_arch = "aarch64", feature = "neon"))]
-use crate::interpolator_neon::NeonInterpolator;
+use crate::interpolator_neon::{self, NeonInterpolator};
+#[cfg(feature = "portable-simd")]
+use crate::interpolator_portable::{PortableInterpolator, PortableSample};
 #[cfg(target_arch = "x86_64")]
-use crate::interpolator_sse::SseInterpolator;
-use crate::sinc::make_sincs;
+use crate::interpolator_sse::{self, SseInterpolator};
+#[cfg(all(target_arch = "wasm32", feature = "wasm-simd"))]
+use crate::interpolator_wasm::{self, WasmInterpolator};
+use crate::io;
+use crate::lanes::ChannelLanes;
+use crate::metering::{Meter, Metrics};
+use crate::modulation::Lfo;
+use crate::normalization::{NormalizationParameters, Normalizer};
+use crate::offline;
+use crate::parallel::{for_each_channel, nbr_workers, DEFAULT_PARALLEL_THRESHOLD};
+use crate::rational::{exact_fraction, snap_ratio, RationalRatio};
+use crate::sinc::{
+    checksum_values, fuse_filter, make_prototype, make_sincs, make_sincs_from_prototype,
+};
 use crate::windows::WindowFunction;
-use crate::{InterpolationParameters, InterpolationType};
+use crate::{InterpolationParameters, InterpolationType, Latency};
 use crate::{Resampler, Sample};
+use std::fmt;
+use std::iter;
+use std::mem;
+use std::ops::Range;
+use std::sync::Arc;
 
-/// Functions for making the scalar product with a sinc
-pub trait SincInterpolator<T> {
+/// The input of the sinc resamplers, either one vector per channel,
+/// or a single buffer where each channel is read with a stride.
+/// The `StridedI16` variant holds interleaved 16-bit integers, that are converted while they are read.
+/// The `Unchecked` variant holds one vector per channel that the caller of
+/// `process_unchecked` guarantees to have the right shape.
+#[derive(Clone, Copy)]
+enum Input<'a, T> {
+    Channels(&'a [Vec<T>]),
+    Slices(&'a [&'a [T]]),
+    Unchecked(&'a [Vec<T>]),
+    Range {
+        data: &'a [Vec<T>],
+        offset: usize,
+        len: usize,
+    },
+    Strided {
+        data: &'a [T],
+        nbr_channels: usize,
+        stride: usize,
+    },
+    StridedI16 {
+        data: &'a [i16],
+        nbr_channels: usize,
+        stride: usize,
+    },
+}
+
+impl<'a, T> Input<'a, T>
+where
+    T: Sample,
+{
+    /// Check that the input has `nbr_channels` channels of `nbr_frames` frames,
+    /// and store the channels that are used in `used_channels`. Empty vectors are ignored.
+    fn used_channels(
+        &self,
+        nbr_channels: usize,
+        nbr_frames: usize,
+        used_channels: &mut Vec<usize>,
+    ) -> ResampleResult<()> {
+        used_channels.clear();
+        match *self {
+            Input::Channels(wave_in) => {
+                check_channels(wave_in, nbr_channels, nbr_frames, used_channels)
+            }
+            Input::Slices(wave_in) => {
+                check_channels(wave_in, nbr_channels, nbr_frames, used_channels)
+            }
+            Input::Unchecked(_) => {
+                used_channels.extend(0..nbr_channels);
+                Ok(())
+            }
+            Input::Range { data, offset, len } => {
+                if data.len() != nbr_channels {
+                    return Err(ResampleError::WrongNumberOfChannels {
+                        expected: nbr_channels,
+                        actual: data.len(),
+                        resampler: String::new(),
+                    });
+                }
+                io::check_range(data, offset, len)?;
+                used_channels.extend((0..nbr_channels).filter(|chan| !data[*chan].is_empty()));
+                if len != nbr_frames {
+                    if let Some(chan) = used_channels.first() {
+                        return Err(ResampleError::WrongNumberOfFrames {
+                            channel: *chan,
+                            expected: nbr_frames,
+                            actual: len,
+                            resampler: String::new(),
+                        });
+                    }
+                }
+                Ok(())
+            }
+            Input::Strided {
+                data,
+                nbr_channels: actual,
+                stride,
+            } => check_strided(data.len(), actual, stride, nbr_channels, nbr_frames)
+                .map(|_| used_channels.extend(0..nbr_channels)),
+            Input::StridedI16 {
+                data,
+                nbr_channels: actual,
+                stride,
+            } => check_strided(data.len(), actual, stride, nbr_channels, nbr_frames)
+                .map(|_| used_channels.extend(0..nbr_channels)),
+        }
+    }
+
+    /// Copy the frames of a channel into `buffer`, and measure them.
+    fn copy_channel(&self, chan: usize, meter: &mut Meter<T>, buffer: &mut [T]) {
+        match *self {
+            Input::Channels(wave_in) => meter.copy_and_measure(chan, &wave_in[chan], buffer),
+            Input::Slices(wave_in) => meter.copy_and_measure(chan, wave_in[chan], buffer),
+            Input::Unchecked(wave_in) => {
+                // safe as long as the caller of process_unchecked upholds its preconditions
+                let wave = unsafe { wave_in.get_unchecked(chan) };
+                meter.copy_and_measure(chan, wave, buffer)
+            }
+            Input::Range { data, offset, len } => {
+                meter.copy_and_measure(chan, &data[chan][offset..offset + len], buffer)
+            }
+            Input::Strided { data, stride, .. } => {
+                io::read_strided(data, chan, stride, buffer);
+                meter.measure(chan, buffer);
+            }
+            Input::StridedI16 { data, stride, .. } => {
+                convert::read_strided_i16(data, chan, stride, buffer);
+                meter.measure(chan, buffer);
+            }
+        }
+    }
+}
+
+/// Check that a strided buffer of length `len` with `actual` channels
+/// holds `nbr_frames` frames of `nbr_channels` channels.
+fn check_strided(
+    len: usize,
+    actual: usize,
+    stride: usize,
+    nbr_channels: usize,
+    nbr_frames: usize,
+) -> ResampleResult<()> {
+    if actual != nbr_channels {
+        return Err(ResampleError::WrongNumberOfChannels {
+            expected: nbr_channels,
+            actual,
+            resampler: String::new(),
+        });
+    }
+    io::check_strided_len(len, nbr_channels, stride, nbr_frames)
+}
+
+/// Check that `wave_in` has `nbr_channels` channels of `nbr_frames` frames,
+/// and add the channels that are used to `used_channels`. Empty channels are ignored.
+fn check_channels<T, V>(
+    wave_in: &[V],
+    nbr_channels: usize,
+    nbr_frames: usize,
+    used_channels: &mut Vec<usize>,
+) -> ResampleResult<()>
+where
+    V: AsRef<[T]>,
+{
+    if wave_in.len() != nbr_channels {
+        return Err(ResampleError::WrongNumberOfChannels {
+            expected: nbr_channels,
+            actual: wave_in.len(),
+            resampler: String::new(),
+        });
+    }
+    for (chan, wave) in wave_in.iter().enumerate() {
+        let wave = wave.as_ref();
+        if !wave.is_empty() {
+            used_channels.push(chan);
+            if wave.len() != nbr_frames {
+                return Err(ResampleError::WrongNumberOfFrames {
+                    channel: chan,
+                    expected: nbr_frames,
+                    actual: wave.len(),
+                    resampler: String::new(),
+                });
+            }
+        }
+    }
+    Ok(())
+}
+
+/// The largest relative deviation from the original ratio allowed by "set_resample_ratio".
+const MAX_RATIO_DEVIATION: f64 = 0.1;
+
+/// Get the number of output frames, following the one at `last_index`,
+/// that interpolate input from before the start of the next chunk.
+/// These are the frames that are held back until more input arrives.
+fn pending_frames(last_index: f64, resample_ratio: f64, sinc_len: usize) -> usize {
+    let limit = -((sinc_len / 2) as f64);
+    let mut idx = last_index + 1.0 / resample_ratio;
+    let mut nbr_frames = 0;
+    while idx < limit {
+        idx += 1.0 / resample_ratio;
+        nbr_frames += 1;
+    }
+    nbr_frames
+}
+
+/// Functions for making the scalar product with a sinc.
+///
+/// Interpolators must be `Send` and `Sync`, so that the resamplers using them can be
+/// moved between threads.
+///
+/// The constructors of the sinc resamplers pick the fastest of the SIMD kernels
+/// that the CPU and the enabled features support. The other interpolators,
+/// like the ones in `interpolator_f32`, `interpolator_q15`, `interpolator_symmetric`
+/// and `interpolator_table`, trade speed for memory or accuracy, so they are never chosen
+/// automatically. To use one of them, create it explicitly
+/// and give it to `new_with_interpolator` of the sinc resamplers.
+pub trait SincInterpolator<T>: Send + Sync {
     /// Make the scalar product between the waveform starting at `index` and the sinc of `subindex`.
     fn get_sinc_interpolated(&self, wave: &[T], index: usize, subindex: usize) -> T;
 
@@ -26,6 +243,27 @@ pub trait SincInterpolator<T> {
 
     /// Get number of sincs used for oversampling
     fn nbr_sincs(&self) -> usize;
+
+    /// Get a checksum of the sinc filters.
+    /// The coefficients are read back by interpolating unit impulses,
+    /// so the result does not depend on how the sincs are stored.
+    fn checksum(&self) -> u64
+    where
+        T: Sample,
+    {
+        let sinc_len = self.len();
+        let mut wave = vec![T::zero(); sinc_len + 1];
+        let mut values = vec![sinc_len as f64, self.nbr_sincs() as f64];
+        for subindex in 0..self.nbr_sincs() {
+            for tap in 0..sinc_len {
+                wave[tap] = T::one();
+                let value = self.get_sinc_interpolated(&wave, 0, subindex);
+                values.push(value.to_f64().unwrap_or(0.0));
+                wave[tap] = T::zero();
+            }
+        }
+        checksum_values(values)
+    }
 }
 
 /// A plain scalar interpolator
@@ -35,6 +273,15 @@ pub struct ScalarInterpolator<T> {
     nbr_sincs: usize,
 }
 
+impl<T> fmt::Debug for ScalarInterpolator<T> {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        f.debug_struct("ScalarInterpolator")
+            .field("sinc_len", &self.length)
+            .field("nbr_sincs", &self.nbr_sincs)
+            .finish()
+    }
+}
+
 impl<T> SincInterpolator<T> for ScalarInterpolator<T>
 where
     T: Sample,
@@ -106,12 +353,19 @@ where
         f_cutoff: f32,
         window: WindowFunction,
     ) -> Self {
-        assert!(sinc_len % 8 == 0, "Sinc length must be a multiple of 8");
         let sincs = make_sincs(sinc_len, oversampling_factor, f_cutoff, window);
+        Self::new_with_sincs(sincs)
+    }
+
+    /// Create a new ScalarInterpolator from a set of sincs, one per oversampling step.
+    /// All sincs must have the same length, which must be a multiple of 8.
+    pub fn new_with_sincs(sincs: Vec<Vec<T>>) -> Self {
+        let sinc_len = sincs[0].len();
+        assert!(sinc_len % 8 == 0, "Sinc length must be a multiple of 8");
         Self {
+            nbr_sincs: sincs.len(),
             sincs,
             length: sinc_len,
-            nbr_sincs: oversampling_factor,
         }
     }
 }
@@ -121,15 +375,37 @@ where
 ///
 /// The resampling is done by creating a number of intermediate points (defined by oversampling_factor)
 /// by sinc interpolation. The new samples are then calculated by interpolating between these points.
+///
+/// Cloning a resampler is cheap: the clones share the sinc filters, which are immutable,
+/// and only the buffers and the state are copied. This is useful when creating
+/// several resamplers with the same settings, for example one per stream.
+#[derive(Clone)]
 pub struct SincFixedIn<T> {
     nbr_channels: usize,
     chunk_size: usize,
     last_index: f64,
     resample_ratio: f64,
     resample_ratio_original: f64,
-    interpolator: Box<dyn SincInterpolator<T>>,
+    target_ratio: f64,
+    ratio_smoothing: usize,
+    interpolator: Arc<dyn SincInterpolator<T>>,
+    phases: PhaseIndex,
+    lanes: Option<ChannelLanes<T>>,
+    convolution: Option<FftConvolution<T>>,
+    cutoff: Option<f64>,
     buffer: Vec<Vec<T>>,
+    buffer_start: usize,
     interpolation: InterpolationType,
+    preview: bool,
+    reverse: bool,
+    modulation: Option<Lfo>,
+    meter: Meter<T>,
+    silence_threshold: Option<T>,
+    parallel_threshold: usize,
+    normalizer: Normalizer<T>,
+    output_buffer: Vec<Vec<T>>,
+    ratios: Vec<f64>,
+    channels: ChannelLists,
 }
 
 /// An asynchronous resampler that return a fixed number of audio frames.
@@ -137,6 +413,9 @@ pub struct SincFixedIn<T> {
 ///
 /// The resampling is done by creating a number of intermediate points (defined by oversampling_factor)
 /// by sinc interpolation. The new samples are then calculated by interpolating between these points.
+///
+/// Like for `SincFixedIn`, the clones of a resampler share the sinc filters.
+#[derive(Clone)]
 pub struct SincFixedOut<T> {
     nbr_channels: usize,
     chunk_size: usize,
@@ -145,11 +424,41 @@ pub struct SincFixedOut<T> {
     current_buffer_fill: usize,
     resample_ratio: f64,
     resample_ratio_original: f64,
-    interpolator: Box<dyn SincInterpolator<T>>,
+    target_ratio: f64,
+    ratio_smoothing: usize,
+    interpolator: Arc<dyn SincInterpolator<T>>,
+    phases: PhaseIndex,
+    lanes: Option<ChannelLanes<T>>,
+    convolution: Option<FftConvolution<T>>,
+    cutoff: Option<f64>,
     buffer: Vec<Vec<T>>,
+    buffer_start: usize,
     interpolation: InterpolationType,
+    preview: bool,
+    reverse: bool,
+    modulation: Option<Lfo>,
+    meter: Meter<T>,
+    silence_threshold: Option<T>,
+    parallel_threshold: usize,
+    normalizer: Normalizer<T>,
+    output_buffer: Vec<Vec<T>>,
+    ratios: Vec<f64>,
+    channels: ChannelLists,
+}
+
+/// Get the cutoff of the anti-aliasing filter relative to the input Nyquist frequency,
+/// scaled down by the resample ratio when downsampling.
+pub(crate) fn design_cutoff(resample_ratio: f64, f_cutoff: f32) -> f32 {
+    if resample_ratio >= 1.0 {
+        f_cutoff
+    } else {
+        f_cutoff * resample_ratio as f32
+    }
 }
 
+/// Helper function for making the fastest available interpolator for the given parameters.
+/// The windowed sincs are calculated once, and then handed to the SIMD kernel chosen at runtime,
+/// see `make_interpolator_from_sincs`.
 pub fn make_interpolator<T>(
     sinc_len: usize,
     resample_ratio: f64,
@@ -161,44 +470,238 @@ where
     T: Sample,
 {
     let sinc_len = 8 * (((sinc_len as f32) / 8.0).ceil() as usize);
-    let f_cutoff = if resample_ratio >= 1.0 {
-        f_cutoff
-    } else {
-        f_cutoff * resample_ratio as f32
-    };
+    let f_cutoff = design_cutoff(resample_ratio, f_cutoff);
+    let sincs = make_sincs(sinc_len, oversampling_factor, f_cutoff, window);
+    make_interpolator_from_sincs(sincs)
+}
+
+/// Helper function for making an interpolator with a user filter fused into the windowed sinc.
+/// The gain of the user filter at zero frequency is kept.
+pub fn make_interpolator_with_filter<T>(
+    parameters: &InterpolationParameters,
+    resample_ratio: f64,
+    taps: &[T],
+) -> Box<dyn SincInterpolator<T>>
+where
+    T: Sample,
+{
+    let sinc_len = 8 * (((parameters.sinc_len as f32) / 8.0).ceil() as usize);
+    let f_cutoff = design_cutoff(resample_ratio, parameters.f_cutoff);
+    let factor = parameters.oversampling_factor;
+    let prototype = make_prototype::<T>(sinc_len, factor, f_cutoff, parameters.window.clone());
+    let fused = fuse_filter(&prototype, factor, taps);
+    let mut gain = T::zero();
+    for tap in taps.iter() {
+        gain += *tap;
+    }
+    let mut sincs = make_sincs_from_prototype(&fused, factor);
+    for sinc in sincs.iter_mut() {
+        for value in sinc.iter_mut() {
+            *value *= gain;
+        }
+    }
+    make_interpolator_from_sincs(sincs)
+}
+
+/// Helper function for making an interpolator from a prototype filter.
+/// The prototype has `oversampling_factor` taps per sinc, and the sinc length
+/// must be a multiple of 8. The prototype is normalized to unity gain.
+pub fn make_interpolator_from_prototype<T>(
+    prototype: &[T],
+    oversampling_factor: usize,
+) -> Box<dyn SincInterpolator<T>>
+where
+    T: Sample,
+{
+    let sincs = make_sincs_from_prototype(prototype, oversampling_factor);
+    make_interpolator_from_sincs(sincs)
+}
+
+/// Helper function for making an interpolator from a precalculated table,
+/// that holds all the sincs one after the other, see `sinc_table_source`.
+/// The sinc length must be a multiple of 8.
+/// The table is copied once, into the layout of the chosen SIMD kernel.
+pub fn make_interpolator_from_table<T>(table: &[T], sinc_len: usize) -> Box<dyn SincInterpolator<T>>
+where
+    T: Sample,
+{
+    assert!(
+        sinc_len > 0 && table.len() / sinc_len * sinc_len == table.len(),
+        "Table has {} values, which is not a multiple of the sinc length {}",
+        table.len(),
+        sinc_len
+    );
+    let sincs = table.chunks(sinc_len).map(|sinc| sinc.to_vec()).collect();
+    make_interpolator_from_sincs(sincs)
+}
+
+/// Make the fastest available interpolator for a set of sincs.
+///
+/// This is the single place where the SIMD kernel is chosen. The kernels are tried from the fastest
+/// to the slowest, and the first one where the CPU supports the instructions it needs
+/// takes the sincs, without copying them for the others:
+/// AVX-512, AVX with FMA and then SSE3 on x86_64, Neon on aarch64, and SIMD128 on WebAssembly,
+/// depending on the enabled features. The scalar kernel, or the portable SIMD kernel when
+/// the `portable-simd` feature is enabled, is used when none of them are available.
+fn make_interpolator_from_sincs<T>(sincs: Vec<Vec<T>>) -> Box<dyn SincInterpolator<T>>
+where
+    T: Sample,
+{
+    #[cfg(all(target_arch = "x86_64", feature = "avx512"))]
+    if interpolator_avx512::is_supported() {
+        let interpolator = Avx512Interpolator::<T>::new_with_sincs(sincs);
+        return Box::new(interpolator.expect("AVX-512 is supported"));
+    }
 
     #[cfg(all(target_arch = "x86_64", feature = "avx"))]
-    if let Ok(interpolator) =
-        AvxInterpolator::<T>::new(sinc_len, oversampling_factor, f_cutoff, window)
-    {
-        return Box::new(interpolator);
+    if interpolator_avx::is_supported() {
+        let interpolator = AvxInterpolator::<T>::new_with_sincs(sincs);
+        return Box::new(interpolator.expect("AVX and FMA are supported"));
     }
 
     #[cfg(target_arch = "x86_64")]
-    if let Ok(interpolator) =
-        SseInterpolator::<T>::new(sinc_len, oversampling_factor, f_cutoff, window)
-    {
-        return Box::new(interpolator);
+    if interpolator_sse::is_supported() {
+        let interpolator = SseInterpolator::<T>::new_with_sincs(sincs);
+        return Box::new(interpolator.expect("SSE3 is supported"));
     }
 
     #[cfg(all(target_arch = "aarch64", feature = "neon"))]
-    if let Ok(interpolator) =
-        NeonInterpolator::<T>::new(sinc_len, oversampling_factor, f_cutoff, window)
-    {
-        return Box::new(interpolator);
+    if interpolator_neon::is_supported() {
+        let interpolator = NeonInterpolator::<T>::new_with_sincs(sincs);
+        return Box::new(interpolator.expect("Neon is supported"));
     }
 
-    Box::new(ScalarInterpolator::<T>::new(
-        sinc_len,
-        oversampling_factor,
-        f_cutoff,
-        window,
-    ))
+    #[cfg(all(target_arch = "wasm32", feature = "wasm-simd"))]
+    if interpolator_wasm::is_supported() {
+        let interpolator = WasmInterpolator::<T>::new_with_sincs(sincs);
+        return Box::new(interpolator.expect("SIMD128 is supported"));
+    }
+
+    #[cfg(feature = "portable-simd")]
+    let fallback = Box::new(PortableInterpolator::<T>::new_with_sincs(sincs));
+    #[cfg(not(feature = "portable-simd"))]
+    let fallback = Box::new(ScalarInterpolator::<T>::new_with_sincs(sincs));
+    fallback
+}
+
+/// The lists of channels used while processing a chunk,
+/// kept in the resampler so that they don't need to be allocated for every chunk.
+#[derive(Clone)]
+struct ChannelLists {
+    /// The channels that have input.
+    used: Vec<usize>,
+    /// The used channels that are not silent.
+    active: Vec<usize>,
+    /// The active channels that are left for the normal kernel.
+    remaining: Vec<usize>,
+}
+
+impl ChannelLists {
+    fn new(nbr_channels: usize) -> Self {
+        ChannelLists {
+            used: Vec::with_capacity(nbr_channels),
+            active: Vec::with_capacity(nbr_channels),
+            remaining: Vec::with_capacity(nbr_channels),
+        }
+    }
+}
+
+/// Store the channels among `used_channels` that are not silent in the `window` of the buffer
+/// in `active_channels`. Without a silence threshold, all used channels are active.
+fn active_channels<T>(
+    buffer: &[Vec<T>],
+    silence_threshold: Option<T>,
+    used_channels: &[usize],
+    window: Range<usize>,
+    active_channels: &mut Vec<usize>,
+) where
+    T: Sample,
+{
+    active_channels.clear();
+    match silence_threshold {
+        Some(threshold) => active_channels.extend(used_channels.iter().cloned().filter(|chan| {
+            buffer[*chan][window.clone()]
+                .iter()
+                .any(|sample| sample.abs() > threshold)
+        })),
+        None => active_channels.extend_from_slice(used_channels),
+    }
+}
+
+/// The number of chunks that fit in the input buffers of the sinc resamplers,
+/// besides the history that is kept from the previous chunks.
+/// The window of the current chunk moves forward through the buffers,
+/// and the history only has to be moved back to the start when the window reaches the end.
+const BUFFER_CHUNKS: usize = 4;
+
+/// Move the window of the input buffers forward by the `consumed` frames of the last chunk,
+/// to make room for `new_frames` frames after the `history` frames that are kept.
+/// When there is not enough room left at the end of the buffers,
+/// the history is moved back to the start. Returns the new start of the window.
+fn advance_window<T>(
+    buffer: &mut [Vec<T>],
+    start: usize,
+    consumed: usize,
+    history: usize,
+    new_frames: usize,
+) -> usize
+where
+    T: Sample,
+{
+    let start = start + consumed;
+    let capacity = buffer.first().map(|wav| wav.len()).unwrap_or(0);
+    if start + history + new_frames <= capacity {
+        return start;
+    }
+    for wav in buffer.iter_mut() {
+        wav.copy_within(start..start + history, 0);
+    }
+    0
+}
+
+/// Read the sincs out of an interpolator, by interpolating a unit impulse.
+/// This works for any kernel, also for custom ones, and gives the same filters.
+pub(crate) fn read_sincs<T>(interpolator: &dyn SincInterpolator<T>) -> Vec<Vec<T>>
+where
+    T: Sample,
+{
+    let length = interpolator.len();
+    let mut impulse = vec![T::zero(); 2 * length + 1];
+    impulse[length] = T::one();
+    (0..interpolator.nbr_sincs())
+        .map(|subindex| {
+            (0..length)
+                .map(|tap| interpolator.get_sinc_interpolated(&impulse, length - tap, subindex))
+                .collect()
+        })
+        .collect()
+}
+
+/// Calculate all points of the oversampled version of `nbr_frames` frames of a buffer,
+/// with the first point at index -sinc_len relative to the start of the newest chunk.
+pub(crate) fn oversample_wave<T>(
+    interpolator: &dyn SincInterpolator<T>,
+    buf: &[T],
+    nbr_frames: usize,
+) -> Vec<T>
+where
+    T: Sample,
+{
+    let sinc_len = interpolator.len();
+    let factor = interpolator.nbr_sincs();
+    let mut wave = Vec::with_capacity(nbr_frames * factor);
+    for index in sinc_len..(nbr_frames + sinc_len) {
+        for subindex in 0..factor {
+            wave.push(interpolator.get_sinc_interpolated(buf, index, subindex));
+        }
+    }
+    wave
 }
 
 /// Perform cubic polynomial interpolation to get value at x.
 /// Input points are assumed to be at x = -1, 0, 1, 2
-fn interp_cubic<T>(x: T, yvals: &[T; 4]) -> T
+#[cfg(not(feature = "portable-simd"))]
+pub(crate) fn interp_cubic<T>(x: T, yvals: &[T; 4]) -> T
 where
     T: Sample,
 {
@@ -213,14 +716,346 @@ where
     a0 + a1 * x + a2 * x2 + a3 * x3
 }
 
+/// Perform cubic polynomial interpolation to get value at x, using portable SIMD.
+/// Input points are assumed to be at x = -1, 0, 1, 2
+#[cfg(feature = "portable-simd")]
+pub(crate) fn interp_cubic<T>(x: T, yvals: &[T; 4]) -> T
+where
+    T: Sample,
+{
+    <T as PortableSample>::interp_cubic(x, yvals)
+}
+
 /// Linear interpolation between two points at x=0 and x=1
-fn interp_lin<T>(x: T, yvals: &[T; 2]) -> T
+pub(crate) fn interp_lin<T>(x: T, yvals: &[T; 2]) -> T
 where
     T: Sample,
 {
     (T::one() - x) * yvals[0] + x * yvals[1]
 }
 
+/// Get the steps between the positions of consecutive output frames,
+/// which are the inverses of the ratios. The last ratio is used for any remaining frames.
+/// A constant ratio gives a constant step, without any division or indexing per frame.
+pub(crate) fn index_steps(ratios: &[f64]) -> impl Iterator<Item = f64> + '_ {
+    let (last, curve) = ratios.split_last().expect("at least one ratio");
+    curve
+        .iter()
+        .map(|ratio| 1.0 / ratio)
+        .chain(iter::repeat(1.0 / last))
+}
+
+/// Interpolate the output frames of one channel from its buffer.
+/// The position of each frame is found by stepping from `last_index`
+/// by the inverse of the ratio for that frame. The last ratio is used for any remaining frames.
+#[allow(clippy::too_many_arguments)]
+fn interpolate_channel<T>(
+    interpolator: &dyn SincInterpolator<T>,
+    phases: PhaseIndex,
+    interpolation: InterpolationType,
+    preview: bool,
+    buf: &[T],
+    wave_out: &mut [T],
+    last_index: f64,
+    ratios: &[f64],
+) where
+    T: Sample,
+{
+    let sinc_len = interpolator.len();
+    let oversampling_factor = interpolator.nbr_sincs();
+    // the buffer starts with the history of two sinc lengths before the current chunk
+    let offset = 2 * sinc_len as isize;
+    let frames = wave_out.iter_mut().zip(index_steps(ratios));
+    let mut idx = last_index;
+    match interpolation {
+        _ if preview => {
+            for (out, step) in frames {
+                idx += step;
+                let nearest = get_nearest_sample(idx, sinc_len, oversampling_factor);
+                *out = buf[(nearest + offset) as usize];
+            }
+        }
+        InterpolationType::Cubic => {
+            let mut points = [T::zero(); 4];
+            let mut nearest = [(0isize, 0isize); 4];
+            for (out, step) in frames {
+                idx += step;
+                phases.nearest_times_4(idx, &mut nearest);
+                let pos = idx * oversampling_factor as f64;
+                let frac_offset = T::coerce(pos - pos.floor());
+                for (n, p) in nearest.iter().zip(points.iter_mut()) {
+                    *p = interpolator.get_sinc_interpolated(
+                        buf,
+                        (n.0 + offset) as usize,
+                        n.1 as usize,
+                    );
+                }
+                *out = interp_cubic(frac_offset, &points);
+            }
+        }
+        InterpolationType::Linear => {
+            let mut points = [T::zero(); 2];
+            let mut nearest = [(0isize, 0isize); 2];
+            for (out, step) in frames {
+                idx += step;
+                phases.nearest_times_2(idx, &mut nearest);
+                let pos = idx * oversampling_factor as f64;
+                let frac_offset = T::coerce(pos - pos.floor());
+                for (n, p) in nearest.iter().zip(points.iter_mut()) {
+                    *p = interpolator.get_sinc_interpolated(
+                        buf,
+                        (n.0 + offset) as usize,
+                        n.1 as usize,
+                    );
+                }
+                *out = interp_lin(frac_offset, &points);
+            }
+        }
+        InterpolationType::Nearest => {
+            for (out, step) in frames {
+                idx += step;
+                let nearest = phases.nearest_time(idx);
+                *out = interpolator.get_sinc_interpolated(
+                    buf,
+                    (nearest.0 + offset) as usize,
+                    nearest.1 as usize,
+                );
+            }
+        }
+    }
+}
+
+/// A warning that the resample ratio has been changed so far that the Nyquist frequency
+/// of the output is below the cutoff of the anti-aliasing filter,
+/// as returned by `aliasing_warning`.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct AliasingWarning {
+    /// The resample ratio that gives the warning.
+    pub ratio: f64,
+    /// The cutoff of the anti-aliasing filter, relative to the input Nyquist frequency.
+    /// This is the lowest ratio where the filter works as designed.
+    pub cutoff: f64,
+    /// How much higher the gain of the filter is at the Nyquist frequency of the output,
+    /// than at the cutoff, in dB.
+    pub excess_db: f64,
+}
+
+/// Get the gain in dB of the first sinc of the interpolator, at a `frequency`
+/// relative to the Nyquist frequency. The gain is normalized to the gain at DC.
+fn sinc_gain_db<T>(interpolator: &dyn SincInterpolator<T>, frequency: f64) -> f64
+where
+    T: Sample,
+{
+    let sinc_len = interpolator.len();
+    let mut wave = vec![T::zero(); sinc_len + 1];
+    let mut dc = 0.0;
+    let mut re = 0.0;
+    let mut im = 0.0;
+    for tap in 0..sinc_len {
+        wave[tap] = T::one();
+        let value = interpolator
+            .get_sinc_interpolated(&wave, 0, 0)
+            .to_f64()
+            .unwrap_or(0.0);
+        wave[tap] = T::zero();
+        let angle = std::f64::consts::PI * frequency * tap as f64;
+        dc += value;
+        re += value * angle.cos();
+        im -= value * angle.sin();
+    }
+    10.0 * ((re * re + im * im) / (dc * dc)).log10()
+}
+
+/// Calculate the resample ratio after `nbr_frames` frames of exponential smoothing
+/// towards the target ratio. The target is returned once the difference is negligible.
+fn smooth_ratio(ratio: f64, target: f64, time_constant: usize, nbr_frames: usize) -> f64 {
+    if time_constant == 0 {
+        return target;
+    }
+    let smoothed = target + (ratio - target) * (-(nbr_frames as f64) / time_constant as f64).exp();
+    if (smoothed - target).abs() < 1.0e-9 * target {
+        target
+    } else {
+        smoothed
+    }
+}
+
+/// Snap the ratio to a fraction for "new_rational", and adjust the parameters to match.
+fn snap_parameters(
+    resample_ratio: f64,
+    mut parameters: InterpolationParameters,
+    max_oversampling_factor: usize,
+    tolerance: f64,
+) -> (f64, InterpolationParameters) {
+    match snap_ratio(resample_ratio, max_oversampling_factor, tolerance) {
+        Some(snapped) => {
+            parameters.interpolation = InterpolationType::Nearest;
+            parameters.oversampling_factor = snapped.numerator;
+            (snapped.value(), parameters)
+        }
+        None => (resample_ratio, parameters),
+    }
+}
+
+/// Get the ratio between the sample rates for "new_from_rates",
+/// calculated from the reduced fraction to be as exact as an `f64` allows.
+fn rate_ratio(fs_in: usize, fs_out: usize) -> f64 {
+    let fraction = RationalRatio::from_rates(fs_in, fs_out);
+    debug!(
+        "Resample ratio from sample rates: {}/{}",
+        fraction.numerator, fraction.denominator
+    );
+    fraction.value()
+}
+
+/// Calculate a curve of resample ratios for the given number of frames,
+/// by smoothing the ratio towards the target, and scaling it with the factors of
+/// an optional low frequency oscillator.
+/// The ratios are limited to +-10% of the original ratio.
+/// A constant ratio gives a curve with a single value.
+/// The curve replaces the contents of `ratios`, which only allocates if it needs to grow.
+fn ratio_curve(
+    ratio: f64,
+    target: f64,
+    time_constant: usize,
+    lfo: Option<&Lfo>,
+    ratio_original: f64,
+    nbr_frames: usize,
+    ratios: &mut Vec<f64>,
+) {
+    ratios.clear();
+    if lfo.is_none() && ratio == target {
+        ratios.push(ratio);
+        return;
+    }
+    let mut factors = lfo.map(|lfo| lfo.factors(nbr_frames));
+    ratios.extend((0..nbr_frames).map(|n| {
+        let factor = factors
+            .as_mut()
+            .and_then(|factors| factors.next())
+            .unwrap_or(1.0);
+        (smooth_ratio(ratio, target, time_constant, n) * factor)
+            .max(0.9 * ratio_original)
+            .min(1.1 * ratio_original)
+    }));
+}
+
+/// Get the largest number of frames that `SincFixedIn` can return for a chunk,
+/// when the ratio is changed by at most +-10% from the original one.
+fn max_output_frames(chunk_size: usize, ratio_original: f64) -> usize {
+    (chunk_size as f64 * ratio_original * 1.1).ceil() as usize + 1
+}
+
+/// Check the ratio and sizes given to the constructors of the sinc resamplers.
+fn validate_sizes(
+    resample_ratio: f64,
+    chunk_size: usize,
+    nbr_channels: usize,
+) -> ResampleResult<()> {
+    ResampleError::check(
+        resample_ratio > 0.0 && resample_ratio.is_finite(),
+        "resample_ratio",
+        resample_ratio,
+        "must be a positive number",
+    )?;
+    ResampleError::check(
+        chunk_size > 0,
+        "chunk_size",
+        chunk_size,
+        "must be larger than zero",
+    )?;
+    ResampleError::check(
+        nbr_channels > 0,
+        "nbr_channels",
+        nbr_channels,
+        "must be larger than zero",
+    )
+}
+
+/// Check the parameters given to the constructors of the sinc resamplers.
+fn validate_parameters(
+    resample_ratio: f64,
+    parameters: &InterpolationParameters,
+    chunk_size: usize,
+    nbr_channels: usize,
+) -> ResampleResult<()> {
+    validate_sizes(resample_ratio, chunk_size, nbr_channels)?;
+    ResampleError::check(
+        parameters.sinc_len > 0,
+        "sinc_len",
+        parameters.sinc_len,
+        "must be larger than zero",
+    )?;
+    ResampleError::check(
+        parameters.f_cutoff > 0.0 && parameters.f_cutoff <= 1.0,
+        "f_cutoff",
+        parameters.f_cutoff,
+        "must be larger than zero and at most 1.0",
+    )?;
+    ResampleError::check(
+        parameters.oversampling_factor > 0,
+        "oversampling_factor",
+        parameters.oversampling_factor,
+        "must be larger than zero",
+    )?;
+    validate_window(parameters)
+}
+
+/// Check the parameters of the window function.
+fn validate_window(parameters: &InterpolationParameters) -> ResampleResult<()> {
+    match &parameters.window {
+        WindowFunction::Gaussian(sigma) => ResampleError::check(
+            *sigma > 0.0 && sigma.is_finite(),
+            "the sigma of the Gaussian window",
+            *sigma,
+            "must be a positive number",
+        ),
+        WindowFunction::CustomTaps(taps) => {
+            let sinc_len = 8 * (((parameters.sinc_len as f32) / 8.0).ceil() as usize);
+            ResampleError::check(
+                taps.len() == sinc_len || taps.len() == sinc_len * parameters.oversampling_factor,
+                "the number of custom window taps",
+                taps.len(),
+                "must be sinc_len rounded up to a multiple of 8, or that times oversampling_factor",
+            )
+        }
+        _ => Ok(()),
+    }
+}
+
+/// Check a prototype filter with `nbr_taps` taps, for `oversampling_factor` sincs.
+fn validate_prototype(nbr_taps: usize, oversampling_factor: usize) -> ResampleResult<()> {
+    ResampleError::check(
+        oversampling_factor > 0,
+        "oversampling_factor",
+        oversampling_factor,
+        "must be larger than zero",
+    )?;
+    let block = 8 * oversampling_factor;
+    ResampleError::check(
+        nbr_taps > 0 && nbr_taps / block * block == nbr_taps,
+        "the number of prototype taps",
+        nbr_taps,
+        "must be a multiple of 8 times oversampling_factor, and larger than zero",
+    )
+}
+
+/// Check a sinc table with `nbr_values` values, for sincs of length `sinc_len`.
+fn validate_table(nbr_values: usize, sinc_len: usize) -> ResampleResult<()> {
+    ResampleError::check(
+        sinc_len > 0 && sinc_len / 8 * 8 == sinc_len,
+        "sinc_len",
+        sinc_len,
+        "must be a multiple of 8, and larger than zero",
+    )?;
+    ResampleError::check(
+        nbr_values > 0 && nbr_values / sinc_len * sinc_len == nbr_values,
+        "the number of table values",
+        nbr_values,
+        "must be a multiple of sinc_len, and larger than zero",
+    )
+}
+
 impl<T> SincFixedIn<T>
 where
     T: Sample,
@@ -232,441 +1067,1158 @@ where
     /// - `parameters`: Parameters for interpolation, see `InterpolationParameters`
     /// - `chunk_size`: size of input data in frames
     /// - `nbr_channels`: number of channels in input/output
+    ///
+    /// # Errors
+    ///
+    /// The function returns `ResampleError::InvalidParameter` if the resample ratio is not a positive number,
+    /// if `sinc_len`, `oversampling_factor`, `chunk_size` or `nbr_channels` is zero,
+    /// if `f_cutoff` is not larger than zero and at most 1.0,
+    /// if the sigma of a Gaussian window is not a positive number,
+    /// or if a custom window has the wrong number of taps.
     pub fn new(
         resample_ratio: f64,
         parameters: InterpolationParameters,
         chunk_size: usize,
         nbr_channels: usize,
-    ) -> Self {
+    ) -> ResampleResult<Self> {
+        Self::new_with_kernel(
+            resample_ratio,
+            parameters,
+            chunk_size,
+            nbr_channels,
+            make_interpolator_from_sincs,
+        )
+    }
+
+    /// Create a new SincFixedIn like `new`, with the interpolator made from the sincs by `make_kernel`
+    /// instead of the kernel chosen at runtime.
+    pub(crate) fn new_with_kernel<F>(
+        resample_ratio: f64,
+        parameters: InterpolationParameters,
+        chunk_size: usize,
+        nbr_channels: usize,
+        make_kernel: F,
+    ) -> ResampleResult<Self>
+    where
+        F: FnOnce(Vec<Vec<T>>) -> Box<dyn SincInterpolator<T>>,
+    {
+        validate_parameters(resample_ratio, &parameters, chunk_size, nbr_channels)?;
         debug!(
             "Create new SincFixedIn, ratio: {}, chunk_size: {}, channels: {}, parameters: {:?}",
             resample_ratio, chunk_size, nbr_channels, parameters
         );
-
-        let interpolator = make_interpolator(
-            parameters.sinc_len,
-            resample_ratio,
-            parameters.f_cutoff,
+        let cutoff = design_cutoff(resample_ratio, parameters.f_cutoff);
+        let sinc_len = 8 * (((parameters.sinc_len as f32) / 8.0).ceil() as usize);
+        let sincs = make_sincs(
+            sinc_len,
             parameters.oversampling_factor,
+            cutoff,
             parameters.window,
         );
+        let interpolator = make_kernel(sincs);
 
-        Self::new_with_interpolator(
+        let mut resampler = Self::build(
             resample_ratio,
             parameters.interpolation,
             interpolator,
             chunk_size,
             nbr_channels,
-        )
+        );
+        resampler.cutoff = Some(cutoff as f64);
+        Ok(resampler)
     }
 
-    /// Create a new SincFixedIn using an existing Interpolator
+    /// Create a new SincFixedIn without anti-aliasing when downsampling.
+    ///
+    /// Normally the cutoff frequency of the interpolation filter is scaled down by the
+    /// resample ratio when downsampling, to remove everything above the new Nyquist frequency.
+    /// With this constructor the cutoff is relative to the input sample rate for all ratios,
+    /// so the full bandwidth of the input is kept. This avoids needless rolloff when the input
+    /// is already band limited, as is often the case for non-audio data like envelopes or
+    /// sensor readings. Any content above the new Nyquist frequency is aliased.
     ///
     /// Parameters are:
     /// - `resample_ratio`: Ratio between output and input sample rates.
-    /// - `interpolation_type`: Parameters for interpolation, see `InterpolationParameters`
-    /// - `interpolator`:  The interpolator to use
-    /// - `chunk_size`: size of output data in frames
+    /// - `parameters`: Parameters for interpolation, see `InterpolationParameters`
+    /// - `chunk_size`: size of input data in frames
     /// - `nbr_channels`: number of channels in input/output
-    pub fn new_with_interpolator(
+    ///
+    /// # Errors
+    ///
+    /// The function returns `ResampleError::InvalidParameter` for the same parameters as `new`.
+    pub fn new_without_anti_aliasing(
         resample_ratio: f64,
-        interpolation_type: InterpolationType,
-        interpolator: Box<dyn SincInterpolator<T>>,
+        parameters: InterpolationParameters,
         chunk_size: usize,
         nbr_channels: usize,
-    ) -> Self {
-        let buffer = vec![vec![T::zero(); chunk_size + 2 * interpolator.len()]; nbr_channels];
-
-        SincFixedIn {
-            nbr_channels,
-            chunk_size,
-            last_index: -((interpolator.len() / 2) as f64),
-            resample_ratio,
-            resample_ratio_original: resample_ratio,
-            interpolator,
-            buffer,
-            interpolation: interpolation_type,
-        }
-    }
-}
+    ) -> ResampleResult<Self> {
+        validate_parameters(resample_ratio, &parameters, chunk_size, nbr_channels)?;
+        debug!(
+            "Create new SincFixedIn without anti-aliasing, ratio: {}, chunk_size: {}, channels: {}, parameters: {:?}",
+            resample_ratio, chunk_size, nbr_channels, parameters
+        );
+        // a ratio of 1.0 keeps the cutoff unscaled
+        let cutoff = parameters.f_cutoff;
+        let interpolator = make_interpolator(
+            parameters.sinc_len,
+            1.0,
+            parameters.f_cutoff,
+            parameters.oversampling_factor,
+            parameters.window,
+        );
+        let mut resampler = Self::build(
+            resample_ratio,
+            parameters.interpolation,
+            interpolator,
+            chunk_size,
+            nbr_channels,
+        );
+        resampler.cutoff = Some(cutoff as f64);
+        Ok(resampler)
+    }
 
-impl<T> Resampler<T> for SincFixedIn<T>
-where
-    T: Sample,
-{
-    /// Resample a chunk of audio. The input length is fixed, and the output varies in length.
-    /// If the waveform for a channel is empty, this channel will be ignored and produce a
-    /// corresponding empty output waveform.
+    /// Create a new SincFixedIn with a user filter, for example an equalizer or a de-emphasis filter,
+    /// fused into the anti-aliasing filter.
+    ///
+    /// The user filter is an FIR filter at the input sample rate, given by its taps.
+    /// It is convolved with the windowed sinc when the resampler is created,
+    /// so that the filtering is done in the same pass as the resampling.
+    /// This makes the sinc longer by the length of the user filter, rounded up to a multiple of 8.
+    /// The combined filter is centered like the sinc, so a symmetric user filter adds no delay.
+    /// The gain of the user filter is kept, unlike for a prototype filter.
+    ///
+    /// Parameters are:
+    /// - `resample_ratio`: Ratio between output and input sample rates.
+    /// - `parameters`: Parameters for interpolation, see `InterpolationParameters`
+    /// - `taps`: Taps of the user filter.
+    /// - `chunk_size`: size of input data in frames
+    /// - `nbr_channels`: number of channels in input/output
+    ///
     /// # Errors
     ///
-    /// The function returns an error if the length of the input data is not equal
-    /// to the number of channels and chunk size defined when creating the instance.
-    fn process(&mut self, wave_in: &[Vec<T>]) -> ResampleResult<Vec<Vec<T>>> {
-        if wave_in.len() != self.nbr_channels {
-            return Err(ResampleError::WrongNumberOfChannels {
-                expected: self.nbr_channels,
-                actual: wave_in.len(),
-            });
-        }
-        let mut used_channels = Vec::new();
-        for (chan, wave) in wave_in.iter().enumerate() {
-            if !wave.is_empty() {
-                used_channels.push(chan);
-                if wave.len() != self.chunk_size {
-                    return Err(ResampleError::WrongNumberOfFrames {
-                        channel: chan,
-                        expected: self.chunk_size,
-                        actual: wave.len(),
-                    });
-                }
-            }
-        }
-        let sinc_len = self.interpolator.len();
-        let oversampling_factor = self.interpolator.nbr_sincs();
-        let t_ratio = 1.0 / self.resample_ratio as f64;
-        let end_idx = self.chunk_size as isize - (sinc_len as isize + 1) - t_ratio.ceil() as isize;
-        //update buffer with new data
-        for wav in self.buffer.iter_mut() {
-            for idx in 0..(2 * sinc_len) {
-                wav[idx] = wav[idx + self.chunk_size];
-            }
-        }
-
-        let mut wave_out = vec![Vec::new(); self.nbr_channels];
-
-        for chan in used_channels.iter() {
-            for (idx, sample) in wave_in[*chan].iter().enumerate() {
-                self.buffer[*chan][idx + 2 * sinc_len] = *sample;
-            }
-            wave_out[*chan] =
-                vec![T::zero(); (self.chunk_size as f64 * self.resample_ratio + 10.0) as usize];
-        }
-
-        let mut idx = self.last_index;
-
-        let mut n = 0;
-
-        match self.interpolation {
-            InterpolationType::Cubic => {
-                let mut points = [T::zero(); 4];
-                let mut nearest = [(0isize, 0isize); 4];
-                while idx < end_idx as f64 {
-                    idx += t_ratio;
-                    get_nearest_times_4(idx, oversampling_factor as isize, &mut nearest);
-                    let frac = idx * oversampling_factor as f64
-                        - (idx * oversampling_factor as f64).floor();
-                    let frac_offset = T::coerce(frac);
-                    for chan in used_channels.iter() {
-                        let buf = &self.buffer[*chan];
-                        for (n, p) in nearest.iter().zip(points.iter_mut()) {
-                            *p = self.interpolator.get_sinc_interpolated(
-                                &buf,
-                                (n.0 + 2 * sinc_len as isize) as usize,
-                                n.1 as usize,
-                            );
-                        }
-                        wave_out[*chan][n] = interp_cubic(frac_offset, &points);
-                    }
-                    n += 1;
-                }
-            }
-            InterpolationType::Linear => {
-                let mut points = [T::zero(); 2];
-                let mut nearest = [(0isize, 0isize); 2];
-                while idx < end_idx as f64 {
-                    idx += t_ratio;
-                    get_nearest_times_2(idx, oversampling_factor as isize, &mut nearest);
-                    let frac = idx * oversampling_factor as f64
-                        - (idx * oversampling_factor as f64).floor();
-                    let frac_offset = T::coerce(frac);
-                    for chan in used_channels.iter() {
-                        let buf = &self.buffer[*chan];
-                        for (n, p) in nearest.iter().zip(points.iter_mut()) {
-                            *p = self.interpolator.get_sinc_interpolated(
-                                &buf,
-                                (n.0 + 2 * sinc_len as isize) as usize,
-                                n.1 as usize,
-                            );
-                        }
-                        wave_out[*chan][n] = interp_lin(frac_offset, &points);
-                    }
-                    n += 1;
-                }
-            }
-            InterpolationType::Nearest => {
-                let mut point;
-                let mut nearest;
-                while idx < end_idx as f64 {
-                    idx += t_ratio;
-                    nearest = get_nearest_time(idx, oversampling_factor as isize);
-                    for chan in used_channels.iter() {
-                        let buf = &self.buffer[*chan];
-                        point = self.interpolator.get_sinc_interpolated(
-                            &buf,
-                            (nearest.0 + 2 * sinc_len as isize) as usize,
-                            nearest.1 as usize,
-                        );
-                        wave_out[*chan][n] = point;
-                    }
-                    n += 1;
-                }
-            }
-        }
-
-        // store last index for next iteration
-        self.last_index = idx - self.chunk_size as f64;
-        for chan in used_channels.iter() {
-            //for w in wave_out.iter_mut() {
-            wave_out[*chan].truncate(n);
-        }
-        trace!(
-            "Resampling channels {:?}, {} frames in, {} frames out",
-            used_channels,
-            self.chunk_size,
-            n,
+    /// The function returns `ResampleError::InvalidParameter` for the same parameters as `new`,
+    /// and if `taps` is empty.
+    pub fn new_with_filter(
+        resample_ratio: f64,
+        parameters: InterpolationParameters,
+        taps: &[T],
+        chunk_size: usize,
+        nbr_channels: usize,
+    ) -> ResampleResult<Self> {
+        validate_parameters(resample_ratio, &parameters, chunk_size, nbr_channels)?;
+        ResampleError::check(
+            !taps.is_empty(),
+            "the number of filter taps",
+            taps.len(),
+            "must be larger than zero",
+        )?;
+        debug!(
+            "Create new SincFixedIn with a user filter, ratio: {}, taps: {}, parameters: {:?}",
+            resample_ratio,
+            taps.len(),
+            parameters
+        );
+        let cutoff = design_cutoff(resample_ratio, parameters.f_cutoff);
+        let interpolator = make_interpolator_with_filter(&parameters, resample_ratio, taps);
+        let mut resampler = Self::build(
+            resample_ratio,
+            parameters.interpolation,
+            interpolator,
+            chunk_size,
+            nbr_channels,
         );
-        Ok(wave_out)
+        resampler.cutoff = Some(cutoff as f64);
+        Ok(resampler)
     }
 
-    /// Query for the number of frames needed for the next call to "process".
-    /// Will always return the chunk_size defined when creating the instance.
-    fn nbr_frames_needed(&self) -> usize {
-        self.chunk_size
+    /// Create a new SincFixedIn that snaps the ratio to a nearby fraction, for exact resampling.
+    ///
+    /// If `snap_ratio` finds a fraction L/M within `tolerance` of `resample_ratio`,
+    /// with L at most `max_oversampling_factor`, the resampler runs at the ratio L/M,
+    /// using `Nearest` interpolation with an oversampling factor of L.
+    /// Every output frame then falls exactly on an intermediate point,
+    /// and there is no interpolation error, just like for a synchronous resampler.
+    /// Otherwise the ratio and parameters are used as given.
+    /// Use "rational_ratio" to check if the ratio was snapped, and to get the fraction.
+    ///
+    /// Parameters are:
+    /// - `resample_ratio`: Ratio between output and input sample rates.
+    /// - `parameters`: Parameters for interpolation, see `InterpolationParameters`
+    /// - `max_oversampling_factor`: Largest numerator L to consider.
+    /// - `tolerance`: Largest accepted relative difference between the fraction and `resample_ratio`.
+    /// - `chunk_size`: size of input data in frames
+    /// - `nbr_channels`: number of channels in input/output
+    ///
+    /// # Errors
+    ///
+    /// The function returns `ResampleError::InvalidParameter` for the same parameters as `new`.
+    pub fn new_rational(
+        resample_ratio: f64,
+        parameters: InterpolationParameters,
+        max_oversampling_factor: usize,
+        tolerance: f64,
+        chunk_size: usize,
+        nbr_channels: usize,
+    ) -> ResampleResult<Self> {
+        validate_parameters(resample_ratio, &parameters, chunk_size, nbr_channels)?;
+        let (resample_ratio, parameters) = snap_parameters(
+            resample_ratio,
+            parameters,
+            max_oversampling_factor,
+            tolerance,
+        );
+        Self::new(resample_ratio, parameters, chunk_size, nbr_channels)
     }
 
-    /// Update the resample ratio. New value must be within +-10% of the original one
-    fn set_resample_ratio(&mut self, new_ratio: f64) -> ResampleResult<()> {
-        trace!("Change resample ratio to {}", new_ratio);
-        if (new_ratio / self.resample_ratio_original > 0.9)
-            && (new_ratio / self.resample_ratio_original < 1.1)
-        {
-            self.resample_ratio = new_ratio;
-            Ok(())
-        } else {
-            Err(ResampleError::BadRatioUpdate)
-        }
-    }
-    /// Update the resample ratio relative to the original one
-    fn set_resample_ratio_relative(&mut self, rel_ratio: f64) -> ResampleResult<()> {
-        let new_ratio = self.resample_ratio_original * rel_ratio;
-        self.set_resample_ratio(new_ratio)
+    /// Create a new SincFixedIn for resampling from `fs_in` to `fs_out`.
+    ///
+    /// The ratio is calculated from the reduced fraction L/M of the sample rates,
+    /// and the parameters are used as given, so the ratio can be adjusted like for "new".
+    /// The resampling is exact when the parameters use `Nearest` interpolation
+    /// with an oversampling factor that is a multiple of L, which "rational_ratio" then reports.
+    ///
+    /// Parameters are:
+    /// - `fs_in`: Input sample rate.
+    /// - `fs_out`: Output sample rate.
+    /// - `parameters`: Parameters for interpolation, see `InterpolationParameters`
+    /// - `chunk_size`: size of input data in frames
+    /// - `nbr_channels`: number of channels in input/output
+    ///
+    /// # Errors
+    ///
+    /// The function returns `ResampleError::InvalidParameter` if a sample rate is zero,
+    /// and for the same parameters as `new`.
+    pub fn new_from_rates(
+        fs_in: usize,
+        fs_out: usize,
+        parameters: InterpolationParameters,
+        chunk_size: usize,
+        nbr_channels: usize,
+    ) -> ResampleResult<Self> {
+        ResampleError::check(fs_in > 0, "fs_in", fs_in, "must be larger than zero")?;
+        ResampleError::check(fs_out > 0, "fs_out", fs_out, "must be larger than zero")?;
+        Self::new(
+            rate_ratio(fs_in, fs_out),
+            parameters,
+            chunk_size,
+            nbr_channels,
+        )
     }
-}
 
-impl<T> SincFixedOut<T>
-where
-    T: Sample,
-{
-    /// Create a new SincFixedOut
+    /// Create a new SincFixedIn using a prototype filter designed by the user,
+    /// instead of the windowed sinc made from `InterpolationParameters`.
+    ///
+    /// The prototype is the impulse response of the anti-aliasing filter at the oversampled rate,
+    /// with `sinc_len * oversampling_factor` taps, where `sinc_len` must be a multiple of 8.
+    /// It is normalized to unity gain at zero frequency, so the scaling of the taps does not matter.
+    /// Note that the cutoff is not adjusted for the resample ratio,
+    /// so for downsampling the prototype must be designed for the output sample rate.
     ///
     /// Parameters are:
     /// - `resample_ratio`: Ratio between output and input sample rates.
-    /// - `parameters`: Parameters for interpolation, see `InterpolationParameters`
-    /// - `chunk_size`: size of output data in frames
+    /// - `prototype`: Taps of the prototype filter.
+    /// - `oversampling_factor`: Number of intermediate points per input sample.
+    /// - `interpolation_type`: Type of interpolation between the intermediate points.
+    /// - `chunk_size`: size of input data in frames
     /// - `nbr_channels`: number of channels in input/output
-    pub fn new(
+    ///
+    /// # Errors
+    ///
+    /// The function returns `ResampleError::InvalidParameter` if the resample ratio is not a positive number,
+    /// if `oversampling_factor`, `chunk_size` or `nbr_channels` is zero,
+    /// or if the number of taps of the prototype is not a non-zero multiple of 8 times `oversampling_factor`.
+    pub fn new_from_prototype(
         resample_ratio: f64,
-        parameters: InterpolationParameters,
+        prototype: &[T],
+        oversampling_factor: usize,
+        interpolation_type: InterpolationType,
         chunk_size: usize,
         nbr_channels: usize,
-    ) -> Self {
+    ) -> ResampleResult<Self> {
+        validate_sizes(resample_ratio, chunk_size, nbr_channels)?;
+        validate_prototype(prototype.len(), oversampling_factor)?;
         debug!(
-            "Create new SincFixedIn, ratio: {}, chunk_size: {}, channels: {}, parameters: {:?}",
-            resample_ratio, chunk_size, nbr_channels, parameters
-        );
-        let interpolator = make_interpolator(
-            parameters.sinc_len,
+            "Create new SincFixedIn from prototype, ratio: {}, taps: {}, oversampling: {}",
             resample_ratio,
-            parameters.f_cutoff,
-            parameters.oversampling_factor,
-            parameters.window,
+            prototype.len(),
+            oversampling_factor
         );
+        let interpolator = make_interpolator_from_prototype(prototype, oversampling_factor);
+        Ok(Self::build(
+            resample_ratio,
+            interpolation_type,
+            interpolator,
+            chunk_size,
+            nbr_channels,
+        ))
+    }
 
-        Self::new_with_interpolator(
+    /// Create a new SincFixedIn using precalculated sinc tables,
+    /// for example generated at compile time with `sinc_table_source`.
+    /// This avoids calculating the sincs when creating the resampler.
+    /// The table is still copied once, into the layout of the fastest available kernel,
+    /// and the buffers of the resampler are allocated, which takes time and memory
+    /// in proportion to the size of the table. To use a static table in place,
+    /// give an `interpolator_table::TableInterpolator` to `new_with_interpolator`.
+    ///
+    /// The table holds all the sincs one after the other, each `sinc_len` long,
+    /// where `sinc_len` must be a multiple of 8. The number of sincs gives the oversampling factor.
+    /// The table is used as is, so it must be made for the given resample ratio.
+    ///
+    /// Parameters are:
+    /// - `resample_ratio`: Ratio between output and input sample rates.
+    /// - `table`: The sinc table.
+    /// - `sinc_len`: Length of each sinc in the table.
+    /// - `interpolation_type`: Type of interpolation between the intermediate points.
+    /// - `chunk_size`: size of input data in frames
+    /// - `nbr_channels`: number of channels in input/output
+    ///
+    /// # Errors
+    ///
+    /// The function returns `ResampleError::InvalidParameter` if the resample ratio is not a positive number,
+    /// if `chunk_size` or `nbr_channels` is zero, if `sinc_len` is not a non-zero multiple of 8,
+    /// or if the length of the table is not a non-zero multiple of `sinc_len`.
+    pub fn new_from_table(
+        resample_ratio: f64,
+        table: &[T],
+        sinc_len: usize,
+        interpolation_type: InterpolationType,
+        chunk_size: usize,
+        nbr_channels: usize,
+    ) -> ResampleResult<Self> {
+        validate_sizes(resample_ratio, chunk_size, nbr_channels)?;
+        validate_table(table.len(), sinc_len)?;
+        debug!(
+            "Create new SincFixedIn from table, ratio: {}, sinc_len: {}, values: {}",
             resample_ratio,
-            parameters.interpolation,
+            sinc_len,
+            table.len()
+        );
+        let interpolator = make_interpolator_from_table(table, sinc_len);
+        Ok(Self::build(
+            resample_ratio,
+            interpolation_type,
             interpolator,
             chunk_size,
             nbr_channels,
-        )
+        ))
     }
 
-    /// Create a new SincFixedOut using an existing Interpolator
+    /// Create a new SincFixedIn using an existing Interpolator
     ///
     /// Parameters are:
     /// - `resample_ratio`: Ratio between output and input sample rates.
     /// - `interpolation_type`: Parameters for interpolation, see `InterpolationParameters`
     /// - `interpolator`:  The interpolator to use
-    /// - `chunk_size`: size of output data in frames
+    /// - `chunk_size`: size of input data in frames
     /// - `nbr_channels`: number of channels in input/output
+    ///
+    /// # Errors
+    ///
+    /// The function returns `ResampleError::InvalidParameter` if the resample ratio is not a positive number,
+    /// or if `chunk_size` or `nbr_channels` is zero.
     pub fn new_with_interpolator(
         resample_ratio: f64,
         interpolation_type: InterpolationType,
         interpolator: Box<dyn SincInterpolator<T>>,
         chunk_size: usize,
         nbr_channels: usize,
+    ) -> ResampleResult<Self> {
+        validate_sizes(resample_ratio, chunk_size, nbr_channels)?;
+        Ok(Self::build(
+            resample_ratio,
+            interpolation_type,
+            interpolator,
+            chunk_size,
+            nbr_channels,
+        ))
+    }
+
+    /// Create a new SincFixedIn from an interpolator, with parameters that are already validated.
+    fn build(
+        resample_ratio: f64,
+        interpolation_type: InterpolationType,
+        interpolator: Box<dyn SincInterpolator<T>>,
+        chunk_size: usize,
+        nbr_channels: usize,
     ) -> Self {
-        let needed_input_size =
-            (chunk_size as f64 / resample_ratio).ceil() as usize + 2 + interpolator.len() / 2;
-        let buffer =
-            vec![vec![T::zero(); 3 * needed_input_size / 2 + 2 * interpolator.len()]; nbr_channels];
+        let buffer_len = BUFFER_CHUNKS * chunk_size + 2 * interpolator.len();
+        let buffer = vec![vec![T::zero(); buffer_len]; nbr_channels];
+        // reserve room for the largest possible output, to not allocate while processing
+        let max_frames_out = max_output_frames(chunk_size, resample_ratio);
+        let mut ratios = Vec::with_capacity(max_frames_out);
+        ratios.push(resample_ratio);
 
-        SincFixedOut {
+        SincFixedIn {
             nbr_channels,
             chunk_size,
-            needed_input_size,
             last_index: -((interpolator.len() / 2) as f64),
-            current_buffer_fill: needed_input_size,
             resample_ratio,
             resample_ratio_original: resample_ratio,
-            interpolator,
+            target_ratio: resample_ratio,
+            ratio_smoothing: 0,
+            lanes: ChannelLanes::new(
+                interpolator.as_ref(),
+                nbr_channels,
+                chunk_size + 2 * interpolator.len(),
+                max_frames_out,
+            ),
+            convolution: FftConvolution::new(
+                interpolator.as_ref(),
+                DEFAULT_CONVOLUTION_THRESHOLD,
+                buffer_len,
+                max_frames_out,
+            ),
+            phases: PhaseIndex::new(interpolator.nbr_sincs()),
+            interpolator: Arc::from(interpolator),
+            cutoff: None,
             buffer,
+            buffer_start: 0,
             interpolation: interpolation_type,
+            preview: false,
+            reverse: false,
+            modulation: None,
+            meter: Meter::new(nbr_channels),
+            silence_threshold: None,
+            parallel_threshold: DEFAULT_PARALLEL_THRESHOLD,
+            normalizer: Normalizer::new(),
+            output_buffer: (0..nbr_channels)
+                .map(|_| Vec::with_capacity(max_frames_out))
+                .collect(),
+            ratios,
+            channels: ChannelLists::new(nbr_channels),
         }
     }
-}
 
-impl<T> Resampler<T> for SincFixedOut<T>
-where
-    T: Sample,
-{
-    /// Query for the number of frames needed for the next call to "process".
-    fn nbr_frames_needed(&self) -> usize {
-        self.needed_input_size
+    /// Enable or disable the preview mode.
+    /// In preview mode, each output sample is simply a copy of the nearest input sample,
+    /// without any filtering or interpolation. This is very cheap but gives poor quality,
+    /// and is intended for things like scrubbing, where responsiveness matters more than quality.
+    /// The mode can be switched at any time, the delay is the same in both modes.
+    pub fn set_preview_mode(&mut self, preview: bool) {
+        trace!("Set preview mode to {}", preview);
+        self.preview = preview;
     }
 
-    /// Resample a chunk of audio. The required input length is provided by
-    /// the "nbr_frames_needed" function, and the output length is fixed.
-    /// If the waveform for a channel is empty, this channel will be ignored and produce a
-    /// corresponding empty output waveform.
-    /// # Errors
+    /// Check if the preview mode is enabled.
+    pub fn is_preview_mode(&self) -> bool {
+        self.preview
+    }
+
+    /// Enable or disable reverse playback.
+    /// In reverse mode, each chunk of input is expected to hold the frames that precede
+    /// the previous chunk, in normal forward order. The resampler reverses each chunk internally
+    /// and produces output that plays the input backwards.
+    /// Since the sinc filters are symmetric, the anti-aliasing is the same in both directions.
     ///
-    /// The function returns an error if the length of the input data is not
-    /// equal to the number of channels defined when creating the instance,
-    /// and the number of audio frames given by "nbr_frames_needed".
-    fn process(&mut self, wave_in: &[Vec<T>]) -> ResampleResult<Vec<Vec<T>>> {
-        //update buffer with new data
-        if wave_in.len() != self.nbr_channels {
-            return Err(ResampleError::WrongNumberOfChannels {
-                expected: self.nbr_channels,
-                actual: wave_in.len(),
-            });
-        }
-        let sinc_len = self.interpolator.len();
-        let oversampling_factor = self.interpolator.nbr_sincs();
-        let mut used_channels = Vec::new();
-        for (chan, wave) in wave_in.iter().enumerate() {
-            if !wave.is_empty() {
-                used_channels.push(chan);
-                if wave.len() != self.needed_input_size {
-                    return Err(ResampleError::WrongNumberOfFrames {
-                        channel: chan,
-                        expected: self.needed_input_size,
-                        actual: wave.len(),
-                    });
-                }
-            }
-        }
-        for wav in self.buffer.iter_mut() {
-            for idx in 0..(2 * sinc_len) {
-                wav[idx] = wav[idx + self.current_buffer_fill];
-            }
-        }
-        self.current_buffer_fill = self.needed_input_size;
+    /// The direction can be changed at any time. The frames already in the internal buffer
+    /// are then played back as a turnaround, like when reversing a tape.
+    pub fn set_reverse(&mut self, reverse: bool) {
+        trace!("Set reverse playback to {}", reverse);
+        self.reverse = reverse;
+    }
 
-        let mut wave_out = vec![Vec::new(); self.nbr_channels];
+    /// Check if reverse playback is enabled.
+    pub fn is_reverse(&self) -> bool {
+        self.reverse
+    }
 
-        for chan in used_channels.iter() {
-            for (idx, sample) in wave_in[*chan].iter().enumerate() {
-                self.buffer[*chan][idx + 2 * sinc_len] = *sample;
-            }
-            wave_out[*chan] = vec![T::zero(); self.chunk_size];
-        }
-
-        let mut idx = self.last_index;
-        let t_ratio = 1.0 / self.resample_ratio as f64;
-
-        match self.interpolation {
-            InterpolationType::Cubic => {
-                let mut points = [T::zero(); 4];
-                let mut nearest = [(0isize, 0isize); 4];
-                for n in 0..self.chunk_size {
-                    idx += t_ratio;
-                    get_nearest_times_4(idx, oversampling_factor as isize, &mut nearest);
-                    let frac = idx * oversampling_factor as f64
-                        - (idx * oversampling_factor as f64).floor();
-                    let frac_offset = T::coerce(frac);
-                    for chan in used_channels.iter() {
-                        let buf = &self.buffer[*chan];
-                        for (n, p) in nearest.iter().zip(points.iter_mut()) {
-                            *p = self.interpolator.get_sinc_interpolated(
-                                &buf,
-                                (n.0 + 2 * sinc_len as isize) as usize,
-                                n.1 as usize,
-                            );
-                        }
-                        wave_out[*chan][n] = interp_cubic(frac_offset, &points);
-                    }
-                }
-            }
-            InterpolationType::Linear => {
-                let mut points = [T::zero(); 2];
-                let mut nearest = [(0isize, 0isize); 2];
-                for n in 0..self.chunk_size {
-                    idx += t_ratio;
-                    get_nearest_times_2(idx, oversampling_factor as isize, &mut nearest);
-                    let frac = idx * oversampling_factor as f64
-                        - (idx * oversampling_factor as f64).floor();
-                    let frac_offset = T::coerce(frac);
-                    for chan in used_channels.iter() {
-                        let buf = &self.buffer[*chan];
-                        for (n, p) in nearest.iter().zip(points.iter_mut()) {
-                            *p = self.interpolator.get_sinc_interpolated(
-                                &buf,
-                                (n.0 + 2 * sinc_len as isize) as usize,
-                                n.1 as usize,
-                            );
-                        }
-                        wave_out[*chan][n] = interp_lin(frac_offset, &points);
-                    }
-                }
-            }
-            InterpolationType::Nearest => {
-                let mut point;
-                let mut nearest;
-                for n in 0..self.chunk_size {
-                    idx += t_ratio;
-                    nearest = get_nearest_time(idx, oversampling_factor as isize);
-                    for chan in used_channels.iter() {
-                        let buf = &self.buffer[*chan];
-                        point = self.interpolator.get_sinc_interpolated(
-                            &buf,
-                            (nearest.0 + 2 * sinc_len as isize) as usize,
-                            nearest.1 as usize,
-                        );
-                        wave_out[*chan][n] = point;
-                    }
-                }
-            }
-        }
+    /// Enable or disable peak metering of the input.
+    /// When enabled, the peak levels are measured while the input is copied
+    /// into the internal buffer, and can be read with "input_peaks" after each call to "process".
+    pub fn set_peak_metering(&mut self, enabled: bool) {
+        trace!("Set peak metering to {}", enabled);
+        self.meter.set_peak_enabled(enabled);
+    }
 
-        let prev_input_len = self.needed_input_size;
-        // store last index for next iteration
-        self.last_index = idx - self.current_buffer_fill as f64;
-        self.needed_input_size = (self.last_index as f32
-            + self.chunk_size as f32 / self.resample_ratio as f32
-            + sinc_len as f32)
-            .ceil() as usize
-            + 2;
-        trace!(
-            "Resampling channels {:?}, {} frames in, {} frames out. Next needed length: {} frames, last index {}",
-            used_channels,
-            prev_input_len,
-            self.chunk_size,
-            self.needed_input_size,
-            self.last_index
+    /// Get the peak absolute values of the input of the last call to "process", one per channel.
+    /// The values are zero for channels that were not processed, and when metering is disabled.
+    pub fn input_peaks(&self) -> &[T] {
+        self.meter.peaks()
+    }
+
+    /// Enable or disable RMS metering of the input.
+    /// The RMS value of each channel is calculated per call to "process", and can be read with "metrics".
+    pub fn set_rms_metering(&mut self, enabled: bool) {
+        trace!("Set RMS metering to {}", enabled);
+        self.meter.set_rms_enabled(enabled);
+    }
+
+    /// Enable loudness metering of the input, or disable it by passing `None`.
+    /// The value is the sample rate of the input, needed for the K-weighting filter.
+    /// The loudness is accumulated over all calls to "process" until the metering
+    /// is disabled or enabled again, and can be read with "metrics".
+    pub fn set_loudness_metering(&mut self, sample_rate: Option<f64>) {
+        trace!("Set loudness metering to {:?}", sample_rate);
+        self.meter.set_loudness_sample_rate(sample_rate);
+    }
+
+    /// Get the measurements from the enabled input meters.
+    pub fn metrics(&self) -> &Metrics<T> {
+        self.meter.metrics()
+    }
+
+    /// Enable skipping of silent input, by setting a threshold for what is considered silence.
+    /// Channels where all the samples currently in the internal buffer are at or below the
+    /// threshold produce silent output, without running the sinc interpolation.
+    /// Since the buffer also holds the tail of the previous chunks,
+    /// the output fades out normally when the input becomes silent.
+    /// A threshold of zero only skips input that is exactly zero,
+    /// and gives the same output as without skipping.
+    /// Pass `None` to disable the skipping, which is the default.
+    pub fn set_silence_threshold(&mut self, threshold: Option<T>) {
+        trace!("Set silence threshold to {:?}", threshold);
+        self.silence_threshold = threshold;
+    }
+
+    /// Set the number of channels from which the channels are resampled in parallel,
+    /// on the rayon thread pool. The default is 8 channels.
+    /// For fewer channels, the overhead of distributing the work is larger than the gain.
+    /// This only has an effect when the `parallel` feature is enabled.
+    pub fn set_parallel_threshold(&mut self, nbr_channels: usize) {
+        trace!("Set parallel threshold to {} channels", nbr_channels);
+        self.parallel_threshold = nbr_channels;
+    }
+
+    /// Set the sinc length from which the interpolated points may be calculated
+    /// by FFT convolution instead of one by one. The default is 2048.
+    /// The convolution is only used for the sincs where it is cheaper,
+    /// which are those that are needed for many points of a chunk.
+    /// It pays off for long chunks and low oversampling factors.
+    /// Preparing it takes some time, and stores the spectra of the sincs.
+    /// Pass `usize::MAX` to always calculate the points one by one.
+    pub fn set_convolution_threshold(&mut self, sinc_len: usize) {
+        trace!("Set convolution threshold to {}", sinc_len);
+        let buffer_len = self.buffer.first().map(|wav| wav.len()).unwrap_or(0);
+        self.convolution = FftConvolution::new(
+            self.interpolator.as_ref(),
+            sinc_len,
+            buffer_len,
+            max_output_frames(self.chunk_size, self.resample_ratio_original),
+        );
+    }
+
+    /// Enable automatic gain of the output, or disable it by passing `None`.
+    /// The gain is applied while the output is written, and is updated after each chunk
+    /// to bring the output level towards the target.
+    /// The gain changes are ramped smoothly, with the time constant given in the parameters.
+    /// Note that this is not a limiter, and a sudden increase of the level may overshoot the target.
+    /// Changing the parameters resets the gain to unity.
+    pub fn set_normalization(&mut self, parameters: Option<NormalizationParameters>) {
+        trace!("Set normalization to {:?}", parameters);
+        self.normalizer.set_parameters(parameters);
+    }
+
+    /// Get the current gain of the output normalization.
+    /// This is always 1.0 when the normalization is disabled.
+    pub fn normalization_gain(&self) -> f64 {
+        self.normalizer.gain()
+    }
+
+    /// Move the position of the next output frame back by `frames` input frames,
+    /// which delays the output. A negative value moves it forward.
+    pub(crate) fn shift_phase(&mut self, frames: f64) {
+        self.last_index -= frames;
+    }
+
+    /// Get the latency of the resampler, for the current resample ratio.
+    /// The sinc interpolation starts with an offset that compensates for the delay of the sinc filter,
+    /// so the remaining latency is small, and it is negative when downsampling.
+    pub fn latency(&self) -> Latency {
+        let input_frames =
+            1.0 - 1.0 / self.resample_ratio - 1.0 / self.interpolator.nbr_sincs() as f64;
+        Latency {
+            input_frames,
+            output_frames: input_frames * self.resample_ratio,
+        }
+    }
+
+    /// Get the resample ratio as a fraction L/M, if the resampler is resampling exactly,
+    /// as set up by "new_rational".
+    /// This requires `Nearest` interpolation with an oversampling factor that is a multiple of L,
+    /// and that the ratio is not being smoothed or modulated.
+    /// Returns `None` otherwise, for example after the ratio has been changed to a value
+    /// that doesn't fit the oversampling factor.
+    pub fn rational_ratio(&self) -> Option<RationalRatio> {
+        if self.interpolation != InterpolationType::Nearest
+            || self.modulation.is_some()
+            || self.resample_ratio != self.target_ratio
+        {
+            return None;
+        }
+        exact_fraction(self.resample_ratio, self.interpolator.nbr_sincs())
+    }
+
+    /// Get the range of resample ratios where the anti-aliasing filter is valid,
+    /// meaning that its cutoff is at or below the Nyquist frequency of the output.
+    /// The range is also limited to the +-10% that "set_resample_ratio" accepts.
+    /// Adaptive controllers can keep their ratio within this range to avoid aliasing.
+    ///
+    /// Like at the original ratio, the transition band above the cutoff may still
+    /// let a little of the highest frequencies alias.
+    /// Returns `None` if the cutoff is not known, which is the case for resamplers
+    /// created from a prototype, a table or an interpolator,
+    /// or if no ratio in the allowed range is valid.
+    pub fn valid_ratio_range(&self) -> Option<(f64, f64)> {
+        let cutoff = self.cutoff?;
+        let lowest = 0.9 * self.resample_ratio_original;
+        let highest = 1.1 * self.resample_ratio_original;
+        let lowest = if cutoff > lowest { cutoff } else { lowest };
+        if lowest < highest {
+            Some((lowest, highest))
+        } else {
+            None
+        }
+    }
+
+    /// Check if the resample ratio has been changed so that the output may alias,
+    /// because the Nyquist frequency of the output is below the cutoff of the anti-aliasing filter.
+    /// While the ratio is smoothed, the lower of the current and the target ratio is checked.
+    /// The warning tells how much the filter gain at the output Nyquist frequency
+    /// exceeds the gain at the cutoff, which is about -6 dB.
+    /// Returns `None` if the ratio is within the range given by `valid_ratio_range`,
+    /// or if the cutoff is not known.
+    pub fn aliasing_warning(&self) -> Option<AliasingWarning> {
+        let cutoff = self.cutoff?;
+        let ratio = if self.target_ratio < self.resample_ratio {
+            self.target_ratio
+        } else {
+            self.resample_ratio
+        };
+        if ratio >= cutoff {
+            return None;
+        }
+        let excess_db = sinc_gain_db(self.interpolator.as_ref(), ratio)
+            - sinc_gain_db(self.interpolator.as_ref(), cutoff);
+        Some(AliasingWarning {
+            ratio,
+            cutoff,
+            excess_db,
+        })
+    }
+
+    /// Get a checksum of the sinc filters used by this resampler.
+    /// Two resamplers with the same checksum use identical filters,
+    /// which can be used to verify that different machines or versions of the library
+    /// will give the same output. The checksum is calculated on each call,
+    /// which takes about as long as creating the resampler.
+    pub fn filter_checksum(&self) -> u64 {
+        self.interpolator.checksum()
+    }
+
+    /// Get the oversampled version of the input, as used by the sinc interpolation.
+    /// This returns `chunk_size` times the oversampling factor points per channel,
+    /// covering the same range as the last chunk given to "process",
+    /// but delayed by the length of the sinc filter.
+    /// The points are calculated on demand, which is about as expensive as
+    /// running the sinc interpolation for every one of them.
+    /// Useful for analysis, true-peak metering and custom interpolation.
+    pub fn oversampled_input(&self) -> Vec<Vec<T>> {
+        self.buffer
+            .iter()
+            .map(|buf| {
+                oversample_wave(
+                    self.interpolator.as_ref(),
+                    &buf[self.buffer_start..],
+                    self.chunk_size,
+                )
+            })
+            .collect()
+    }
+
+    /// Clear the buffered input of one channel, so that it continues from silence.
+    pub(crate) fn clear_channel(&mut self, chan: usize) {
+        for value in self.buffer[chan].iter_mut() {
+            *value = T::zero();
+        }
+    }
+
+    /// Resample a chunk given as one slice per channel, like "process_slices",
+    /// and return the internal output buffers instead of copying them.
+    pub(crate) fn process_slices_buffered(
+        &mut self,
+        wave_in: &[&[T]],
+    ) -> ResampleResult<&[Vec<T>]> {
+        self.process_chunk(Input::Slices(wave_in))?;
+        Ok(&self.output_buffer)
+    }
+
+    /// Attach a low frequency oscillator that modulates the resample ratio,
+    /// or remove it by passing `None`.
+    /// The oscillator is advanced by one step per output frame.
+    /// The modulated ratio is limited to +-10% of the original one.
+    pub fn set_modulation(&mut self, lfo: Option<Lfo>) {
+        trace!("Set modulation to {:?}", lfo);
+        self.modulation = lfo;
+        self.update_ratios();
+    }
+
+    /// Set the time constant for smoothing changes of the resample ratio, in output frames.
+    /// When set, a new ratio given to "set_resample_ratio" is not applied immediately,
+    /// but is approached exponentially over the following output frames, also across calls to "process".
+    /// This avoids audible steps when the ratio is updated at a low rate.
+    /// A value of zero disables the smoothing, and jumps directly to the latest target ratio.
+    pub fn set_ratio_smoothing(&mut self, time_constant: usize) {
+        trace!(
+            "Set ratio smoothing time constant to {} frames",
+            time_constant
+        );
+        self.ratio_smoothing = time_constant;
+        if time_constant == 0 {
+            self.resample_ratio = self.target_ratio;
+        }
+        self.update_ratios();
+    }
+
+    /// Get the ratios for the output frames of the next call to "process", starting at the given ratio.
+    /// This includes smoothing, and modulation by the given oscillator.
+    fn modulated_ratios(&self, ratio: f64, lfo: Option<&Lfo>, ratios: &mut Vec<f64>) {
+        let max_frames = max_output_frames(self.chunk_size, self.resample_ratio_original);
+        ratio_curve(
+            ratio,
+            self.target_ratio,
+            self.ratio_smoothing,
+            lfo,
+            self.resample_ratio_original,
+            max_frames,
+            ratios,
+        )
+    }
+
+    /// Update the stored ratios for the next call to "process",
+    /// after a change of the ratio, the smoothing or the modulation.
+    fn update_ratios(&mut self) {
+        let mut ratios = mem::take(&mut self.ratios);
+        self.modulated_ratios(self.resample_ratio, self.modulation.as_ref(), &mut ratios);
+        self.ratios = ratios;
+    }
+
+    /// Resample a chunk of audio, using the given resample ratio for each output frame.
+    /// The last ratio is used for any remaining frames.
+    /// The output is written to the output buffer, and the number of output frames is returned.
+    fn process_with_ratios(&mut self, input: Input<T>, ratios: &[f64]) -> ResampleResult<usize> {
+        input
+            .used_channels(self.nbr_channels, self.chunk_size, &mut self.channels.used)
+            .map_err(|err| err.with_resampler(self))?;
+        let sinc_len = self.interpolator.len();
+        // move the window past the last chunk, keeping the history before it
+        self.buffer_start = advance_window(
+            &mut self.buffer,
+            self.buffer_start,
+            self.chunk_size,
+            2 * sinc_len,
+            self.chunk_size,
+        );
+        let start = self.buffer_start;
+        let window = start..start + 2 * sinc_len + self.chunk_size;
+
+        for wave in self.output_buffer.iter_mut() {
+            wave.clear();
+        }
+        let (nbr_frames_out, next_index) = self.calc_output_frames(self.last_index, ratios);
+
+        self.meter.clear();
+        for chan in self.channels.used.iter() {
+            let buffer = &mut self.buffer[*chan][start + 2 * sinc_len..window.end];
+            input.copy_channel(*chan, &mut self.meter, buffer);
+            if self.reverse {
+                buffer.reverse();
+            }
+            self.output_buffer[*chan].resize(nbr_frames_out, T::zero());
+        }
+
+        // Channels where all samples in the buffer are silent produce silent output,
+        // and can skip the interpolation.
+        active_channels(
+            &self.buffer,
+            self.silence_threshold,
+            &self.channels.used,
+            window.clone(),
+            &mut self.channels.active,
+        );
+        // long sincs may be convolved with FFTs,
+        // and with many channels, groups of channels are interpolated together
+        match (self.convolution.as_mut(), self.lanes.as_mut()) {
+            (Some(convolution), _) if !self.preview => {
+                convolution.process(
+                    &*self.interpolator,
+                    &self.buffer,
+                    &mut self.output_buffer,
+                    &self.channels.active,
+                    start,
+                    self.interpolation,
+                    self.last_index,
+                    ratios,
+                    self.parallel_threshold,
+                );
+                self.channels.remaining.clear();
+            }
+            (_, Some(lanes)) if !self.preview => lanes.process(
+                &self.buffer,
+                &mut self.output_buffer,
+                &self.channels.active,
+                window,
+                self.interpolation,
+                self.last_index,
+                ratios,
+                self.parallel_threshold,
+                &mut self.channels.remaining,
+            ),
+            _ => {
+                self.channels.remaining.clear();
+                self.channels
+                    .remaining
+                    .extend_from_slice(&self.channels.active);
+            }
+        }
+        self.normalizer.start_chunk(nbr_frames_out);
+
+        let interpolator = &*self.interpolator;
+        let phases = self.phases;
+        let interpolation = self.interpolation;
+        let preview = self.preview;
+        let last_index = self.last_index;
+        let buffer = &self.buffer;
+        let remaining_channels = &self.channels.remaining;
+        let channels = self
+            .output_buffer
+            .iter_mut()
+            .enumerate()
+            .filter(|(chan, _)| remaining_channels.contains(chan))
+            .map(|(chan, wave_out)| (&buffer[chan][start..], &mut wave_out[..]));
+        // the workers carry no state, and a vector of them doesn't allocate
+        let mut workers = vec![(); nbr_workers(self.nbr_channels, self.parallel_threshold)];
+        for_each_channel(
+            channels,
+            &mut workers,
+            self.parallel_threshold,
+            |(buf, wave_out), _| {
+                interpolate_channel(
+                    interpolator,
+                    phases,
+                    interpolation,
+                    preview,
+                    buf,
+                    wave_out,
+                    last_index,
+                    ratios,
+                )
+            },
+        );
+        for chan in self.channels.active.iter() {
+            self.normalizer.apply_wave(&mut self.output_buffer[*chan]);
+        }
+
+        // store last index for next iteration
+        self.normalizer.finish_chunk(nbr_frames_out);
+        self.last_index = next_index;
+        trace!(
+            "Resampling channels {:?}, {} frames in, {} frames out",
+            self.channels.used,
+            self.chunk_size,
+            nbr_frames_out,
+        );
+        Ok(nbr_frames_out)
+    }
+
+    /// Resample a chunk of audio at the current resample ratio, and update the modulation and
+    /// smoothing. The output is written to the output buffer.
+    fn process_chunk(&mut self, input: Input<T>) -> ResampleResult<()> {
+        let ratios = mem::take(&mut self.ratios);
+        let result = self.process_with_ratios(input, &ratios);
+        self.ratios = ratios;
+        let nbr_frames_out = result?;
+        if let Some(lfo) = self.modulation.as_mut() {
+            lfo.advance(nbr_frames_out);
+        }
+        self.resample_ratio = smooth_ratio(
+            self.resample_ratio,
+            self.target_ratio,
+            self.ratio_smoothing,
+            nbr_frames_out,
+        );
+        self.update_ratios();
+        Ok(())
+    }
+
+    /// Resample a chunk of audio, with the resample ratio varying from one output frame to the next.
+    /// This is intended for effects like tape or vinyl emulation,
+    /// where the speed changes continuously.
+    ///
+    /// The `ratios` slice holds one resample ratio per output frame.
+    /// If the chunk produces more frames than there are values, the last value is held
+    /// for the remaining frames, and any values beyond the number of produced frames are ignored.
+    /// Use `nbr_output_frames_next` to get an estimate of how many frames will be produced.
+    /// The last value becomes the new resample ratio, used by following calls to "process".
+    /// An empty slice gives the same result as "process".
+    ///
+    /// # Errors
+    ///
+    /// The function returns an error if any of the ratios is not within +-10% of the original one,
+    /// or if the input data doesn't match the number of channels and chunk size.
+    pub fn process_with_ratio_curve(
+        &mut self,
+        wave_in: &[Vec<T>],
+        ratios: &[f64],
+    ) -> ResampleResult<Vec<Vec<T>>> {
+        if ratios.is_empty() {
+            return self.process(wave_in);
+        }
+        if let Some(ratio) = ratios.iter().find(|r| {
+            !((*r / self.resample_ratio_original > 0.9)
+                && (*r / self.resample_ratio_original < 1.1))
+        }) {
+            return Err(ResampleError::RatioOutOfBounds {
+                provided: *ratio,
+                original: self.resample_ratio_original,
+                max_deviation: MAX_RATIO_DEVIATION,
+            });
+        }
+        self.process_with_ratios(Input::Channels(wave_in), ratios)?;
+        self.resample_ratio = ratios[ratios.len() - 1];
+        self.target_ratio = self.resample_ratio;
+        self.update_ratios();
+        Ok(self.output_buffer.clone())
+    }
+
+    /// Predict the number of frames returned by each of the next `nbr_chunks` calls to "process",
+    /// assuming the resample ratio is not changed in between.
+    /// Any smoothing and modulation is included in the prediction.
+    ///
+    /// The number of output frames varies slightly between calls, since a chunk of input
+    /// generally does not correspond to a whole number of output frames.
+    /// The fractional remainder is carried over to the next call, and this function
+    /// steps through the same index arithmetic as "process", so the returned
+    /// sequence matches the actual output lengths exactly.
+    pub fn output_frames_schedule(&self, nbr_chunks: usize) -> Vec<usize> {
+        let mut lfo = self.modulation.clone();
+        let mut ratio = self.resample_ratio;
+        let mut last_index = self.last_index;
+        let mut schedule = Vec::with_capacity(nbr_chunks);
+        let mut ratios = Vec::new();
+        for _ in 0..nbr_chunks {
+            self.modulated_ratios(ratio, lfo.as_ref(), &mut ratios);
+            let (frames, next_index) = self.calc_output_frames(last_index, &ratios);
+            schedule.push(frames);
+            last_index = next_index;
+            if let Some(lfo) = lfo.as_mut() {
+                lfo.advance(frames);
+            }
+            ratio = smooth_ratio(ratio, self.target_ratio, self.ratio_smoothing, frames);
+        }
+        schedule
+    }
+
+    /// Calculate the number of frames a call to "process" will produce when starting at `last_index`,
+    /// and the value of `last_index` after that call.
+    /// This steps through the same sequence of indices as the interpolation loop,
+    /// to make sure the result matches exactly.
+    fn calc_output_frames(&self, last_index: f64, ratios: &[f64]) -> (usize, f64) {
+        let sinc_len = self.interpolator.len();
+        let t_ratio_max = ratios.iter().fold(0.0, |acc: f64, r| acc.max(1.0 / r));
+        let end_idx =
+            self.chunk_size as isize - (sinc_len as isize + 1) - t_ratio_max.ceil() as isize;
+        let mut idx = last_index;
+        let mut n = 0;
+        for step in index_steps(ratios) {
+            if idx >= end_idx as f64 {
+                break;
+            }
+            idx += step;
+            n += 1;
+        }
+        (n, idx - self.chunk_size as f64)
+    }
+}
+
+/// Shows the configuration and the current state, without the contents of the buffers.
+impl<T> fmt::Debug for SincFixedIn<T> {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        f.debug_struct("SincFixedIn")
+            .field("nbr_channels", &self.nbr_channels)
+            .field("chunk_size", &self.chunk_size)
+            .field("resample_ratio", &self.resample_ratio)
+            .field("target_ratio", &self.target_ratio)
+            .field("resample_ratio_original", &self.resample_ratio_original)
+            .field("sinc_len", &self.interpolator.len())
+            .field("oversampling_factor", &self.interpolator.nbr_sincs())
+            .field("interpolation", &self.interpolation)
+            .field("last_index", &self.last_index)
+            .finish()
+    }
+}
+
+impl<T> Resampler<T> for SincFixedIn<T>
+where
+    T: Sample,
+{
+    /// Resample a chunk of audio. The input length is fixed, and the output varies in length.
+    /// If the waveform for a channel is empty, this channel will be ignored and produce a
+    /// corresponding empty output waveform.
+    /// # Errors
+    ///
+    /// The function returns an error if the length of the input data is not equal
+    /// to the number of channels and chunk size defined when creating the instance.
+    fn process(&mut self, wave_in: &[Vec<T>]) -> ResampleResult<Vec<Vec<T>>> {
+        self.process_chunk(Input::Channels(wave_in))?;
+        Ok(self.output_buffer.clone())
+    }
+
+    /// Resample a chunk of audio like "process", but pass the output of each channel
+    /// to the closure `f` instead of returning it.
+    /// The output is taken directly from an internal buffer, without allocating any output vectors.
+    fn process_with(
+        &mut self,
+        wave_in: &[Vec<T>],
+        f: &mut dyn FnMut(usize, &[T]),
+    ) -> ResampleResult<()> {
+        self.process_chunk(Input::Channels(wave_in))?;
+        for (chan, wave) in self.output_buffer.iter().enumerate() {
+            f(chan, wave);
+        }
+        Ok(())
+    }
+
+    fn aliasing_warning(&self) -> Option<AliasingWarning> {
+        SincFixedIn::aliasing_warning(self)
+    }
+
+    fn output_delay(&self) -> f64 {
+        self.latency().output_frames
+    }
+
+    /// Clear the buffered input and return to the original resample ratio,
+    /// see "reset" of the `Resampler` trait. The sinc tables and the settings are kept.
+    fn reset(&mut self) {
+        for value in self.buffer.iter_mut().flatten() {
+            *value = T::zero();
+        }
+        self.buffer_start = 0;
+        for wave in self.output_buffer.iter_mut() {
+            wave.clear();
+        }
+        self.last_index = -((self.interpolator.len() / 2) as f64);
+        self.resample_ratio = self.resample_ratio_original;
+        self.target_ratio = self.resample_ratio_original;
+        if let Some(lfo) = self.modulation.as_mut() {
+            lfo.reset();
+        }
+        self.update_ratios();
+        self.meter.clear();
+        self.normalizer.reset();
+    }
+
+    /// Emit the output frames that are held back at the end of a stream, see "flush"
+    /// of the `Resampler` trait. The number of frames is calculated for the current resample ratio.
+    fn flush(&mut self) -> ResampleResult<Vec<Vec<T>>> {
+        let nbr_frames = pending_frames(
+            self.last_index,
+            self.resample_ratio,
+            self.interpolator.len(),
         );
-        Ok(wave_out)
+        offline::flush_with_silence(self, self.nbr_channels, nbr_frames)
+    }
+
+    /// Resample a chunk of audio like "process_unchecked" of the `Resampler` trait,
+    /// without checking the number of channels and frames, and without looking for empty channels.
+    unsafe fn process_unchecked(&mut self, wave_in: &[Vec<T>]) -> ResampleResult<Vec<Vec<T>>> {
+        self.process_chunk(Input::Unchecked(wave_in))?;
+        Ok(self.output_buffer.clone())
+    }
+
+    /// Resample a chunk of audio like "process_slices" of the `Resampler` trait,
+    /// but read the slices directly into the internal buffer, without copying them first.
+    fn process_slices(&mut self, wave_in: &[&[T]]) -> ResampleResult<Vec<Vec<T>>> {
+        self.process_chunk(Input::Slices(wave_in))?;
+        Ok(self.output_buffer.clone())
+    }
+
+    /// Resample a chunk of audio like "process_range" of the `Resampler` trait,
+    /// but read the range directly into the internal buffer, without copying it first.
+    fn process_range(
+        &mut self,
+        wave_in: &[Vec<T>],
+        offset: usize,
+        len: usize,
+    ) -> ResampleResult<Vec<Vec<T>>> {
+        self.process_chunk(Input::Range {
+            data: wave_in,
+            offset,
+            len,
+        })?;
+        Ok(self.output_buffer.clone())
+    }
+
+    /// Resample a chunk of audio like "process_strided" of the `Resampler` trait,
+    /// but read the input directly into the internal buffer, without de-interleaving it first.
+    fn process_strided(
+        &mut self,
+        nbr_channels: usize,
+        wave_in: &[T],
+        stride_in: usize,
+        wave_out: &mut [T],
+        stride_out: usize,
+    ) -> ResampleResult<usize> {
+        io::assert_strides(nbr_channels, stride_in, stride_out);
+        let nbr_frames_out = self.nbr_output_frames_next();
+        io::check_strided_len(wave_out.len(), nbr_channels, stride_out, nbr_frames_out)
+            .map_err(|err| err.with_resampler(self))?;
+        self.process_chunk(Input::Strided {
+            data: wave_in,
+            nbr_channels,
+            stride: stride_in,
+        })?;
+        for (chan, wave) in self.output_buffer.iter().enumerate() {
+            io::write_strided(wave_out, chan, stride_out, wave);
+        }
+        Ok(self
+            .output_buffer
+            .iter()
+            .map(|wave| wave.len())
+            .max()
+            .unwrap_or(0))
+    }
+
+    /// Resample a chunk of interleaved 16-bit audio like "process_interleaved_i16" of the `Resampler` trait,
+    /// but convert the input directly into the internal buffer while de-interleaving it,
+    /// and convert the output while interleaving it.
+    fn process_interleaved_i16(
+        &mut self,
+        nbr_channels: usize,
+        wave_in: &[i16],
+        wave_out: &mut [i16],
+        mut dither: Option<&mut Dither>,
+    ) -> ResampleResult<usize> {
+        io::assert_strides(nbr_channels, nbr_channels, nbr_channels);
+        let nbr_frames_out = self.nbr_output_frames_next();
+        io::check_strided_len(wave_out.len(), nbr_channels, nbr_channels, nbr_frames_out)
+            .map_err(|err| err.with_resampler(self))?;
+        self.process_chunk(Input::StridedI16 {
+            data: wave_in,
+            nbr_channels,
+            stride: nbr_channels,
+        })?;
+        for (chan, wave) in self.output_buffer.iter().enumerate() {
+            convert::write_strided_i16(wave_out, chan, nbr_channels, wave, dither.as_deref_mut());
+        }
+        Ok(self
+            .output_buffer
+            .iter()
+            .map(|wave| wave.len())
+            .max()
+            .unwrap_or(0))
+    }
+
+    /// Query for the number of frames needed for the next call to "process".
+    /// Will always return the chunk_size defined when creating the instance.
+    fn nbr_frames_needed(&self) -> usize {
+        self.chunk_size
+    }
+
+    /// Query for the number of frames that the next call to "process" will return.
+    /// This varies between calls, and is updated when the resample ratio is changed.
+    fn nbr_output_frames_next(&self) -> usize {
+        self.calc_output_frames(self.last_index, &self.ratios).0
     }
 
     /// Update the resample ratio. New value must be within +-10% of the original one
@@ -675,177 +2227,1944 @@ where
         if (new_ratio / self.resample_ratio_original > 0.9)
             && (new_ratio / self.resample_ratio_original < 1.1)
         {
-            self.resample_ratio = new_ratio;
-            self.needed_input_size = (self.last_index as f32
-                + self.chunk_size as f32 / self.resample_ratio as f32
-                + self.interpolator.len() as f32)
-                .ceil() as usize
-                + 2;
+            if let Some(cutoff) = self.cutoff {
+                if new_ratio < cutoff {
+                    warn!(
+                        "Resample ratio {} is below the filter cutoff {}, the output may alias",
+                        new_ratio, cutoff
+                    );
+                }
+            }
+            self.target_ratio = new_ratio;
+            if self.ratio_smoothing == 0 {
+                self.resample_ratio = new_ratio;
+            }
+            self.update_ratios();
             Ok(())
         } else {
-            Err(ResampleError::BadRatioUpdate)
+            Err(ResampleError::RatioOutOfBounds {
+                provided: new_ratio,
+                original: self.resample_ratio_original,
+                max_deviation: MAX_RATIO_DEVIATION,
+            })
         }
     }
-
     /// Update the resample ratio relative to the original one
     fn set_resample_ratio_relative(&mut self, rel_ratio: f64) -> ResampleResult<()> {
         let new_ratio = self.resample_ratio_original * rel_ratio;
         self.set_resample_ratio(new_ratio)
     }
-}
 
-#[cfg(test)]
-mod tests {
-    use super::{interp_cubic, interp_lin};
-    use crate::asynchro::ScalarInterpolator;
-    use crate::asynchro::SincInterpolator;
-    use crate::InterpolationParameters;
-    use crate::InterpolationType;
-    use crate::Resampler;
-    use crate::WindowFunction;
-    use crate::{SincFixedIn, SincFixedOut};
-    use num_traits::Float;
-    use rand::Rng;
+    fn resample_ratio(&self) -> f64 {
+        self.resample_ratio
+    }
 
-    fn get_sinc_interpolated<T: Float>(wave: &[T], index: usize, sinc: &[T]) -> T {
-        let wave_cut = &wave[index..(index + sinc.len())];
-        wave_cut
-            .iter()
-            .zip(sinc.iter())
-            .fold(T::zero(), |acc, (x, y)| acc + *x * *y)
+    fn resample_ratio_original(&self) -> f64 {
+        self.resample_ratio_original
     }
 
-    #[test]
-    fn test_scalar_interpolator_64() {
-        let mut rng = rand::thread_rng();
-        let mut wave = Vec::new();
-        for _ in 0..2048 {
-            wave.push(rng.gen::<f64>());
-        }
-        let sinc_len = 256;
-        let f_cutoff = 0.9473371669037001;
-        let oversampling_factor = 256;
-        let window = WindowFunction::BlackmanHarris2;
+    fn chunk_size(&self) -> usize {
+        self.chunk_size
+    }
 
-        let interpolator =
-            ScalarInterpolator::<f64>::new(sinc_len, oversampling_factor, f_cutoff, window);
-        let value = interpolator.get_sinc_interpolated(&wave, 333, 123);
-        let check = get_sinc_interpolated(&wave, 333, &interpolator.sincs[123]);
-        assert!((value - check).abs() < 1.0e-9);
+    fn nbr_channels(&self) -> usize {
+        self.nbr_channels
     }
 
-    #[test]
-    fn test_scalar_interpolator_32() {
-        let mut rng = rand::thread_rng();
-        let mut wave = Vec::new();
-        for _ in 0..2048 {
-            wave.push(rng.gen::<f32>());
-        }
-        let sinc_len = 256;
-        let f_cutoff = 0.9473371669037001;
-        let oversampling_factor = 256;
-        let window = WindowFunction::BlackmanHarris2;
+    fn sinc_len(&self) -> Option<usize> {
+        Some(self.interpolator.len())
+    }
+}
 
-        let interpolator =
-            ScalarInterpolator::<f32>::new(sinc_len, oversampling_factor, f_cutoff, window);
-        let value = interpolator.get_sinc_interpolated(&wave, 333, 123);
-        let check = get_sinc_interpolated(&wave, 333, &interpolator.sincs[123]);
-        assert!((value - check).abs() < 1.0e-6);
+impl<T> SincFixedOut<T>
+where
+    T: Sample,
+{
+    /// Create a new SincFixedOut
+    ///
+    /// Parameters are:
+    /// - `resample_ratio`: Ratio between output and input sample rates.
+    /// - `parameters`: Parameters for interpolation, see `InterpolationParameters`
+    /// - `chunk_size`: size of output data in frames
+    /// - `nbr_channels`: number of channels in input/output
+    ///
+    /// # Errors
+    ///
+    /// The function returns `ResampleError::InvalidParameter` if the resample ratio is not a positive number,
+    /// if `sinc_len`, `oversampling_factor`, `chunk_size` or `nbr_channels` is zero,
+    /// if `f_cutoff` is not larger than zero and at most 1.0,
+    /// if the sigma of a Gaussian window is not a positive number,
+    /// or if a custom window has the wrong number of taps.
+    pub fn new(
+        resample_ratio: f64,
+        parameters: InterpolationParameters,
+        chunk_size: usize,
+        nbr_channels: usize,
+    ) -> ResampleResult<Self> {
+        Self::new_with_kernel(
+            resample_ratio,
+            parameters,
+            chunk_size,
+            nbr_channels,
+            make_interpolator_from_sincs,
+        )
     }
 
-    #[test]
-    fn int_cubic() {
+    /// Create a new SincFixedOut like `new`, with the interpolator made from the sincs by `make_kernel`
+    /// instead of the kernel chosen at runtime.
+    pub(crate) fn new_with_kernel<F>(
+        resample_ratio: f64,
+        parameters: InterpolationParameters,
+        chunk_size: usize,
+        nbr_channels: usize,
+        make_kernel: F,
+    ) -> ResampleResult<Self>
+    where
+        F: FnOnce(Vec<Vec<T>>) -> Box<dyn SincInterpolator<T>>,
+    {
+        validate_parameters(resample_ratio, &parameters, chunk_size, nbr_channels)?;
+        debug!(
+            "Create new SincFixedOut, ratio: {}, chunk_size: {}, channels: {}, parameters: {:?}",
+            resample_ratio, chunk_size, nbr_channels, parameters
+        );
+        let cutoff = design_cutoff(resample_ratio, parameters.f_cutoff);
+        let sinc_len = 8 * (((parameters.sinc_len as f32) / 8.0).ceil() as usize);
+        let sincs = make_sincs(
+            sinc_len,
+            parameters.oversampling_factor,
+            cutoff,
+            parameters.window,
+        );
+        let interpolator = make_kernel(sincs);
+
+        let mut resampler = Self::build(
+            resample_ratio,
+            parameters.interpolation,
+            interpolator,
+            chunk_size,
+            nbr_channels,
+        );
+        resampler.cutoff = Some(cutoff as f64);
+        Ok(resampler)
+    }
+
+    /// Create a new SincFixedOut without anti-aliasing when downsampling.
+    ///
+    /// Normally the cutoff frequency of the interpolation filter is scaled down by the
+    /// resample ratio when downsampling, to remove everything above the new Nyquist frequency.
+    /// With this constructor the cutoff is relative to the input sample rate for all ratios,
+    /// so the full bandwidth of the input is kept. This avoids needless rolloff when the input
+    /// is already band limited, as is often the case for non-audio data like envelopes or
+    /// sensor readings. Any content above the new Nyquist frequency is aliased.
+    ///
+    /// Parameters are:
+    /// - `resample_ratio`: Ratio between output and input sample rates.
+    /// - `parameters`: Parameters for interpolation, see `InterpolationParameters`
+    /// - `chunk_size`: size of output data in frames
+    /// - `nbr_channels`: number of channels in input/output
+    ///
+    /// # Errors
+    ///
+    /// The function returns `ResampleError::InvalidParameter` for the same parameters as `new`.
+    pub fn new_without_anti_aliasing(
+        resample_ratio: f64,
+        parameters: InterpolationParameters,
+        chunk_size: usize,
+        nbr_channels: usize,
+    ) -> ResampleResult<Self> {
+        validate_parameters(resample_ratio, &parameters, chunk_size, nbr_channels)?;
+        debug!(
+            "Create new SincFixedOut without anti-aliasing, ratio: {}, chunk_size: {}, channels: {}, parameters: {:?}",
+            resample_ratio, chunk_size, nbr_channels, parameters
+        );
+        // a ratio of 1.0 keeps the cutoff unscaled
+        let cutoff = parameters.f_cutoff;
+        let interpolator = make_interpolator(
+            parameters.sinc_len,
+            1.0,
+            parameters.f_cutoff,
+            parameters.oversampling_factor,
+            parameters.window,
+        );
+        let mut resampler = Self::build(
+            resample_ratio,
+            parameters.interpolation,
+            interpolator,
+            chunk_size,
+            nbr_channels,
+        );
+        resampler.cutoff = Some(cutoff as f64);
+        Ok(resampler)
+    }
+
+    /// Create a new SincFixedOut with a user filter, for example an equalizer or a de-emphasis filter,
+    /// fused into the anti-aliasing filter.
+    ///
+    /// The user filter is an FIR filter at the input sample rate, given by its taps.
+    /// It is convolved with the windowed sinc when the resampler is created,
+    /// so that the filtering is done in the same pass as the resampling.
+    /// This makes the sinc longer by the length of the user filter, rounded up to a multiple of 8.
+    /// The combined filter is centered like the sinc, so a symmetric user filter adds no delay.
+    /// The gain of the user filter is kept, unlike for a prototype filter.
+    ///
+    /// Parameters are:
+    /// - `resample_ratio`: Ratio between output and input sample rates.
+    /// - `parameters`: Parameters for interpolation, see `InterpolationParameters`
+    /// - `taps`: Taps of the user filter.
+    /// - `chunk_size`: size of output data in frames
+    /// - `nbr_channels`: number of channels in input/output
+    ///
+    /// # Errors
+    ///
+    /// The function returns `ResampleError::InvalidParameter` for the same parameters as `new`,
+    /// and if `taps` is empty.
+    pub fn new_with_filter(
+        resample_ratio: f64,
+        parameters: InterpolationParameters,
+        taps: &[T],
+        chunk_size: usize,
+        nbr_channels: usize,
+    ) -> ResampleResult<Self> {
+        validate_parameters(resample_ratio, &parameters, chunk_size, nbr_channels)?;
+        ResampleError::check(
+            !taps.is_empty(),
+            "the number of filter taps",
+            taps.len(),
+            "must be larger than zero",
+        )?;
+        debug!(
+            "Create new SincFixedOut with a user filter, ratio: {}, taps: {}, parameters: {:?}",
+            resample_ratio,
+            taps.len(),
+            parameters
+        );
+        let cutoff = design_cutoff(resample_ratio, parameters.f_cutoff);
+        let interpolator = make_interpolator_with_filter(&parameters, resample_ratio, taps);
+        let mut resampler = Self::build(
+            resample_ratio,
+            parameters.interpolation,
+            interpolator,
+            chunk_size,
+            nbr_channels,
+        );
+        resampler.cutoff = Some(cutoff as f64);
+        Ok(resampler)
+    }
+
+    /// Create a new SincFixedOut that snaps the ratio to a nearby fraction, for exact resampling.
+    ///
+    /// If `snap_ratio` finds a fraction L/M within `tolerance` of `resample_ratio`,
+    /// with L at most `max_oversampling_factor`, the resampler runs at the ratio L/M,
+    /// using `Nearest` interpolation with an oversampling factor of L.
+    /// Every output frame then falls exactly on an intermediate point,
+    /// and there is no interpolation error, just like for a synchronous resampler.
+    /// Otherwise the ratio and parameters are used as given.
+    /// Use "rational_ratio" to check if the ratio was snapped, and to get the fraction.
+    ///
+    /// Parameters are:
+    /// - `resample_ratio`: Ratio between output and input sample rates.
+    /// - `parameters`: Parameters for interpolation, see `InterpolationParameters`
+    /// - `max_oversampling_factor`: Largest numerator L to consider.
+    /// - `tolerance`: Largest accepted relative difference between the fraction and `resample_ratio`.
+    /// - `chunk_size`: size of output data in frames
+    /// - `nbr_channels`: number of channels in input/output
+    ///
+    /// # Errors
+    ///
+    /// The function returns `ResampleError::InvalidParameter` for the same parameters as `new`.
+    pub fn new_rational(
+        resample_ratio: f64,
+        parameters: InterpolationParameters,
+        max_oversampling_factor: usize,
+        tolerance: f64,
+        chunk_size: usize,
+        nbr_channels: usize,
+    ) -> ResampleResult<Self> {
+        validate_parameters(resample_ratio, &parameters, chunk_size, nbr_channels)?;
+        let (resample_ratio, parameters) = snap_parameters(
+            resample_ratio,
+            parameters,
+            max_oversampling_factor,
+            tolerance,
+        );
+        Self::new(resample_ratio, parameters, chunk_size, nbr_channels)
+    }
+
+    /// Create a new SincFixedOut for resampling from `fs_in` to `fs_out`.
+    ///
+    /// The ratio is calculated from the reduced fraction L/M of the sample rates,
+    /// and the parameters are used as given, so the ratio can be adjusted like for "new".
+    /// The resampling is exact when the parameters use `Nearest` interpolation
+    /// with an oversampling factor that is a multiple of L, which "rational_ratio" then reports.
+    ///
+    /// Parameters are:
+    /// - `fs_in`: Input sample rate.
+    /// - `fs_out`: Output sample rate.
+    /// - `parameters`: Parameters for interpolation, see `InterpolationParameters`
+    /// - `chunk_size`: size of output data in frames
+    /// - `nbr_channels`: number of channels in input/output
+    ///
+    /// # Errors
+    ///
+    /// The function returns `ResampleError::InvalidParameter` if a sample rate is zero,
+    /// and for the same parameters as `new`.
+    pub fn new_from_rates(
+        fs_in: usize,
+        fs_out: usize,
+        parameters: InterpolationParameters,
+        chunk_size: usize,
+        nbr_channels: usize,
+    ) -> ResampleResult<Self> {
+        ResampleError::check(fs_in > 0, "fs_in", fs_in, "must be larger than zero")?;
+        ResampleError::check(fs_out > 0, "fs_out", fs_out, "must be larger than zero")?;
+        Self::new(
+            rate_ratio(fs_in, fs_out),
+            parameters,
+            chunk_size,
+            nbr_channels,
+        )
+    }
+
+    /// Create a new SincFixedOut using a prototype filter designed by the user,
+    /// instead of the windowed sinc made from `InterpolationParameters`.
+    ///
+    /// The prototype is the impulse response of the anti-aliasing filter at the oversampled rate,
+    /// with `sinc_len * oversampling_factor` taps, where `sinc_len` must be a multiple of 8.
+    /// It is normalized to unity gain at zero frequency, so the scaling of the taps does not matter.
+    /// Note that the cutoff is not adjusted for the resample ratio,
+    /// so for downsampling the prototype must be designed for the output sample rate.
+    ///
+    /// Parameters are:
+    /// - `resample_ratio`: Ratio between output and input sample rates.
+    /// - `prototype`: Taps of the prototype filter.
+    /// - `oversampling_factor`: Number of intermediate points per input sample.
+    /// - `interpolation_type`: Type of interpolation between the intermediate points.
+    /// - `chunk_size`: size of output data in frames
+    /// - `nbr_channels`: number of channels in input/output
+    ///
+    /// # Errors
+    ///
+    /// The function returns `ResampleError::InvalidParameter` if the resample ratio is not a positive number,
+    /// if `oversampling_factor`, `chunk_size` or `nbr_channels` is zero,
+    /// or if the number of taps of the prototype is not a non-zero multiple of 8 times `oversampling_factor`.
+    pub fn new_from_prototype(
+        resample_ratio: f64,
+        prototype: &[T],
+        oversampling_factor: usize,
+        interpolation_type: InterpolationType,
+        chunk_size: usize,
+        nbr_channels: usize,
+    ) -> ResampleResult<Self> {
+        validate_sizes(resample_ratio, chunk_size, nbr_channels)?;
+        validate_prototype(prototype.len(), oversampling_factor)?;
+        debug!(
+            "Create new SincFixedOut from prototype, ratio: {}, taps: {}, oversampling: {}",
+            resample_ratio,
+            prototype.len(),
+            oversampling_factor
+        );
+        let interpolator = make_interpolator_from_prototype(prototype, oversampling_factor);
+        Ok(Self::build(
+            resample_ratio,
+            interpolation_type,
+            interpolator,
+            chunk_size,
+            nbr_channels,
+        ))
+    }
+
+    /// Create a new SincFixedOut using precalculated sinc tables,
+    /// for example generated at compile time with `sinc_table_source`.
+    /// This avoids calculating the sincs when creating the resampler.
+    /// The table is still copied once, into the layout of the fastest available kernel,
+    /// and the buffers of the resampler are allocated, which takes time and memory
+    /// in proportion to the size of the table. To use a static table in place,
+    /// give an `interpolator_table::TableInterpolator` to `new_with_interpolator`.
+    ///
+    /// The table holds all the sincs one after the other, each `sinc_len` long,
+    /// where `sinc_len` must be a multiple of 8. The number of sincs gives the oversampling factor.
+    /// The table is used as is, so it must be made for the given resample ratio.
+    ///
+    /// Parameters are:
+    /// - `resample_ratio`: Ratio between output and input sample rates.
+    /// - `table`: The sinc table.
+    /// - `sinc_len`: Length of each sinc in the table.
+    /// - `interpolation_type`: Type of interpolation between the intermediate points.
+    /// - `chunk_size`: size of output data in frames
+    /// - `nbr_channels`: number of channels in input/output
+    ///
+    /// # Errors
+    ///
+    /// The function returns `ResampleError::InvalidParameter` if the resample ratio is not a positive number,
+    /// if `chunk_size` or `nbr_channels` is zero, if `sinc_len` is not a non-zero multiple of 8,
+    /// or if the length of the table is not a non-zero multiple of `sinc_len`.
+    pub fn new_from_table(
+        resample_ratio: f64,
+        table: &[T],
+        sinc_len: usize,
+        interpolation_type: InterpolationType,
+        chunk_size: usize,
+        nbr_channels: usize,
+    ) -> ResampleResult<Self> {
+        validate_sizes(resample_ratio, chunk_size, nbr_channels)?;
+        validate_table(table.len(), sinc_len)?;
+        debug!(
+            "Create new SincFixedOut from table, ratio: {}, sinc_len: {}, values: {}",
+            resample_ratio,
+            sinc_len,
+            table.len()
+        );
+        let interpolator = make_interpolator_from_table(table, sinc_len);
+        Ok(Self::build(
+            resample_ratio,
+            interpolation_type,
+            interpolator,
+            chunk_size,
+            nbr_channels,
+        ))
+    }
+
+    /// Create a new SincFixedOut using an existing Interpolator
+    ///
+    /// Parameters are:
+    /// - `resample_ratio`: Ratio between output and input sample rates.
+    /// - `interpolation_type`: Parameters for interpolation, see `InterpolationParameters`
+    /// - `interpolator`:  The interpolator to use
+    /// - `chunk_size`: size of output data in frames
+    /// - `nbr_channels`: number of channels in input/output
+    ///
+    /// # Errors
+    ///
+    /// The function returns `ResampleError::InvalidParameter` if the resample ratio is not a positive number,
+    /// or if `chunk_size` or `nbr_channels` is zero.
+    pub fn new_with_interpolator(
+        resample_ratio: f64,
+        interpolation_type: InterpolationType,
+        interpolator: Box<dyn SincInterpolator<T>>,
+        chunk_size: usize,
+        nbr_channels: usize,
+    ) -> ResampleResult<Self> {
+        validate_sizes(resample_ratio, chunk_size, nbr_channels)?;
+        Ok(Self::build(
+            resample_ratio,
+            interpolation_type,
+            interpolator,
+            chunk_size,
+            nbr_channels,
+        ))
+    }
+
+    /// Create a new SincFixedOut from an interpolator, with parameters that are already validated.
+    fn build(
+        resample_ratio: f64,
+        interpolation_type: InterpolationType,
+        interpolator: Box<dyn SincInterpolator<T>>,
+        chunk_size: usize,
+        nbr_channels: usize,
+    ) -> Self {
+        let needed_input_size =
+            (chunk_size as f64 / resample_ratio).ceil() as usize + 2 + interpolator.len() / 2;
+        let buffer_len = (BUFFER_CHUNKS + 1) * needed_input_size
+            + needed_input_size / 2
+            + 2 * interpolator.len();
+        let buffer = vec![vec![T::zero(); buffer_len]; nbr_channels];
+        let mut ratios = Vec::with_capacity(chunk_size);
+        ratios.push(resample_ratio);
+
+        SincFixedOut {
+            nbr_channels,
+            chunk_size,
+            needed_input_size,
+            last_index: -((interpolator.len() / 2) as f64),
+            current_buffer_fill: needed_input_size,
+            resample_ratio,
+            resample_ratio_original: resample_ratio,
+            target_ratio: resample_ratio,
+            ratio_smoothing: 0,
+            lanes: ChannelLanes::new(interpolator.as_ref(), nbr_channels, buffer_len, chunk_size),
+            convolution: FftConvolution::new(
+                interpolator.as_ref(),
+                DEFAULT_CONVOLUTION_THRESHOLD,
+                buffer_len,
+                chunk_size,
+            ),
+            phases: PhaseIndex::new(interpolator.nbr_sincs()),
+            interpolator: Arc::from(interpolator),
+            cutoff: None,
+            buffer,
+            buffer_start: 0,
+            interpolation: interpolation_type,
+            preview: false,
+            reverse: false,
+            modulation: None,
+            meter: Meter::new(nbr_channels),
+            silence_threshold: None,
+            parallel_threshold: DEFAULT_PARALLEL_THRESHOLD,
+            normalizer: Normalizer::new(),
+            output_buffer: (0..nbr_channels)
+                .map(|_| Vec::with_capacity(chunk_size))
+                .collect(),
+            ratios,
+            channels: ChannelLists::new(nbr_channels),
+        }
+    }
+
+    /// Enable or disable the preview mode.
+    /// In preview mode, each output sample is simply a copy of the nearest input sample,
+    /// without any filtering or interpolation. This is very cheap but gives poor quality,
+    /// and is intended for things like scrubbing, where responsiveness matters more than quality.
+    /// The mode can be switched at any time, the delay is the same in both modes.
+    pub fn set_preview_mode(&mut self, preview: bool) {
+        trace!("Set preview mode to {}", preview);
+        self.preview = preview;
+    }
+
+    /// Check if the preview mode is enabled.
+    pub fn is_preview_mode(&self) -> bool {
+        self.preview
+    }
+
+    /// Enable or disable reverse playback.
+    /// In reverse mode, each chunk of input is expected to hold the frames that precede
+    /// the previous chunk, in normal forward order. The resampler reverses each chunk internally
+    /// and produces output that plays the input backwards.
+    /// Since the sinc filters are symmetric, the anti-aliasing is the same in both directions.
+    ///
+    /// The direction can be changed at any time. The frames already in the internal buffer
+    /// are then played back as a turnaround, like when reversing a tape.
+    pub fn set_reverse(&mut self, reverse: bool) {
+        trace!("Set reverse playback to {}", reverse);
+        self.reverse = reverse;
+    }
+
+    /// Check if reverse playback is enabled.
+    pub fn is_reverse(&self) -> bool {
+        self.reverse
+    }
+
+    /// Enable or disable peak metering of the input.
+    /// When enabled, the peak levels are measured while the input is copied
+    /// into the internal buffer, and can be read with "input_peaks" after each call to "process".
+    pub fn set_peak_metering(&mut self, enabled: bool) {
+        trace!("Set peak metering to {}", enabled);
+        self.meter.set_peak_enabled(enabled);
+    }
+
+    /// Get the peak absolute values of the input of the last call to "process", one per channel.
+    /// The values are zero for channels that were not processed, and when metering is disabled.
+    pub fn input_peaks(&self) -> &[T] {
+        self.meter.peaks()
+    }
+
+    /// Enable or disable RMS metering of the input.
+    /// The RMS value of each channel is calculated per call to "process", and can be read with "metrics".
+    pub fn set_rms_metering(&mut self, enabled: bool) {
+        trace!("Set RMS metering to {}", enabled);
+        self.meter.set_rms_enabled(enabled);
+    }
+
+    /// Enable loudness metering of the input, or disable it by passing `None`.
+    /// The value is the sample rate of the input, needed for the K-weighting filter.
+    /// The loudness is accumulated over all calls to "process" until the metering
+    /// is disabled or enabled again, and can be read with "metrics".
+    pub fn set_loudness_metering(&mut self, sample_rate: Option<f64>) {
+        trace!("Set loudness metering to {:?}", sample_rate);
+        self.meter.set_loudness_sample_rate(sample_rate);
+    }
+
+    /// Get the measurements from the enabled input meters.
+    pub fn metrics(&self) -> &Metrics<T> {
+        self.meter.metrics()
+    }
+
+    /// Enable skipping of silent input, by setting a threshold for what is considered silence.
+    /// Channels where all the samples currently in the internal buffer are at or below the
+    /// threshold produce silent output, without running the sinc interpolation.
+    /// Since the buffer also holds the tail of the previous chunks,
+    /// the output fades out normally when the input becomes silent.
+    /// A threshold of zero only skips input that is exactly zero,
+    /// and gives the same output as without skipping.
+    /// Pass `None` to disable the skipping, which is the default.
+    pub fn set_silence_threshold(&mut self, threshold: Option<T>) {
+        trace!("Set silence threshold to {:?}", threshold);
+        self.silence_threshold = threshold;
+    }
+
+    /// Set the number of channels from which the channels are resampled in parallel,
+    /// on the rayon thread pool. The default is 8 channels.
+    /// For fewer channels, the overhead of distributing the work is larger than the gain.
+    /// This only has an effect when the `parallel` feature is enabled.
+    pub fn set_parallel_threshold(&mut self, nbr_channels: usize) {
+        trace!("Set parallel threshold to {} channels", nbr_channels);
+        self.parallel_threshold = nbr_channels;
+    }
+
+    /// Set the sinc length from which the interpolated points may be calculated
+    /// by FFT convolution instead of one by one. The default is 2048.
+    /// The convolution is only used for the sincs where it is cheaper,
+    /// which are those that are needed for many points of a chunk.
+    /// It pays off for long chunks and low oversampling factors.
+    /// Preparing it takes some time, and stores the spectra of the sincs.
+    /// Pass `usize::MAX` to always calculate the points one by one.
+    pub fn set_convolution_threshold(&mut self, sinc_len: usize) {
+        trace!("Set convolution threshold to {}", sinc_len);
+        let buffer_len = self.buffer.first().map(|wav| wav.len()).unwrap_or(0);
+        self.convolution = FftConvolution::new(
+            self.interpolator.as_ref(),
+            sinc_len,
+            buffer_len,
+            self.chunk_size,
+        );
+    }
+
+    /// Enable automatic gain of the output, or disable it by passing `None`.
+    /// The gain is applied while the output is written, and is updated after each chunk
+    /// to bring the output level towards the target.
+    /// The gain changes are ramped smoothly, with the time constant given in the parameters.
+    /// Note that this is not a limiter, and a sudden increase of the level may overshoot the target.
+    /// Changing the parameters resets the gain to unity.
+    pub fn set_normalization(&mut self, parameters: Option<NormalizationParameters>) {
+        trace!("Set normalization to {:?}", parameters);
+        self.normalizer.set_parameters(parameters);
+    }
+
+    /// Get the current gain of the output normalization.
+    /// This is always 1.0 when the normalization is disabled.
+    pub fn normalization_gain(&self) -> f64 {
+        self.normalizer.gain()
+    }
+
+    /// Get the latency of the resampler, for the current resample ratio.
+    /// The sinc interpolation starts with an offset that compensates for the delay of the sinc filter,
+    /// so the remaining latency is small, and it is negative when downsampling.
+    pub fn latency(&self) -> Latency {
+        let input_frames =
+            1.0 - 1.0 / self.resample_ratio - 1.0 / self.interpolator.nbr_sincs() as f64;
+        Latency {
+            input_frames,
+            output_frames: input_frames * self.resample_ratio,
+        }
+    }
+
+    /// Get the resample ratio as a fraction L/M, if the resampler is resampling exactly,
+    /// as set up by "new_rational".
+    /// This requires `Nearest` interpolation with an oversampling factor that is a multiple of L,
+    /// and that the ratio is not being smoothed or modulated.
+    /// Returns `None` otherwise, for example after the ratio has been changed to a value
+    /// that doesn't fit the oversampling factor.
+    pub fn rational_ratio(&self) -> Option<RationalRatio> {
+        if self.interpolation != InterpolationType::Nearest
+            || self.modulation.is_some()
+            || self.resample_ratio != self.target_ratio
+        {
+            return None;
+        }
+        exact_fraction(self.resample_ratio, self.interpolator.nbr_sincs())
+    }
+
+    /// Get the range of resample ratios where the anti-aliasing filter is valid,
+    /// meaning that its cutoff is at or below the Nyquist frequency of the output.
+    /// The range is also limited to the +-10% that "set_resample_ratio" accepts.
+    /// Adaptive controllers can keep their ratio within this range to avoid aliasing.
+    ///
+    /// Like at the original ratio, the transition band above the cutoff may still
+    /// let a little of the highest frequencies alias.
+    /// Returns `None` if the cutoff is not known, which is the case for resamplers
+    /// created from a prototype, a table or an interpolator,
+    /// or if no ratio in the allowed range is valid.
+    pub fn valid_ratio_range(&self) -> Option<(f64, f64)> {
+        let cutoff = self.cutoff?;
+        let lowest = 0.9 * self.resample_ratio_original;
+        let highest = 1.1 * self.resample_ratio_original;
+        let lowest = if cutoff > lowest { cutoff } else { lowest };
+        if lowest < highest {
+            Some((lowest, highest))
+        } else {
+            None
+        }
+    }
+
+    /// Check if the resample ratio has been changed so that the output may alias,
+    /// because the Nyquist frequency of the output is below the cutoff of the anti-aliasing filter.
+    /// While the ratio is smoothed, the lower of the current and the target ratio is checked.
+    /// The warning tells how much the filter gain at the output Nyquist frequency
+    /// exceeds the gain at the cutoff, which is about -6 dB.
+    /// Returns `None` if the ratio is within the range given by `valid_ratio_range`,
+    /// or if the cutoff is not known.
+    pub fn aliasing_warning(&self) -> Option<AliasingWarning> {
+        let cutoff = self.cutoff?;
+        let ratio = if self.target_ratio < self.resample_ratio {
+            self.target_ratio
+        } else {
+            self.resample_ratio
+        };
+        if ratio >= cutoff {
+            return None;
+        }
+        let excess_db = sinc_gain_db(self.interpolator.as_ref(), ratio)
+            - sinc_gain_db(self.interpolator.as_ref(), cutoff);
+        Some(AliasingWarning {
+            ratio,
+            cutoff,
+            excess_db,
+        })
+    }
+
+    /// Get a checksum of the sinc filters used by this resampler.
+    /// Two resamplers with the same checksum use identical filters,
+    /// which can be used to verify that different machines or versions of the library
+    /// will give the same output. The checksum is calculated on each call,
+    /// which takes about as long as creating the resampler.
+    pub fn filter_checksum(&self) -> u64 {
+        self.interpolator.checksum()
+    }
+
+    /// Get the oversampled version of the input, as used by the sinc interpolation.
+    /// This returns the number of frames of the last chunk times the oversampling factor points
+    /// per channel,
+    /// covering the same range as the last chunk given to "process",
+    /// but delayed by the length of the sinc filter.
+    /// The points are calculated on demand, which is about as expensive as
+    /// running the sinc interpolation for every one of them.
+    /// Useful for analysis, true-peak metering and custom interpolation.
+    pub fn oversampled_input(&self) -> Vec<Vec<T>> {
+        self.buffer
+            .iter()
+            .map(|buf| {
+                oversample_wave(
+                    self.interpolator.as_ref(),
+                    &buf[self.buffer_start..],
+                    self.current_buffer_fill,
+                )
+            })
+            .collect()
+    }
+
+    /// Attach a low frequency oscillator that modulates the resample ratio,
+    /// or remove it by passing `None`.
+    /// The oscillator is advanced by one step per output frame.
+    /// The modulated ratio is limited to +-10% of the original one.
+    pub fn set_modulation(&mut self, lfo: Option<Lfo>) {
+        trace!("Set modulation to {:?}", lfo);
+        self.modulation = lfo;
+        self.update_ratios();
+    }
+
+    /// Set the time constant for smoothing changes of the resample ratio, in output frames.
+    /// When set, a new ratio given to "set_resample_ratio" is not applied immediately,
+    /// but is approached exponentially over the following output frames, also across calls to "process".
+    /// This avoids audible steps when the ratio is updated at a low rate.
+    /// A value of zero disables the smoothing, and jumps directly to the latest target ratio.
+    pub fn set_ratio_smoothing(&mut self, time_constant: usize) {
+        trace!(
+            "Set ratio smoothing time constant to {} frames",
+            time_constant
+        );
+        self.ratio_smoothing = time_constant;
+        if time_constant == 0 {
+            self.resample_ratio = self.target_ratio;
+        }
+        self.update_ratios();
+    }
+
+    /// Change the number of output frames returned by each call to "process",
+    /// for example when the playback device has been reconfigured.
+    /// The buffers are resized as needed, and the buffered input is kept,
+    /// so that the output continues without any gap or discontinuity.
+    /// Call "nbr_frames_needed" afterwards to get the new number of input frames needed.
+    pub fn set_chunk_size(&mut self, chunk_size: usize) {
+        trace!("Change chunk size to {}", chunk_size);
+        self.chunk_size = chunk_size;
+        self.update_ratios();
+        let sinc_len = self.interpolator.len();
+        let max_needed_size =
+            (chunk_size as f64 / self.resample_ratio).ceil() as usize + 2 + sinc_len / 2;
+        // the window with the buffered frames is moved to the start, and must fit
+        let buffer_len =
+            ((BUFFER_CHUNKS + 1) * max_needed_size + max_needed_size / 2 + 2 * sinc_len)
+                .max(self.current_buffer_fill + 2 * sinc_len)
+                .max(self.needed_input_size + 2 * sinc_len);
+        let start = self.buffer_start;
+        for wav in self.buffer.iter_mut() {
+            wav.copy_within(start..start + self.current_buffer_fill + 2 * sinc_len, 0);
+            wav.resize(buffer_len, T::zero());
+        }
+        self.buffer_start = 0;
+    }
+
+    /// Query for the number of frames needed for a call to "process_with_ratio_curve"
+    /// with the given ratio curve.
+    pub fn nbr_frames_needed_for_ratio_curve(&self, ratios: &[f64]) -> usize {
+        if ratios.is_empty() {
+            return self.needed_input_size;
+        }
+        let t_sum: f64 = index_steps(ratios).take(self.chunk_size).sum();
+        (self.last_index as f32 + t_sum as f32 + self.interpolator.len() as f32).ceil() as usize + 2
+    }
+
+    /// Resample a chunk of audio at the current resample ratio, and update the modulation and
+    /// smoothing. The output is written to the output buffer.
+    fn process_chunk(&mut self, input: Input<T>) -> ResampleResult<()> {
+        let ratios = mem::take(&mut self.ratios);
+        let result = self.process_with_ratios(input, &ratios, self.needed_input_size);
+        self.ratios = ratios;
+        result?;
+        if let Some(lfo) = self.modulation.as_mut() {
+            lfo.advance(self.chunk_size);
+        }
+        if self.ratios.len() > 1 {
+            self.resample_ratio = smooth_ratio(
+                self.resample_ratio,
+                self.target_ratio,
+                self.ratio_smoothing,
+                self.chunk_size,
+            );
+        }
+        self.update_ratios();
+        Ok(())
+    }
+
+    /// Resample a chunk of audio, with the resample ratio varying from one output frame to the next.
+    /// This is intended for effects like tape or vinyl emulation,
+    /// where the speed changes continuously.
+    ///
+    /// The `ratios` slice holds one resample ratio per output frame.
+    /// If there are fewer values than the chunk size, the last value is held
+    /// for the remaining frames, and any values beyond the chunk size are ignored.
+    /// The number of input frames needed is given by "nbr_frames_needed_for_ratio_curve".
+    /// The last value becomes the new resample ratio, used by following calls to "process".
+    /// An empty slice gives the same result as "process".
+    ///
+    /// # Errors
+    ///
+    /// The function returns an error if any of the ratios is not within +-10% of the original one,
+    /// or if the input data doesn't match the number of channels and needed number of frames.
+    pub fn process_with_ratio_curve(
+        &mut self,
+        wave_in: &[Vec<T>],
+        ratios: &[f64],
+    ) -> ResampleResult<Vec<Vec<T>>> {
+        if ratios.is_empty() {
+            return self.process(wave_in);
+        }
+        if let Some(ratio) = ratios.iter().find(|r| {
+            !((*r / self.resample_ratio_original > 0.9)
+                && (*r / self.resample_ratio_original < 1.1))
+        }) {
+            return Err(ResampleError::RatioOutOfBounds {
+                provided: *ratio,
+                original: self.resample_ratio_original,
+                max_deviation: MAX_RATIO_DEVIATION,
+            });
+        }
+        let frames_needed = self.nbr_frames_needed_for_ratio_curve(ratios);
+        self.process_with_ratios(Input::Channels(wave_in), ratios, frames_needed)?;
+        self.resample_ratio = ratios[ratios.len() - 1];
+        self.target_ratio = self.resample_ratio;
+        self.update_ratios();
+        Ok(self.output_buffer.clone())
+    }
+
+    /// Resample a chunk of audio, using the given resample ratio for each output frame.
+    /// The last ratio is used for any remaining frames.
+    /// The input must contain `frames_needed` frames, and the output is written to the output buffer.
+    fn process_with_ratios(
+        &mut self,
+        input: Input<T>,
+        ratios: &[f64],
+        frames_needed: usize,
+    ) -> ResampleResult<()> {
+        //update buffer with new data
+        input
+            .used_channels(self.nbr_channels, frames_needed, &mut self.channels.used)
+            .map_err(|err| err.with_resampler(self))?;
+        let sinc_len = self.interpolator.len();
+        // move the window past the last chunk, keeping the history before it
+        self.buffer_start = advance_window(
+            &mut self.buffer,
+            self.buffer_start,
+            self.current_buffer_fill,
+            2 * sinc_len,
+            frames_needed,
+        );
+        self.current_buffer_fill = frames_needed;
+        let start = self.buffer_start;
+        let window = start..start + 2 * sinc_len + frames_needed;
+
+        for wave in self.output_buffer.iter_mut() {
+            wave.clear();
+        }
+
+        self.meter.clear();
+        for chan in self.channels.used.iter() {
+            let buffer = &mut self.buffer[*chan][start + 2 * sinc_len..window.end];
+            input.copy_channel(*chan, &mut self.meter, buffer);
+            if self.reverse {
+                buffer.reverse();
+            }
+            self.output_buffer[*chan].resize(self.chunk_size, T::zero());
+        }
+
+        // Channels where all samples in the buffer are silent produce silent output,
+        // and can skip the interpolation.
+        active_channels(
+            &self.buffer,
+            self.silence_threshold,
+            &self.channels.used,
+            window.clone(),
+            &mut self.channels.active,
+        );
+        // long sincs may be convolved with FFTs,
+        // and with many channels, groups of channels are interpolated together
+        match (self.convolution.as_mut(), self.lanes.as_mut()) {
+            (Some(convolution), _) if !self.preview => {
+                convolution.process(
+                    &*self.interpolator,
+                    &self.buffer,
+                    &mut self.output_buffer,
+                    &self.channels.active,
+                    start,
+                    self.interpolation,
+                    self.last_index,
+                    ratios,
+                    self.parallel_threshold,
+                );
+                self.channels.remaining.clear();
+            }
+            (_, Some(lanes)) if !self.preview => lanes.process(
+                &self.buffer,
+                &mut self.output_buffer,
+                &self.channels.active,
+                window,
+                self.interpolation,
+                self.last_index,
+                ratios,
+                self.parallel_threshold,
+                &mut self.channels.remaining,
+            ),
+            _ => {
+                self.channels.remaining.clear();
+                self.channels
+                    .remaining
+                    .extend_from_slice(&self.channels.active);
+            }
+        }
+        self.normalizer.start_chunk(self.chunk_size);
+
+        let interpolator = &*self.interpolator;
+        let phases = self.phases;
+        let interpolation = self.interpolation;
+        let preview = self.preview;
+        let last_index = self.last_index;
+        let buffer = &self.buffer;
+        let remaining_channels = &self.channels.remaining;
+        let channels = self
+            .output_buffer
+            .iter_mut()
+            .enumerate()
+            .filter(|(chan, _)| remaining_channels.contains(chan))
+            .map(|(chan, wave_out)| (&buffer[chan][start..], &mut wave_out[..]));
+        // the workers carry no state, and a vector of them doesn't allocate
+        let mut workers = vec![(); nbr_workers(self.nbr_channels, self.parallel_threshold)];
+        for_each_channel(
+            channels,
+            &mut workers,
+            self.parallel_threshold,
+            |(buf, wave_out), _| {
+                interpolate_channel(
+                    interpolator,
+                    phases,
+                    interpolation,
+                    preview,
+                    buf,
+                    wave_out,
+                    last_index,
+                    ratios,
+                )
+            },
+        );
+        for chan in self.channels.active.iter() {
+            self.normalizer.apply_wave(&mut self.output_buffer[*chan]);
+        }
+
+        let idx = index_steps(ratios)
+            .take(self.chunk_size)
+            .fold(self.last_index, |idx, step| idx + step);
+
+        self.normalizer.finish_chunk(self.chunk_size);
+
+        let prev_input_len = frames_needed;
+        // store last index for next iteration
+        self.last_index = idx - self.current_buffer_fill as f64;
+        trace!(
+            "Resampling channels {:?}, {} frames in, {} frames out, last index {}",
+            self.channels.used,
+            prev_input_len,
+            self.chunk_size,
+            self.last_index
+        );
+        Ok(())
+    }
+
+    /// Update the ratios for the output frames of the next call to "process",
+    /// including any smoothing and modulation,
+    /// and the number of input frames that are needed for them.
+    fn update_ratios(&mut self) {
+        let mut ratios = mem::take(&mut self.ratios);
+        ratio_curve(
+            self.resample_ratio,
+            self.target_ratio,
+            self.ratio_smoothing,
+            self.modulation.as_ref(),
+            self.resample_ratio_original,
+            self.chunk_size,
+            &mut ratios,
+        );
+        self.needed_input_size =
+            if self.modulation.is_some() || self.resample_ratio != self.target_ratio {
+                self.nbr_frames_needed_for_ratio_curve(&ratios)
+            } else {
+                (self.last_index as f32
+                    + self.chunk_size as f32 / self.resample_ratio as f32
+                    + self.interpolator.len() as f32)
+                    .ceil() as usize
+                    + 2
+            };
+        self.ratios = ratios;
+    }
+}
+
+/// Shows the configuration and the current state, without the contents of the buffers.
+impl<T> fmt::Debug for SincFixedOut<T> {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        f.debug_struct("SincFixedOut")
+            .field("nbr_channels", &self.nbr_channels)
+            .field("chunk_size", &self.chunk_size)
+            .field("needed_input_size", &self.needed_input_size)
+            .field("resample_ratio", &self.resample_ratio)
+            .field("target_ratio", &self.target_ratio)
+            .field("resample_ratio_original", &self.resample_ratio_original)
+            .field("sinc_len", &self.interpolator.len())
+            .field("oversampling_factor", &self.interpolator.nbr_sincs())
+            .field("interpolation", &self.interpolation)
+            .field("last_index", &self.last_index)
+            .finish()
+    }
+}
+
+impl<T> Resampler<T> for SincFixedOut<T>
+where
+    T: Sample,
+{
+    /// Query for the number of frames needed for the next call to "process".
+    fn nbr_frames_needed(&self) -> usize {
+        self.needed_input_size
+    }
+
+    /// Query for the number of frames that the next call to "process" will return.
+    /// Will always return the chunk_size defined when creating the instance.
+    fn nbr_output_frames_next(&self) -> usize {
+        self.chunk_size
+    }
+
+    /// Resample a chunk of audio. The required input length is provided by
+    /// the "nbr_frames_needed" function, and the output length is fixed.
+    /// If the waveform for a channel is empty, this channel will be ignored and produce a
+    /// corresponding empty output waveform.
+    /// # Errors
+    ///
+    /// The function returns an error if the length of the input data is not
+    /// equal to the number of channels defined when creating the instance,
+    /// and the number of audio frames given by "nbr_frames_needed".
+    fn process(&mut self, wave_in: &[Vec<T>]) -> ResampleResult<Vec<Vec<T>>> {
+        self.process_chunk(Input::Channels(wave_in))?;
+        Ok(self.output_buffer.clone())
+    }
+
+    /// Resample a chunk of audio like "process", but pass the output of each channel
+    /// to the closure `f` instead of returning it.
+    /// The output is taken directly from an internal buffer, without allocating any output vectors.
+    fn process_with(
+        &mut self,
+        wave_in: &[Vec<T>],
+        f: &mut dyn FnMut(usize, &[T]),
+    ) -> ResampleResult<()> {
+        self.process_chunk(Input::Channels(wave_in))?;
+        for (chan, wave) in self.output_buffer.iter().enumerate() {
+            f(chan, wave);
+        }
+        Ok(())
+    }
+
+    fn aliasing_warning(&self) -> Option<AliasingWarning> {
+        SincFixedOut::aliasing_warning(self)
+    }
+
+    fn output_delay(&self) -> f64 {
+        self.latency().output_frames
+    }
+
+    /// Clear the buffered input and return to the original resample ratio,
+    /// see "reset" of the `Resampler` trait. The sinc tables and the settings are kept.
+    fn reset(&mut self) {
+        for value in self.buffer.iter_mut().flatten() {
+            *value = T::zero();
+        }
+        self.buffer_start = 0;
+        for wave in self.output_buffer.iter_mut() {
+            wave.clear();
+        }
+        self.last_index = -((self.interpolator.len() / 2) as f64);
+        self.resample_ratio = self.resample_ratio_original;
+        self.target_ratio = self.resample_ratio_original;
+        if let Some(lfo) = self.modulation.as_mut() {
+            lfo.reset();
+        }
+        self.update_ratios();
+        self.meter.clear();
+        self.normalizer.reset();
+        self.needed_input_size = (self.chunk_size as f64 / self.resample_ratio).ceil() as usize
+            + 2
+            + self.interpolator.len() / 2;
+        self.current_buffer_fill = self.needed_input_size;
+    }
+
+    /// Emit the output frames that are held back at the end of a stream, see "flush"
+    /// of the `Resampler` trait. The number of frames is calculated for the current resample ratio.
+    /// It is usually less than a chunk, and the last chunk of silence is truncated.
+    fn flush(&mut self) -> ResampleResult<Vec<Vec<T>>> {
+        let nbr_frames = pending_frames(
+            self.last_index,
+            self.resample_ratio,
+            self.interpolator.len(),
+        );
+        offline::flush_with_silence(self, self.nbr_channels, nbr_frames)
+    }
+
+    /// Resample a chunk of audio like "process_unchecked" of the `Resampler` trait,
+    /// without checking the number of channels and frames, and without looking for empty channels.
+    unsafe fn process_unchecked(&mut self, wave_in: &[Vec<T>]) -> ResampleResult<Vec<Vec<T>>> {
+        self.process_chunk(Input::Unchecked(wave_in))?;
+        Ok(self.output_buffer.clone())
+    }
+
+    /// Resample a chunk of audio like "process_slices" of the `Resampler` trait,
+    /// but read the slices directly into the internal buffer, without copying them first.
+    fn process_slices(&mut self, wave_in: &[&[T]]) -> ResampleResult<Vec<Vec<T>>> {
+        self.process_chunk(Input::Slices(wave_in))?;
+        Ok(self.output_buffer.clone())
+    }
+
+    /// Resample a chunk of audio like "process_range" of the `Resampler` trait,
+    /// but read the range directly into the internal buffer, without copying it first.
+    fn process_range(
+        &mut self,
+        wave_in: &[Vec<T>],
+        offset: usize,
+        len: usize,
+    ) -> ResampleResult<Vec<Vec<T>>> {
+        self.process_chunk(Input::Range {
+            data: wave_in,
+            offset,
+            len,
+        })?;
+        Ok(self.output_buffer.clone())
+    }
+
+    /// Resample a chunk of audio like "process_strided" of the `Resampler` trait,
+    /// but read the input directly into the internal buffer, without de-interleaving it first.
+    fn process_strided(
+        &mut self,
+        nbr_channels: usize,
+        wave_in: &[T],
+        stride_in: usize,
+        wave_out: &mut [T],
+        stride_out: usize,
+    ) -> ResampleResult<usize> {
+        io::assert_strides(nbr_channels, stride_in, stride_out);
+        let nbr_frames_out = self.nbr_output_frames_next();
+        io::check_strided_len(wave_out.len(), nbr_channels, stride_out, nbr_frames_out)
+            .map_err(|err| err.with_resampler(self))?;
+        self.process_chunk(Input::Strided {
+            data: wave_in,
+            nbr_channels,
+            stride: stride_in,
+        })?;
+        for (chan, wave) in self.output_buffer.iter().enumerate() {
+            io::write_strided(wave_out, chan, stride_out, wave);
+        }
+        Ok(self
+            .output_buffer
+            .iter()
+            .map(|wave| wave.len())
+            .max()
+            .unwrap_or(0))
+    }
+
+    /// Resample a chunk of interleaved 16-bit audio like "process_interleaved_i16" of the `Resampler` trait,
+    /// but convert the input directly into the internal buffer while de-interleaving it,
+    /// and convert the output while interleaving it.
+    fn process_interleaved_i16(
+        &mut self,
+        nbr_channels: usize,
+        wave_in: &[i16],
+        wave_out: &mut [i16],
+        mut dither: Option<&mut Dither>,
+    ) -> ResampleResult<usize> {
+        io::assert_strides(nbr_channels, nbr_channels, nbr_channels);
+        let nbr_frames_out = self.nbr_output_frames_next();
+        io::check_strided_len(wave_out.len(), nbr_channels, nbr_channels, nbr_frames_out)
+            .map_err(|err| err.with_resampler(self))?;
+        self.process_chunk(Input::StridedI16 {
+            data: wave_in,
+            nbr_channels,
+            stride: nbr_channels,
+        })?;
+        for (chan, wave) in self.output_buffer.iter().enumerate() {
+            convert::write_strided_i16(wave_out, chan, nbr_channels, wave, dither.as_deref_mut());
+        }
+        Ok(self
+            .output_buffer
+            .iter()
+            .map(|wave| wave.len())
+            .max()
+            .unwrap_or(0))
+    }
+
+    /// Update the resample ratio. New value must be within +-10% of the original one
+    fn set_resample_ratio(&mut self, new_ratio: f64) -> ResampleResult<()> {
+        trace!("Change resample ratio to {}", new_ratio);
+        if (new_ratio / self.resample_ratio_original > 0.9)
+            && (new_ratio / self.resample_ratio_original < 1.1)
+        {
+            if let Some(cutoff) = self.cutoff {
+                if new_ratio < cutoff {
+                    warn!(
+                        "Resample ratio {} is below the filter cutoff {}, the output may alias",
+                        new_ratio, cutoff
+                    );
+                }
+            }
+            self.target_ratio = new_ratio;
+            if self.ratio_smoothing == 0 {
+                self.resample_ratio = new_ratio;
+            }
+            self.update_ratios();
+            Ok(())
+        } else {
+            Err(ResampleError::RatioOutOfBounds {
+                provided: new_ratio,
+                original: self.resample_ratio_original,
+                max_deviation: MAX_RATIO_DEVIATION,
+            })
+        }
+    }
+
+    /// Update the resample ratio relative to the original one
+    fn set_resample_ratio_relative(&mut self, rel_ratio: f64) -> ResampleResult<()> {
+        let new_ratio = self.resample_ratio_original * rel_ratio;
+        self.set_resample_ratio(new_ratio)
+    }
+
+    fn resample_ratio(&self) -> f64 {
+        self.resample_ratio
+    }
+
+    fn resample_ratio_original(&self) -> f64 {
+        self.resample_ratio_original
+    }
+
+    fn chunk_size(&self) -> usize {
+        self.chunk_size
+    }
+
+    fn nbr_channels(&self) -> usize {
+        self.nbr_channels
+    }
+
+    fn sinc_len(&self) -> Option<usize> {
+        Some(self.interpolator.len())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{index_steps, interp_cubic, interp_lin};
+    use crate::asynchro::ScalarInterpolator;
+    use crate::asynchro::SincInterpolator;
+    use crate::sinc::make_sincs;
+    use crate::InterpolationParameters;
+    use crate::InterpolationType;
+    use crate::Resampler;
+    use crate::WindowFunction;
+    use crate::{process_all, sinc_table_source, SincFixedIn, SincFixedOut};
+    use crate::{Lfo, LfoWaveform};
+    use crate::{NormalizationParameters, NormalizationTarget, Oversampler};
+    use num_traits::Float;
+    use rand::Rng;
+    use std::sync::Arc;
+
+    fn get_sinc_interpolated<T: Float>(wave: &[T], index: usize, sinc: &[T]) -> T {
+        let wave_cut = &wave[index..(index + sinc.len())];
+        wave_cut
+            .iter()
+            .zip(sinc.iter())
+            .fold(T::zero(), |acc, (x, y)| acc + *x * *y)
+    }
+
+    #[test]
+    fn test_scalar_interpolator_64() {
+        let mut rng = rand::thread_rng();
+        let mut wave = Vec::new();
+        for _ in 0..2048 {
+            wave.push(rng.gen::<f64>());
+        }
+        let sinc_len = 256;
+        let f_cutoff = 0.9473371669037001;
+        let oversampling_factor = 256;
+        let window = WindowFunction::BlackmanHarris2;
+
+        let interpolator =
+            ScalarInterpolator::<f64>::new(sinc_len, oversampling_factor, f_cutoff, window);
+        let value = interpolator.get_sinc_interpolated(&wave, 333, 123);
+        let check = get_sinc_interpolated(&wave, 333, &interpolator.sincs[123]);
+        assert!((value - check).abs() < 1.0e-9);
+    }
+
+    #[test]
+    fn test_scalar_interpolator_32() {
+        let mut rng = rand::thread_rng();
+        let mut wave = Vec::new();
+        for _ in 0..2048 {
+            wave.push(rng.gen::<f32>());
+        }
+        let sinc_len = 256;
+        let f_cutoff = 0.9473371669037001;
+        let oversampling_factor = 256;
+        let window = WindowFunction::BlackmanHarris2;
+
+        let interpolator =
+            ScalarInterpolator::<f32>::new(sinc_len, oversampling_factor, f_cutoff, window);
+        let value = interpolator.get_sinc_interpolated(&wave, 333, 123);
+        let check = get_sinc_interpolated(&wave, 333, &interpolator.sincs[123]);
+        assert!((value - check).abs() < 1.0e-6);
+    }
+
+    #[test]
+    fn int_cubic() {
+        let params = InterpolationParameters {
+            sinc_len: 64,
+            f_cutoff: 0.95,
+            interpolation: InterpolationType::Cubic,
+            oversampling_factor: 16,
+            window: WindowFunction::BlackmanHarris2,
+        };
+        let _resampler = SincFixedIn::<f64>::new(1.2, params, 1024, 2).unwrap();
+        let yvals = [0.0f64, 2.0f64, 4.0f64, 6.0f64];
+        let interp = interp_cubic(0.5f64, &yvals);
+        assert_eq!(interp, 3.0f64);
+    }
+
+    #[test]
+    fn int_lin_32() {
+        let params = InterpolationParameters {
+            sinc_len: 64,
+            f_cutoff: 0.95,
+            interpolation: InterpolationType::Cubic,
+            oversampling_factor: 16,
+            window: WindowFunction::BlackmanHarris2,
+        };
+        let _resampler = SincFixedIn::<f32>::new(1.2, params, 1024, 2).unwrap();
+        let yvals = [1.0f32, 5.0f32];
+        let interp = interp_lin(0.25f32, &yvals);
+        assert_eq!(interp, 2.0f32);
+    }
+
+    #[test]
+    fn int_cubic_32() {
+        let params = InterpolationParameters {
+            sinc_len: 64,
+            f_cutoff: 0.95,
+            interpolation: InterpolationType::Cubic,
+            oversampling_factor: 16,
+            window: WindowFunction::BlackmanHarris2,
+        };
+        let _resampler = SincFixedIn::<f32>::new(1.2, params, 1024, 2).unwrap();
+        let yvals = [0.0f32, 2.0f32, 4.0f32, 6.0f32];
+        let interp = interp_cubic(0.5f32, &yvals);
+        assert_eq!(interp, 3.0f32);
+    }
+
+    #[test]
+    fn int_lin() {
+        let params = InterpolationParameters {
+            sinc_len: 64,
+            f_cutoff: 0.95,
+            interpolation: InterpolationType::Cubic,
+            oversampling_factor: 16,
+            window: WindowFunction::BlackmanHarris2,
+        };
+        let _resampler = SincFixedIn::<f64>::new(1.2, params, 1024, 2).unwrap();
+        let yvals = [1.0f64, 5.0f64];
+        let interp = interp_lin(0.25f64, &yvals);
+        assert_eq!(interp, 2.0f64);
+    }
+
+    #[test]
+    fn steps_hold_last_ratio() {
+        let steps: Vec<f64> = index_steps(&[2.0, 4.0]).take(4).collect();
+        assert_eq!(steps, vec![0.5, 0.25, 0.25, 0.25]);
+        let steps: Vec<f64> = index_steps(&[0.5]).take(2).collect();
+        assert_eq!(steps, vec![2.0, 2.0]);
+    }
+
+    #[test]
+    fn make_resampler_fi() {
+        let params = InterpolationParameters {
+            sinc_len: 64,
+            f_cutoff: 0.95,
+            interpolation: InterpolationType::Cubic,
+            oversampling_factor: 16,
+            window: WindowFunction::BlackmanHarris2,
+        };
+        let mut resampler = SincFixedIn::<f64>::new(1.2, params, 1024, 2).unwrap();
+        let waves = vec![vec![0.0f64; 1024]; 2];
+        let out = resampler.process(&waves).unwrap();
+        assert_eq!(out.len(), 2, "Expected {} channels, got {}", 2, out.len());
+        assert!(
+            out[0].len() > 1150 && out[0].len() < 1229,
+            "expected {} - {} samples, got {}",
+            1150,
+            1229,
+            out[0].len()
+        );
+        let out2 = resampler.process(&waves).unwrap();
+        assert_eq!(out2.len(), 2, "Expected {} channels, got {}", 2, out2.len());
+        assert!(
+            out2[0].len() > 1226 && out2[0].len() < 1232,
+            "expected {} - {} samples, got {}",
+            1226,
+            1232,
+            out2[0].len()
+        );
+    }
+
+    #[test]
+    fn make_resampler_fi_32() {
+        let params = InterpolationParameters {
+            sinc_len: 64,
+            f_cutoff: 0.95,
+            interpolation: InterpolationType::Cubic,
+            oversampling_factor: 16,
+            window: WindowFunction::BlackmanHarris2,
+        };
+        let mut resampler = SincFixedIn::<f32>::new(1.2, params, 1024, 2).unwrap();
+        let waves = vec![vec![0.0f32; 1024]; 2];
+        let out = resampler.process(&waves).unwrap();
+        assert_eq!(out.len(), 2, "Expected {} channels, got {}", 2, out.len());
+        assert!(
+            out[0].len() > 1150 && out[0].len() < 1229,
+            "expected {} - {} samples, got {}",
+            1150,
+            1229,
+            out[0].len()
+        );
+        let out2 = resampler.process(&waves).unwrap();
+        assert_eq!(out2.len(), 2, "Expected {} channels, got {}", 2, out2.len());
+        assert!(
+            out2[0].len() > 1226 && out2[0].len() < 1232,
+            "expected {} - {} samples, got {}",
+            1226,
+            1232,
+            out2[0].len()
+        );
+    }
+
+    #[test]
+    fn make_resampler_fi_skipped() {
+        let params = InterpolationParameters {
+            sinc_len: 64,
+            f_cutoff: 0.95,
+            interpolation: InterpolationType::Cubic,
+            oversampling_factor: 16,
+            window: WindowFunction::BlackmanHarris2,
+        };
+        let mut resampler = SincFixedIn::<f64>::new(1.2, params, 1024, 2).unwrap();
+        let waves = vec![vec![0.0f64; 1024], Vec::new()];
+        let out = resampler.process(&waves).unwrap();
+        assert_eq!(out.len(), 2);
+        assert!(out[0].len() > 1150 && out[0].len() < 1250);
+        assert!(out[1].is_empty());
+        let waves = vec![Vec::new(), vec![0.0f64; 1024]];
+        let out = resampler.process(&waves).unwrap();
+        assert_eq!(out.len(), 2);
+        assert!(out[1].len() > 1150 && out[0].len() < 1250);
+        assert!(out[0].is_empty());
+    }
+
+    #[test]
+    fn make_resampler_fi_downsample() {
+        // Replicate settings from reported issue
+        let params = InterpolationParameters {
+            sinc_len: 256,
+            f_cutoff: 0.95,
+            interpolation: InterpolationType::Cubic,
+            oversampling_factor: 160,
+            window: WindowFunction::BlackmanHarris2,
+        };
+        let mut resampler =
+            SincFixedIn::<f64>::new(16000 as f64 / 96000 as f64, params, 1024, 2).unwrap();
+        let waves = vec![vec![0.0f64; 1024]; 2];
+        let out = resampler.process(&waves).unwrap();
+        assert_eq!(out.len(), 2, "Expected {} channels, got {}", 2, out.len());
+        assert!(
+            out[0].len() > 140 && out[0].len() < 200,
+            "expected {} - {} samples, got {}",
+            140,
+            200,
+            out[0].len()
+        );
+        let out2 = resampler.process(&waves).unwrap();
+        assert_eq!(out2.len(), 2, "Expected {} channels, got {}", 2, out2.len());
+        assert!(
+            out2[0].len() > 167 && out2[0].len() < 173,
+            "expected {} - {} samples, got {}",
+            167,
+            173,
+            out2[0].len()
+        );
+    }
+
+    #[test]
+    fn make_resampler_fi_upsample() {
+        // Replicate settings from reported issue
+        let params = InterpolationParameters {
+            sinc_len: 256,
+            f_cutoff: 0.95,
+            interpolation: InterpolationType::Cubic,
+            oversampling_factor: 160,
+            window: WindowFunction::BlackmanHarris2,
+        };
+        let mut resampler =
+            SincFixedIn::<f64>::new(192000 as f64 / 44100 as f64, params, 1024, 2).unwrap();
+        let waves = vec![vec![0.0f64; 1024]; 2];
+        let out = resampler.process(&waves).unwrap();
+        assert_eq!(out.len(), 2, "Expected {} channels, got {}", 2, out.len());
+        assert!(
+            out[0].len() > 3800 && out[0].len() < 4458,
+            "expected {} - {} samples, got {}",
+            3800,
+            4458,
+            out[0].len()
+        );
+        let out2 = resampler.process(&waves).unwrap();
+        assert_eq!(out2.len(), 2, "Expected {} channels, got {}", 2, out2.len());
+        assert!(
+            out2[0].len() > 4455 && out2[0].len() < 4461,
+            "expected {} - {} samples, got {}",
+            4455,
+            4461,
+            out2[0].len()
+        );
+    }
+
+    #[test]
+    fn check_output_frames_fi() {
+        let params = InterpolationParameters {
+            sinc_len: 64,
+            f_cutoff: 0.95,
+            interpolation: InterpolationType::Cubic,
+            oversampling_factor: 16,
+            window: WindowFunction::BlackmanHarris2,
+        };
+        let mut resampler = SincFixedIn::<f64>::new(1.2, params, 1024, 2).unwrap();
+        let waves = vec![vec![0.0f64; 1024]; 2];
+        for rel_ratio in [1.0, 1.05, 0.95, 1.09, 0.91].iter() {
+            resampler.set_resample_ratio_relative(*rel_ratio).unwrap();
+            for _ in 0..3 {
+                let expected = resampler.nbr_output_frames_next();
+                let out = resampler.process(&waves).unwrap();
+                assert_eq!(out[0].len(), expected);
+            }
+        }
+    }
+
+    #[test]
+    fn check_output_frames_schedule_fi() {
+        let params = InterpolationParameters {
+            sinc_len: 64,
+            f_cutoff: 0.95,
+            interpolation: InterpolationType::Linear,
+            oversampling_factor: 16,
+            window: WindowFunction::BlackmanHarris2,
+        };
+        let mut resampler = SincFixedIn::<f64>::new(48000.0 / 44100.0, params, 1000, 1).unwrap();
+        let schedule = resampler.output_frames_schedule(20);
+        let waves = vec![vec![0.0f64; 1000]; 1];
+        for expected in schedule.iter() {
+            let out = resampler.process(&waves).unwrap();
+            assert_eq!(out[0].len(), *expected);
+        }
+    }
+
+    #[test]
+    fn preview_mode_fi() {
+        let params = || InterpolationParameters {
+            sinc_len: 64,
+            f_cutoff: 0.95,
+            interpolation: InterpolationType::Cubic,
+            oversampling_factor: 16,
+            window: WindowFunction::BlackmanHarris2,
+        };
+        let mut resampler = SincFixedIn::<f64>::new(1.2, params(), 1024, 1).unwrap();
+        let mut preview = SincFixedIn::<f64>::new(1.2, params(), 1024, 1).unwrap();
+        preview.set_preview_mode(true);
+        assert!(preview.is_preview_mode());
+        for chunk in 0..3 {
+            let waves = vec![(0..1024)
+                .map(|n| (((chunk * 1024 + n) as f64) * std::f64::consts::PI / 100.0).sin())
+                .collect::<Vec<f64>>()];
+            let out = resampler.process(&waves).unwrap();
+            let out_preview = preview.process(&waves).unwrap();
+            assert_eq!(out[0].len(), out_preview[0].len());
+            if chunk > 0 {
+                for (a, b) in out[0].iter().zip(out_preview[0].iter()) {
+                    assert!((a - b).abs() < 0.05, "{} vs {}", a, b);
+                }
+            }
+        }
+    }
+
+    #[test]
+    fn preview_mode_fo() {
+        let params = || InterpolationParameters {
+            sinc_len: 64,
+            f_cutoff: 0.95,
+            interpolation: InterpolationType::Cubic,
+            oversampling_factor: 16,
+            window: WindowFunction::BlackmanHarris2,
+        };
+        let mut resampler = SincFixedOut::<f64>::new(0.8, params(), 1024, 1).unwrap();
+        let mut preview = SincFixedOut::<f64>::new(0.8, params(), 1024, 1).unwrap();
+        preview.set_preview_mode(true);
+        let mut start = 0;
+        for chunk in 0..3 {
+            let frames = resampler.nbr_frames_needed();
+            assert_eq!(frames, preview.nbr_frames_needed());
+            let waves = vec![(start..start + frames)
+                .map(|n| ((n as f64) * std::f64::consts::PI / 100.0).sin())
+                .collect::<Vec<f64>>()];
+            start += frames;
+            let out = resampler.process(&waves).unwrap();
+            let out_preview = preview.process(&waves).unwrap();
+            if chunk > 0 {
+                for (a, b) in out[0].iter().zip(out_preview[0].iter()) {
+                    assert!((a - b).abs() < 0.05, "{} vs {}", a, b);
+                }
+            }
+        }
+    }
+
+    #[test]
+    fn reverse_fi() {
+        let params = || InterpolationParameters {
+            sinc_len: 64,
+            f_cutoff: 0.95,
+            interpolation: InterpolationType::Cubic,
+            oversampling_factor: 16,
+            window: WindowFunction::BlackmanHarris2,
+        };
+        let mut rng = rand::thread_rng();
+        let wave: Vec<f64> = (0..3 * 1024).map(|_| rng.gen::<f64>()).collect();
+        let mut wave_rev = wave.clone();
+        wave_rev.reverse();
+        let mut resampler = SincFixedIn::<f64>::new(1.2, params(), 1024, 1).unwrap();
+        let mut reverse = SincFixedIn::<f64>::new(1.2, params(), 1024, 1).unwrap();
+        reverse.set_reverse(true);
+        assert!(reverse.is_reverse());
+        for (chunk, chunk_rev) in wave_rev.chunks(1024).zip(wave.rchunks(1024)) {
+            let out = resampler.process(&[chunk.to_vec()]).unwrap();
+            let out_rev = reverse.process(&[chunk_rev.to_vec()]).unwrap();
+            assert_eq!(out, out_rev);
+        }
+    }
+
+    #[test]
+    fn reverse_fo() {
+        let params = || InterpolationParameters {
+            sinc_len: 64,
+            f_cutoff: 0.95,
+            interpolation: InterpolationType::Linear,
+            oversampling_factor: 16,
+            window: WindowFunction::BlackmanHarris2,
+        };
+        let mut rng = rand::thread_rng();
+        let mut resampler = SincFixedOut::<f64>::new(0.8, params(), 1024, 1).unwrap();
+        let mut reverse = SincFixedOut::<f64>::new(0.8, params(), 1024, 1).unwrap();
+        reverse.set_reverse(true);
+        for _ in 0..3 {
+            let frames = resampler.nbr_frames_needed();
+            let chunk: Vec<f64> = (0..frames).map(|_| rng.gen::<f64>()).collect();
+            let mut chunk_rev = chunk.clone();
+            chunk_rev.reverse();
+            let out = resampler.process(&[chunk]).unwrap();
+            let out_rev = reverse.process(&[chunk_rev]).unwrap();
+            assert_eq!(out, out_rev);
+        }
+    }
+
+    #[test]
+    fn check_output_frames_fo() {
+        let params = InterpolationParameters {
+            sinc_len: 64,
+            f_cutoff: 0.95,
+            interpolation: InterpolationType::Cubic,
+            oversampling_factor: 16,
+            window: WindowFunction::BlackmanHarris2,
+        };
+        let mut resampler = SincFixedOut::<f64>::new(1.2, params, 1024, 2).unwrap();
+        for rel_ratio in [1.0, 1.05, 0.95].iter() {
+            resampler.set_resample_ratio_relative(*rel_ratio).unwrap();
+            let frames = resampler.nbr_frames_needed();
+            let waves = vec![vec![0.0f64; frames]; 2];
+            let expected = resampler.nbr_output_frames_next();
+            let out = resampler.process(&waves).unwrap();
+            assert_eq!(out[0].len(), expected);
+        }
+    }
+
+    #[test]
+    fn ratio_curve_fi() {
+        let params = || InterpolationParameters {
+            sinc_len: 64,
+            f_cutoff: 0.95,
+            interpolation: InterpolationType::Cubic,
+            oversampling_factor: 16,
+            window: WindowFunction::BlackmanHarris2,
+        };
+        let mut rng = rand::thread_rng();
+        let mut resampler = SincFixedIn::<f64>::new(1.2, params(), 1024, 1).unwrap();
+        let mut curved = SincFixedIn::<f64>::new(1.2, params(), 1024, 1).unwrap();
+        let waves = vec![(0..1024).map(|_| rng.gen::<f64>()).collect::<Vec<f64>>()];
+        let out = resampler.process(&waves).unwrap();
+        let out_curve = curved.process_with_ratio_curve(&waves, &[1.2; 10]).unwrap();
+        assert_eq!(out, out_curve);
+
+        let waves = vec![vec![0.0f64; 1024]; 1];
+        let curve: Vec<f64> = (0..1300).map(|n| 1.1 + 0.2 * n as f64 / 1300.0).collect();
+        let out = curved.process_with_ratio_curve(&waves, &curve).unwrap();
+        assert!(out[0].len() > 1024 && out[0].len() < 1300);
+        assert!(curved.nbr_output_frames_next() > resampler.nbr_output_frames_next());
+        assert!(curved
+            .process_with_ratio_curve(&waves, &[1.2, 1.4])
+            .is_err());
+        assert!(curved
+            .process_with_ratio_curve(&waves, &[1.2, f64::NAN])
+            .is_err());
+    }
+
+    #[test]
+    fn ratio_curve_fo() {
+        let params = || InterpolationParameters {
+            sinc_len: 64,
+            f_cutoff: 0.95,
+            interpolation: InterpolationType::Cubic,
+            oversampling_factor: 16,
+            window: WindowFunction::BlackmanHarris2,
+        };
+        let mut rng = rand::thread_rng();
+        let mut resampler = SincFixedOut::<f64>::new(1.2, params(), 1024, 1).unwrap();
+        let mut curved = SincFixedOut::<f64>::new(1.2, params(), 1024, 1).unwrap();
+        let frames = resampler.nbr_frames_needed();
+        assert_eq!(curved.nbr_frames_needed_for_ratio_curve(&[1.2]), frames);
+        let waves = vec![(0..frames).map(|_| rng.gen::<f64>()).collect::<Vec<f64>>()];
+        let out = resampler.process(&waves).unwrap();
+        let out_curve = curved.process_with_ratio_curve(&waves, &[1.2]).unwrap();
+        assert_eq!(out, out_curve);
+
+        let curve: Vec<f64> = (0..1024).map(|n| 1.1 + 0.2 * n as f64 / 1024.0).collect();
+        let frames = curved.nbr_frames_needed_for_ratio_curve(&curve);
+        assert!(frames > 1024 / 13 * 10 && frames < 1024 / 11 * 10 + 100);
+        let waves = vec![vec![0.0f64; frames]; 1];
+        let out = curved.process_with_ratio_curve(&waves, &curve).unwrap();
+        assert_eq!(out[0].len(), 1024);
+        assert!(curved.nbr_frames_needed() < resampler.nbr_frames_needed());
+        let waves = vec![vec![0.0f64; curved.nbr_frames_needed()]; 1];
+        assert!(curved
+            .process_with_ratio_curve(&waves, &[1.2, f64::NAN])
+            .is_err());
+    }
+
+    #[test]
+    fn modulation_fi() {
+        let params = InterpolationParameters {
+            sinc_len: 64,
+            f_cutoff: 0.95,
+            interpolation: InterpolationType::Linear,
+            oversampling_factor: 16,
+            window: WindowFunction::BlackmanHarris2,
+        };
+        let mut resampler = SincFixedIn::<f64>::new(1.2, params, 1024, 1).unwrap();
+        resampler.set_modulation(Some(Lfo::new(LfoWaveform::Sine, 5.0, 50.0, 48000.0)));
+        let schedule = resampler.output_frames_schedule(10);
+        let waves = vec![vec![0.0f64; 1024]; 1];
+        for expected in schedule.iter() {
+            assert_eq!(resampler.nbr_output_frames_next(), *expected);
+            let out = resampler.process(&waves).unwrap();
+            assert_eq!(out[0].len(), *expected);
+        }
+        // 50 cents is about 3%, so the output lengths vary more than without modulation
+        let min = schedule.iter().min().unwrap();
+        let max = schedule.iter().max().unwrap();
+        assert!(max - min > 10);
+    }
+
+    #[test]
+    fn modulation_fo() {
+        let params = InterpolationParameters {
+            sinc_len: 64,
+            f_cutoff: 0.95,
+            interpolation: InterpolationType::Linear,
+            oversampling_factor: 16,
+            window: WindowFunction::BlackmanHarris2,
+        };
+        let mut resampler = SincFixedOut::<f64>::new(1.2, params, 1024, 1).unwrap();
+        let unmodulated = resampler.nbr_frames_needed();
+        resampler.set_modulation(Some(Lfo::new(LfoWaveform::RandomWalk, 5.0, 50.0, 48000.0)));
+        let mut needed = Vec::new();
+        for _ in 0..10 {
+            let frames = resampler.nbr_frames_needed();
+            needed.push(frames);
+            let waves = vec![vec![0.0f64; frames]; 1];
+            let out = resampler.process(&waves).unwrap();
+            assert_eq!(out[0].len(), 1024);
+        }
+        assert!(needed.iter().any(|frames| *frames != unmodulated));
+    }
+
+    #[test]
+    fn ratio_smoothing_fi() {
         let params = InterpolationParameters {
             sinc_len: 64,
             f_cutoff: 0.95,
-            interpolation: InterpolationType::Cubic,
+            interpolation: InterpolationType::Linear,
             oversampling_factor: 16,
             window: WindowFunction::BlackmanHarris2,
         };
-        let _resampler = SincFixedIn::<f64>::new(1.2, params, 1024, 2);
-        let yvals = [0.0f64, 2.0f64, 4.0f64, 6.0f64];
-        let interp = interp_cubic(0.5f64, &yvals);
-        assert_eq!(interp, 3.0f64);
+        let mut resampler = SincFixedIn::<f64>::new(1.0, params, 1024, 1).unwrap();
+        resampler.set_ratio_smoothing(1000);
+        resampler.set_resample_ratio(1.05).unwrap();
+        assert_eq!(resampler.resample_ratio, 1.0);
+        let schedule = resampler.output_frames_schedule(30);
+        let waves = vec![vec![0.0f64; 1024]; 1];
+        let mut ratios = Vec::new();
+        for expected in schedule.iter() {
+            let out = resampler.process(&waves).unwrap();
+            assert_eq!(out[0].len(), *expected);
+            ratios.push(resampler.resample_ratio);
+        }
+        assert!(ratios.windows(2).all(|pair| pair[1] >= pair[0]));
+        assert!(ratios[0] > 1.0 && ratios[0] < 1.05);
+        assert_eq!(ratios[29], 1.05);
     }
 
     #[test]
-    fn int_lin_32() {
+    fn ratio_smoothing_fo() {
         let params = InterpolationParameters {
             sinc_len: 64,
             f_cutoff: 0.95,
-            interpolation: InterpolationType::Cubic,
+            interpolation: InterpolationType::Linear,
             oversampling_factor: 16,
             window: WindowFunction::BlackmanHarris2,
         };
-        let _resampler = SincFixedIn::<f32>::new(1.2, params, 1024, 2);
-        let yvals = [1.0f32, 5.0f32];
-        let interp = interp_lin(0.25f32, &yvals);
-        assert_eq!(interp, 2.0f32);
+        let mut resampler = SincFixedOut::<f64>::new(1.0, params, 1024, 1).unwrap();
+        resampler.set_ratio_smoothing(1000);
+        resampler.set_resample_ratio(0.95).unwrap();
+        for _ in 0..30 {
+            let frames = resampler.nbr_frames_needed();
+            let waves = vec![vec![0.0f64; frames]; 1];
+            let out = resampler.process(&waves).unwrap();
+            assert_eq!(out[0].len(), 1024);
+        }
+        assert_eq!(resampler.resample_ratio, 0.95);
+        resampler.set_resample_ratio(1.05).unwrap();
+        resampler.set_ratio_smoothing(0);
+        assert_eq!(resampler.resample_ratio, 1.05);
     }
 
     #[test]
-    fn int_cubic_32() {
+    fn peak_metering_fo() {
         let params = InterpolationParameters {
             sinc_len: 64,
             f_cutoff: 0.95,
-            interpolation: InterpolationType::Cubic,
+            interpolation: InterpolationType::Linear,
             oversampling_factor: 16,
             window: WindowFunction::BlackmanHarris2,
         };
-        let _resampler = SincFixedIn::<f32>::new(1.2, params, 1024, 2);
-        let yvals = [0.0f32, 2.0f32, 4.0f32, 6.0f32];
-        let interp = interp_cubic(0.5f32, &yvals);
-        assert_eq!(interp, 3.0f32);
+        let mut resampler = SincFixedOut::<f64>::new(1.2, params, 1024, 2).unwrap();
+        resampler.set_peak_metering(true);
+        let frames = resampler.nbr_frames_needed();
+        let mut waves = vec![vec![0.0f64; frames]; 2];
+        waves[0][10] = 0.5;
+        waves[1][frames - 1] = -0.9;
+        resampler.process(&waves).unwrap();
+        assert_eq!(resampler.input_peaks(), &[0.5, 0.9]);
+        resampler.set_peak_metering(false);
+        let frames = resampler.nbr_frames_needed();
+        let waves = vec![vec![0.5f64; frames]; 2];
+        resampler.process(&waves).unwrap();
+        assert_eq!(resampler.input_peaks(), &[0.0, 0.0]);
     }
 
     #[test]
-    fn int_lin() {
-        let params = InterpolationParameters {
+    fn silence_skip_fi() {
+        let params = || InterpolationParameters {
             sinc_len: 64,
             f_cutoff: 0.95,
             interpolation: InterpolationType::Cubic,
             oversampling_factor: 16,
             window: WindowFunction::BlackmanHarris2,
         };
-        let _resampler = SincFixedIn::<f64>::new(1.2, params, 1024, 2);
-        let yvals = [1.0f64, 5.0f64];
-        let interp = interp_lin(0.25f64, &yvals);
-        assert_eq!(interp, 2.0f64);
+        let mut rng = rand::thread_rng();
+        let mut resampler = SincFixedIn::<f64>::new(1.2, params(), 256, 2).unwrap();
+        let mut skipping = SincFixedIn::<f64>::new(1.2, params(), 256, 2).unwrap();
+        skipping.set_silence_threshold(Some(0.0));
+        let noise: Vec<f64> = (0..256).map(|_| rng.gen::<f64>()).collect();
+        let silence = vec![0.0; 256];
+        for chunk in [&noise, &silence, &silence, &noise, &silence].iter() {
+            let waves = vec![chunk.to_vec(), silence.clone()];
+            let out = resampler.process(&waves).unwrap();
+            let out_skip = skipping.process(&waves).unwrap();
+            assert_eq!(out, out_skip);
+        }
     }
 
     #[test]
-    fn make_resampler_fi() {
-        let params = InterpolationParameters {
+    fn filter_checksum() {
+        let params = || InterpolationParameters {
             sinc_len: 64,
             f_cutoff: 0.95,
             interpolation: InterpolationType::Cubic,
             oversampling_factor: 16,
             window: WindowFunction::BlackmanHarris2,
         };
-        let mut resampler = SincFixedIn::<f64>::new(1.2, params, 1024, 2);
-        let waves = vec![vec![0.0f64; 1024]; 2];
-        let out = resampler.process(&waves).unwrap();
-        assert_eq!(out.len(), 2, "Expected {} channels, got {}", 2, out.len());
-        assert!(
-            out[0].len() > 1150 && out[0].len() < 1229,
-            "expected {} - {} samples, got {}",
-            1150,
-            1229,
-            out[0].len()
+        let resampler_in = SincFixedIn::<f64>::new(1.2, params(), 256, 2).unwrap();
+        let resampler_out = SincFixedOut::<f64>::new(1.5, params(), 256, 2).unwrap();
+        let scalar = ScalarInterpolator::<f64>::new(64, 16, 0.95, WindowFunction::BlackmanHarris2);
+        assert_eq!(resampler_in.filter_checksum(), scalar.checksum());
+        assert_eq!(
+            resampler_in.filter_checksum(),
+            resampler_out.filter_checksum()
         );
-        let out2 = resampler.process(&waves).unwrap();
-        assert_eq!(out2.len(), 2, "Expected {} channels, got {}", 2, out2.len());
-        assert!(
-            out2[0].len() > 1226 && out2[0].len() < 1232,
-            "expected {} - {} samples, got {}",
-            1226,
-            1232,
-            out2[0].len()
+        let downsampler = SincFixedIn::<f64>::new(0.5, params(), 256, 2).unwrap();
+        assert_ne!(
+            resampler_in.filter_checksum(),
+            downsampler.filter_checksum()
         );
     }
 
     #[test]
-    fn make_resampler_fi_32() {
+    fn prototype_fi() {
         let params = InterpolationParameters {
             sinc_len: 64,
             f_cutoff: 0.95,
@@ -853,112 +4172,220 @@ mod tests {
             oversampling_factor: 16,
             window: WindowFunction::BlackmanHarris2,
         };
-        let mut resampler = SincFixedIn::<f32>::new(1.2, params, 1024, 2);
-        let waves = vec![vec![0.0f32; 1024]; 2];
-        let out = resampler.process(&waves).unwrap();
-        assert_eq!(out.len(), 2, "Expected {} channels, got {}", 2, out.len());
-        assert!(
-            out[0].len() > 1150 && out[0].len() < 1229,
-            "expected {} - {} samples, got {}",
-            1150,
-            1229,
-            out[0].len()
-        );
-        let out2 = resampler.process(&waves).unwrap();
-        assert_eq!(out2.len(), 2, "Expected {} channels, got {}", 2, out2.len());
-        assert!(
-            out2[0].len() > 1226 && out2[0].len() < 1232,
-            "expected {} - {} samples, got {}",
-            1226,
-            1232,
-            out2[0].len()
-        );
+        // rebuild the prototype from the sincs, with a different scaling
+        let sincs = make_sincs::<f64>(64, 16, 0.95, WindowFunction::BlackmanHarris2);
+        let mut prototype = vec![0.0; 64 * 16];
+        for p in 0..64 {
+            for n in 0..16 {
+                prototype[16 * p + n] = 2.5 * sincs[16 - n - 1][p];
+            }
+        }
+        let mut resampler = SincFixedIn::<f64>::new(1.2, params, 256, 1).unwrap();
+        let mut custom = SincFixedIn::<f64>::new_from_prototype(
+            1.2,
+            &prototype,
+            16,
+            InterpolationType::Cubic,
+            256,
+            1,
+        )
+        .unwrap();
+        let mut rng = rand::thread_rng();
+        for _ in 0..3 {
+            let waves = vec![(0..256).map(|_| rng.gen::<f64>()).collect::<Vec<f64>>()];
+            let out = resampler.process(&waves).unwrap();
+            let out_custom = custom.process(&waves).unwrap();
+            assert_eq!(out[0].len(), out_custom[0].len());
+            for (value, custom_value) in out[0].iter().zip(out_custom[0].iter()) {
+                assert!((value - custom_value).abs() < 1.0e-9);
+            }
+        }
     }
 
     #[test]
-    fn make_resampler_fi_skipped() {
-        let params = InterpolationParameters {
+    fn change_chunk_size_fo() {
+        let params = || InterpolationParameters {
             sinc_len: 64,
             f_cutoff: 0.95,
             interpolation: InterpolationType::Cubic,
             oversampling_factor: 16,
             window: WindowFunction::BlackmanHarris2,
         };
-        let mut resampler = SincFixedIn::<f64>::new(1.2, params, 1024, 2);
-        let waves = vec![vec![0.0f64; 1024], Vec::new()];
-        let out = resampler.process(&waves).unwrap();
-        assert_eq!(out.len(), 2);
-        assert!(out[0].len() > 1150 && out[0].len() < 1250);
-        assert!(out[1].is_empty());
-        let waves = vec![Vec::new(), vec![0.0f64; 1024]];
-        let out = resampler.process(&waves).unwrap();
-        assert_eq!(out.len(), 2);
-        assert!(out[1].len() > 1150 && out[0].len() < 1250);
-        assert!(out[0].is_empty());
+        let mut rng = rand::thread_rng();
+        let wave = (0..4000).map(|_| rng.gen::<f64>()).collect::<Vec<f64>>();
+        let run = |resampler: &mut SincFixedOut<f64>, sizes: &[usize]| {
+            let mut position = 0;
+            let mut output = Vec::new();
+            for size in sizes {
+                if resampler.nbr_output_frames_next() != *size {
+                    resampler.set_chunk_size(*size);
+                }
+                let nbr_frames = resampler.nbr_frames_needed();
+                let chunk = vec![wave[position..position + nbr_frames].to_vec()];
+                position += nbr_frames;
+                let out = resampler.process(&chunk).unwrap();
+                assert_eq!(out[0].len(), *size);
+                output.extend_from_slice(&out[0]);
+            }
+            output
+        };
+        let mut resampler = SincFixedOut::<f64>::new(1.3, params(), 256, 1).unwrap();
+        let mut reference = SincFixedOut::<f64>::new(1.3, params(), 128, 1).unwrap();
+        let output = run(&mut resampler, &[256, 256, 128, 128, 128, 1024]);
+        let expected = run(&mut reference, &[128; 15]);
+        assert_eq!(output.len(), expected.len());
+        for (value, expected) in output.iter().zip(expected.iter()) {
+            assert!((value - expected).abs() < 1.0e-9);
+        }
     }
 
     #[test]
-    fn make_resampler_fi_downsample() {
-        // Replicate settings from reported issue
+    fn without_anti_aliasing_fi() {
+        let params = || InterpolationParameters {
+            sinc_len: 64,
+            f_cutoff: 0.95,
+            interpolation: InterpolationType::Linear,
+            oversampling_factor: 16,
+            window: WindowFunction::BlackmanHarris2,
+        };
+        // a tone at 60% of the input Nyquist frequency, above the output Nyquist frequency
+        let wave = vec![(0..4096)
+            .map(|n| (0.6 * std::f64::consts::PI * n as f64).sin())
+            .collect::<Vec<f64>>()];
+        let rms = |resampler: &mut SincFixedIn<f64>| {
+            let output = process_all(resampler, &wave).unwrap();
+            let tail = &output[0][500..1500];
+            (tail.iter().map(|v| v * v).sum::<f64>() / tail.len() as f64).sqrt()
+        };
+        let mut filtered = SincFixedIn::<f64>::new(0.5, params(), 1024, 1).unwrap();
+        let mut unfiltered =
+            SincFixedIn::<f64>::new_without_anti_aliasing(0.5, params(), 1024, 1).unwrap();
+        assert!(rms(&mut filtered) < 0.01);
+        assert!((rms(&mut unfiltered) - 0.5f64.sqrt()).abs() < 0.05);
+    }
+
+    #[test]
+    fn table_fo() {
         let params = InterpolationParameters {
-            sinc_len: 256,
+            sinc_len: 60,
+            f_cutoff: 0.95,
+            interpolation: InterpolationType::Linear,
+            oversampling_factor: 16,
+            window: WindowFunction::BlackmanHarris2,
+        };
+        let source = sinc_table_source::<f32>("SINCS", &params, 0.8);
+        assert!(source.starts_with("pub const SINCS_SINC_LEN: usize = 64;\n"));
+        assert!(source.contains("pub static SINCS: [f32; 1024] = [\n"));
+        let source_f64 = sinc_table_source::<f64>("SINCS", &params, 0.8);
+        assert!(source_f64.contains("pub static SINCS: [f64; 1024] = [\n"));
+        let table = source
+            .lines()
+            .filter(|line| line.starts_with("    "))
+            .map(|line| line.trim().trim_end_matches(',').parse::<f32>().unwrap())
+            .collect::<Vec<f32>>();
+        assert_eq!(table.len(), 1024);
+
+        let mut resampler = SincFixedOut::<f32>::new(0.8, params, 256, 1).unwrap();
+        let mut baked =
+            SincFixedOut::<f32>::new_from_table(0.8, &table, 64, InterpolationType::Linear, 256, 1)
+                .unwrap();
+        assert_eq!(resampler.nbr_frames_needed(), baked.nbr_frames_needed());
+        let mut rng = rand::thread_rng();
+        for _ in 0..3 {
+            let nbr_frames = resampler.nbr_frames_needed();
+            let waves = vec![(0..nbr_frames)
+                .map(|_| rng.gen::<f32>())
+                .collect::<Vec<f32>>()];
+            let out = resampler.process(&waves).unwrap();
+            let out_baked = baked.process(&waves).unwrap();
+            for (value, baked_value) in out[0].iter().zip(out_baked[0].iter()) {
+                assert!((value - baked_value).abs() < 1.0e-5);
+            }
+        }
+    }
+
+    #[test]
+    fn latency() {
+        let params = || InterpolationParameters {
+            sinc_len: 64,
             f_cutoff: 0.95,
             interpolation: InterpolationType::Cubic,
-            oversampling_factor: 160,
+            oversampling_factor: 64,
             window: WindowFunction::BlackmanHarris2,
         };
-        let mut resampler = SincFixedIn::<f64>::new(16000 as f64 / 96000 as f64, params, 1024, 2);
-        let waves = vec![vec![0.0f64; 1024]; 2];
-        let out = resampler.process(&waves).unwrap();
-        assert_eq!(out.len(), 2, "Expected {} channels, got {}", 2, out.len());
-        assert!(
-            out[0].len() > 140 && out[0].len() < 200,
-            "expected {} - {} samples, got {}",
-            140,
-            200,
-            out[0].len()
-        );
-        let out2 = resampler.process(&waves).unwrap();
-        assert_eq!(out2.len(), 2, "Expected {} channels, got {}", 2, out2.len());
-        assert!(
-            out2[0].len() > 167 && out2[0].len() < 173,
-            "expected {} - {} samples, got {}",
-            167,
-            173,
-            out2[0].len()
-        );
+        let waves = vec![(0..10000)
+            .map(|n| (0.05 * n as f64).sin())
+            .collect::<Vec<f64>>()];
+        for ratio in &[0.7, 2.0] {
+            let mut resampler = SincFixedIn::<f64>::new(*ratio, params(), 256, 1).unwrap();
+            let mut resampler_out = SincFixedOut::<f64>::new(*ratio, params(), 256, 1).unwrap();
+            let latency = resampler.latency();
+            assert_eq!(latency, resampler_out.latency());
+            assert!((latency.output_frames - latency.input_frames * ratio).abs() < 1.0e-12);
+            let out = process_all(&mut resampler, &waves).unwrap();
+            let out_fo = process_all(&mut resampler_out, &waves).unwrap();
+            for n in 1000..5000 {
+                let expected = (0.05 * (n as f64 / ratio - latency.input_frames)).sin();
+                assert!((out[0][n] - expected).abs() < 1.0e-6);
+                assert!((out_fo[0][n] - expected).abs() < 1.0e-6);
+            }
+        }
     }
 
     #[test]
-    fn make_resampler_fi_upsample() {
-        // Replicate settings from reported issue
+    fn normalization_fo() {
         let params = InterpolationParameters {
-            sinc_len: 256,
+            sinc_len: 64,
             f_cutoff: 0.95,
-            interpolation: InterpolationType::Cubic,
-            oversampling_factor: 160,
+            interpolation: InterpolationType::Linear,
+            oversampling_factor: 16,
             window: WindowFunction::BlackmanHarris2,
         };
-        let mut resampler = SincFixedIn::<f64>::new(192000 as f64 / 44100 as f64, params, 1024, 2);
-        let waves = vec![vec![0.0f64; 1024]; 2];
-        let out = resampler.process(&waves).unwrap();
-        assert_eq!(out.len(), 2, "Expected {} channels, got {}", 2, out.len());
-        assert!(
-            out[0].len() > 3800 && out[0].len() < 4458,
-            "expected {} - {} samples, got {}",
-            3800,
-            4458,
-            out[0].len()
-        );
-        let out2 = resampler.process(&waves).unwrap();
-        assert_eq!(out2.len(), 2, "Expected {} channels, got {}", 2, out2.len());
-        assert!(
-            out2[0].len() > 4455 && out2[0].len() < 4461,
-            "expected {} - {} samples, got {}",
-            4455,
-            4461,
-            out2[0].len()
-        );
+        let mut resampler = SincFixedOut::<f64>::new(1.2, params, 1024, 1).unwrap();
+        resampler.set_normalization(Some(NormalizationParameters {
+            target: NormalizationTarget::Peak(0.8),
+            time_constant: 2048,
+            max_gain: 10.0,
+        }));
+        let mut phase = 0.0;
+        let mut peak = 0.0;
+        for _ in 0..30 {
+            let frames = resampler.nbr_frames_needed();
+            let wave: Vec<f64> = (0..frames)
+                .map(|_| {
+                    phase += 0.05;
+                    0.2 * phase.sin()
+                })
+                .collect();
+            let out = resampler.process(&[wave]).unwrap();
+            peak = out[0].iter().fold(0.0, |acc: f64, x| acc.max(x.abs()));
+        }
+        assert!((peak - 0.8).abs() < 0.01, "{}", peak);
+        assert!((resampler.normalization_gain() - 4.0).abs() < 0.05);
+    }
+
+    #[test]
+    fn oversampled_input_fi() {
+        let params = || InterpolationParameters {
+            sinc_len: 64,
+            f_cutoff: 0.95,
+            interpolation: InterpolationType::Linear,
+            oversampling_factor: 16,
+            window: WindowFunction::BlackmanHarris2,
+        };
+        let mut rng = rand::thread_rng();
+        let mut resampler = SincFixedIn::<f64>::new(1.2, params(), 128, 2).unwrap();
+        let mut oversampler = Oversampler::<f64>::new(1.2, params(), 128, 2);
+        for _ in 0..3 {
+            let waves: Vec<Vec<f64>> = (0..2)
+                .map(|_| (0..128).map(|_| rng.gen::<f64>()).collect())
+                .collect();
+            resampler.process(&waves).unwrap();
+            let expected = oversampler.process(&waves).unwrap();
+            let oversampled = resampler.oversampled_input();
+            assert_eq!(oversampled[0].len(), 128 * 16);
+            assert_eq!(oversampled, expected);
+        }
     }
 
     #[test]
@@ -970,7 +4397,7 @@ mod tests {
             oversampling_factor: 16,
             window: WindowFunction::BlackmanHarris2,
         };
-        let mut resampler = SincFixedOut::<f64>::new(1.2, params, 1024, 2);
+        let mut resampler = SincFixedOut::<f64>::new(1.2, params, 1024, 2).unwrap();
         let frames = resampler.nbr_frames_needed();
         println!("{}", frames);
         assert!(frames > 800 && frames < 900);
@@ -989,7 +4416,7 @@ mod tests {
             oversampling_factor: 16,
             window: WindowFunction::BlackmanHarris2,
         };
-        let mut resampler = SincFixedOut::<f32>::new(1.2, params, 1024, 2);
+        let mut resampler = SincFixedOut::<f32>::new(1.2, params, 1024, 2).unwrap();
         let frames = resampler.nbr_frames_needed();
         println!("{}", frames);
         assert!(frames > 800 && frames < 900);
@@ -1008,7 +4435,7 @@ mod tests {
             oversampling_factor: 16,
             window: WindowFunction::BlackmanHarris2,
         };
-        let mut resampler = SincFixedOut::<f64>::new(1.2, params, 1024, 2);
+        let mut resampler = SincFixedOut::<f64>::new(1.2, params, 1024, 2).unwrap();
         let frames = resampler.nbr_frames_needed();
         println!("{}", frames);
         assert!(frames > 800 && frames < 900);
@@ -1045,7 +4472,7 @@ mod tests {
             oversampling_factor: 160,
             window: WindowFunction::BlackmanHarris2,
         };
-        let mut resampler = SincFixedOut::<f64>::new(0.125, params, 1024, 2);
+        let mut resampler = SincFixedOut::<f64>::new(0.125, params, 1024, 2).unwrap();
         let frames = resampler.nbr_frames_needed();
         println!("{}", frames);
         assert!(
@@ -1093,7 +4520,7 @@ mod tests {
             oversampling_factor: 160,
             window: WindowFunction::BlackmanHarris2,
         };
-        let mut resampler = SincFixedOut::<f64>::new(8.0, params, 1024, 2);
+        let mut resampler = SincFixedOut::<f64>::new(8.0, params, 1024, 2).unwrap();
         let frames = resampler.nbr_frames_needed();
         println!("{}", frames);
         assert!(
@@ -1131,4 +4558,263 @@ mod tests {
             out2[0].len()
         );
     }
+
+    #[test]
+    fn clone_shares_sincs() {
+        let params = InterpolationParameters {
+            sinc_len: 64,
+            f_cutoff: 0.95,
+            interpolation: InterpolationType::Cubic,
+            oversampling_factor: 128,
+            window: WindowFunction::BlackmanHarris2,
+        };
+        let waves = vec![(0..256)
+            .map(|n| (n as f64 * 0.05).sin())
+            .collect::<Vec<f64>>()];
+        let mut resampler = SincFixedIn::<f64>::new(1.2, params.clone(), 256, 1).unwrap();
+        let mut clone = resampler.clone();
+        assert!(Arc::ptr_eq(&resampler.interpolator, &clone.interpolator));
+        for _ in 0..3 {
+            assert_eq!(
+                resampler.process(&waves).unwrap(),
+                clone.process(&waves).unwrap()
+            );
+        }
+        // the state is copied, not shared
+        let mut prototype = SincFixedOut::<f64>::new(0.8, params, 256, 1).unwrap();
+        let mut clone = prototype.clone();
+        clone
+            .process(&[vec![0.5; clone.nbr_frames_needed()]])
+            .unwrap();
+        let waves = vec![vec![0.0; prototype.nbr_frames_needed()]];
+        assert!(prototype.process(&waves).unwrap()[0]
+            .iter()
+            .all(|v| *v == 0.0));
+    }
+
+    #[test]
+    fn rational_ratio() {
+        let params = InterpolationParameters {
+            sinc_len: 64,
+            f_cutoff: 0.95,
+            interpolation: InterpolationType::Cubic,
+            oversampling_factor: 128,
+            window: WindowFunction::BlackmanHarris2,
+        };
+        let ratio = 48000.0 / 44100.0 * (1.0 + 1.0e-8);
+        let mut resampler =
+            SincFixedIn::<f64>::new_rational(ratio, params.clone(), 256, 1.0e-6, 1024, 1).unwrap();
+        let fraction = resampler.rational_ratio().unwrap();
+        assert_eq!((fraction.numerator, fraction.denominator), (160, 147));
+        let exact_params = InterpolationParameters {
+            interpolation: InterpolationType::Nearest,
+            oversampling_factor: 160,
+            ..params.clone()
+        };
+        let mut reference = SincFixedIn::<f64>::new(160.0 / 147.0, exact_params, 1024, 1).unwrap();
+        let waves = vec![(0..1024)
+            .map(|n| (n as f64 * 0.05).sin())
+            .collect::<Vec<f64>>()];
+        for _ in 0..3 {
+            assert_eq!(
+                resampler.process(&waves).unwrap(),
+                reference.process(&waves).unwrap()
+            );
+        }
+        resampler.set_resample_ratio(1.09).unwrap();
+        assert_eq!(resampler.rational_ratio(), None);
+
+        // an irrational ratio is kept, with the given parameters
+        let resampler = SincFixedOut::<f64>::new_rational(
+            std::f64::consts::SQRT_2,
+            params.clone(),
+            256,
+            1.0e-9,
+            1024,
+            1,
+        )
+        .unwrap();
+        assert_eq!(resampler.rational_ratio(), None);
+        let resampler =
+            SincFixedOut::<f64>::new_rational(0.5, params.clone(), 256, 1.0e-9, 1024, 1).unwrap();
+        let fraction = resampler.rational_ratio().unwrap();
+        assert_eq!((fraction.numerator, fraction.denominator), (1, 2));
+
+        // sample rates give the exact fraction, with nearest interpolation
+        // and an oversampling factor that fits it
+        let resampler =
+            SincFixedIn::<f64>::new_from_rates(44100, 22050, params.clone(), 1024, 1).unwrap();
+        assert_eq!(resampler.rational_ratio(), None);
+        let nearest = InterpolationParameters {
+            interpolation: InterpolationType::Nearest,
+            oversampling_factor: 320,
+            ..params.clone()
+        };
+        let resampler =
+            SincFixedIn::<f64>::new_from_rates(44100, 22050, nearest.clone(), 1024, 1).unwrap();
+        let fraction = resampler.rational_ratio().unwrap();
+        assert_eq!((fraction.numerator, fraction.denominator), (1, 2));
+        let resampler =
+            SincFixedOut::<f64>::new_from_rates(44100, 48000, nearest, 1024, 1).unwrap();
+        let fraction = resampler.rational_ratio().unwrap();
+        assert_eq!((fraction.numerator, fraction.denominator), (160, 147));
+    }
+
+    #[test]
+    fn change_ratio_from_rates() {
+        let params = InterpolationParameters {
+            sinc_len: 64,
+            f_cutoff: 0.95,
+            interpolation: InterpolationType::Cubic,
+            oversampling_factor: 16,
+            window: WindowFunction::BlackmanHarris2,
+        };
+        // the interpolation of the parameters is kept, so an adjusted ratio
+        // is resampled just like by a resampler made with "new"
+        let mut from_rates =
+            SincFixedIn::<f64>::new_from_rates(48000, 24000, params.clone(), 256, 1).unwrap();
+        let mut direct = SincFixedIn::<f64>::new(0.5, params, 256, 1).unwrap();
+        from_rates.set_resample_ratio(0.505).unwrap();
+        direct.set_resample_ratio(0.505).unwrap();
+        let wave: Vec<f64> = (0..256).map(|n| (n as f64 * 0.05).sin()).collect();
+        for _ in 0..3 {
+            let output = from_rates.process(std::slice::from_ref(&wave)).unwrap();
+            let expected = direct.process(std::slice::from_ref(&wave)).unwrap();
+            assert_eq!(output, expected);
+        }
+    }
+
+    #[test]
+    fn valid_ratio_range() {
+        let params = InterpolationParameters {
+            sinc_len: 64,
+            f_cutoff: 0.95,
+            interpolation: InterpolationType::Cubic,
+            oversampling_factor: 16,
+            window: WindowFunction::BlackmanHarris2,
+        };
+        // the cutoff is at 0.95 times the output Nyquist frequency
+        let resampler = SincFixedIn::<f64>::new(0.5, params.clone(), 1024, 1).unwrap();
+        let (lowest, highest) = resampler.valid_ratio_range().unwrap();
+        assert!((lowest - 0.475).abs() < 1.0e-6);
+        assert!((highest - 0.55).abs() < 1.0e-9);
+        // upsampling is limited only by the allowed range
+        let resampler = SincFixedOut::<f64>::new(2.0, params.clone(), 1024, 1).unwrap();
+        assert_eq!(resampler.valid_ratio_range(), Some((1.8, 2.2)));
+        // without anti-aliasing, any downsampling may alias
+        let resampler =
+            SincFixedIn::<f64>::new_without_anti_aliasing(0.5, params, 1024, 1).unwrap();
+        assert_eq!(resampler.valid_ratio_range(), None);
+        let prototype = vec![1.0; 64 * 16];
+        let resampler = SincFixedOut::<f64>::new_from_prototype(
+            0.5,
+            &prototype,
+            16,
+            InterpolationType::Cubic,
+            1024,
+            1,
+        )
+        .unwrap();
+        assert_eq!(resampler.valid_ratio_range(), None);
+    }
+
+    #[test]
+    fn aliasing_warning() {
+        let params = InterpolationParameters {
+            sinc_len: 128,
+            f_cutoff: 0.95,
+            interpolation: InterpolationType::Cubic,
+            oversampling_factor: 16,
+            window: WindowFunction::BlackmanHarris2,
+        };
+        let mut resampler = SincFixedIn::<f64>::new(0.5, params, 1024, 1).unwrap();
+        assert_eq!(resampler.aliasing_warning(), None);
+        resampler.set_resample_ratio(0.49).unwrap();
+        assert_eq!(resampler.aliasing_warning(), None);
+        resampler.set_resample_ratio(0.46).unwrap();
+        let warning = resampler.aliasing_warning().unwrap();
+        assert_eq!(warning.ratio, 0.46);
+        assert!((warning.cutoff - 0.475).abs() < 1.0e-6);
+        assert!(warning.excess_db > 1.0);
+        // further down the filter passes more
+        resampler.set_resample_ratio(0.455).unwrap();
+        assert!(resampler.aliasing_warning().unwrap().excess_db > warning.excess_db);
+        resampler.set_resample_ratio(0.5).unwrap();
+        assert_eq!(resampler.aliasing_warning(), None);
+    }
+
+    #[test]
+    fn fused_user_filter() {
+        let params = InterpolationParameters {
+            sinc_len: 128,
+            f_cutoff: 0.95,
+            interpolation: InterpolationType::Cubic,
+            oversampling_factor: 64,
+            window: WindowFunction::BlackmanHarris2,
+        };
+        let taps = [0.25, 1.0, 0.25];
+        let mut fused =
+            SincFixedIn::<f64>::new_with_filter(1.5, params.clone(), &taps, 256, 1).unwrap();
+        let mut plain = SincFixedIn::<f64>::new(1.5, params, 256, 1).unwrap();
+        let mut rng = rand::thread_rng();
+        let mut wave: Vec<f64> = (0..1024).map(|_| rng.gen_range(-1.0..1.0)).collect();
+        // the filtered signal would start one frame before the input
+        wave[0] = 0.0;
+        // the same filter applied separately, centered to give no delay
+        let filtered: Vec<f64> = (0..wave.len())
+            .map(|n| {
+                let before = if n > 0 { wave[n - 1] } else { 0.0 };
+                let after = wave.get(n + 1).cloned().unwrap_or(0.0);
+                taps[0] * before + taps[1] * wave[n] + taps[2] * after
+            })
+            .collect();
+        // the longer sinc delays the output within the chunks, but not in time
+        let mut out_fused = Vec::new();
+        let mut out_plain = Vec::new();
+        for (chunk, filtered_chunk) in wave.chunks(256).zip(filtered.chunks(256)) {
+            out_fused.extend(fused.process(&[chunk.to_vec()]).unwrap().remove(0));
+            out_plain.extend(plain.process(&[filtered_chunk.to_vec()]).unwrap().remove(0));
+        }
+        assert!(out_fused.len() > 1000);
+        for (a, b) in out_fused.iter().zip(out_plain.iter()) {
+            assert!((a - b).abs() < 1.0e-9);
+        }
+    }
+
+    #[test]
+    fn buffer_window_wraps_fo() {
+        let params = InterpolationParameters {
+            sinc_len: 64,
+            f_cutoff: 0.95,
+            interpolation: InterpolationType::Cubic,
+            oversampling_factor: 16,
+            window: WindowFunction::BlackmanHarris2,
+        };
+        let wave: Vec<f64> = (0..20000).map(|n| (n as f64 * 0.01).sin()).collect();
+        let mut steady = SincFixedOut::<f64>::new(1.3, params.clone(), 100, 1).unwrap();
+        let mut changing = SincFixedOut::<f64>::new(1.3, params, 100, 1).unwrap();
+        // many chunks, so that the window reaches the end of the buffer several times,
+        // also while the chunk size changes
+        let mut results = Vec::new();
+        for resampler in [&mut steady, &mut changing].iter_mut() {
+            let mut pos = 0;
+            let mut out = Vec::new();
+            for chunk in 0..60 {
+                if chunk % 10 == 5 && resampler.chunk_size() == 100 {
+                    resampler.set_chunk_size(37);
+                } else if chunk % 10 == 0 && resampler.chunk_size() == 37 {
+                    resampler.set_chunk_size(100);
+                }
+                let needed = resampler.nbr_frames_needed();
+                let input = vec![wave[pos..pos + needed].to_vec()];
+                pos += needed;
+                out.extend(resampler.process(&input).unwrap().remove(0));
+            }
+            results.push(out);
+        }
+        assert!(results[1].len() > 3000);
+        for (a, b) in results[0].iter().zip(results[1].iter()) {
+            assert!((a - b).abs() < 1.0e-9);
+        }
+    }
 }
@@ -0,0 +1,216 @@
+use crate::error::{ResampleError, ResampleResult};
+use crate::{InterpolationParameters, Resampler, Sample, SincFixedIn};
+use std::fmt;
+
+/// Identifies a stream of a `BatchResampler`.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub struct StreamId(usize);
+
+/// A resampler for many independent mono streams with the same parameters,
+/// like the voice streams of a conferencing server.
+///
+/// All streams are resampled by a single `SincFixedIn`, with one channel per stream slot.
+/// This means that there is only one set of sinc filters,
+/// and that each call processes all the streams together.
+/// With 8 or more streams, groups of 8 streams are interpolated at once,
+/// with one stream in each lane of the SIMD vectors.
+///
+/// Streams are added and removed with `add_stream` and `remove_stream`, up to a maximum number.
+/// All streams share the same clock, so a stream that is added later starts at the current
+/// position of the others, with silence as its history.
+/// Each call to `process_batch` gets one chunk of input for some of the active streams.
+/// The active streams that are not included get a chunk of silence, to stay in step with the others.
+pub struct BatchResampler<T> {
+    resampler: SincFixedIn<T>,
+    active: Vec<bool>,
+    silence: Vec<T>,
+}
+
+impl<T> BatchResampler<T>
+where
+    T: Sample,
+{
+    /// Create a new BatchResampler.
+    ///
+    /// Parameters are:
+    /// - `resample_ratio`: Ratio between output and input sample rates.
+    /// - `parameters`: Parameters for the sinc interpolation, see `InterpolationParameters`.
+    /// - `chunk_size`: Size of the input of each stream in frames.
+    /// - `max_streams`: The largest number of streams that can be active at the same time.
+    ///
+    /// # Errors
+    ///
+    /// The function returns `ResampleError::InvalidParameter` for the same parameters as `SincFixedIn::new`,
+    /// where `max_streams` is the number of channels.
+    pub fn new(
+        resample_ratio: f64,
+        parameters: InterpolationParameters,
+        chunk_size: usize,
+        max_streams: usize,
+    ) -> ResampleResult<Self> {
+        debug!(
+            "Create new BatchResampler, ratio: {}, chunk_size: {}, max_streams: {}",
+            resample_ratio, chunk_size, max_streams
+        );
+        Ok(BatchResampler {
+            resampler: SincFixedIn::new(resample_ratio, parameters, chunk_size, max_streams)?,
+            active: vec![false; max_streams],
+            silence: vec![T::zero(); chunk_size],
+        })
+    }
+
+    /// Add a stream, and return its id.
+    /// Returns `None` if the maximum number of streams are already active.
+    pub fn add_stream(&mut self) -> Option<StreamId> {
+        let slot = self.active.iter().position(|active| !active)?;
+        self.active[slot] = true;
+        self.resampler.clear_channel(slot);
+        trace!("Add stream {}", slot);
+        Some(StreamId(slot))
+    }
+
+    /// Remove a stream. Its id may then be reused by a later call to `add_stream`.
+    pub fn remove_stream(&mut self, stream: StreamId) {
+        trace!("Remove stream {}", stream.0);
+        if let Some(active) = self.active.get_mut(stream.0) {
+            *active = false;
+        }
+    }
+
+    /// Get the number of active streams.
+    pub fn nbr_streams(&self) -> usize {
+        self.active.iter().filter(|active| **active).count()
+    }
+
+    /// Get the largest number of streams that can be active at the same time.
+    pub fn max_streams(&self) -> usize {
+        self.active.len()
+    }
+
+    /// Get the number of input frames of each stream for a call to `process_batch`.
+    pub fn chunk_size(&self) -> usize {
+        self.silence.len()
+    }
+
+    /// Get the number of output frames of each stream from the next call to `process_batch`.
+    pub fn nbr_output_frames_next(&self) -> usize {
+        self.resampler.nbr_output_frames_next()
+    }
+
+    /// Get the delay of the output in frames, see "output_delay" of the `Resampler` trait.
+    pub fn output_delay(&self) -> f64 {
+        self.resampler.output_delay()
+    }
+
+    /// Resample one chunk of each of the given streams.
+    /// The input of `streams[n]` is `inputs[n]`, and its output is written to `outputs[n]`,
+    /// replacing the previous contents.
+    /// All streams get the same number of output frames, which is returned.
+    ///
+    /// # Errors
+    ///
+    /// The function returns an error if the three slices have different lengths,
+    /// if a stream is not active, or if an input doesn't have the chunk size.
+    pub fn process_batch(
+        &mut self,
+        streams: &[StreamId],
+        inputs: &[&[T]],
+        outputs: &mut [Vec<T>],
+    ) -> ResampleResult<usize> {
+        for actual in [inputs.len(), outputs.len()].iter() {
+            if *actual != streams.len() {
+                return Err(ResampleError::WrongNumberOfChannels {
+                    expected: streams.len(),
+                    actual: *actual,
+                    resampler: format!("{:?}", self),
+                });
+            }
+        }
+        let silence = &self.silence[..];
+        let mut wave_in: Vec<&[T]> = self
+            .active
+            .iter()
+            .map(|active| if *active { silence } else { &[] })
+            .collect();
+        for (stream, input) in streams.iter().zip(inputs.iter()) {
+            if !self.active.get(stream.0).cloned().unwrap_or(false) {
+                return Err(ResampleError::InvalidParameter {
+                    name: "streams",
+                    value: format!("{:?}", stream),
+                    requirement: "must be active",
+                });
+            }
+            wave_in[stream.0] = *input;
+        }
+        let wave_out = self.resampler.process_slices_buffered(&wave_in)?;
+        let mut nbr_frames = 0;
+        for (stream, output) in streams.iter().zip(outputs.iter_mut()) {
+            output.clear();
+            output.extend_from_slice(&wave_out[stream.0]);
+            nbr_frames = output.len();
+        }
+        Ok(nbr_frames)
+    }
+}
+
+/// Shows the number of streams, but not the inner resampler.
+impl<T> fmt::Debug for BatchResampler<T>
+where
+    T: Sample,
+{
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        f.debug_struct("BatchResampler")
+            .field("nbr_streams", &self.nbr_streams())
+            .field("max_streams", &self.max_streams())
+            .field("chunk_size", &self.chunk_size())
+            .finish()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::batch::BatchResampler;
+    use crate::{InterpolationParameters, InterpolationType, WindowFunction};
+    use crate::{Resampler, SincFixedIn};
+
+    #[test]
+    fn streams_are_independent() {
+        let params = InterpolationParameters {
+            sinc_len: 64,
+            f_cutoff: 0.95,
+            interpolation: InterpolationType::Cubic,
+            oversampling_factor: 16,
+            window: WindowFunction::BlackmanHarris2,
+        };
+        let mut batch = BatchResampler::<f64>::new(1.5, params.clone(), 256, 4).unwrap();
+        let first = batch.add_stream().unwrap();
+        let second = batch.add_stream().unwrap();
+        assert_eq!(batch.nbr_streams(), 2);
+        let mut single = SincFixedIn::<f64>::new(1.5, params, 256, 1).unwrap();
+
+        let mut outputs = vec![Vec::new(); 2];
+        for chunk in 0..3 {
+            let wave: Vec<f64> = (chunk * 256..(chunk + 1) * 256)
+                .map(|n| (n as f64 * 0.05).sin())
+                .collect();
+            let noise: Vec<f64> = (0..256).map(|n| ((n * 7919) % 13) as f64 / 13.0).collect();
+            let nbr_frames = batch
+                .process_batch(&[second, first], &[&noise[..], &wave[..]], &mut outputs)
+                .unwrap();
+            let expected = single.process(&[wave]).unwrap();
+            assert_eq!(nbr_frames, expected[0].len());
+            assert_eq!(outputs[1], expected[0]);
+        }
+
+        // a stream that is removed and added again starts from silence
+        batch.remove_stream(second);
+        assert!(batch
+            .process_batch(&[second], &[&[0.0; 256][..]], &mut outputs[..1])
+            .is_err());
+        let second = batch.add_stream().unwrap();
+        batch
+            .process_batch(&[second], &[&[0.0; 256][..]], &mut outputs[..1])
+            .unwrap();
+        assert!(outputs[0].iter().all(|value| *value == 0.0));
+    }
+}
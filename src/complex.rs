@@ -0,0 +1,193 @@
+use crate::error::{ResampleError, ResampleResult};
+use crate::{AliasingWarning, Resampler, Sample};
+use num_complex::Complex;
+use std::fmt;
+
+/// A resampler for complex valued signals, like the IQ baseband signals used in software defined radio.
+///
+/// The real and imaginary parts of each complex channel are resampled as two channels of the inner resampler,
+/// with the real part in channel `2 * n` and the imaginary part in channel `2 * n + 1`.
+/// Since all channels of a resampler use the same filters and the same phase,
+/// both parts are delayed and interpolated identically, which keeps the phase relation between them.
+/// Any resampler type can be used, and it must be created with twice the number of complex channels.
+pub struct ComplexResampler<T> {
+    resampler: Box<dyn Resampler<T>>,
+    nbr_channels: usize,
+    parts: Vec<Vec<T>>,
+}
+
+impl<T> ComplexResampler<T>
+where
+    T: Sample,
+{
+    /// Create a new resampler for `nbr_channels` complex channels,
+    /// using an inner resampler with `2 * nbr_channels` real channels.
+    pub fn new(resampler: Box<dyn Resampler<T>>, nbr_channels: usize) -> Self {
+        debug!(
+            "Create new ComplexResampler, complex channels: {}",
+            nbr_channels
+        );
+        ComplexResampler {
+            resampler,
+            nbr_channels,
+            parts: vec![Vec::new(); 2 * nbr_channels],
+        }
+    }
+
+    /// Get the inner resampler.
+    pub fn inner(&mut self) -> &mut dyn Resampler<T> {
+        self.resampler.as_mut()
+    }
+
+    /// Split the complex input into real and imaginary parts.
+    /// Empty channels give two empty parts, which the inner resampler then skips.
+    fn split(&mut self, wave_in: &[Vec<Complex<T>>]) {
+        for (parts, wave) in self.parts.chunks_mut(2).zip(wave_in.iter()) {
+            parts[0].clear();
+            parts[1].clear();
+            parts[0].extend(wave.iter().map(|value| value.re));
+            parts[1].extend(wave.iter().map(|value| value.im));
+        }
+    }
+}
+
+/// Merge pairs of real and imaginary parts into complex channels.
+fn merge<T: Sample>(parts: &[Vec<T>]) -> Vec<Vec<Complex<T>>> {
+    parts
+        .chunks(2)
+        .map(|parts| {
+            parts[0]
+                .iter()
+                .zip(parts[1].iter())
+                .map(|(re, im)| Complex::new(*re, *im))
+                .collect()
+        })
+        .collect()
+}
+
+/// Shows the number of channels, but not the inner resampler.
+impl<T> fmt::Debug for ComplexResampler<T> {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        f.debug_struct("ComplexResampler")
+            .field("nbr_channels", &self.nbr_channels)
+            .finish()
+    }
+}
+
+impl<T> Resampler<Complex<T>> for ComplexResampler<T>
+where
+    T: Sample,
+{
+    /// Resample a chunk of complex audio.
+    ///
+    /// # Errors
+    ///
+    /// The function returns an error if the number of channels doesn't match,
+    /// or if the inner resampler returns an error.
+    fn process(&mut self, wave_in: &[Vec<Complex<T>>]) -> ResampleResult<Vec<Vec<Complex<T>>>> {
+        if wave_in.len() != self.nbr_channels {
+            return Err(ResampleError::WrongNumberOfChannels {
+                expected: self.nbr_channels,
+                actual: wave_in.len(),
+                resampler: format!("{:?}", self),
+            });
+        }
+        self.split(wave_in);
+        let parts = self.resampler.process(&self.parts)?;
+        Ok(merge(&parts))
+    }
+
+    fn output_delay(&self) -> f64 {
+        self.resampler.output_delay()
+    }
+
+    fn reset(&mut self) {
+        self.resampler.reset();
+    }
+
+    fn flush(&mut self) -> ResampleResult<Vec<Vec<Complex<T>>>> {
+        let parts = self.resampler.flush()?;
+        Ok(merge(&parts))
+    }
+
+    fn nbr_frames_needed(&self) -> usize {
+        self.resampler.nbr_frames_needed()
+    }
+
+    fn nbr_output_frames_next(&self) -> usize {
+        self.resampler.nbr_output_frames_next()
+    }
+
+    fn set_resample_ratio(&mut self, new_ratio: f64) -> ResampleResult<()> {
+        self.resampler.set_resample_ratio(new_ratio)
+    }
+
+    fn set_resample_ratio_relative(&mut self, rel_ratio: f64) -> ResampleResult<()> {
+        self.resampler.set_resample_ratio_relative(rel_ratio)
+    }
+
+    fn aliasing_warning(&self) -> Option<AliasingWarning> {
+        self.resampler.aliasing_warning()
+    }
+
+    fn resample_ratio(&self) -> f64 {
+        self.resampler.resample_ratio()
+    }
+
+    fn resample_ratio_original(&self) -> f64 {
+        self.resampler.resample_ratio_original()
+    }
+
+    fn chunk_size(&self) -> usize {
+        self.resampler.chunk_size()
+    }
+
+    /// Get the number of complex channels, half the number of channels of the inner resampler.
+    fn nbr_channels(&self) -> usize {
+        self.nbr_channels
+    }
+
+    fn sinc_len(&self) -> Option<usize> {
+        self.resampler.sinc_len()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::complex::ComplexResampler;
+    use crate::{InterpolationParameters, InterpolationType, WindowFunction};
+    use crate::{Resampler, SincFixedIn};
+    use num_complex::Complex;
+
+    #[test]
+    fn resample_iq() {
+        let params = InterpolationParameters {
+            sinc_len: 128,
+            f_cutoff: 0.95,
+            interpolation: InterpolationType::Cubic,
+            oversampling_factor: 128,
+            window: WindowFunction::BlackmanHarris2,
+        };
+        let mut resampler = ComplexResampler::new(
+            Box::new(SincFixedIn::<f64>::new(2.0, params, 256, 2).unwrap()),
+            1,
+        );
+        assert!(resampler.process(&[vec![], vec![]]).is_err());
+
+        // a negative frequency is only possible with complex values
+        let freq = -0.3;
+        let mut output = Vec::new();
+        for chunk in 0..8 {
+            let wave: Vec<Complex<f64>> = (chunk * 256..(chunk + 1) * 256)
+                .map(|n| Complex::from_polar(1.0, freq * n as f64))
+                .collect();
+            output.extend(resampler.process(&[wave]).unwrap().remove(0));
+        }
+        // after the start, the output rotates at half the angular frequency, with unit amplitude
+        for pair in output[512..1024].windows(2) {
+            assert!((pair[0].norm() - 1.0).abs() < 1.0e-3);
+            let step = (pair[1] / pair[0]).arg();
+            assert!((step - freq / 2.0).abs() < 1.0e-3);
+        }
+    }
+}
@@ -0,0 +1,261 @@
+//! Conversions between floating point samples and integer formats.
+//!
+//! The floating point samples use the range -1.0 to +1.0,
+//! which is mapped to the full range of the integer format.
+//! When converting to integers, the values are rounded and clamped to the valid range,
+//! and can optionally be dithered with triangular (TPDF) noise to decorrelate the
+//! quantization error from the signal.
+//! The 24-bit format uses the lower 24 bits of an `i32`.
+//!
+//! The loops without dither have no dependencies between the samples,
+//! and are written so that the compiler can auto-vectorize them.
+//!
+//! For interleaved 16-bit audio, `Resampler::process_interleaved_i16` combines these conversions
+//! with de-interleaving and interleaving, and avoids the intermediate buffers.
+
+use crate::Sample;
+
+/// A source of triangular (TPDF) dither noise, with a peak amplitude of one least significant bit.
+///
+/// The noise is generated by a simple xorshift generator,
+/// so the same seed always gives the same noise.
+#[derive(Debug, Clone)]
+pub struct Dither {
+    state: u32,
+}
+
+impl Dither {
+    /// Create a new dither source, with the given seed.
+    pub fn new(seed: u32) -> Self {
+        // xorshift gets stuck at zero
+        let state = if seed == 0 { 0x9e37_79b9 } else { seed };
+        Dither { state }
+    }
+
+    /// Get the next uniformly distributed value, in the range 0.0 to 1.0.
+    fn next_uniform(&mut self) -> f64 {
+        self.state ^= self.state << 13;
+        self.state ^= self.state >> 17;
+        self.state ^= self.state << 5;
+        self.state as f64 / 4_294_967_296.0
+    }
+
+    /// Get the next noise value, in least significant bits.
+    fn next_value(&mut self) -> f64 {
+        self.next_uniform() - self.next_uniform()
+    }
+}
+
+/// Convert float samples to integers with `bits` bits, using `cast` to store each value.
+fn to_integer<T, I, F>(
+    input: &[T],
+    output: &mut [I],
+    bits: u32,
+    dither: Option<&mut Dither>,
+    cast: F,
+) where
+    T: Sample,
+    F: Fn(f64) -> I,
+{
+    assert_eq!(
+        input.len(),
+        output.len(),
+        "Input and output must have the same length"
+    );
+    let scale = (1u64 << (bits - 1)) as f64;
+    match dither {
+        None => {
+            for (out, value) in output.iter_mut().zip(input.iter()) {
+                let value = value.to_f64().unwrap_or(0.0) * scale;
+                *out = cast(quantize(value, scale));
+            }
+        }
+        Some(dither) => {
+            for (out, value) in output.iter_mut().zip(input.iter()) {
+                let value = value.to_f64().unwrap_or(0.0) * scale + dither.next_value();
+                *out = cast(quantize(value, scale));
+            }
+        }
+    }
+}
+
+/// Round a value that is scaled to the range of the integer format, and clamp it to that range.
+#[inline]
+fn quantize(value: f64, scale: f64) -> f64 {
+    value.round().max(-scale).min(scale - 1.0)
+}
+
+/// Convert integers with `bits` bits to float samples, using `cast` to read each value.
+fn from_integer<T, I, F>(input: &[I], output: &mut [T], bits: u32, cast: F)
+where
+    T: Sample,
+    I: Copy,
+    F: Fn(I) -> f64,
+{
+    assert_eq!(
+        input.len(),
+        output.len(),
+        "Input and output must have the same length"
+    );
+    let scale = 1.0 / (1u64 << (bits - 1)) as f64;
+    for (out, value) in output.iter_mut().zip(input.iter()) {
+        *out = T::coerce(cast(*value) * scale);
+    }
+}
+
+/// Convert float samples to 16-bit integers, with optional dither.
+///
+/// # Panics
+///
+/// Panics if `input` and `output` have different lengths.
+pub fn to_i16<T: Sample>(input: &[T], output: &mut [i16], dither: Option<&mut Dither>) {
+    to_integer(input, output, 16, dither, |value| value as i16);
+}
+
+/// Convert float samples to 24-bit integers stored in `i32`, with optional dither.
+///
+/// # Panics
+///
+/// Panics if `input` and `output` have different lengths.
+pub fn to_i24<T: Sample>(input: &[T], output: &mut [i32], dither: Option<&mut Dither>) {
+    to_integer(input, output, 24, dither, |value| value as i32);
+}
+
+/// Convert float samples to 32-bit integers, with optional dither.
+/// Note that `f32` samples only have 24 bits of precision.
+///
+/// # Panics
+///
+/// Panics if `input` and `output` have different lengths.
+pub fn to_i32<T: Sample>(input: &[T], output: &mut [i32], dither: Option<&mut Dither>) {
+    to_integer(input, output, 32, dither, |value| value as i32);
+}
+
+/// Convert 16-bit integers to float samples.
+///
+/// # Panics
+///
+/// Panics if `input` and `output` have different lengths.
+pub fn from_i16<T: Sample>(input: &[i16], output: &mut [T]) {
+    from_integer(input, output, 16, f64::from);
+}
+
+/// Convert 24-bit integers stored in `i32` to float samples.
+///
+/// # Panics
+///
+/// Panics if `input` and `output` have different lengths.
+pub fn from_i24<T: Sample>(input: &[i32], output: &mut [T]) {
+    from_integer(input, output, 24, f64::from);
+}
+
+/// Convert 32-bit integers to float samples.
+///
+/// # Panics
+///
+/// Panics if `input` and `output` have different lengths.
+pub fn from_i32<T: Sample>(input: &[i32], output: &mut [T]) {
+    from_integer(input, output, 32, f64::from);
+}
+
+/// Convert the 16-bit integers of a channel, read from `data` with a stride starting at `channel`,
+/// to float samples in `buffer`.
+/// This combines de-interleaving and converting in a single pass over the data.
+pub(crate) fn read_strided_i16<T: Sample>(
+    data: &[i16],
+    channel: usize,
+    stride: usize,
+    buffer: &mut [T],
+) {
+    let scale = 1.0 / 32768.0;
+    for (value, sample) in buffer
+        .iter_mut()
+        .zip(data[channel..].iter().step_by(stride))
+    {
+        *value = T::coerce(f64::from(*sample) * scale);
+    }
+}
+
+/// Convert the float samples of a channel to 16-bit integers with optional dither,
+/// and write them to `data` with a stride starting at `channel`.
+/// This combines converting and interleaving in a single pass over the data.
+/// Frames that don't fit in `data` are dropped.
+pub(crate) fn write_strided_i16<T: Sample>(
+    data: &mut [i16],
+    channel: usize,
+    stride: usize,
+    frames: &[T],
+    dither: Option<&mut Dither>,
+) {
+    if channel >= data.len() {
+        return;
+    }
+    let scale = 32768.0;
+    let outputs = data[channel..]
+        .iter_mut()
+        .step_by(stride)
+        .zip(frames.iter());
+    match dither {
+        None => {
+            for (out, value) in outputs {
+                let value = value.to_f64().unwrap_or(0.0) * scale;
+                *out = quantize(value, scale) as i16;
+            }
+        }
+        Some(dither) => {
+            for (out, value) in outputs {
+                let value = value.to_f64().unwrap_or(0.0) * scale + dither.next_value();
+                *out = quantize(value, scale) as i16;
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::convert::{from_i16, from_i24, from_i32, to_i16, to_i24, to_i32, Dither};
+
+    #[test]
+    fn convert_and_clamp() {
+        let input = vec![0.0f32, 0.5, -0.5, 1.0, -1.0, 1.5, -1.5];
+        let mut output = vec![0i16; input.len()];
+        to_i16(&input, &mut output, None);
+        assert_eq!(output, vec![0, 16384, -16384, 32767, -32768, 32767, -32768]);
+        let mut output = vec![0i32; input.len()];
+        to_i24(&input, &mut output, None);
+        assert_eq!(
+            output,
+            vec![0, 4194304, -4194304, 8388607, -8388608, 8388607, -8388608]
+        );
+        let input = vec![0.25f64, 1.0, -1.0];
+        let mut output = vec![0i32; input.len()];
+        to_i32(&input, &mut output, None);
+        assert_eq!(output, vec![536870912, 2147483647, -2147483648]);
+
+        let mut back = vec![0.0f64; 3];
+        from_i32(&output, &mut back);
+        assert_eq!(back[0], 0.25);
+        from_i16(&[16384, -32768, 0], &mut back);
+        assert_eq!(back, vec![0.5, -1.0, 0.0]);
+        from_i24(&[4194304, -8388608, 0], &mut back);
+        assert_eq!(back, vec![0.5, -1.0, 0.0]);
+    }
+
+    #[test]
+    fn dither() {
+        // a constant level between two steps is only preserved on average with dither
+        let input = vec![0.3 / 32768.0; 10000];
+        let mut output = vec![0i16; input.len()];
+        to_i16(&input, &mut output, None);
+        assert!(output.iter().all(|value| *value == 0));
+        let mut dither = Dither::new(1);
+        to_i16(&input, &mut output, Some(&mut dither));
+        assert!(output.iter().all(|value| value.abs() <= 2));
+        let mean = output.iter().map(|value| *value as f64).sum::<f64>() / output.len() as f64;
+        assert!((mean - 0.3).abs() < 0.05);
+        // the noise is repeatable
+        let mut repeated = vec![0i16; input.len()];
+        to_i16(&input, &mut repeated, Some(&mut Dither::new(1)));
+        assert_eq!(output, repeated);
+    }
+}
@@ -0,0 +1,447 @@
+use crate::asynchro::{index_steps, read_sincs, SincInterpolator};
+use crate::fft::{default_backend, ForwardFft, InverseFft};
+use crate::interpolation::*;
+use crate::parallel::{for_each_channel, nbr_workers};
+use crate::{InterpolationType, Sample};
+use num_complex::Complex;
+use num_traits::Zero;
+use std::sync::Arc;
+
+/// Default sinc length from which the sinc resamplers calculate the points by FFT convolution.
+pub(crate) const DEFAULT_CONVOLUTION_THRESHOLD: usize = 2048;
+
+/// Number of partitions that each sinc is split into.
+const PARTITIONS: usize = 4;
+
+/// The sincs of an interpolator, prepared for uniformly partitioned convolution.
+///
+/// Each sinc is reversed, so that the convolution gives the scalar products
+/// of `get_sinc_interpolated`, and split into `PARTITIONS` partitions of `block_len` taps.
+/// The partitions are stored as spectra of length `2 * block_len`,
+/// scaled to normalize the inverse FFT.
+struct ConvolutionFilter<T> {
+    spectra: Vec<Vec<Vec<Complex<T>>>>,
+    fft: Arc<dyn ForwardFft<T>>,
+    ifft: Arc<dyn InverseFft<T>>,
+    phases: PhaseIndex,
+    sinc_len: usize,
+    block_len: usize,
+    /// Rough estimate of the cost of calculating one block of points, in multiply-adds.
+    /// The cost of calculating a single point directly is `sinc_len`.
+    block_cost: usize,
+}
+
+/// The buffers for convolving one channel.
+#[derive(Clone)]
+struct ConvolutionScratch<T> {
+    scratch_fw: Vec<Complex<T>>,
+    scratch_inv: Vec<Complex<T>>,
+    frame: Vec<T>,
+    spectrum: Vec<Complex<T>>,
+    /// The spectra of the overlapping frames of the input, one per block.
+    input_f: Vec<Vec<Complex<T>>>,
+    /// Which of the input spectra have been calculated for the current channel.
+    transformed: Vec<bool>,
+    /// The points of the blocks of one sinc.
+    values: Vec<T>,
+}
+
+/// One of the points that an output frame is interpolated from,
+/// with its weight in the interpolation.
+#[derive(Clone, Copy, Debug)]
+struct Point<T> {
+    frame: usize,
+    index: usize,
+    subindex: usize,
+    weight: T,
+}
+
+/// An alternative way of calculating the sinc interpolated points, for long sincs.
+///
+/// Calculating the points one by one takes `sinc_len` multiply-adds per point.
+/// When a chunk needs many points of the same sinc, it's cheaper to calculate all points
+/// of that sinc for a range of positions, by FFT convolution of the input with the sinc.
+/// The cost per point then grows with the logarithm of the sinc length, instead of linearly.
+///
+/// The points needed by a chunk are first listed and sorted by sinc.
+/// Each sinc then uses the cheaper of the two ways, depending on how many points it has,
+/// and how far they are spread out. The FFT convolution is used for the sincs with many
+/// points, which means long chunks and low oversampling factors.
+/// The output frames are interpolated from the points as usual, and match those of the direct
+/// calculation to within rounding errors. The preview mode doesn't use the points at all.
+///
+/// The clones of a resampler share the filter spectra.
+#[derive(Clone)]
+pub(crate) struct FftConvolution<T> {
+    filter: Arc<ConvolutionFilter<T>>,
+    points: Vec<Point<T>>,
+    scratches: Vec<ConvolutionScratch<T>>,
+}
+
+impl<T> ConvolutionFilter<T>
+where
+    T: Sample,
+{
+    /// Returns `None` if there is no FFT backend.
+    fn new(interpolator: &dyn SincInterpolator<T>) -> Option<Self> {
+        let sinc_len = interpolator.len();
+        let block_len = (sinc_len.max(1) - 1) / PARTITIONS + 1;
+        let mut backend = default_backend().ok()?;
+        let fft = backend.plan_forward(2 * block_len).ok()?;
+        let ifft = backend.plan_inverse(2 * block_len).ok()?;
+        let mut scratch_fw = vec![Complex::zero(); fft.scratch_len()];
+        let mut frame = vec![T::zero(); 2 * block_len];
+        let spectra = read_sincs(interpolator)
+            .iter()
+            .map(|sinc| {
+                (0..PARTITIONS)
+                    .map(|partition| {
+                        for (n, value) in frame.iter_mut().enumerate() {
+                            let tap = partition * block_len + n;
+                            *value = if n < block_len && tap < sinc_len {
+                                sinc[sinc_len - 1 - tap] / T::coerce(2 * block_len)
+                            } else {
+                                T::zero()
+                            };
+                        }
+                        let mut spectrum = vec![Complex::zero(); block_len + 1];
+                        fft.process(&mut frame, &mut spectrum, &mut scratch_fw);
+                        spectrum
+                    })
+                    .collect()
+            })
+            .collect();
+        // the multiplications of the spectra, and an inverse FFT
+        let log_len = (2 * block_len).next_power_of_two().trailing_zeros() as usize;
+        let block_cost = 4 * PARTITIONS * (block_len + 1) + 5 * block_len * log_len;
+        Some(ConvolutionFilter {
+            spectra,
+            fft,
+            ifft,
+            phases: PhaseIndex::new(interpolator.nbr_sincs()),
+            sinc_len,
+            block_len,
+            block_cost,
+        })
+    }
+
+    /// Interpolate the output frames of one channel from its buffer, using the given points.
+    fn convolve_channel(
+        &self,
+        interpolator: &dyn SincInterpolator<T>,
+        points: &[Point<T>],
+        scratch: &mut ConvolutionScratch<T>,
+        buf: &[T],
+        wave_out: &mut [T],
+    ) {
+        for value in wave_out.iter_mut() {
+            *value = T::zero();
+        }
+        let nbr_blocks = buf.len() / self.block_len + 1;
+        if scratch.input_f.len() < nbr_blocks {
+            scratch
+                .input_f
+                .resize(nbr_blocks, vec![Complex::zero(); self.block_len + 1]);
+        }
+        scratch
+            .values
+            .resize(nbr_blocks * self.block_len, T::zero());
+        scratch.transformed.clear();
+        scratch.transformed.resize(nbr_blocks, false);
+
+        let mut rest = points;
+        while let Some(first) = rest.first() {
+            let subindex = first.subindex;
+            let count = rest
+                .iter()
+                .take_while(|point| point.subindex == subindex)
+                .count();
+            let (group, tail) = rest.split_at(count);
+            rest = tail;
+            let (min_index, max_index) = group.iter().fold((usize::MAX, 0), |(min, max), point| {
+                (min.min(point.index), max.max(point.index))
+            });
+            // the point at `index` is the convolution at `index + sinc_len - 1`
+            let first_block = (min_index + self.sinc_len - 1) / self.block_len;
+            let last_block = (max_index + self.sinc_len - 1) / self.block_len;
+            if count * self.sinc_len <= (last_block - first_block + 1) * self.block_cost {
+                for point in group.iter() {
+                    wave_out[point.frame] += point.weight
+                        * interpolator.get_sinc_interpolated(buf, point.index, subindex);
+                }
+                continue;
+            }
+            for block in first_block..=last_block {
+                self.convolve_block(scratch, buf, subindex, block, block - first_block);
+            }
+            let start = first_block * self.block_len;
+            for point in group.iter() {
+                wave_out[point.frame] +=
+                    point.weight * scratch.values[point.index + self.sinc_len - 1 - start];
+            }
+        }
+    }
+
+    /// Calculate the block number `block` of the convolution of the buffer with a sinc,
+    /// and store it as block number `slot` of the values.
+    fn convolve_block(
+        &self,
+        scratch: &mut ConvolutionScratch<T>,
+        buf: &[T],
+        subindex: usize,
+        block: usize,
+        slot: usize,
+    ) {
+        for value in scratch.spectrum.iter_mut() {
+            *value = Complex::zero();
+        }
+        // the frames before the start of the buffer are silent
+        for (partition, filter_f) in self.spectra[subindex].iter().enumerate().take(block + 1) {
+            let input = block - partition;
+            self.transform_input(scratch, buf, input);
+            for (out, (spec, filt)) in scratch
+                .spectrum
+                .iter_mut()
+                .zip(scratch.input_f[input].iter().zip(filter_f.iter()))
+            {
+                *out += *spec * filt;
+            }
+        }
+        self.ifft.process(
+            &mut scratch.spectrum,
+            &mut scratch.frame,
+            &mut scratch.scratch_inv,
+        );
+        // the second half of each frame holds the result of the overlap-save
+        scratch.values[slot * self.block_len..(slot + 1) * self.block_len]
+            .copy_from_slice(&scratch.frame[self.block_len..]);
+    }
+
+    /// Calculate the spectrum of the frame of the buffer that ends with block number `block`,
+    /// unless it has already been calculated for this channel.
+    fn transform_input(&self, scratch: &mut ConvolutionScratch<T>, buf: &[T], block: usize) {
+        if scratch.transformed[block] {
+            return;
+        }
+        // the frame starts one block before the block, with zeros outside of the buffer
+        let start = block * self.block_len;
+        for (n, value) in scratch.frame.iter_mut().enumerate() {
+            *value = (start + n)
+                .checked_sub(self.block_len)
+                .and_then(|pos| buf.get(pos))
+                .cloned()
+                .unwrap_or_else(T::zero);
+        }
+        self.fft.process(
+            &mut scratch.frame,
+            &mut scratch.input_f[block],
+            &mut scratch.scratch_fw,
+        );
+        scratch.transformed[block] = true;
+    }
+}
+
+impl<T> ConvolutionScratch<T>
+where
+    T: Sample,
+{
+    /// Create the buffers, with room for an input buffer of `buffer_len` frames.
+    fn new(filter: &ConvolutionFilter<T>, buffer_len: usize) -> Self {
+        let nbr_blocks = buffer_len / filter.block_len + 1;
+        ConvolutionScratch {
+            scratch_fw: vec![Complex::zero(); filter.fft.scratch_len()],
+            scratch_inv: vec![Complex::zero(); filter.ifft.scratch_len()],
+            frame: vec![T::zero(); 2 * filter.block_len],
+            spectrum: vec![Complex::zero(); filter.block_len + 1],
+            input_f: vec![vec![Complex::zero(); filter.block_len + 1]; nbr_blocks],
+            transformed: vec![false; nbr_blocks],
+            values: vec![T::zero(); nbr_blocks * filter.block_len],
+        }
+    }
+}
+
+impl<T> FftConvolution<T>
+where
+    T: Sample,
+{
+    /// Prepare the convolution for the sincs of an interpolator,
+    /// or return `None` if they are shorter than `threshold`,
+    /// or if no FFT backend is enabled.
+    /// The buffers get room for input buffers of `buffer_len` frames
+    /// and `max_frames_out` output frames, so that processing doesn't need to allocate.
+    pub fn new(
+        interpolator: &dyn SincInterpolator<T>,
+        threshold: usize,
+        buffer_len: usize,
+        max_frames_out: usize,
+    ) -> Option<Self> {
+        if interpolator.len() < threshold {
+            return None;
+        }
+        debug!(
+            "Using FFT convolution for sincs of length {}",
+            interpolator.len()
+        );
+        let filter = ConvolutionFilter::new(interpolator)?;
+        let scratches = vec![ConvolutionScratch::new(&filter, buffer_len)];
+        Some(FftConvolution {
+            filter: Arc::new(filter),
+            points: Vec::with_capacity(4 * max_frames_out),
+            scratches,
+        })
+    }
+
+    /// List the points that the output frames are interpolated from, sorted by sinc.
+    fn find_points(
+        &mut self,
+        interpolation: InterpolationType,
+        last_index: f64,
+        ratios: &[f64],
+        nbr_frames: usize,
+    ) {
+        let oversampling_factor = self.filter.spectra.len();
+        let phases = self.filter.phases;
+        let offset = 2 * self.filter.sinc_len as isize;
+        let points = &mut self.points;
+        points.clear();
+        let mut add_point = |frame: usize, nearest: (isize, isize), weight: T| {
+            points.push(Point {
+                frame,
+                index: (nearest.0 + offset) as usize,
+                subindex: nearest.1 as usize,
+                weight,
+            })
+        };
+        let mut idx = last_index;
+        for (frame, step) in index_steps(ratios).take(nbr_frames).enumerate() {
+            idx += step;
+            let pos = idx * oversampling_factor as f64;
+            let x = T::coerce(pos - pos.floor());
+            match interpolation {
+                InterpolationType::Cubic => {
+                    let mut nearest = [(0isize, 0isize); 4];
+                    phases.nearest_times_4(idx, &mut nearest);
+                    // the weights of the points in `interp_cubic`
+                    let x2 = x * x;
+                    let x3 = x2 * x;
+                    let half = T::coerce(0.5);
+                    let sixth = T::one() / T::coerce(6.0);
+                    let weights = [
+                        -(T::one() / T::coerce(3.0)) * x + half * x2 - sixth * x3,
+                        T::one() - half * x - x2 + half * x3,
+                        x + half * x2 - half * x3,
+                        sixth * (x3 - x),
+                    ];
+                    for (n, weight) in nearest.iter().zip(weights.iter()) {
+                        add_point(frame, *n, *weight);
+                    }
+                }
+                InterpolationType::Linear => {
+                    let mut nearest = [(0isize, 0isize); 2];
+                    phases.nearest_times_2(idx, &mut nearest);
+                    add_point(frame, nearest[0], T::one() - x);
+                    add_point(frame, nearest[1], x);
+                }
+                InterpolationType::Nearest => {
+                    let nearest = phases.nearest_time(idx);
+                    add_point(frame, nearest, T::one());
+                }
+            }
+        }
+        points.sort_unstable_by_key(|point| point.subindex);
+    }
+
+    /// Interpolate the given channels, from the buffers starting at `start`,
+    /// into the output buffers. These must already have the right length.
+    #[allow(clippy::too_many_arguments)]
+    pub fn process(
+        &mut self,
+        interpolator: &dyn SincInterpolator<T>,
+        buffer: &[Vec<T>],
+        output_buffer: &mut [Vec<T>],
+        channels: &[usize],
+        start: usize,
+        interpolation: InterpolationType,
+        last_index: f64,
+        ratios: &[f64],
+        parallel_threshold: usize,
+    ) {
+        let nbr_frames = channels
+            .first()
+            .map(|chan| output_buffer[*chan].len())
+            .unwrap_or(0);
+        self.find_points(interpolation, last_index, ratios, nbr_frames);
+        // one set of buffers for each channel that may be processed in parallel
+        let workers = nbr_workers(output_buffer.len(), parallel_threshold);
+        if self.scratches.len() < workers {
+            let first = self.scratches[0].clone();
+            self.scratches.resize(workers, first);
+        }
+        let filter = &*self.filter;
+        let points = &self.points[..];
+        let channels = output_buffer
+            .iter_mut()
+            .enumerate()
+            .filter(|(chan, _)| channels.contains(chan))
+            .map(|(chan, wave_out)| (&buffer[chan][start..], &mut wave_out[..]));
+        for_each_channel(
+            channels,
+            &mut self.scratches,
+            parallel_threshold,
+            |(buf, wave_out), scratch| {
+                filter.convolve_channel(interpolator, points, scratch, buf, wave_out)
+            },
+        );
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::asynchro::SincInterpolator;
+    use crate::convolution::{ConvolutionFilter, ConvolutionScratch};
+    use crate::{InterpolationParameters, InterpolationType, WindowFunction};
+    use crate::{Resampler, ScalarInterpolator, SincFixedIn};
+
+    #[test]
+    fn convolution_matches_direct() {
+        let interpolator = ScalarInterpolator::<f64>::new(512, 4, 0.95, WindowFunction::Hann);
+        let buf: Vec<f64> = (0..4096).map(|n| (n as f64 * 0.03).sin()).collect();
+        let filter = ConvolutionFilter::new(&interpolator).unwrap();
+        let mut scratch = ConvolutionScratch::new(&filter, buf.len());
+        filter.convolve_block(&mut scratch, &buf, 1, 7, 0);
+        for (n, value) in scratch.values[..filter.block_len].iter().enumerate() {
+            let index = 7 * filter.block_len + n + 1 - filter.sinc_len;
+            let expected = interpolator.get_sinc_interpolated(&buf, index, 1);
+            assert!((value - expected).abs() < 1.0e-12);
+        }
+
+        // long chunks with few sincs use the convolution
+        let params = InterpolationParameters {
+            sinc_len: 512,
+            f_cutoff: 0.95,
+            interpolation: InterpolationType::Cubic,
+            oversampling_factor: 4,
+            window: WindowFunction::Hann,
+        };
+        let mut convolved = SincFixedIn::<f64>::new(1.1, params.clone(), 2048, 2).unwrap();
+        convolved.set_convolution_threshold(512);
+        let mut direct = SincFixedIn::<f64>::new(1.1, params, 2048, 2).unwrap();
+        for chunk in 0..3 {
+            let waves: Vec<Vec<f64>> = (0..2)
+                .map(|chan| {
+                    (chunk * 2048..(chunk + 1) * 2048)
+                        .map(|n| (n as f64 * 0.01 * (chan + 1) as f64).sin())
+                        .collect()
+                })
+                .collect();
+            let out = convolved.process(&waves).unwrap();
+            let expected = direct.process(&waves).unwrap();
+            for (wave, exp) in out.iter().zip(expected.iter()) {
+                assert_eq!(wave.len(), exp.len());
+                for (value, exp) in wave.iter().zip(exp.iter()) {
+                    assert!((value - exp).abs() < 1.0e-9);
+                }
+            }
+        }
+    }
+}
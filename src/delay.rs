@@ -0,0 +1,213 @@
+use crate::error::{ResampleError, ResampleResult};
+use crate::{InterpolationParameters, Latency, Resampler, Sample, SincFixedIn};
+use std::fmt;
+
+/// A fractional delay line, that shifts a signal in time without changing the sample rate.
+///
+/// This is useful for example for time aligning the signals from microphones at different distances,
+/// where the difference is not a whole number of samples.
+/// It uses a `SincFixedIn` with a fixed ratio of 1.0, and offsets the phase of the sinc interpolation
+/// by the delay. The delay can be changed while running, which gives a jump in the output.
+/// The ratio can't be changed, and "set_resample_ratio" returns an error.
+pub struct FractionalDelay<T> {
+    resampler: SincFixedIn<T>,
+    delay: f64,
+    max_delay: f64,
+}
+
+impl<T> FractionalDelay<T>
+where
+    T: Sample,
+{
+    /// Create a new fractional delay line.
+    ///
+    /// Parameters are:
+    /// - `parameters`: Parameters for the sinc interpolation, see `InterpolationParameters`.
+    /// - `delay`: The delay in frames, between zero and the sinc length.
+    /// - `chunk_size`: Size of input data in frames.
+    /// - `nbr_channels`: Number of channels in input/output.
+    ///
+    /// # Errors
+    ///
+    /// The function returns `ResampleError::InvalidParameter` if the delay is out of range,
+    /// and for the same parameters as `SincFixedIn::new`.
+    pub fn new(
+        parameters: InterpolationParameters,
+        delay: f64,
+        chunk_size: usize,
+        nbr_channels: usize,
+    ) -> ResampleResult<Self> {
+        let max_delay = parameters.sinc_len as f64;
+        ResampleError::check(
+            delay >= 0.0 && delay <= max_delay,
+            "delay",
+            delay,
+            "must be between zero and the sinc length",
+        )?;
+        debug!(
+            "Create new FractionalDelay, delay: {}, chunk_size: {}, channels: {}",
+            delay, chunk_size, nbr_channels
+        );
+        let mut fractional_delay = FractionalDelay {
+            resampler: SincFixedIn::new(1.0, parameters, chunk_size, nbr_channels)?,
+            delay: 0.0,
+            max_delay,
+        };
+        fractional_delay.set_delay(delay);
+        Ok(fractional_delay)
+    }
+
+    /// Get the current delay in frames.
+    pub fn delay(&self) -> f64 {
+        self.delay
+    }
+
+    /// Set a new delay in frames, between zero and the sinc length.
+    /// The new delay applies from the next call to "process".
+    /// When the delay changes, that call returns a few more or fewer frames than the chunk size.
+    ///
+    /// # Panics
+    ///
+    /// Panics if the delay is out of range.
+    pub fn set_delay(&mut self, delay: f64) {
+        assert!(
+            delay >= 0.0 && delay <= self.max_delay,
+            "Delay {} is out of range, must be between 0 and {}",
+            delay,
+            self.max_delay
+        );
+        trace!("Set fractional delay to {}", delay);
+        self.resampler.shift_phase(delay - self.delay);
+        self.delay = delay;
+    }
+
+    /// Get the total latency, including the delay.
+    pub fn latency(&self) -> Latency {
+        let latency = self.resampler.latency();
+        Latency {
+            input_frames: latency.input_frames + self.delay,
+            output_frames: latency.output_frames + self.delay,
+        }
+    }
+}
+
+/// Shows the delay, but not the inner resampler.
+impl<T> fmt::Debug for FractionalDelay<T> {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        f.debug_struct("FractionalDelay")
+            .field("delay", &self.delay)
+            .field("max_delay", &self.max_delay)
+            .finish()
+    }
+}
+
+impl<T> Resampler<T> for FractionalDelay<T>
+where
+    T: Sample,
+{
+    fn process(&mut self, wave_in: &[Vec<T>]) -> ResampleResult<Vec<Vec<T>>> {
+        self.resampler.process(wave_in)
+    }
+
+    fn process_with(
+        &mut self,
+        wave_in: &[Vec<T>],
+        f: &mut dyn FnMut(usize, &[T]),
+    ) -> ResampleResult<()> {
+        self.resampler.process_with(wave_in, f)
+    }
+
+    /// The delay includes the fractional delay, see "latency".
+    fn output_delay(&self) -> f64 {
+        self.latency().output_frames
+    }
+
+    /// Clear the buffered input, and keep the current delay.
+    fn reset(&mut self) {
+        self.resampler.reset();
+        self.resampler.shift_phase(self.delay);
+    }
+
+    fn flush(&mut self) -> ResampleResult<Vec<Vec<T>>> {
+        self.resampler.flush()
+    }
+
+    fn nbr_frames_needed(&self) -> usize {
+        self.resampler.nbr_frames_needed()
+    }
+
+    fn nbr_output_frames_next(&self) -> usize {
+        self.resampler.nbr_output_frames_next()
+    }
+
+    /// The ratio of a delay line is fixed, so this always returns an error.
+    fn set_resample_ratio(&mut self, _new_ratio: f64) -> ResampleResult<()> {
+        Err(ResampleError::SyncNotAdjustable)
+    }
+
+    /// The ratio of a delay line is fixed, so this always returns an error.
+    fn set_resample_ratio_relative(&mut self, _rel_ratio: f64) -> ResampleResult<()> {
+        Err(ResampleError::SyncNotAdjustable)
+    }
+
+    fn resample_ratio(&self) -> f64 {
+        self.resampler.resample_ratio()
+    }
+
+    fn resample_ratio_original(&self) -> f64 {
+        self.resampler.resample_ratio_original()
+    }
+
+    fn chunk_size(&self) -> usize {
+        self.resampler.chunk_size()
+    }
+
+    fn nbr_channels(&self) -> usize {
+        self.resampler.nbr_channels()
+    }
+
+    fn sinc_len(&self) -> Option<usize> {
+        self.resampler.sinc_len()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::delay::FractionalDelay;
+    use crate::reference::ReferenceResampler;
+    use crate::Resampler;
+    use crate::{InterpolationParameters, InterpolationType, WindowFunction};
+
+    #[test]
+    fn delay_signal() {
+        let params = InterpolationParameters {
+            sinc_len: 128,
+            f_cutoff: 0.95,
+            interpolation: InterpolationType::Cubic,
+            oversampling_factor: 256,
+            window: WindowFunction::BlackmanHarris2,
+        };
+        let mut delay = FractionalDelay::<f64>::new(params.clone(), 0.37, 512, 1).unwrap();
+        assert!(delay.set_resample_ratio(1.1).is_err());
+        let wave: Vec<f64> = (0..2048).map(|n| (n as f64 * 0.1).sin()).collect();
+        let mut output: Vec<f64> = Vec::new();
+        for chunk in wave.chunks(512) {
+            let out = delay.process(&[chunk.to_vec()]).unwrap();
+            output.extend(out[0].iter());
+        }
+        let reference = ReferenceResampler::new(1.0, &params);
+        let expected = reference.resample(&wave, delay.latency().input_frames);
+        let max_error = output[256..1792]
+            .iter()
+            .zip(expected[256..1792].iter())
+            .map(|(out, exp)| (out - exp).abs())
+            .fold(0.0, f64::max);
+        assert!(max_error < 1.0e-6, "max error {}", max_error);
+
+        // a longer delay gives a longer chunk once
+        delay.set_delay(2.5);
+        assert_eq!(delay.nbr_output_frames_next(), 514);
+        assert_eq!(delay.process(&[vec![0.0; 512]]).unwrap()[0].len(), 514);
+        assert_eq!(delay.nbr_output_frames_next(), 512);
+    }
+}
@@ -0,0 +1,228 @@
+use crate::error::ResampleResult;
+use crate::Resampler;
+use std::fmt;
+
+/// A simple proportional-integral controller for the clock drift between two devices.
+///
+/// The input is a measured error, for example the deviation of a buffer fill level
+/// from its target, relative to the target.
+/// The output is a relative resample ratio, limited to `1 +- max_deviation`.
+#[derive(Debug, Clone)]
+pub struct DriftController {
+    proportional_gain: f64,
+    integral_gain: f64,
+    max_deviation: f64,
+    integral: f64,
+    drift: f64,
+}
+
+impl DriftController {
+    /// Create a new controller.
+    ///
+    /// Parameters are:
+    /// - `proportional_gain`: Gain for the current error.
+    /// - `integral_gain`: Gain for the accumulated error.
+    /// - `max_deviation`: Maximum relative deviation of the output from 1.0.
+    ///   This should be well below 0.1, since the asynchronous resamplers only allow +-10%.
+    pub fn new(proportional_gain: f64, integral_gain: f64, max_deviation: f64) -> Self {
+        DriftController {
+            proportional_gain,
+            integral_gain,
+            max_deviation,
+            integral: 0.0,
+            drift: 1.0,
+        }
+    }
+
+    /// Get the current relative resample ratio.
+    pub fn drift(&self) -> f64 {
+        self.drift
+    }
+
+    /// Reset the controller to a relative ratio of 1.0.
+    pub fn reset(&mut self) {
+        self.integral = 0.0;
+        self.drift = 1.0;
+    }
+
+    /// Update the controller with a new error measurement, and return the new relative ratio.
+    pub fn update(&mut self, error: f64) -> f64 {
+        let integral = self.integral + error;
+        let drift = 1.0 + self.proportional_gain * error + self.integral_gain * integral;
+        let lower = 1.0 - self.max_deviation;
+        let upper = 1.0 + self.max_deviation;
+        // stop integrating while limited, to avoid windup
+        self.drift = if drift < lower {
+            lower
+        } else if drift > upper {
+            upper
+        } else {
+            self.integral = integral;
+            drift
+        };
+        self.drift
+    }
+}
+
+/// A pair of resamplers for the two directions of a full-duplex audio stream,
+/// for example for echo cancellation, where capture and playback must stay in sync.
+///
+/// The capture resampler converts from the device rate to the processing rate,
+/// and the playback resampler converts from the processing rate back to the device rate.
+/// A single drift controller sets the ratios of both, so that the capture ratio is always
+/// scaled by the same factor that the playback ratio is divided by.
+/// The two paths then see exactly mirrored clock corrections.
+pub struct DuplexResampler<T> {
+    capture: Box<dyn Resampler<T>>,
+    playback: Box<dyn Resampler<T>>,
+    controller: DriftController,
+}
+
+/// Shows the drift controller, but not the resamplers.
+impl<T> fmt::Debug for DuplexResampler<T> {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        f.debug_struct("DuplexResampler")
+            .field("controller", &self.controller)
+            .finish()
+    }
+}
+
+impl<T> DuplexResampler<T> {
+    /// Create a new duplex resampler.
+    ///
+    /// Parameters are:
+    /// - `capture`: Resampler from the device rate to the processing rate.
+    /// - `playback`: Resampler from the processing rate to the device rate.
+    ///   Its original resample ratio should be the inverse of the one for capture.
+    /// - `controller`: The drift controller shared by both directions.
+    pub fn new(
+        capture: Box<dyn Resampler<T>>,
+        playback: Box<dyn Resampler<T>>,
+        controller: DriftController,
+    ) -> Self {
+        DuplexResampler {
+            capture,
+            playback,
+            controller,
+        }
+    }
+
+    /// Get the capture resampler.
+    pub fn capture(&mut self) -> &mut dyn Resampler<T> {
+        self.capture.as_mut()
+    }
+
+    /// Get the playback resampler.
+    pub fn playback(&mut self) -> &mut dyn Resampler<T> {
+        self.playback.as_mut()
+    }
+
+    /// Get the current relative resample ratio of the capture direction.
+    /// The playback direction uses the inverse of this value.
+    pub fn drift(&self) -> f64 {
+        self.controller.drift()
+    }
+
+    /// Resample a chunk of captured audio.
+    pub fn process_capture(&mut self, wave_in: &[Vec<T>]) -> ResampleResult<Vec<Vec<T>>> {
+        self.capture.process(wave_in)
+    }
+
+    /// Resample a chunk of audio for playback.
+    pub fn process_playback(&mut self, wave_in: &[Vec<T>]) -> ResampleResult<Vec<Vec<T>>> {
+        self.playback.process(wave_in)
+    }
+
+    /// Update the drift controller with a new error measurement,
+    /// and apply the new ratios to both resamplers.
+    ///
+    /// A positive error means that the device clock is running fast compared to the processing,
+    /// so that the capture needs to produce more frames per device frame.
+    ///
+    /// # Errors
+    ///
+    /// Returns an error if any of the resamplers doesn't accept the new ratio.
+    pub fn update_drift(&mut self, error: f64) -> ResampleResult<()> {
+        let drift = self.controller.update(error);
+        self.apply_drift(drift)
+    }
+
+    /// Set the relative ratio directly, and reset the controller.
+    ///
+    /// # Errors
+    ///
+    /// Returns an error if any of the resamplers doesn't accept the new ratio.
+    pub fn reset_drift(&mut self) -> ResampleResult<()> {
+        self.controller.reset();
+        self.apply_drift(1.0)
+    }
+
+    fn apply_drift(&mut self, drift: f64) -> ResampleResult<()> {
+        trace!("Set duplex drift to {}", drift);
+        self.capture.set_resample_ratio_relative(drift)?;
+        self.playback.set_resample_ratio_relative(1.0 / drift)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::duplex::{DriftController, DuplexResampler};
+    use crate::{FftFixedInOut, Resampler};
+    use crate::{InterpolationParameters, InterpolationType, WindowFunction};
+    use crate::{SincFixedIn, SincFixedOut};
+
+    #[test]
+    fn controller_limits() {
+        let mut controller = DriftController::new(0.1, 0.01, 0.002);
+        assert!((controller.update(0.001) - 1.00011).abs() < 1.0e-12);
+        for _ in 0..1000 {
+            controller.update(1.0);
+        }
+        assert!((controller.drift() - 1.002).abs() < 1.0e-12);
+        // no windup, the output leaves the limit as soon as the error changes sign
+        assert!(controller.update(-0.05) < 1.002);
+        controller.reset();
+        assert_eq!(controller.drift(), 1.0);
+    }
+
+    #[test]
+    fn mirrored_ratios() {
+        let params = || InterpolationParameters {
+            sinc_len: 64,
+            f_cutoff: 0.95,
+            interpolation: InterpolationType::Linear,
+            oversampling_factor: 16,
+            window: WindowFunction::BlackmanHarris2,
+        };
+        let capture = SincFixedIn::<f64>::new(2.0, params(), 1000, 1).unwrap();
+        let playback = SincFixedOut::<f64>::new(0.5, params(), 1000, 1).unwrap();
+        let mut duplex = DuplexResampler::new(
+            Box::new(capture),
+            Box::new(playback),
+            DriftController::new(1.0, 0.0, 0.01),
+        );
+        duplex.update_drift(0.005).unwrap();
+        assert!((duplex.drift() - 1.005).abs() < 1.0e-12);
+        let mut capture = SincFixedIn::<f64>::new(2.0, params(), 1000, 1).unwrap();
+        capture.set_resample_ratio(2.0 * 1.005).unwrap();
+        let mut playback = SincFixedOut::<f64>::new(0.5, params(), 1000, 1).unwrap();
+        playback.set_resample_ratio(0.5 / 1.005).unwrap();
+        assert_eq!(
+            duplex.capture().nbr_output_frames_next(),
+            capture.nbr_output_frames_next()
+        );
+        assert_eq!(
+            duplex.playback().nbr_frames_needed(),
+            playback.nbr_frames_needed()
+        );
+        duplex.reset_drift().unwrap();
+        assert_eq!(duplex.drift(), 1.0);
+
+        let mut fixed = DuplexResampler::<f64>::new(
+            Box::new(FftFixedInOut::new(44100, 48000, 1024, 1).unwrap()),
+            Box::new(FftFixedInOut::new(48000, 44100, 1024, 1).unwrap()),
+            DriftController::new(1.0, 0.0, 0.01),
+        );
+        assert!(fixed.update_drift(0.005).is_err());
+    }
+}
@@ -13,9 +13,15 @@ pub enum CpuFeature {
     /// the fma cpu feature.
     #[cfg(target_arch = "x86_64")]
     Fma,
+    /// x86_64 avx512f cpu feature.
+    #[cfg(all(target_arch = "x86_64", feature = "avx512"))]
+    Avx512f,
     /// aarc64 neon cpu feature.
     #[cfg(all(feature = "neon", target_arch = "aarch64"))]
     Neon,
+    /// WebAssembly simd128 feature.
+    #[cfg(all(target_arch = "wasm32", feature = "wasm-simd"))]
+    Simd128,
 }
 
 impl CpuFeature {
@@ -34,10 +40,19 @@ impl CpuFeature {
             CpuFeature::Fma => {
                 is_x86_feature_detected!("fma")
             }
+            #[cfg(all(target_arch = "x86_64", feature = "avx512"))]
+            CpuFeature::Avx512f => {
+                is_x86_feature_detected!("avx512f")
+            }
             #[cfg(all(feature = "neon", target_arch = "aarch64"))]
             CpuFeature::Neon => {
                 is_aarch64_feature_detected!("neon")
             }
+            // there is no runtime detection, the instructions are enabled when compiling
+            #[cfg(all(target_arch = "wasm32", feature = "wasm-simd"))]
+            CpuFeature::Simd128 => {
+                cfg!(target_feature = "simd128")
+            }
         }
     }
 }
@@ -58,10 +73,18 @@ impl fmt::Display for CpuFeature {
             CpuFeature::Fma => {
                 write!(f, "fma")
             }
+            #[cfg(all(target_arch = "x86_64", feature = "avx512"))]
+            CpuFeature::Avx512f => {
+                write!(f, "avx512f")
+            }
             #[cfg(all(feature = "neon", target_arch = "aarch64"))]
             CpuFeature::Neon => {
                 write!(f, "neon")
             }
+            #[cfg(all(target_arch = "wasm32", feature = "wasm-simd"))]
+            CpuFeature::Simd128 => {
+                write!(f, "simd128")
+            }
         }
     }
 }
@@ -79,52 +102,173 @@ impl fmt::Display for MissingCpuFeature {
 impl error::Error for MissingCpuFeature {}
 
 /// The error type used by `rubato`.
+///
+/// New variants may be added in future versions, so matches must include a wildcard arm.
 #[derive(Debug)]
+#[non_exhaustive]
 pub enum ResampleError {
     /// Error raised when Resample::set_resample_ratio is called with a ratio
-    /// that deviates for more than 10% of the original.
-    BadRatioUpdate,
+    /// that deviates too much from the original, which is 10% for the sinc resamplers
+    /// and 0.1% for trimming the ratio of `FftFixedIn`.
+    RatioOutOfBounds {
+        /// The ratio that was given.
+        provided: f64,
+        /// The original ratio.
+        original: f64,
+        /// The largest allowed relative deviation from the original ratio,
+        /// or infinity if any positive ratio is allowed.
+        max_deviation: f64,
+    },
     /// Error raised when trying to adjust a synchronous resampler.
     SyncNotAdjustable,
     /// Error raised when the number of channels doesn't match expected.
-    WrongNumberOfChannels { expected: usize, actual: usize },
+    /// The `resampler` field holds a summary of the configuration and state
+    /// of the resampler that raised the error, or is empty if it isn't known.
+    WrongNumberOfChannels {
+        expected: usize,
+        actual: usize,
+        resampler: String,
+    },
     /// Error raised when the number of frames in a single channel doesn't match
     /// the expected.
+    /// The `resampler` field holds a summary of the configuration and state
+    /// of the resampler that raised the error, or is empty if it isn't known.
     WrongNumberOfFrames {
         channel: usize,
         expected: usize,
         actual: usize,
+        resampler: String,
+    },
+    /// Error raised when an offline job is cancelled before it is finished.
+    Cancelled,
+    /// Error raised when an FFT resampler is created without a backend,
+    /// when neither the `realfft` nor the `rustfft` feature is enabled.
+    NoFftBackend,
+    /// Error raised when a resampler is created with an invalid parameter.
+    InvalidParameter {
+        /// The name of the parameter.
+        name: &'static str,
+        /// The given value, formatted as a string.
+        value: String,
+        /// The requirement that the value does not meet.
+        requirement: &'static str,
     },
 }
 
 impl fmt::Display for ResampleError {
     fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
         match self {
-            Self::BadRatioUpdate => {
-                write!(f, "New resample ratio is too far off from original")
+            Self::RatioOutOfBounds {
+                provided,
+                original,
+                max_deviation,
+            } => {
+                if max_deviation.is_finite() {
+                    write!(
+                        f,
+                        "New resample ratio {} is too far off from original {}, the limit is {}%",
+                        provided,
+                        original,
+                        100.0 * max_deviation
+                    )
+                } else {
+                    write!(
+                        f,
+                        "New resample ratio {} is not a positive number",
+                        provided
+                    )
+                }
             }
             Self::SyncNotAdjustable { .. } => {
                 write!(f, "Not possible to adjust a synchronous resampler")
             }
-            Self::WrongNumberOfChannels { expected, actual } => {
+            Self::WrongNumberOfChannels {
+                expected,
+                actual,
+                resampler,
+            } => {
                 write!(
                     f,
                     "Wrong number of channels {} in input, expected {}",
                     actual, expected
-                )
+                )?;
+                write_resampler(f, resampler)
             }
             Self::WrongNumberOfFrames {
                 channel,
                 expected,
                 actual,
+                resampler,
             } => {
                 write!(
                     f,
                     "Wrong number of frames {} in input channel {}, expected {}",
                     actual, channel, expected
+                )?;
+                write_resampler(f, resampler)
+            }
+            Self::Cancelled => {
+                write!(f, "The processing was cancelled")
+            }
+            Self::NoFftBackend => {
+                write!(
+                    f,
+                    "No FFT backend is enabled, enable the `realfft` or `rustfft` feature, \
+                     or use `new_with_backend`"
                 )
             }
+            Self::InvalidParameter {
+                name,
+                value,
+                requirement,
+            } => {
+                write!(f, "Invalid value {} for {}, {}", value, name, requirement)
+            }
+        }
+    }
+}
+
+/// Append the summary of the resampler to an error message, if there is one.
+fn write_resampler(f: &mut fmt::Formatter<'_>, resampler: &str) -> fmt::Result {
+    if resampler.is_empty() {
+        Ok(())
+    } else {
+        write!(f, ", in {}", resampler)
+    }
+}
+
+impl ResampleError {
+    /// Check a condition on a parameter, and return an `InvalidParameter` error if it doesn't hold.
+    pub(crate) fn check<V: fmt::Display>(
+        condition: bool,
+        name: &'static str,
+        value: V,
+        requirement: &'static str,
+    ) -> Result<(), Self> {
+        if condition {
+            Ok(())
+        } else {
+            Err(Self::InvalidParameter {
+                name,
+                value: value.to_string(),
+                requirement,
+            })
+        }
+    }
+
+    /// Fill in the summary of the resampler that raised a validation error,
+    /// unless it is already known.
+    pub(crate) fn with_resampler(mut self, summary: &dyn fmt::Debug) -> Self {
+        match &mut self {
+            Self::WrongNumberOfChannels { resampler, .. }
+            | Self::WrongNumberOfFrames { resampler, .. }
+                if resampler.is_empty() =>
+            {
+                *resampler = format!("{:?}", summary);
+            }
+            _ => {}
         }
+        self
     }
 }
 
@@ -0,0 +1,633 @@
+#[cfg(any(
+    feature = "microfft",
+    not(any(feature = "realfft", feature = "rustfft"))
+))]
+use crate::error::ResampleError;
+use crate::error::ResampleResult;
+use crate::Sample;
+#[cfg(feature = "microfft")]
+use microfft::Complex32;
+use num_complex::Complex;
+#[cfg(feature = "realfft")]
+use realfft::{ComplexToReal, RealFftPlanner, RealToComplex};
+#[cfg(feature = "rustfft")]
+use rustfft::{Fft, FftPlanner};
+use std::sync::Arc;
+
+/// A forward FFT of a fixed length, from real input to complex spectrum.
+pub trait ForwardFft<T>: Send + Sync {
+    /// Get the length of the real input.
+    fn len(&self) -> usize;
+
+    /// Check if the length is zero.
+    fn is_empty(&self) -> bool {
+        self.len() == 0
+    }
+
+    /// Get the length of the scratch buffer needed by "process".
+    fn scratch_len(&self) -> usize;
+
+    /// Transform `input` of length `len` into `output` of length `len/2 + 1`.
+    /// The contents of `input` may be used as scratch space, and are undefined afterwards.
+    fn process(&self, input: &mut [T], output: &mut [Complex<T>], scratch: &mut [Complex<T>]);
+}
+
+/// An inverse FFT of a fixed length, from complex spectrum to real output.
+/// The output is not normalized.
+pub trait InverseFft<T>: Send + Sync {
+    /// Get the length of the real output.
+    fn len(&self) -> usize;
+
+    /// Check if the length is zero.
+    fn is_empty(&self) -> bool {
+        self.len() == 0
+    }
+
+    /// Get the length of the scratch buffer needed by "process".
+    fn scratch_len(&self) -> usize;
+
+    /// Transform `input` of length `len/2 + 1` into `output` of length `len`.
+    /// The contents of `input` may be used as scratch space, and are undefined afterwards.
+    fn process(&self, input: &mut [Complex<T>], output: &mut [T], scratch: &mut [Complex<T>]);
+}
+
+/// A planner for the FFTs used by the synchronous resamplers.
+///
+/// The crate includes backends for the `realfft`, `rustfft` and `microfft` crates,
+/// each behind the feature with the same name. Only `realfft` is enabled by default.
+/// The constructors without a backend argument use `RealFftBackend`,
+/// or `RustFftBackend` when only the `rustfft` feature is enabled.
+/// A different FFT implementation can be used by implementing this trait,
+/// and passing it to the `new_with_backend` constructors of the resamplers.
+pub trait FftBackend<T> {
+    /// Create a forward FFT of length `len`.
+    /// Returns an error if the backend doesn't support the length.
+    fn plan_forward(&mut self, len: usize) -> ResampleResult<Arc<dyn ForwardFft<T>>>;
+
+    /// Create an inverse FFT of length `len`.
+    /// Returns an error if the backend doesn't support the length.
+    fn plan_inverse(&mut self, len: usize) -> ResampleResult<Arc<dyn InverseFft<T>>>;
+}
+
+/// Create the backend used by the constructors that don't take a backend argument.
+/// This is `RealFftBackend` when the `realfft` feature is enabled, and otherwise `RustFftBackend`.
+/// Returns `ResampleError::NoFftBackend` if neither feature is enabled.
+pub(crate) fn default_backend<T>() -> ResampleResult<Box<dyn FftBackend<T>>>
+where
+    T: Sample,
+{
+    #[cfg(feature = "realfft")]
+    let backend: ResampleResult<Box<dyn FftBackend<T>>> = Ok(Box::new(RealFftBackend::new()));
+    #[cfg(all(not(feature = "realfft"), feature = "rustfft"))]
+    let backend: ResampleResult<Box<dyn FftBackend<T>>> = Ok(Box::new(RustFftBackend::new()));
+    #[cfg(not(any(feature = "realfft", feature = "rustfft")))]
+    let backend: ResampleResult<Box<dyn FftBackend<T>>> = Err(ResampleError::NoFftBackend);
+    backend
+}
+
+/// The default FFT backend, using the `realfft` crate.
+#[cfg(feature = "realfft")]
+pub struct RealFftBackend<T: realfft::FftNum> {
+    planner: RealFftPlanner<T>,
+}
+
+#[cfg(feature = "realfft")]
+impl<T: realfft::FftNum> RealFftBackend<T> {
+    /// Create a new backend.
+    pub fn new() -> Self {
+        RealFftBackend {
+            planner: RealFftPlanner::new(),
+        }
+    }
+}
+
+#[cfg(feature = "realfft")]
+impl<T: realfft::FftNum> Default for RealFftBackend<T> {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+#[cfg(feature = "realfft")]
+struct RealFftForward<T> {
+    fft: Arc<dyn RealToComplex<T>>,
+}
+
+#[cfg(feature = "realfft")]
+struct RealFftInverse<T> {
+    fft: Arc<dyn ComplexToReal<T>>,
+}
+
+#[cfg(feature = "realfft")]
+impl<T: realfft::FftNum> ForwardFft<T> for RealFftForward<T> {
+    fn len(&self) -> usize {
+        self.fft.len()
+    }
+
+    fn scratch_len(&self) -> usize {
+        self.fft.get_scratch_len()
+    }
+
+    fn process(&self, input: &mut [T], output: &mut [Complex<T>], scratch: &mut [Complex<T>]) {
+        self.fft
+            .process_with_scratch(input, output, scratch)
+            .unwrap();
+    }
+}
+
+#[cfg(feature = "realfft")]
+impl<T: realfft::FftNum> InverseFft<T> for RealFftInverse<T> {
+    fn len(&self) -> usize {
+        self.fft.len()
+    }
+
+    fn scratch_len(&self) -> usize {
+        self.fft.get_scratch_len()
+    }
+
+    fn process(&self, input: &mut [Complex<T>], output: &mut [T], scratch: &mut [Complex<T>]) {
+        self.fft
+            .process_with_scratch(input, output, scratch)
+            .unwrap();
+    }
+}
+
+#[cfg(feature = "realfft")]
+impl<T: realfft::FftNum> FftBackend<T> for RealFftBackend<T> {
+    fn plan_forward(&mut self, len: usize) -> ResampleResult<Arc<dyn ForwardFft<T>>> {
+        Ok(Arc::new(RealFftForward {
+            fft: self.planner.plan_fft_forward(len),
+        }))
+    }
+
+    fn plan_inverse(&mut self, len: usize) -> ResampleResult<Arc<dyn InverseFft<T>>> {
+        Ok(Arc::new(RealFftInverse {
+            fft: self.planner.plan_fft_inverse(len),
+        }))
+    }
+}
+
+/// An FFT backend using the complex FFTs of the `rustfft` crate.
+///
+/// The real signals are transformed as complex signals with zero imaginary parts,
+/// which takes about twice the time of `RealFftBackend`.
+/// This avoids the extra dependency on `realfft` for applications that already use `rustfft`.
+#[cfg(feature = "rustfft")]
+pub struct RustFftBackend<T: rustfft::FftNum> {
+    planner: FftPlanner<T>,
+}
+
+#[cfg(feature = "rustfft")]
+impl<T: rustfft::FftNum> RustFftBackend<T> {
+    /// Create a new backend.
+    pub fn new() -> Self {
+        RustFftBackend {
+            planner: FftPlanner::new(),
+        }
+    }
+}
+
+#[cfg(feature = "rustfft")]
+impl<T: rustfft::FftNum> Default for RustFftBackend<T> {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+/// A forward or inverse complex FFT of the `rustfft` crate.
+/// The scratch buffer holds the complex signal, followed by the scratch space of the FFT.
+#[cfg(feature = "rustfft")]
+struct RustFftComplex<T> {
+    fft: Arc<dyn Fft<T>>,
+}
+
+#[cfg(feature = "rustfft")]
+impl<T: rustfft::FftNum> ForwardFft<T> for RustFftComplex<T> {
+    fn len(&self) -> usize {
+        self.fft.len()
+    }
+
+    fn scratch_len(&self) -> usize {
+        self.fft.len() + self.fft.get_inplace_scratch_len()
+    }
+
+    fn process(&self, input: &mut [T], output: &mut [Complex<T>], scratch: &mut [Complex<T>]) {
+        let (buffer, scratch) = scratch.split_at_mut(self.fft.len());
+        for (value, sample) in buffer.iter_mut().zip(input.iter()) {
+            *value = Complex::new(*sample, T::zero());
+        }
+        self.fft.process_with_scratch(buffer, scratch);
+        output.copy_from_slice(&buffer[..output.len()]);
+    }
+}
+
+#[cfg(feature = "rustfft")]
+impl<T: rustfft::FftNum> InverseFft<T> for RustFftComplex<T> {
+    fn len(&self) -> usize {
+        self.fft.len()
+    }
+
+    fn scratch_len(&self) -> usize {
+        self.fft.len() + self.fft.get_inplace_scratch_len()
+    }
+
+    fn process(&self, input: &mut [Complex<T>], output: &mut [T], scratch: &mut [Complex<T>]) {
+        let (buffer, scratch) = scratch.split_at_mut(self.fft.len());
+        fill_hermitian(input, buffer);
+        self.fft.process_with_scratch(buffer, scratch);
+        for (sample, value) in output.iter_mut().zip(buffer.iter()) {
+            *sample = value.re;
+        }
+    }
+}
+
+#[cfg(feature = "rustfft")]
+impl<T: rustfft::FftNum> FftBackend<T> for RustFftBackend<T> {
+    fn plan_forward(&mut self, len: usize) -> ResampleResult<Arc<dyn ForwardFft<T>>> {
+        Ok(Arc::new(RustFftComplex {
+            fft: self.planner.plan_fft_forward(len),
+        }))
+    }
+
+    fn plan_inverse(&mut self, len: usize) -> ResampleResult<Arc<dyn InverseFft<T>>> {
+        Ok(Arc::new(RustFftComplex {
+            fft: self.planner.plan_fft_inverse(len),
+        }))
+    }
+}
+
+/// Fill `spectrum` with the full spectrum of a real signal, from the `spectrum.len()/2 + 1`
+/// values of `half`, using that the negative frequencies are the complex conjugates
+/// of the positive ones.
+#[cfg(any(feature = "rustfft", feature = "microfft"))]
+fn fill_hermitian<T: Copy + num_traits::Signed>(half: &[Complex<T>], spectrum: &mut [Complex<T>]) {
+    let len = spectrum.len();
+    spectrum[..=(len / 2)].copy_from_slice(&half[..=(len / 2)]);
+    for n in 1..((len + 1) / 2) {
+        spectrum[len - n] = half[n].conj();
+    }
+}
+
+/// An FFT backend using the `microfft` crate, for embedded targets.
+///
+/// The FFTs of `microfft` are implemented for fixed sizes, and only for `f32`,
+/// so the backend supports the lengths that are powers of two, from 4 to 4096.
+/// The FFT resamplers use lengths given by the sample rates,
+/// so this works when the ratio of the sample rates is a power of two, like 24 to 48 kHz,
+/// and the chunk size makes the lengths powers of two.
+/// Other lengths make the constructors of the resamplers return an error.
+#[cfg(feature = "microfft")]
+#[derive(Debug, Default)]
+pub struct MicroFftBackend;
+
+#[cfg(feature = "microfft")]
+impl MicroFftBackend {
+    /// Create a new backend.
+    pub fn new() -> Self {
+        MicroFftBackend
+    }
+}
+
+/// A forward FFT of the `microfft` crate.
+#[cfg(feature = "microfft")]
+struct MicroFftForward {
+    len: usize,
+}
+
+/// An inverse FFT of the `microfft` crate.
+/// The scratch buffer holds the full complex spectrum.
+#[cfg(feature = "microfft")]
+struct MicroFftInverse {
+    len: usize,
+}
+
+#[cfg(feature = "microfft")]
+impl ForwardFft<f32> for MicroFftForward {
+    fn len(&self) -> usize {
+        self.len
+    }
+
+    fn scratch_len(&self) -> usize {
+        0
+    }
+
+    fn process(&self, input: &mut [f32], output: &mut [Complex32], _scratch: &mut [Complex32]) {
+        let spectrum = micro_rfft(input);
+        // the real value at the Nyquist frequency is packed into the imaginary part of the first bin
+        output[0] = Complex32::new(spectrum[0].re, 0.0);
+        output[self.len / 2] = Complex32::new(spectrum[0].im, 0.0);
+        output[1..(self.len / 2)].copy_from_slice(&spectrum[1..]);
+    }
+}
+
+#[cfg(feature = "microfft")]
+impl InverseFft<f32> for MicroFftInverse {
+    fn len(&self) -> usize {
+        self.len
+    }
+
+    fn scratch_len(&self) -> usize {
+        self.len
+    }
+
+    fn process(&self, input: &mut [Complex32], output: &mut [f32], scratch: &mut [Complex32]) {
+        let buffer = &mut scratch[..self.len];
+        fill_hermitian(input, buffer);
+        micro_ifft(buffer);
+        // microfft normalizes the inverse transform, the other backends don't
+        let scale = self.len as f32;
+        for (sample, value) in output.iter_mut().zip(buffer.iter()) {
+            *sample = value.re * scale;
+        }
+    }
+}
+
+#[cfg(feature = "microfft")]
+impl FftBackend<f32> for MicroFftBackend {
+    fn plan_forward(&mut self, len: usize) -> ResampleResult<Arc<dyn ForwardFft<f32>>> {
+        check_micro_fft_len(len)?;
+        Ok(Arc::new(MicroFftForward { len }))
+    }
+
+    fn plan_inverse(&mut self, len: usize) -> ResampleResult<Arc<dyn InverseFft<f32>>> {
+        check_micro_fft_len(len)?;
+        Ok(Arc::new(MicroFftInverse { len }))
+    }
+}
+
+/// Check that `microfft` has an FFT of the length `len`.
+#[cfg(feature = "microfft")]
+pub(crate) fn check_micro_fft_len(len: usize) -> ResampleResult<()> {
+    ResampleError::check(
+        len.is_power_of_two() && (4..=4096).contains(&len),
+        "FFT length",
+        len,
+        "must be a power of two from 4 to 4096",
+    )
+}
+
+/// Call the function of `microfft` for the length of the buffer, which must be checked
+/// with `check_micro_fft_len` first.
+#[cfg(feature = "microfft")]
+macro_rules! micro_dispatch {
+    ($buffer:expr, $($len:literal => $function:path),*) => {
+        match $buffer.len() {
+            $($len => &mut $function($buffer.try_into().unwrap())[..],)*
+            len => panic!("Unsupported FFT length {}", len),
+        }
+    };
+}
+
+/// Make a real forward FFT in place, with the `microfft` crate.
+/// The result has the real values at zero and at the Nyquist frequency packed into the first bin.
+#[cfg(feature = "microfft")]
+pub(crate) fn micro_rfft(buffer: &mut [f32]) -> &mut [Complex32] {
+    use microfft::real::*;
+    use std::convert::TryInto;
+    micro_dispatch!(buffer,
+        4 => rfft_4, 8 => rfft_8, 16 => rfft_16, 32 => rfft_32, 64 => rfft_64, 128 => rfft_128,
+        256 => rfft_256, 512 => rfft_512, 1024 => rfft_1024, 2048 => rfft_2048, 4096 => rfft_4096)
+}
+
+/// Make a complex forward FFT in place, with the `microfft` crate.
+#[cfg(feature = "microfft")]
+pub(crate) fn micro_cfft(buffer: &mut [Complex32]) -> &mut [Complex32] {
+    use microfft::complex::*;
+    use std::convert::TryInto;
+    micro_dispatch!(buffer,
+        4 => cfft_4, 8 => cfft_8, 16 => cfft_16, 32 => cfft_32, 64 => cfft_64, 128 => cfft_128,
+        256 => cfft_256, 512 => cfft_512, 1024 => cfft_1024, 2048 => cfft_2048, 4096 => cfft_4096)
+}
+
+/// Make a complex inverse FFT in place, with the `microfft` crate.
+/// Unlike the other backends, the result is normalized.
+#[cfg(feature = "microfft")]
+pub(crate) fn micro_ifft(buffer: &mut [Complex32]) -> &mut [Complex32] {
+    use microfft::inverse::*;
+    use std::convert::TryInto;
+    micro_dispatch!(buffer,
+        4 => ifft_4, 8 => ifft_8, 16 => ifft_16, 32 => ifft_32, 64 => ifft_64, 128 => ifft_128,
+        256 => ifft_256, 512 => ifft_512, 1024 => ifft_1024, 2048 => ifft_2048, 4096 => ifft_4096)
+}
+
+#[cfg(test)]
+mod tests {
+    #[cfg(feature = "microfft")]
+    use crate::fft::MicroFftBackend;
+    #[cfg(feature = "rustfft")]
+    use crate::fft::RustFftBackend;
+    use crate::fft::{FftBackend, ForwardFft, InverseFft, RealFftBackend};
+    #[cfg(any(feature = "rustfft", feature = "microfft"))]
+    use crate::Sample;
+    use crate::{FftFixedIn, FftFixedInOut, FftFixedOut, ResampleResult, Resampler};
+    use num_complex::Complex;
+    use std::f64::consts::PI;
+    use std::sync::Arc;
+
+    /// A slow DFT, to check that the resamplers don't depend on the default backend.
+    struct Dft {
+        len: usize,
+    }
+
+    impl ForwardFft<f64> for Dft {
+        fn len(&self) -> usize {
+            self.len
+        }
+
+        fn scratch_len(&self) -> usize {
+            0
+        }
+
+        fn process(&self, input: &mut [f64], output: &mut [Complex<f64>], _: &mut [Complex<f64>]) {
+            for (k, out) in output.iter_mut().enumerate() {
+                *out = Complex::new(0.0, 0.0);
+                for (n, value) in input.iter().enumerate() {
+                    let angle = -2.0 * PI * (k * n) as f64 / self.len as f64;
+                    *out += Complex::from_polar(*value, angle);
+                }
+            }
+        }
+    }
+
+    impl InverseFft<f64> for Dft {
+        fn len(&self) -> usize {
+            self.len
+        }
+
+        fn scratch_len(&self) -> usize {
+            0
+        }
+
+        fn process(&self, input: &mut [Complex<f64>], output: &mut [f64], _: &mut [Complex<f64>]) {
+            for (n, out) in output.iter_mut().enumerate() {
+                *out = 0.0;
+                for k in 0..self.len {
+                    // use the symmetry of the spectrum of a real signal
+                    let value = if k <= self.len / 2 {
+                        input[k]
+                    } else {
+                        input[self.len - k].conj()
+                    };
+                    let angle = 2.0 * PI * (k * n) as f64 / self.len as f64;
+                    *out += (value * Complex::from_polar(1.0, angle)).re;
+                }
+            }
+        }
+    }
+
+    struct DftBackend {
+        nbr_plans: usize,
+    }
+
+    impl FftBackend<f64> for DftBackend {
+        fn plan_forward(&mut self, len: usize) -> ResampleResult<Arc<dyn ForwardFft<f64>>> {
+            self.nbr_plans += 1;
+            Ok(Arc::new(Dft { len }))
+        }
+
+        fn plan_inverse(&mut self, len: usize) -> ResampleResult<Arc<dyn InverseFft<f64>>> {
+            self.nbr_plans += 1;
+            Ok(Arc::new(Dft { len }))
+        }
+    }
+
+    fn assert_close(left: &[Vec<f64>], right: &[Vec<f64>]) {
+        assert_eq!(left.len(), right.len());
+        for (left, right) in left.iter().zip(right.iter()) {
+            assert_eq!(left.len(), right.len());
+            for (l, r) in left.iter().zip(right.iter()) {
+                assert!((l - r).abs() < 1.0e-9);
+            }
+        }
+    }
+
+    #[test]
+    fn default_backend() {
+        let mut backend = RealFftBackend::<f64>::new();
+        let fft = backend.plan_forward(8).unwrap();
+        let ifft = backend.plan_inverse(8).unwrap();
+        assert_eq!(fft.len(), 8);
+        let mut input = vec![1.0, 0.0, 0.0, 0.0, 0.0, 0.0, 0.0, 0.0];
+        let mut spectrum = vec![Complex::new(0.0, 0.0); 5];
+        let mut scratch = vec![Complex::new(0.0, 0.0); fft.scratch_len()];
+        fft.process(&mut input, &mut spectrum, &mut scratch);
+        assert!(spectrum
+            .iter()
+            .all(|value| (value.re - 1.0).abs() < 1.0e-12));
+        let mut output = vec![0.0; 8];
+        let mut scratch = vec![Complex::new(0.0, 0.0); ifft.scratch_len()];
+        ifft.process(&mut spectrum, &mut output, &mut scratch);
+        assert!((output[0] - 8.0).abs() < 1.0e-12);
+        assert!(output[1..].iter().all(|value| value.abs() < 1.0e-12));
+    }
+
+    #[test]
+    fn custom_backend() {
+        let mut backend = DftBackend { nbr_plans: 0 };
+        let wave: Vec<Vec<f64>> = vec![(0..294).map(|n| (n as f64 * 0.1).sin()).collect(); 2];
+
+        let mut resampler =
+            FftFixedInOut::<f64>::new_with_backend(44100, 48000, 294, 2, &mut backend).unwrap();
+        let mut reference = FftFixedInOut::<f64>::new(44100, 48000, 294, 2).unwrap();
+        for _ in 0..3 {
+            assert_close(
+                &resampler.process(&wave).unwrap(),
+                &reference.process(&wave).unwrap(),
+            );
+        }
+
+        let mut resampler =
+            FftFixedIn::<f64>::new_with_backend(44100, 48000, 294, 1, 2, &mut backend).unwrap();
+        let mut reference = FftFixedIn::<f64>::new(44100, 48000, 294, 1, 2).unwrap();
+        assert_close(
+            &resampler.process(&wave).unwrap(),
+            &reference.process(&wave).unwrap(),
+        );
+
+        let mut resampler =
+            FftFixedOut::<f64>::new_with_backend(44100, 48000, 320, 1, 2, &mut backend).unwrap();
+        let mut reference = FftFixedOut::<f64>::new(44100, 48000, 320, 1, 2).unwrap();
+        assert_close(
+            &resampler.process(&wave).unwrap(),
+            &reference.process(&wave).unwrap(),
+        );
+        assert_eq!(backend.nbr_plans, 6);
+    }
+
+    /// Compare the FFTs of a backend with the DFT, for a signal of length `len`,
+    /// to within `tolerance` relative to the largest value of the spectrum.
+    #[cfg(any(feature = "rustfft", feature = "microfft"))]
+    fn check_backend<T: Sample>(backend: &mut dyn FftBackend<T>, len: usize, tolerance: f64) {
+        let signal: Vec<f64> = (0..len).map(|n| (n as f64 * 0.7).sin() + 0.3).collect();
+        let dft = Dft { len };
+        let mut expected = vec![Complex::new(0.0, 0.0); len / 2 + 1];
+        ForwardFft::process(&dft, &mut signal.clone(), &mut expected, &mut []);
+        let scale = expected
+            .iter()
+            .fold(0.0f64, |acc, value| acc.max(value.norm()));
+
+        let fft = backend.plan_forward(len).unwrap();
+        let mut input: Vec<T> = signal.iter().map(|value| T::coerce(*value)).collect();
+        let mut spectrum = vec![Complex::new(T::zero(), T::zero()); len / 2 + 1];
+        let mut scratch = vec![Complex::new(T::zero(), T::zero()); fft.scratch_len()];
+        fft.process(&mut input, &mut spectrum, &mut scratch);
+        for (value, expected) in spectrum.iter().zip(expected.iter()) {
+            let value = Complex::new(value.re.to_f64().unwrap(), value.im.to_f64().unwrap());
+            assert!((value - expected).norm() < tolerance * scale);
+        }
+
+        let ifft = backend.plan_inverse(len).unwrap();
+        let mut output = vec![T::zero(); len];
+        let mut scratch = vec![Complex::new(T::zero(), T::zero()); ifft.scratch_len()];
+        ifft.process(&mut spectrum, &mut output, &mut scratch);
+        for (value, expected) in output.iter().zip(signal.iter()) {
+            let value = value.to_f64().unwrap() / len as f64;
+            assert!((value - expected).abs() < tolerance * scale);
+        }
+    }
+
+    #[cfg(feature = "rustfft")]
+    #[test]
+    fn rustfft_backend() {
+        let mut backend = RustFftBackend::<f64>::new();
+        check_backend(&mut backend, 16, 1.0e-12);
+        check_backend(&mut backend, 15, 1.0e-12);
+        let wave: Vec<Vec<f64>> = vec![(0..294).map(|n| (n as f64 * 0.1).sin()).collect(); 2];
+        let mut resampler =
+            FftFixedInOut::<f64>::new_with_backend(44100, 48000, 294, 2, &mut backend).unwrap();
+        let mut reference = FftFixedInOut::<f64>::new(44100, 48000, 294, 2).unwrap();
+        assert_close(
+            &resampler.process(&wave).unwrap(),
+            &reference.process(&wave).unwrap(),
+        );
+    }
+
+    #[cfg(feature = "microfft")]
+    #[test]
+    fn microfft_backend() {
+        let mut backend = MicroFftBackend::new();
+        check_backend(&mut backend, 4, 1.0e-6);
+        check_backend(&mut backend, 64, 1.0e-6);
+        assert!(backend.plan_forward(12).is_err());
+        assert!(backend.plan_inverse(8192).is_err());
+
+        // the ratio of the sample rates is a power of two
+        let mut resampler =
+            FftFixedInOut::<f32>::new_with_backend(24000, 48000, 256, 2, &mut backend).unwrap();
+        let mut reference = FftFixedInOut::<f32>::new(24000, 48000, 256, 2).unwrap();
+        let frames = resampler.nbr_frames_needed();
+        let wave: Vec<Vec<f32>> = vec![(0..frames).map(|n| (n as f32 * 0.1).sin()).collect(); 2];
+        for (out, expected) in resampler
+            .process(&wave)
+            .unwrap()
+            .iter()
+            .flatten()
+            .zip(reference.process(&wave).unwrap().iter().flatten())
+        {
+            assert!((out - expected).abs() < 1.0e-5);
+        }
+        assert!(
+            FftFixedInOut::<f32>::new_with_backend(44100, 48000, 256, 2, &mut backend).is_err()
+        );
+    }
+}
@@ -0,0 +1,209 @@
+use crate::error::ResampleResult;
+use crate::Resampler;
+use std::fmt;
+
+/// A group of resamplers that follow a common master ratio,
+/// for example one resampler per sound card in a multi-device setup.
+///
+/// Each member has an offset, a relative ratio that is multiplied with the master ratio
+/// to compensate for the clock of that particular device.
+/// Ratio changes are not applied immediately, but at the start of the next call to "process",
+/// so that all members switch to the new ratio at the same chunk boundary.
+pub struct ResamplerGroup<T> {
+    members: Vec<Box<dyn Resampler<T>>>,
+    offsets: Vec<f64>,
+    applied: Vec<f64>,
+    master_ratio: f64,
+    pending: bool,
+}
+
+impl<T> ResamplerGroup<T> {
+    /// Create a new empty group, with a master ratio of 1.0.
+    pub fn new() -> Self {
+        ResamplerGroup {
+            members: Vec::new(),
+            offsets: Vec::new(),
+            applied: Vec::new(),
+            master_ratio: 1.0,
+            pending: false,
+        }
+    }
+
+    /// Add a resampler to the group, and return its index.
+    /// The ratios are applied to the new member at the next call to "process".
+    pub fn add_member(&mut self, resampler: Box<dyn Resampler<T>>, offset: f64) -> usize {
+        self.members.push(resampler);
+        self.offsets.push(offset);
+        self.applied.push(1.0);
+        self.pending = true;
+        self.members.len() - 1
+    }
+
+    /// Get the number of members.
+    pub fn len(&self) -> usize {
+        self.members.len()
+    }
+
+    /// Check if the group has no members.
+    pub fn is_empty(&self) -> bool {
+        self.members.is_empty()
+    }
+
+    /// Get a member of the group.
+    pub fn member(&mut self, index: usize) -> &mut dyn Resampler<T> {
+        self.members[index].as_mut()
+    }
+
+    /// Get the master ratio, relative to the original ratios of the members.
+    pub fn master_ratio(&self) -> f64 {
+        self.master_ratio
+    }
+
+    /// Set a new master ratio, to be applied at the next call to "process".
+    pub fn set_master_ratio(&mut self, rel_ratio: f64) {
+        trace!("Set group master ratio to {}", rel_ratio);
+        self.master_ratio = rel_ratio;
+        self.pending = true;
+    }
+
+    /// Set the offset of a member, to be applied at the next call to "process".
+    pub fn set_offset(&mut self, index: usize, offset: f64) {
+        self.offsets[index] = offset;
+        self.pending = true;
+    }
+
+    /// Apply any pending ratio changes to all members.
+    /// If any member rejects its new ratio, the members that were already updated
+    /// are returned to the previously applied ratios, so that the group stays consistent.
+    fn apply_pending(&mut self) -> ResampleResult<()> {
+        if !self.pending {
+            return Ok(());
+        }
+        for index in 0..self.members.len() {
+            let ratio = self.master_ratio * self.offsets[index];
+            if ratio == self.applied[index] {
+                continue;
+            }
+            if let Err(err) = self.members[index].set_resample_ratio_relative(ratio) {
+                debug!("Member {} rejected ratio {}, rolling back", index, ratio);
+                for prev in 0..index {
+                    let _ = self.members[prev].set_resample_ratio_relative(self.applied[prev]);
+                }
+                return Err(err);
+            }
+        }
+        for index in 0..self.members.len() {
+            self.applied[index] = self.master_ratio * self.offsets[index];
+        }
+        self.pending = false;
+        Ok(())
+    }
+
+    /// Resample one chunk for each member, after applying any pending ratio changes.
+    /// The input `waves` holds the input for each member, in the order they were added.
+    ///
+    /// # Errors
+    ///
+    /// Returns an error if a member rejects the new ratio, or if any member returns an error.
+    /// If a ratio is rejected, nothing is processed and all members keep their previous ratios.
+    /// The change then stays pending, and processing fails until the ratios are changed
+    /// to values that all members accept.
+    /// Members that don't support ratio changes, like the synchronous resamplers,
+    /// can be included as long as their own ratio stays at 1.0.
+    ///
+    /// # Panics
+    ///
+    /// Panics if the number of inputs doesn't match the number of members.
+    pub fn process(&mut self, waves: &[Vec<Vec<T>>]) -> ResampleResult<Vec<Vec<Vec<T>>>> {
+        assert_eq!(
+            waves.len(),
+            self.members.len(),
+            "Expected one input per group member"
+        );
+        self.apply_pending()?;
+        let mut outputs = Vec::with_capacity(self.members.len());
+        for (member, wave_in) in self.members.iter_mut().zip(waves.iter()) {
+            outputs.push(member.process(wave_in)?);
+        }
+        Ok(outputs)
+    }
+}
+
+/// Shows the ratios, but not the member resamplers.
+impl<T> fmt::Debug for ResamplerGroup<T> {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        f.debug_struct("ResamplerGroup")
+            .field("nbr_members", &self.members.len())
+            .field("master_ratio", &self.master_ratio)
+            .field("offsets", &self.offsets)
+            .field("pending", &self.pending)
+            .finish()
+    }
+}
+
+impl<T> Default for ResamplerGroup<T> {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::group::ResamplerGroup;
+    use crate::{FftFixedInOut, Resampler, SincFixedIn};
+    use crate::{InterpolationParameters, InterpolationType, WindowFunction};
+
+    fn params() -> InterpolationParameters {
+        InterpolationParameters {
+            sinc_len: 64,
+            f_cutoff: 0.95,
+            interpolation: InterpolationType::Linear,
+            oversampling_factor: 16,
+            window: WindowFunction::BlackmanHarris2,
+        }
+    }
+
+    #[test]
+    fn group_ratio() {
+        let mut group = ResamplerGroup::<f64>::new();
+        group.add_member(
+            Box::new(SincFixedIn::new(1.5, params(), 512, 1).unwrap()),
+            1.0,
+        );
+        group.add_member(
+            Box::new(SincFixedIn::new(1.5, params(), 512, 1).unwrap()),
+            1.001,
+        );
+        let mut reference = SincFixedIn::<f64>::new(1.5, params(), 512, 1).unwrap();
+        reference.set_resample_ratio_relative(1.001).unwrap();
+        let waves = vec![vec![vec![0.1; 512]], vec![vec![0.1; 512]]];
+        for chunk in 0..4 {
+            if chunk == 2 {
+                group.set_master_ratio(1.05);
+                reference.set_resample_ratio_relative(1.05 * 1.001).unwrap();
+            }
+            let outputs = group.process(&waves).unwrap();
+            let expected = reference.process(&waves[1]).unwrap();
+            assert_eq!(outputs[1], expected);
+        }
+        assert_eq!(group.master_ratio(), 1.05);
+    }
+
+    #[test]
+    fn rejected_ratio() {
+        let mut group = ResamplerGroup::<f64>::new();
+        group.add_member(
+            Box::new(SincFixedIn::new(1.5, params(), 512, 1).unwrap()),
+            1.0,
+        );
+        group.add_member(
+            Box::new(FftFixedInOut::new(44100, 48000, 512, 1).unwrap()),
+            1.0,
+        );
+        let frames = group.member(0).nbr_output_frames_next();
+        group.set_master_ratio(1.05);
+        let waves = vec![vec![vec![0.1; 512]], vec![vec![0.1; 512]]];
+        assert!(group.process(&waves).is_err());
+        assert_eq!(group.member(0).nbr_output_frames_next(), frames);
+    }
+}
@@ -42,11 +42,92 @@ pub fn get_nearest_time(t: f64, factor: isize) -> (isize, isize) {
     (index, subindex)
 }
 
+/// Get the index of the input sample nearest to time t.
+/// This includes the delay of a sinc filter with length `sinc_len` and `factor` sub-phases,
+/// so that the result is aligned with the sinc interpolated points.
+pub fn get_nearest_sample(t: f64, sinc_len: usize, factor: usize) -> isize {
+    (t + (sinc_len / 2) as f64 - 1.0 + 1.0 / factor as f64).round() as isize
+}
+
+/// Finds the nearest oversampled points for a time,
+/// with a faster way for oversampling factors that are powers of two.
+///
+/// The position of a time among the oversampled points is `t * factor`.
+/// For a power of two factor, the index and subindex of a point are the upper and lower bits
+/// of its position, which are found with a shift and a mask instead of the floating point
+/// operations of the general functions. The results are the same.
+/// The choice is made once, when the indexer is created.
+#[derive(Debug, Clone, Copy)]
+pub struct PhaseIndex {
+    factor: isize,
+    /// The base two logarithm of the factor, when it is a power of two.
+    shift: Option<u32>,
+}
+
+impl PhaseIndex {
+    /// Create an indexer for the given oversampling factor.
+    pub fn new(factor: usize) -> Self {
+        let shift = if factor.is_power_of_two() {
+            Some(factor.trailing_zeros())
+        } else {
+            None
+        };
+        PhaseIndex {
+            factor: factor as isize,
+            shift,
+        }
+    }
+
+    /// Split a position among the oversampled points into index and subindex.
+    #[inline]
+    fn split(shift: u32, pos: isize) -> (isize, isize) {
+        (pos >> shift, pos & ((1 << shift) - 1))
+    }
+
+    /// Get the two nearest time points for time t, like `get_nearest_times_2`.
+    #[inline]
+    pub fn nearest_times_2(&self, t: f64, points: &mut [(isize, isize); 2]) {
+        match self.shift {
+            Some(shift) => {
+                let pos = (t * self.factor as f64).floor() as isize;
+                points[0] = Self::split(shift, pos);
+                points[1] = Self::split(shift, pos + 1);
+            }
+            None => get_nearest_times_2(t, self.factor, points),
+        }
+    }
+
+    /// Get the four nearest time points for time t, like `get_nearest_times_4`.
+    #[inline]
+    pub fn nearest_times_4(&self, t: f64, points: &mut [(isize, isize); 4]) {
+        match self.shift {
+            Some(shift) => {
+                let pos = (t * self.factor as f64).floor() as isize;
+                for (offset, point) in points.iter_mut().enumerate() {
+                    *point = Self::split(shift, pos + offset as isize - 1);
+                }
+            }
+            None => get_nearest_times_4(t, self.factor, points),
+        }
+    }
+
+    /// Get the nearest time point for time t, like `get_nearest_time`.
+    #[inline]
+    pub fn nearest_time(&self, t: f64) -> (isize, isize) {
+        match self.shift {
+            Some(shift) => Self::split(shift, (t * self.factor as f64 + 0.5).floor() as isize),
+            None => get_nearest_time(t, self.factor),
+        }
+    }
+}
+
 #[cfg(test)]
 mod tests {
+    use crate::interpolation::get_nearest_sample;
     use crate::interpolation::get_nearest_time;
     use crate::interpolation::get_nearest_times_2;
     use crate::interpolation::get_nearest_times_4;
+    use crate::interpolation::PhaseIndex;
 
     #[test]
     fn get_nearest_2() {
@@ -96,4 +177,30 @@ mod tests {
         let time = get_nearest_time(t, 8);
         assert_eq!(time, (5, 4));
     }
+
+    #[test]
+    fn get_nearest_sample_delayed() {
+        assert_eq!(get_nearest_sample(5.3, 64, 8), 36);
+        assert_eq!(get_nearest_sample(5.5, 64, 8), 37);
+        assert_eq!(get_nearest_sample(-0.7, 64, 8), 30);
+    }
+
+    #[test]
+    fn pow2_matches_general() {
+        let phases = PhaseIndex::new(16);
+        let mut general = [(0isize, 0isize); 4];
+        let mut fast = [(0isize, 0isize); 4];
+        let mut general_2 = [(0isize, 0isize); 2];
+        let mut fast_2 = [(0isize, 0isize); 2];
+        for n in -2000..2000 {
+            let t = n as f64 * 0.01237;
+            get_nearest_times_4(t, 16, &mut general);
+            phases.nearest_times_4(t, &mut fast);
+            assert_eq!(general, fast);
+            get_nearest_times_2(t, 16, &mut general_2);
+            phases.nearest_times_2(t, &mut fast_2);
+            assert_eq!(general_2, fast_2);
+            assert_eq!(get_nearest_time(t, 16), phases.nearest_time(t));
+        }
+    }
 }
@@ -9,7 +9,7 @@ use core::arch::x86_64::{
     _mm256_add_pd, _mm256_fmadd_pd, _mm256_loadu_pd, _mm256_setzero_pd, _mm_add_pd, _mm_hadd_pd,  _mm_store_sd,
 };
 use core::arch::x86_64::{
-    _mm256_fmadd_ps, _mm256_loadu_ps, _mm256_setzero_ps, _mm_add_ps, _mm_hadd_ps, _mm_store_ss,
+    _mm256_add_ps, _mm256_fmadd_ps, _mm256_loadu_ps, _mm256_setzero_ps, _mm_add_ps, _mm_hadd_ps, _mm_store_ss,
 };
 use crate::asynchro::SincInterpolator;
 use crate::Sample;
@@ -17,9 +17,14 @@ use crate::Sample;
 /// Collection of cpu features required for this interpolator.
 static FEATURES: &[CpuFeature] = &[CpuFeature::Avx, CpuFeature::Fma];
 
+/// Check if the CPU supports the instructions used by this kernel.
+pub(crate) fn is_supported() -> bool {
+    FEATURES.iter().all(|feature| feature.is_detected())
+}
+
 /// Trait governing what can be done with an AvxSample.
 pub trait AvxSample: Sized {
-    type Sinc;
+    type Sinc: Send + Sync;
 
     /// Pack sincs into a vector.
     /// 
@@ -71,13 +76,24 @@ impl AvxSample for f32 {
     ) -> f32 {
         let sinc = sincs.get_unchecked(subindex);
         let wave_cut = &wave[index..(index + length)];
-        let mut acc = _mm256_setzero_ps();
+        // two independent accumulators, to hide the latency of the fused multiply-add
+        let mut acc0 = _mm256_setzero_ps();
+        let mut acc1 = _mm256_setzero_ps();
         let mut w_idx = 0;
-        for s_idx in 0..length / 8 {
+        let mut s_idx = 0;
+        for _ in 0..length / 16 {
+            let w0 = _mm256_loadu_ps(wave_cut.get_unchecked(w_idx));
+            let w1 = _mm256_loadu_ps(wave_cut.get_unchecked(w_idx + 8));
+            acc0 = _mm256_fmadd_ps(w0, *sinc.get_unchecked(s_idx), acc0);
+            acc1 = _mm256_fmadd_ps(w1, *sinc.get_unchecked(s_idx + 1), acc1);
+            w_idx += 16;
+            s_idx += 2;
+        }
+        if w_idx < length {
             let w = _mm256_loadu_ps(wave_cut.get_unchecked(w_idx));
-            acc = _mm256_fmadd_ps(w, *sinc.get_unchecked(s_idx), acc);
-            w_idx += 8;
+            acc0 = _mm256_fmadd_ps(w, *sinc.get_unchecked(s_idx), acc0);
         }
+        let acc = _mm256_add_ps(acc0, acc1);
         let acc_high = _mm256_extractf128_ps(acc, 1);
         let acc_low = _mm_add_ps(acc_high, _mm256_castps256_ps128(acc));
         let temp2 = _mm_hadd_ps(acc_low, acc_low);
@@ -179,14 +195,26 @@ impl<T> AvxInterpolator<T> where T: Sample {
             return Err(MissingCpuFeature(*feature));
         }
 
-        assert!(sinc_len % 8 == 0, "Sinc length must be a multiple of 8.");
         let sincs = make_sincs(sinc_len, oversampling_factor, f_cutoff, window);
+        Self::new_with_sincs(sincs)
+    }
+
+    /// Create a new AvxInterpolator from a set of sincs, one per oversampling step.
+    /// All sincs must have the same length, which must be a multiple of 8.
+    pub fn new_with_sincs(sincs: Vec<Vec<T>>) -> Result<Self, MissingCpuFeature> {
+        if let Some(feature) = FEATURES.iter().find(|f| !f.is_detected()) {
+            return Err(MissingCpuFeature(*feature));
+        }
+
+        let sinc_len = sincs[0].len();
+        let nbr_sincs = sincs.len();
+        assert!(sinc_len % 8 == 0, "Sinc length must be a multiple of 8.");
         let sincs = unsafe { <T as AvxSample>::pack_sincs(sincs) };
 
         Ok(Self {
             sincs,
             length: sinc_len,
-            nbr_sincs: oversampling_factor,
+            nbr_sincs,
         })
     }
 }
@@ -219,7 +247,7 @@ mod tests {
         let f_cutoff = 0.9473371669037001;
         let oversampling_factor = 256;
         let window = WindowFunction::BlackmanHarris2;
-        let sincs = make_sincs::<f64>(sinc_len, oversampling_factor, f_cutoff, window);
+        let sincs = make_sincs::<f64>(sinc_len, oversampling_factor, f_cutoff, window.clone());
 
         let interpolator = match AvxInterpolator::<f64>::new(sinc_len, oversampling_factor, f_cutoff, window) {
             Ok(interpolator) => interpolator,
@@ -245,7 +273,7 @@ mod tests {
         let f_cutoff = 0.9473371669037001;
         let oversampling_factor = 256;
         let window = WindowFunction::BlackmanHarris2;
-        let sincs = make_sincs::<f32>(sinc_len, oversampling_factor, f_cutoff, window);
+        let sincs = make_sincs::<f32>(sinc_len, oversampling_factor, f_cutoff, window.clone());
 
         let interpolator = match AvxInterpolator::<f32>::new(sinc_len, oversampling_factor, f_cutoff, window) {
             Ok(interpolator) => interpolator,
@@ -258,5 +286,12 @@ mod tests {
         let value = interpolator.get_sinc_interpolated(&wave, 333, 123);
         let check = get_sinc_interpolated(&wave, 333, &sincs[123]);
         assert!((value - check).abs() < 1.0e-5);
+
+        // a sinc length that is not a multiple of 16 uses one accumulator for the last 8 values
+        let sincs = make_sincs::<f32>(24, 16, f_cutoff, WindowFunction::BlackmanHarris2);
+        let interpolator = AvxInterpolator::<f32>::new_with_sincs(sincs.clone()).unwrap();
+        let value = interpolator.get_sinc_interpolated(&wave, 333, 5);
+        let check = get_sinc_interpolated(&wave, 333, &sincs[5]);
+        assert!((value - check).abs() < 1.0e-5);
     }
 }
@@ -0,0 +1,295 @@
+use crate::asynchro::SincInterpolator;
+use crate::error::{CpuFeature, MissingCpuFeature};
+use crate::sinc::make_sincs;
+use crate::windows::WindowFunction;
+use crate::Sample;
+use core::arch::x86_64::{__m512, _mm512_fmadd_ps, _mm512_maskz_loadu_ps, _mm512_reduce_add_ps};
+use core::arch::x86_64::{__m512d, _mm512_fmadd_pd, _mm512_loadu_pd, _mm512_reduce_add_pd};
+use core::arch::x86_64::{_mm512_loadu_ps, _mm512_setzero_pd, _mm512_setzero_ps};
+
+/// Collection of cpu features required for this interpolator.
+static FEATURES: &[CpuFeature] = &[CpuFeature::Avx512f];
+
+/// Check if the CPU supports the instructions used by this kernel.
+pub(crate) fn is_supported() -> bool {
+    FEATURES.iter().all(|feature| feature.is_detected())
+}
+
+/// Mask selecting the lower 8 of the 16 values of a 512-bit vector of f32.
+const LOWER_HALF: u16 = 0x00ff;
+
+/// Trait governing what can be done with an Avx512Sample.
+pub trait Avx512Sample: Sized {
+    type Sinc: Send + Sync;
+
+    /// Pack sincs into a vector.
+    ///
+    /// # Safety
+    ///
+    /// This is unsafe because it uses target_enable dispatching. There are no
+    /// special requirements from the caller.
+    unsafe fn pack_sincs(sincs: Vec<Vec<Self>>) -> Vec<Vec<Self::Sinc>>;
+
+    /// Interpolate a sinc sample.
+    ///
+    /// # Safety
+    ///
+    /// The caller must ensure that the various indexes are not out of bounds
+    /// in the collection of sincs.
+    unsafe fn get_sinc_interpolated_unsafe(
+        wave: &[Self],
+        index: usize,
+        subindex: usize,
+        sincs: &[Vec<Self::Sinc>],
+        length: usize,
+    ) -> Self;
+}
+
+impl Avx512Sample for f32 {
+    type Sinc = __m512;
+
+    /// The sincs are packed 16 values at a time.
+    /// When the length is not a multiple of 16, the last vector is padded with zeros.
+    #[target_feature(enable = "avx512f")]
+    unsafe fn pack_sincs(sincs: Vec<Vec<Self>>) -> Vec<Vec<Self::Sinc>> {
+        let mut packed_sincs = Vec::new();
+        for sinc in sincs.iter() {
+            let mut packed = Vec::new();
+            for elements in sinc.chunks(16) {
+                let mask = if elements.len() == 16 {
+                    u16::MAX
+                } else {
+                    LOWER_HALF
+                };
+                packed.push(_mm512_maskz_loadu_ps(mask, elements.as_ptr()));
+            }
+            packed_sincs.push(packed);
+        }
+        packed_sincs
+    }
+
+    #[target_feature(enable = "avx512f")]
+    unsafe fn get_sinc_interpolated_unsafe(
+        wave: &[f32],
+        index: usize,
+        subindex: usize,
+        sincs: &[Vec<Self::Sinc>],
+        length: usize,
+    ) -> f32 {
+        let sinc = sincs.get_unchecked(subindex);
+        let wave_cut = &wave[index..(index + length)];
+        let mut acc = _mm512_setzero_ps();
+        let mut w_idx = 0;
+        for s_idx in 0..length / 16 {
+            let w = _mm512_loadu_ps(wave_cut.get_unchecked(w_idx));
+            acc = _mm512_fmadd_ps(w, *sinc.get_unchecked(s_idx), acc);
+            w_idx += 16;
+        }
+        if w_idx < length {
+            // the masked load does not read past the end of the wave
+            let w = _mm512_maskz_loadu_ps(LOWER_HALF, wave_cut.get_unchecked(w_idx));
+            acc = _mm512_fmadd_ps(w, *sinc.get_unchecked(length / 16), acc);
+        }
+        _mm512_reduce_add_ps(acc)
+    }
+}
+
+impl Avx512Sample for f64 {
+    type Sinc = __m512d;
+
+    #[target_feature(enable = "avx512f")]
+    unsafe fn pack_sincs(sincs: Vec<Vec<f64>>) -> Vec<Vec<Self::Sinc>> {
+        let mut packed_sincs = Vec::new();
+        for sinc in sincs.iter() {
+            let mut packed = Vec::new();
+            for elements in sinc.chunks(8) {
+                packed.push(_mm512_loadu_pd(elements.as_ptr()));
+            }
+            packed_sincs.push(packed);
+        }
+        packed_sincs
+    }
+
+    #[target_feature(enable = "avx512f")]
+    unsafe fn get_sinc_interpolated_unsafe(
+        wave: &[f64],
+        index: usize,
+        subindex: usize,
+        sincs: &[Vec<Self::Sinc>],
+        length: usize,
+    ) -> f64 {
+        let sinc = sincs.get_unchecked(subindex);
+        let wave_cut = &wave[index..(index + length)];
+        let mut acc = _mm512_setzero_pd();
+        let mut w_idx = 0;
+        for s_idx in 0..length / 8 {
+            let w = _mm512_loadu_pd(wave_cut.get_unchecked(w_idx));
+            acc = _mm512_fmadd_pd(w, *sinc.get_unchecked(s_idx), acc);
+            w_idx += 8;
+        }
+        _mm512_reduce_add_pd(acc)
+    }
+}
+
+/// An AVX-512 accelerated interpolator.
+///
+/// When the `avx512` feature is enabled, it's chosen before the AVX kernel on CPUs that support it.
+/// Heavy use of 512-bit instructions makes many CPUs lower their clock frequency,
+/// which slows down everything else running on the same core,
+/// so the feature should only be enabled after measuring that it pays off on the target system.
+pub struct Avx512Interpolator<T>
+where
+    T: Avx512Sample,
+{
+    sincs: Vec<Vec<T::Sinc>>,
+    length: usize,
+    nbr_sincs: usize,
+}
+
+impl<T> SincInterpolator<T> for Avx512Interpolator<T>
+where
+    T: Avx512Sample,
+{
+    /// Calculate the scalar produt of an input wave and the selected sinc filter
+    fn get_sinc_interpolated(&self, wave: &[T], index: usize, subindex: usize) -> T {
+        assert!(
+            (index + self.length) < wave.len(),
+            "Tried to interpolate for index {}, max for the given input is {}",
+            index,
+            wave.len() - self.length - 1
+        );
+        assert!(
+            subindex < self.nbr_sincs,
+            "Tried to use sinc subindex {}, max is {}",
+            subindex,
+            self.nbr_sincs - 1
+        );
+        unsafe { T::get_sinc_interpolated_unsafe(wave, index, subindex, &self.sincs, self.length) }
+    }
+
+    fn len(&self) -> usize {
+        self.length
+    }
+
+    fn nbr_sincs(&self) -> usize {
+        self.nbr_sincs
+    }
+}
+
+impl<T> Avx512Interpolator<T>
+where
+    T: Sample,
+{
+    /// Create a new Avx512Interpolator
+    ///
+    /// Parameters are:
+    /// - `sinc_len`: Length of sinc functions.
+    /// - `oversampling_factor`: Number of intermediate sincs (oversampling factor).
+    /// - `f_cutoff`: Relative cutoff frequency.
+    /// - `window`: Window function to use.
+    pub fn new(
+        sinc_len: usize,
+        oversampling_factor: usize,
+        f_cutoff: f32,
+        window: WindowFunction,
+    ) -> Result<Self, MissingCpuFeature> {
+        if let Some(feature) = FEATURES.iter().find(|f| !f.is_detected()) {
+            return Err(MissingCpuFeature(*feature));
+        }
+
+        let sincs = make_sincs(sinc_len, oversampling_factor, f_cutoff, window);
+        Self::new_with_sincs(sincs)
+    }
+
+    /// Create a new Avx512Interpolator from a set of sincs, one per oversampling step.
+    /// All sincs must have the same length, which must be a multiple of 8.
+    pub fn new_with_sincs(sincs: Vec<Vec<T>>) -> Result<Self, MissingCpuFeature> {
+        if let Some(feature) = FEATURES.iter().find(|f| !f.is_detected()) {
+            return Err(MissingCpuFeature(*feature));
+        }
+
+        let sinc_len = sincs[0].len();
+        let nbr_sincs = sincs.len();
+        assert!(sinc_len % 8 == 0, "Sinc length must be a multiple of 8.");
+        let sincs = unsafe { <T as Avx512Sample>::pack_sincs(sincs) };
+
+        Ok(Self {
+            sincs,
+            length: sinc_len,
+            nbr_sincs,
+        })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::asynchro::SincInterpolator;
+    use crate::interpolator_avx512::Avx512Interpolator;
+    use crate::sinc::make_sincs;
+    use crate::WindowFunction;
+    use num_traits::Float;
+    use rand::Rng;
+
+    fn get_sinc_interpolated<T: Float>(wave: &[T], index: usize, sinc: &[T]) -> T {
+        let wave_cut = &wave[index..(index + sinc.len())];
+        wave_cut
+            .iter()
+            .zip(sinc.iter())
+            .fold(T::zero(), |acc, (x, y)| acc + *x * *y)
+    }
+
+    #[test]
+    fn test_avx512_interpolator_64() {
+        let mut rng = rand::thread_rng();
+        let mut wave = Vec::new();
+        for _ in 0..2048 {
+            wave.push(rng.gen::<f64>());
+        }
+        let sinc_len = 256;
+        let f_cutoff = 0.9473371669037001;
+        let oversampling_factor = 256;
+        let window = WindowFunction::BlackmanHarris2;
+        let sincs = make_sincs::<f64>(sinc_len, oversampling_factor, f_cutoff, window.clone());
+
+        let interpolator =
+            match Avx512Interpolator::<f64>::new(sinc_len, oversampling_factor, f_cutoff, window) {
+                Ok(interpolator) => interpolator,
+                Err(..) => {
+                    assert!(!is_x86_feature_detected!("avx512f"));
+                    return;
+                }
+            };
+
+        let value = interpolator.get_sinc_interpolated(&wave, 333, 123);
+        let check = get_sinc_interpolated(&wave, 333, &sincs[123]);
+        assert!((value - check).abs() < 1.0e-9);
+    }
+
+    #[test]
+    fn test_avx512_interpolator_32() {
+        let mut rng = rand::thread_rng();
+        let mut wave = Vec::new();
+        for _ in 0..2048 {
+            wave.push(rng.gen::<f32>());
+        }
+        let f_cutoff = 0.9473371669037001;
+        let window = WindowFunction::BlackmanHarris2;
+
+        // a length of 24 also exercises the half vector at the end
+        for sinc_len in [256, 24].iter() {
+            let sincs = make_sincs::<f32>(*sinc_len, 16, f_cutoff, window.clone());
+            let interpolator = match Avx512Interpolator::<f32>::new_with_sincs(sincs.clone()) {
+                Ok(interpolator) => interpolator,
+                Err(..) => {
+                    assert!(!is_x86_feature_detected!("avx512f"));
+                    return;
+                }
+            };
+            // end exactly at the wave, so that reading too far would be caught
+            let index = wave.len() - sinc_len - 1;
+            let value = interpolator.get_sinc_interpolated(&wave, index, 5);
+            let check = get_sinc_interpolated(&wave, index, &sincs[5]);
+            assert!((value - check).abs() < 1.0e-5);
+        }
+    }
+}
@@ -0,0 +1,205 @@
+use crate::asynchro::SincInterpolator;
+use crate::sinc::make_sincs;
+use crate::windows::WindowFunction;
+use crate::{
+    InterpolationParameters, ResampleError, ResampleResult, Sample, SincFixedIn, SincFixedOut,
+};
+use std::convert::TryInto;
+use std::fmt;
+
+/// An interpolator for sincs with a length that is fixed at compile time.
+///
+/// The sincs are stored as arrays of `LEN` taps, and the wave is cut into an array of the same length.
+/// Since the length of the loop is known, the compiler can unroll it completely,
+/// and vectorize it for the instruction sets that are enabled at compile time.
+/// This is mostly useful on targets without a hand written kernel.
+/// On x86_64, the AVX kernel tends to be faster, unless the crate is compiled with
+/// `-C target-cpu=native` and the sinc is short. It should therefore be measured on the target.
+/// Use it with `new_const` of the sinc resamplers, or create it explicitly and give it to
+/// `new_with_interpolator`.
+pub struct FixedLengthInterpolator<T, const LEN: usize> {
+    sincs: Vec<[T; LEN]>,
+}
+
+impl<T, const LEN: usize> fmt::Debug for FixedLengthInterpolator<T, LEN> {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        f.debug_struct("FixedLengthInterpolator")
+            .field("sinc_len", &LEN)
+            .field("nbr_sincs", &self.sincs.len())
+            .finish()
+    }
+}
+
+impl<T, const LEN: usize> SincInterpolator<T> for FixedLengthInterpolator<T, LEN>
+where
+    T: Sample,
+{
+    /// Calculate the scalar produt of an input wave and the selected sinc filter
+    fn get_sinc_interpolated(&self, wave: &[T], index: usize, subindex: usize) -> T {
+        assert!(
+            (index + LEN) < wave.len(),
+            "Tried to interpolate for index {}, max for the given input is {}",
+            index,
+            wave.len() - LEN - 1
+        );
+        assert!(
+            subindex < self.sincs.len(),
+            "Tried to use sinc subindex {}, max is {}",
+            subindex,
+            self.sincs.len() - 1
+        );
+        let wave_cut: &[T; LEN] = wave[index..index + LEN].try_into().unwrap();
+        let sinc = &self.sincs[subindex];
+        let mut acc = [T::zero(); 8];
+        for (wave_part, sinc_part) in wave_cut.chunks_exact(8).zip(sinc.chunks_exact(8)) {
+            for (a, (w, s)) in acc.iter_mut().zip(wave_part.iter().zip(sinc_part.iter())) {
+                *a += *w * *s;
+            }
+        }
+        acc.iter().fold(T::zero(), |sum, a| sum + *a)
+    }
+
+    fn len(&self) -> usize {
+        LEN
+    }
+
+    fn nbr_sincs(&self) -> usize {
+        self.sincs.len()
+    }
+}
+
+impl<T, const LEN: usize> FixedLengthInterpolator<T, LEN>
+where
+    T: Sample,
+{
+    /// Create a new FixedLengthInterpolator, with sincs of length `LEN`.
+    ///
+    /// Parameters are:
+    /// - `oversampling_factor`: Number of intermediate sincs (oversampling factor).
+    /// - `f_cutoff`: Relative cutoff frequency.
+    /// - `window`: Window function to use.
+    pub fn new(oversampling_factor: usize, f_cutoff: f32, window: WindowFunction) -> Self {
+        let sincs = make_sincs(LEN, oversampling_factor, f_cutoff, window);
+        Self::new_with_sincs(sincs)
+    }
+
+    /// Create a new FixedLengthInterpolator from a set of sincs, one per oversampling step.
+    /// All sincs must have the length `LEN`, which must be a multiple of 8.
+    pub fn new_with_sincs(sincs: Vec<Vec<T>>) -> Self {
+        assert_eq!(LEN % 8, 0, "Sinc length must be a multiple of 8");
+        debug!(
+            "Create fixed length interpolator, {} sincs of length {}",
+            sincs.len(),
+            LEN
+        );
+        Self {
+            sincs: sincs
+                .iter()
+                .map(|sinc| {
+                    sinc[..]
+                        .try_into()
+                        .expect("Sincs must have the fixed length")
+                })
+                .collect(),
+        }
+    }
+}
+
+impl<T> SincFixedIn<T>
+where
+    T: Sample,
+{
+    /// Create a new SincFixedIn like `new`, with a sinc length `LEN` that is fixed at compile time,
+    /// using a `FixedLengthInterpolator`. The `sinc_len` of the parameters is ignored.
+    ///
+    /// # Errors
+    ///
+    /// The function returns `ResampleError::InvalidParameter` for the same parameters as `new`,
+    /// and if `LEN` is not a multiple of 8.
+    pub fn new_const<const LEN: usize>(
+        resample_ratio: f64,
+        mut parameters: InterpolationParameters,
+        chunk_size: usize,
+        nbr_channels: usize,
+    ) -> ResampleResult<Self> {
+        ResampleError::check(
+            LEN / 8 * 8 == LEN,
+            "sinc_len",
+            LEN,
+            "must be a multiple of 8 for a fixed length",
+        )?;
+        parameters.sinc_len = LEN;
+        Self::new_with_kernel(
+            resample_ratio,
+            parameters,
+            chunk_size,
+            nbr_channels,
+            |sincs| -> Box<dyn SincInterpolator<T>> {
+                Box::new(FixedLengthInterpolator::<T, LEN>::new_with_sincs(sincs))
+            },
+        )
+    }
+}
+
+impl<T> SincFixedOut<T>
+where
+    T: Sample,
+{
+    /// Create a new SincFixedOut like `new`, with a sinc length `LEN` that is fixed at compile time,
+    /// using a `FixedLengthInterpolator`. The `sinc_len` of the parameters is ignored.
+    ///
+    /// # Errors
+    ///
+    /// The function returns `ResampleError::InvalidParameter` for the same parameters as `new`,
+    /// and if `LEN` is not a multiple of 8.
+    pub fn new_const<const LEN: usize>(
+        resample_ratio: f64,
+        mut parameters: InterpolationParameters,
+        chunk_size: usize,
+        nbr_channels: usize,
+    ) -> ResampleResult<Self> {
+        ResampleError::check(
+            LEN / 8 * 8 == LEN,
+            "sinc_len",
+            LEN,
+            "must be a multiple of 8 for a fixed length",
+        )?;
+        parameters.sinc_len = LEN;
+        Self::new_with_kernel(
+            resample_ratio,
+            parameters,
+            chunk_size,
+            nbr_channels,
+            |sincs| -> Box<dyn SincInterpolator<T>> {
+                Box::new(FixedLengthInterpolator::<T, LEN>::new_with_sincs(sincs))
+            },
+        )
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::{InterpolationParameters, InterpolationType, WindowFunction};
+    use crate::{Resampler, SincFixedIn};
+
+    #[test]
+    fn const_matches_runtime() {
+        let params = InterpolationParameters {
+            sinc_len: 64,
+            f_cutoff: 0.95,
+            interpolation: InterpolationType::Cubic,
+            oversampling_factor: 16,
+            window: WindowFunction::BlackmanHarris2,
+        };
+        let mut fixed = SincFixedIn::<f64>::new_const::<64>(1.2, params.clone(), 256, 1).unwrap();
+        let mut runtime = SincFixedIn::<f64>::new(1.2, params.clone(), 256, 1).unwrap();
+        let wave: Vec<f64> = (0..256).map(|n| (n as f64 * 0.05).sin()).collect();
+        let out = fixed.process(std::slice::from_ref(&wave)).unwrap();
+        let expected = runtime.process(&[wave]).unwrap();
+        assert_eq!(out[0].len(), expected[0].len());
+        for (value, exp) in out[0].iter().zip(expected[0].iter()) {
+            assert!((value - exp).abs() < 1.0e-12);
+        }
+        assert!(SincFixedIn::<f64>::new_const::<60>(1.2, params, 256, 1).is_err());
+    }
+}
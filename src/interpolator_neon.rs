@@ -10,9 +10,14 @@ use crate::Sample;
 /// Collection of cpu features required for this interpolator.
 static FEATURES: &[CpuFeature] = &[CpuFeature::Neon];
 
+/// Check if the CPU supports the instructions used by this kernel.
+pub(crate) fn is_supported() -> bool {
+    FEATURES.iter().all(|feature| feature.is_detected())
+}
+
 /// Trait governing what can be done with an NeonSample.
 pub trait NeonSample: Sized {
-    type Sinc;
+    type Sinc: Send + Sync;
 
     /// Pack sincs into a vector.
     /// 
@@ -183,14 +188,26 @@ impl<T> NeonInterpolator<T> where T: Sample {
             return Err(MissingCpuFeature(*feature));
         }
 
-        assert!(sinc_len % 8 == 0, "Sinc length must be a multiple of 8.");
         let sincs = make_sincs(sinc_len, oversampling_factor, f_cutoff, window);
+        Self::new_with_sincs(sincs)
+    }
+
+    /// Create a new NeonInterpolator from a set of sincs, one per oversampling step.
+    /// All sincs must have the same length, which must be a multiple of 8.
+    pub fn new_with_sincs(sincs: Vec<Vec<T>>) -> Result<Self, MissingCpuFeature> {
+        if let Some(feature) = FEATURES.iter().find(|f| !f.is_detected()) {
+            return Err(MissingCpuFeature(*feature));
+        }
+
+        let sinc_len = sincs[0].len();
+        let nbr_sincs = sincs.len();
+        assert!(sinc_len % 8 == 0, "Sinc length must be a multiple of 8.");
         let sincs = unsafe { <T as NeonSample>::pack_sincs(sincs) };
 
         Ok(Self {
             sincs,
             length: sinc_len,
-            nbr_sincs: oversampling_factor,
+            nbr_sincs,
         })
     }
 }
@@ -223,7 +240,7 @@ mod tests {
         let f_cutoff = 0.9473371669037001;
         let oversampling_factor = 256;
         let window = WindowFunction::BlackmanHarris2;
-        let sincs = make_sincs::<f64>(sinc_len, oversampling_factor, f_cutoff, window);
+        let sincs = make_sincs::<f64>(sinc_len, oversampling_factor, f_cutoff, window.clone());
         let interpolator =
             NeonInterpolator::<f64>::new(sinc_len, oversampling_factor, f_cutoff, window).unwrap();
         let value = interpolator.get_sinc_interpolated(&wave, 333, 123);
@@ -242,7 +259,7 @@ mod tests {
         let f_cutoff = 0.9473371669037001;
         let oversampling_factor = 256;
         let window = WindowFunction::BlackmanHarris2;
-        let sincs = make_sincs::<f32>(sinc_len, oversampling_factor, f_cutoff, window);
+        let sincs = make_sincs::<f32>(sinc_len, oversampling_factor, f_cutoff, window.clone());
         let interpolator =
             NeonInterpolator::<f32>::new(sinc_len, oversampling_factor, f_cutoff, window).unwrap();
         let value = interpolator.get_sinc_interpolated(&wave, 333, 123);
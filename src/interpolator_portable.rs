@@ -0,0 +1,261 @@
+use crate::asynchro::SincInterpolator;
+use crate::sinc::make_sincs;
+use crate::windows::WindowFunction;
+use crate::Sample;
+use std::simd::num::SimdFloat;
+use std::simd::{f32x4, f32x8, f64x4};
+
+/// Trait governing what can be done with a PortableSample.
+pub trait PortableSample: Sized {
+    type Sinc: Send + Sync;
+
+    /// Pack sincs into a vector.
+    fn pack_sincs(sincs: Vec<Vec<Self>>) -> Vec<Vec<Self::Sinc>>;
+
+    /// Interpolate a sinc sample.
+    ///
+    /// # Safety
+    ///
+    /// The caller must ensure that the various indexes are not out of bounds
+    /// in the collection of sincs.
+    unsafe fn get_sinc_interpolated_unsafe(
+        wave: &[Self],
+        index: usize,
+        subindex: usize,
+        sincs: &[Vec<Self::Sinc>],
+        length: usize,
+    ) -> Self;
+
+    /// Perform cubic polynomial interpolation to get value at x,
+    /// as a scalar product between the points and the weights of the polynomial.
+    /// Input points are assumed to be at x = -1, 0, 1, 2
+    fn interp_cubic(x: Self, yvals: &[Self; 4]) -> Self;
+}
+
+impl PortableSample for f32 {
+    type Sinc = f32x8;
+
+    fn pack_sincs(sincs: Vec<Vec<Self>>) -> Vec<Vec<Self::Sinc>> {
+        sincs
+            .iter()
+            .map(|sinc| sinc.chunks(8).map(f32x8::from_slice).collect())
+            .collect()
+    }
+
+    unsafe fn get_sinc_interpolated_unsafe(
+        wave: &[f32],
+        index: usize,
+        subindex: usize,
+        sincs: &[Vec<Self::Sinc>],
+        length: usize,
+    ) -> f32 {
+        let sinc = sincs.get_unchecked(subindex);
+        let wave_cut = &wave[index..(index + length)];
+        let mut acc0 = f32x8::splat(0.0);
+        let mut acc1 = f32x8::splat(0.0);
+        let mut pairs = wave_cut.chunks_exact(16).zip(sinc.chunks_exact(2));
+        for (w, s) in &mut pairs {
+            acc0 += f32x8::from_slice(&w[..8]) * s[0];
+            acc1 += f32x8::from_slice(&w[8..]) * s[1];
+        }
+        if length % 16 != 0 {
+            acc0 += f32x8::from_slice(&wave_cut[length - 8..]) * sinc[length / 8 - 1];
+        }
+        (acc0 + acc1).reduce_sum()
+    }
+
+    fn interp_cubic(x: f32, yvals: &[f32; 4]) -> f32 {
+        let x2 = x * x;
+        let x3 = x2 * x;
+        let weights = f32x4::from_array([
+            -x / 3.0 + 0.5 * x2 - x3 / 6.0,
+            1.0 - 0.5 * x - x2 + 0.5 * x3,
+            x + 0.5 * x2 - 0.5 * x3,
+            (x3 - x) / 6.0,
+        ]);
+        (f32x4::from_array(*yvals) * weights).reduce_sum()
+    }
+}
+
+impl PortableSample for f64 {
+    type Sinc = f64x4;
+
+    fn pack_sincs(sincs: Vec<Vec<Self>>) -> Vec<Vec<Self::Sinc>> {
+        sincs
+            .iter()
+            .map(|sinc| sinc.chunks(4).map(f64x4::from_slice).collect())
+            .collect()
+    }
+
+    unsafe fn get_sinc_interpolated_unsafe(
+        wave: &[f64],
+        index: usize,
+        subindex: usize,
+        sincs: &[Vec<Self::Sinc>],
+        length: usize,
+    ) -> f64 {
+        let sinc = sincs.get_unchecked(subindex);
+        let wave_cut = &wave[index..(index + length)];
+        let mut acc0 = f64x4::splat(0.0);
+        let mut acc1 = f64x4::splat(0.0);
+        for (w, s) in wave_cut.chunks_exact(8).zip(sinc.chunks_exact(2)) {
+            acc0 += f64x4::from_slice(&w[..4]) * s[0];
+            acc1 += f64x4::from_slice(&w[4..]) * s[1];
+        }
+        (acc0 + acc1).reduce_sum()
+    }
+
+    fn interp_cubic(x: f64, yvals: &[f64; 4]) -> f64 {
+        let x2 = x * x;
+        let x3 = x2 * x;
+        let weights = f64x4::from_array([
+            -x / 3.0 + 0.5 * x2 - x3 / 6.0,
+            1.0 - 0.5 * x - x2 + 0.5 * x3,
+            x + 0.5 * x2 - 0.5 * x3,
+            (x3 - x) / 6.0,
+        ]);
+        (f64x4::from_array(*yvals) * weights).reduce_sum()
+    }
+}
+
+/// An interpolator using the portable SIMD types of the standard library.
+/// The compiler translates these to the vector instructions of the target,
+/// so this gives vectorization also on architectures without a hand written kernel.
+/// It replaces `ScalarInterpolator` as the fallback when the `portable-simd` feature is enabled.
+pub struct PortableInterpolator<T>
+where
+    T: PortableSample,
+{
+    sincs: Vec<Vec<T::Sinc>>,
+    length: usize,
+    nbr_sincs: usize,
+}
+
+impl<T> SincInterpolator<T> for PortableInterpolator<T>
+where
+    T: PortableSample,
+{
+    /// Calculate the scalar produt of an input wave and the selected sinc filter
+    fn get_sinc_interpolated(&self, wave: &[T], index: usize, subindex: usize) -> T {
+        assert!(
+            (index + self.length) < wave.len(),
+            "Tried to interpolate for index {}, max for the given input is {}",
+            index,
+            wave.len() - self.length - 1
+        );
+        assert!(
+            subindex < self.nbr_sincs,
+            "Tried to use sinc subindex {}, max is {}",
+            subindex,
+            self.nbr_sincs - 1
+        );
+        unsafe { T::get_sinc_interpolated_unsafe(wave, index, subindex, &self.sincs, self.length) }
+    }
+
+    fn len(&self) -> usize {
+        self.length
+    }
+
+    fn nbr_sincs(&self) -> usize {
+        self.nbr_sincs
+    }
+}
+
+impl<T> PortableInterpolator<T>
+where
+    T: Sample,
+{
+    /// Create a new PortableInterpolator
+    ///
+    /// Parameters are:
+    /// - `sinc_len`: Length of sinc functions.
+    /// - `oversampling_factor`: Number of intermediate sincs (oversampling factor).
+    /// - `f_cutoff`: Relative cutoff frequency.
+    /// - `window`: Window function to use.
+    pub fn new(
+        sinc_len: usize,
+        oversampling_factor: usize,
+        f_cutoff: f32,
+        window: WindowFunction,
+    ) -> Self {
+        let sincs = make_sincs(sinc_len, oversampling_factor, f_cutoff, window);
+        Self::new_with_sincs(sincs)
+    }
+
+    /// Create a new PortableInterpolator from a set of sincs, one per oversampling step.
+    /// All sincs must have the same length, which must be a multiple of 8.
+    pub fn new_with_sincs(sincs: Vec<Vec<T>>) -> Self {
+        let sinc_len = sincs[0].len();
+        let nbr_sincs = sincs.len();
+        assert!(sinc_len % 8 == 0, "Sinc length must be a multiple of 8.");
+        Self {
+            sincs: <T as PortableSample>::pack_sincs(sincs),
+            length: sinc_len,
+            nbr_sincs,
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::asynchro::SincInterpolator;
+    use crate::interpolator_portable::{PortableInterpolator, PortableSample};
+    use crate::sinc::make_sincs;
+    use crate::WindowFunction;
+    use num_traits::Float;
+    use rand::Rng;
+
+    fn get_sinc_interpolated<T: Float>(wave: &[T], index: usize, sinc: &[T]) -> T {
+        let wave_cut = &wave[index..(index + sinc.len())];
+        wave_cut
+            .iter()
+            .zip(sinc.iter())
+            .fold(T::zero(), |acc, (x, y)| acc + *x * *y)
+    }
+
+    #[test]
+    fn test_portable_interpolator_64() {
+        let mut rng = rand::thread_rng();
+        let mut wave = Vec::new();
+        for _ in 0..2048 {
+            wave.push(rng.gen::<f64>());
+        }
+        let sinc_len = 256;
+        let f_cutoff = 0.9473371669037001;
+        let oversampling_factor = 256;
+        let window = WindowFunction::BlackmanHarris2;
+        let sincs = make_sincs::<f64>(sinc_len, oversampling_factor, f_cutoff, window.clone());
+        let interpolator =
+            PortableInterpolator::<f64>::new(sinc_len, oversampling_factor, f_cutoff, window);
+        let value = interpolator.get_sinc_interpolated(&wave, 333, 123);
+        let check = get_sinc_interpolated(&wave, 333, &sincs[123]);
+        assert!((value - check).abs() < 1.0e-9);
+    }
+
+    #[test]
+    fn test_portable_interpolator_32() {
+        let mut rng = rand::thread_rng();
+        let mut wave = Vec::new();
+        for _ in 0..2048 {
+            wave.push(rng.gen::<f32>());
+        }
+        let f_cutoff = 0.9473371669037001;
+        let window = WindowFunction::BlackmanHarris2;
+        // a length of 24 also exercises the single vector at the end
+        for sinc_len in [256, 24].iter() {
+            let sincs = make_sincs::<f32>(*sinc_len, 16, f_cutoff, window.clone());
+            let interpolator = PortableInterpolator::<f32>::new_with_sincs(sincs.clone());
+            let value = interpolator.get_sinc_interpolated(&wave, 333, 5);
+            let check = get_sinc_interpolated(&wave, 333, &sincs[5]);
+            assert!((value - check).abs() < 1.0e-5);
+        }
+    }
+
+    #[test]
+    fn test_portable_cubic() {
+        let yvals = [0.0f64, 2.0, 4.0, 6.0];
+        assert!((<f64 as PortableSample>::interp_cubic(0.5, &yvals) - 3.0).abs() < 1.0e-12);
+        let yvals = [0.0f32, 1.0, 4.0, 9.0];
+        assert!((<f32 as PortableSample>::interp_cubic(0.5, &yvals) - 2.25).abs() < 1.0e-6);
+    }
+}
@@ -0,0 +1,133 @@
+use crate::asynchro::SincInterpolator;
+use crate::Sample;
+use std::fmt;
+
+/// An interpolator that reads the sincs directly from a static table,
+/// for example generated at compile time with `sinc_table_source`.
+///
+/// The table holds all the sincs one after the other, and is borrowed rather than copied,
+/// so creating the interpolator takes no time and no memory, whatever the size of the table.
+/// This suits targets where the tables should stay in read-only memory,
+/// or where resamplers are created often. The scalar product is calculated with
+/// several accumulators like the scalar kernel, since the SIMD kernels need the sincs
+/// in their own layout. To use the SIMD kernels, create the resampler with `new_from_table`,
+/// which copies the table once.
+pub struct TableInterpolator<T: 'static> {
+    table: &'static [T],
+    length: usize,
+    nbr_sincs: usize,
+}
+
+impl<T> fmt::Debug for TableInterpolator<T> {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        f.debug_struct("TableInterpolator")
+            .field("sinc_len", &self.length)
+            .field("nbr_sincs", &self.nbr_sincs)
+            .finish()
+    }
+}
+
+impl<T> SincInterpolator<T> for TableInterpolator<T>
+where
+    T: Sample,
+{
+    /// Calculate the scalar produt of an input wave and the selected sinc filter
+    fn get_sinc_interpolated(&self, wave: &[T], index: usize, subindex: usize) -> T {
+        assert!(
+            (index + self.length) < wave.len(),
+            "Tried to interpolate for index {}, max for the given input is {}",
+            index,
+            wave.len() - self.length - 1
+        );
+        assert!(
+            subindex < self.nbr_sincs,
+            "Tried to use sinc subindex {}, max is {}",
+            subindex,
+            self.nbr_sincs - 1
+        );
+        let wave_cut = &wave[index..(index + self.length)];
+        let sinc = &self.table[(subindex * self.length)..((subindex + 1) * self.length)];
+        let mut acc = [T::zero(); 8];
+        for (wave_part, sinc_part) in wave_cut.chunks_exact(8).zip(sinc.chunks_exact(8)) {
+            for (a, (w, s)) in acc.iter_mut().zip(wave_part.iter().zip(sinc_part.iter())) {
+                *a += *w * *s;
+            }
+        }
+        acc.iter().fold(T::zero(), |sum, a| sum + *a)
+    }
+
+    fn len(&self) -> usize {
+        self.length
+    }
+
+    fn nbr_sincs(&self) -> usize {
+        self.nbr_sincs
+    }
+}
+
+impl<T> TableInterpolator<T>
+where
+    T: Sample,
+{
+    /// Create a new TableInterpolator from a table with all the sincs stored one after the other,
+    /// each `sinc_len` long. The number of sincs gives the oversampling factor.
+    /// The sinc length must be a multiple of 8, and the length of the table a multiple of it.
+    pub fn new(table: &'static [T], sinc_len: usize) -> Self {
+        assert!(
+            sinc_len > 0 && sinc_len / 8 * 8 == sinc_len,
+            "Sinc length must be a multiple of 8"
+        );
+        assert!(
+            !table.is_empty() && table.len() / sinc_len * sinc_len == table.len(),
+            "Table has {} values, which is not a multiple of the sinc length {}",
+            table.len(),
+            sinc_len
+        );
+        debug!(
+            "Create table interpolator, {} sincs of length {}",
+            table.len() / sinc_len,
+            sinc_len
+        );
+        Self {
+            table,
+            length: sinc_len,
+            nbr_sincs: table.len() / sinc_len,
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::asynchro::SincInterpolator;
+    use crate::interpolator_table::TableInterpolator;
+    use crate::ScalarInterpolator;
+    use rand::Rng;
+
+    static TABLE: [f64; 32] = [
+        0.0, 0.01, -0.03, 0.1, 0.9, -0.05, 0.02, 0.0, //
+        0.0, 0.02, -0.05, 0.3, 0.7, -0.04, 0.01, 0.0, //
+        0.0, 0.01, -0.04, 0.5, 0.5, -0.04, 0.01, 0.0, //
+        0.0, 0.01, -0.04, 0.7, 0.3, -0.05, 0.02, 0.0, //
+    ];
+
+    #[test]
+    fn matches_scalar() {
+        let mut rng = rand::thread_rng();
+        let wave: Vec<f64> = (0..64).map(|_| rng.gen::<f64>()).collect();
+        let sincs = TABLE.chunks(8).map(|sinc| sinc.to_vec()).collect();
+        let scalar = ScalarInterpolator::new_with_sincs(sincs);
+        let table = TableInterpolator::new(&TABLE, 8);
+        assert_eq!(table.nbr_sincs(), 4);
+        for subindex in 0..4 {
+            let expected = scalar.get_sinc_interpolated(&wave, 13, subindex);
+            let value = table.get_sinc_interpolated(&wave, 13, subindex);
+            assert!((value - expected).abs() < 1.0e-12);
+        }
+    }
+
+    #[test]
+    #[should_panic]
+    fn bad_table_length() {
+        let _ = TableInterpolator::new(&TABLE[..28], 8);
+    }
+}
@@ -0,0 +1,278 @@
+use crate::asynchro::SincInterpolator;
+use crate::error::{CpuFeature, MissingCpuFeature};
+use crate::sinc::make_sincs;
+use crate::windows::WindowFunction;
+use crate::Sample;
+use core::arch::wasm32::{f32x4_add, f32x4_extract_lane, f32x4_mul, f32x4_splat};
+use core::arch::wasm32::{f64x2_add, f64x2_extract_lane, f64x2_mul, f64x2_splat};
+use core::arch::wasm32::{v128, v128_load};
+
+/// Collection of cpu features required for this interpolator.
+static FEATURES: &[CpuFeature] = &[CpuFeature::Simd128];
+
+/// Check if the CPU supports the instructions used by this kernel.
+pub(crate) fn is_supported() -> bool {
+    FEATURES.iter().all(|feature| feature.is_detected())
+}
+
+/// Trait governing what can be done with a WasmSample.
+pub trait WasmSample: Sized {
+    type Sinc: Send + Sync;
+
+    /// Pack sincs into a vector.
+    ///
+    /// # Safety
+    ///
+    /// This is unsafe because it uses target_enable dispatching. There are no
+    /// special requirements from the caller.
+    unsafe fn pack_sincs(sincs: Vec<Vec<Self>>) -> Vec<Vec<Self::Sinc>>;
+
+    /// Interpolate a sinc sample.
+    ///
+    /// # Safety
+    ///
+    /// The caller must ensure that the various indexes are not out of bounds
+    /// in the collection of sincs.
+    unsafe fn get_sinc_interpolated_unsafe(
+        wave: &[Self],
+        index: usize,
+        subindex: usize,
+        sincs: &[Vec<Self::Sinc>],
+        length: usize,
+    ) -> Self;
+}
+
+impl WasmSample for f32 {
+    type Sinc = v128;
+
+    #[target_feature(enable = "simd128")]
+    unsafe fn pack_sincs(sincs: Vec<Vec<Self>>) -> Vec<Vec<Self::Sinc>> {
+        let mut packed_sincs = Vec::new();
+        for sinc in sincs.iter() {
+            let mut packed = Vec::new();
+            for elements in sinc.chunks(4) {
+                let packed_elems = v128_load(elements.as_ptr() as *const v128);
+                packed.push(packed_elems);
+            }
+            packed_sincs.push(packed);
+        }
+        packed_sincs
+    }
+
+    #[target_feature(enable = "simd128")]
+    unsafe fn get_sinc_interpolated_unsafe(
+        wave: &[f32],
+        index: usize,
+        subindex: usize,
+        sincs: &[Vec<Self::Sinc>],
+        length: usize,
+    ) -> f32 {
+        let sinc = sincs.get_unchecked(subindex);
+        let wave_cut = &wave[index..(index + length)];
+        let mut acc0 = f32x4_splat(0.0);
+        let mut acc1 = f32x4_splat(0.0);
+        let mut w_idx = 0;
+        let mut s_idx = 0;
+        for _ in 0..wave_cut.len() / 8 {
+            let w0 = v128_load(wave_cut.get_unchecked(w_idx) as *const f32 as *const v128);
+            let w1 = v128_load(wave_cut.get_unchecked(w_idx + 4) as *const f32 as *const v128);
+            acc0 = f32x4_add(acc0, f32x4_mul(w0, *sinc.get_unchecked(s_idx)));
+            acc1 = f32x4_add(acc1, f32x4_mul(w1, *sinc.get_unchecked(s_idx + 1)));
+            w_idx += 8;
+            s_idx += 2;
+        }
+        let packedsum = f32x4_add(acc0, acc1);
+        f32x4_extract_lane::<0>(packedsum)
+            + f32x4_extract_lane::<1>(packedsum)
+            + f32x4_extract_lane::<2>(packedsum)
+            + f32x4_extract_lane::<3>(packedsum)
+    }
+}
+
+impl WasmSample for f64 {
+    type Sinc = v128;
+
+    #[target_feature(enable = "simd128")]
+    unsafe fn pack_sincs(sincs: Vec<Vec<f64>>) -> Vec<Vec<Self::Sinc>> {
+        let mut packed_sincs = Vec::new();
+        for sinc in sincs.iter() {
+            let mut packed = Vec::new();
+            for elements in sinc.chunks(2) {
+                let packed_elems = v128_load(elements.as_ptr() as *const v128);
+                packed.push(packed_elems);
+            }
+            packed_sincs.push(packed);
+        }
+        packed_sincs
+    }
+
+    #[target_feature(enable = "simd128")]
+    unsafe fn get_sinc_interpolated_unsafe(
+        wave: &[f64],
+        index: usize,
+        subindex: usize,
+        sincs: &[Vec<Self::Sinc>],
+        length: usize,
+    ) -> f64 {
+        let sinc = sincs.get_unchecked(subindex);
+        let wave_cut = &wave[index..(index + length)];
+        let mut acc0 = f64x2_splat(0.0);
+        let mut acc1 = f64x2_splat(0.0);
+        let mut acc2 = f64x2_splat(0.0);
+        let mut acc3 = f64x2_splat(0.0);
+        let mut w_idx = 0;
+        let mut s_idx = 0;
+        for _ in 0..wave_cut.len() / 8 {
+            let w0 = v128_load(wave_cut.get_unchecked(w_idx) as *const f64 as *const v128);
+            let w1 = v128_load(wave_cut.get_unchecked(w_idx + 2) as *const f64 as *const v128);
+            let w2 = v128_load(wave_cut.get_unchecked(w_idx + 4) as *const f64 as *const v128);
+            let w3 = v128_load(wave_cut.get_unchecked(w_idx + 6) as *const f64 as *const v128);
+            acc0 = f64x2_add(acc0, f64x2_mul(w0, *sinc.get_unchecked(s_idx)));
+            acc1 = f64x2_add(acc1, f64x2_mul(w1, *sinc.get_unchecked(s_idx + 1)));
+            acc2 = f64x2_add(acc2, f64x2_mul(w2, *sinc.get_unchecked(s_idx + 2)));
+            acc3 = f64x2_add(acc3, f64x2_mul(w3, *sinc.get_unchecked(s_idx + 3)));
+            w_idx += 8;
+            s_idx += 4;
+        }
+        let packedsum = f64x2_add(f64x2_add(acc0, acc1), f64x2_add(acc2, acc3));
+        f64x2_extract_lane::<0>(packedsum) + f64x2_extract_lane::<1>(packedsum)
+    }
+}
+
+/// A WebAssembly SIMD accelerated interpolator
+pub struct WasmInterpolator<T>
+where
+    T: WasmSample,
+{
+    sincs: Vec<Vec<T::Sinc>>,
+    length: usize,
+    nbr_sincs: usize,
+}
+
+impl<T> SincInterpolator<T> for WasmInterpolator<T>
+where
+    T: Sample,
+{
+    /// Calculate the scalar produt of an input wave and the selected sinc filter
+    fn get_sinc_interpolated(&self, wave: &[T], index: usize, subindex: usize) -> T {
+        assert!(
+            (index + self.length) < wave.len(),
+            "Tried to interpolate for index {}, max for the given input is {}",
+            index,
+            wave.len() - self.length - 1
+        );
+        assert!(
+            subindex < self.nbr_sincs,
+            "Tried to use sinc subindex {}, max is {}",
+            subindex,
+            self.nbr_sincs - 1
+        );
+        unsafe { T::get_sinc_interpolated_unsafe(wave, index, subindex, &self.sincs, self.length) }
+    }
+
+    fn len(&self) -> usize {
+        self.length
+    }
+
+    fn nbr_sincs(&self) -> usize {
+        self.nbr_sincs
+    }
+}
+
+impl<T> WasmInterpolator<T>
+where
+    T: Sample,
+{
+    /// Create a new WasmInterpolator
+    ///
+    /// Parameters are:
+    /// - `sinc_len`: Length of sinc functions.
+    /// - `oversampling_factor`: Number of intermediate sincs (oversampling factor).
+    /// - `f_cutoff`: Relative cutoff frequency.
+    /// - `window`: Window function to use.
+    pub fn new(
+        sinc_len: usize,
+        oversampling_factor: usize,
+        f_cutoff: f32,
+        window: WindowFunction,
+    ) -> Result<Self, MissingCpuFeature> {
+        if let Some(feature) = FEATURES.iter().find(|f| !f.is_detected()) {
+            return Err(MissingCpuFeature(*feature));
+        }
+
+        let sincs = make_sincs(sinc_len, oversampling_factor, f_cutoff, window);
+        Self::new_with_sincs(sincs)
+    }
+
+    /// Create a new WasmInterpolator from a set of sincs, one per oversampling step.
+    /// All sincs must have the same length, which must be a multiple of 8.
+    pub fn new_with_sincs(sincs: Vec<Vec<T>>) -> Result<Self, MissingCpuFeature> {
+        if let Some(feature) = FEATURES.iter().find(|f| !f.is_detected()) {
+            return Err(MissingCpuFeature(*feature));
+        }
+
+        let sinc_len = sincs[0].len();
+        let nbr_sincs = sincs.len();
+        assert!(sinc_len % 8 == 0, "Sinc length must be a multiple of 8.");
+        let sincs = unsafe { <T as WasmSample>::pack_sincs(sincs) };
+
+        Ok(Self {
+            sincs,
+            length: sinc_len,
+            nbr_sincs,
+        })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::asynchro::SincInterpolator;
+    use crate::interpolator_wasm::WasmInterpolator;
+    use crate::sinc::make_sincs;
+    use crate::WindowFunction;
+    use num_traits::Float;
+
+    fn get_sinc_interpolated<T: Float>(wave: &[T], index: usize, sinc: &[T]) -> T {
+        let wave_cut = &wave[index..(index + sinc.len())];
+        wave_cut
+            .iter()
+            .zip(sinc.iter())
+            .fold(T::zero(), |acc, (x, y)| acc + *x * *y)
+    }
+
+    #[test]
+    fn test_wasm_interpolator_64() {
+        let wave: Vec<f64> = (0..2048).map(|n| (n as f64 * 0.37).sin()).collect();
+        let sinc_len = 256;
+        let f_cutoff = 0.9473371669037001;
+        let oversampling_factor = 256;
+        let window = WindowFunction::BlackmanHarris2;
+        let sincs = make_sincs::<f64>(sinc_len, oversampling_factor, f_cutoff, window.clone());
+        let interpolator =
+            match WasmInterpolator::<f64>::new(sinc_len, oversampling_factor, f_cutoff, window) {
+                Ok(interpolator) => interpolator,
+                Err(..) => return,
+            };
+        let value = interpolator.get_sinc_interpolated(&wave, 333, 123);
+        let check = get_sinc_interpolated(&wave, 333, &sincs[123]);
+        assert!((value - check).abs() < 1.0e-9);
+    }
+
+    #[test]
+    fn test_wasm_interpolator_32() {
+        let wave: Vec<f32> = (0..2048).map(|n| (n as f32 * 0.37).sin()).collect();
+        let sinc_len = 256;
+        let f_cutoff = 0.9473371669037001;
+        let oversampling_factor = 256;
+        let window = WindowFunction::BlackmanHarris2;
+        let sincs = make_sincs::<f32>(sinc_len, oversampling_factor, f_cutoff, window.clone());
+        let interpolator =
+            match WasmInterpolator::<f32>::new(sinc_len, oversampling_factor, f_cutoff, window) {
+                Ok(interpolator) => interpolator,
+                Err(..) => return,
+            };
+        let value = interpolator.get_sinc_interpolated(&wave, 333, 123);
+        let check = get_sinc_interpolated(&wave, 333, &sincs[123]);
+        assert!((value - check).abs() < 1.0e-5);
+    }
+}
@@ -0,0 +1,400 @@
+use crate::error::{ResampleError, ResampleResult};
+use std::collections::VecDeque;
+
+/// A destination for the output of a resampler.
+///
+/// The resampler calls `write_frames` once per channel for each processed chunk,
+/// with the new output frames of that channel.
+/// This allows writing the output directly into for example a playback ring buffer,
+/// see `Resampler::process_into_sink`.
+pub trait AudioSink<T> {
+    /// Append frames to a channel.
+    fn write_frames(&mut self, channel: usize, frames: &[T]);
+}
+
+/// Non-interleaved output, with one vector per channel.
+/// Vectors are added if a channel is written that doesn't exist yet.
+impl<T> AudioSink<T> for Vec<Vec<T>>
+where
+    T: Copy,
+{
+    fn write_frames(&mut self, channel: usize, frames: &[T]) {
+        if channel >= self.len() {
+            self.resize(channel + 1, Vec::new());
+        }
+        self[channel].extend_from_slice(frames);
+    }
+}
+
+/// Non-interleaved ring buffers, with one `VecDeque` per channel.
+/// The consumer reads from the front of each queue, while the resampler appends at the back.
+impl<T> AudioSink<T> for Vec<VecDeque<T>>
+where
+    T: Copy,
+{
+    fn write_frames(&mut self, channel: usize, frames: &[T]) {
+        if channel >= self.len() {
+            self.resize(channel + 1, VecDeque::new());
+        }
+        self[channel].extend(frames.iter().copied());
+    }
+}
+
+/// An output buffer that stores the frames interleaved, as needed by most audio APIs.
+///
+/// Each channel keeps its own write position, so the channels may be written in any order.
+/// The buffer grows as needed, and the part where all channels have been written
+/// can be taken out with `take_frames`.
+#[derive(Debug, Clone)]
+pub struct InterleavedSink<T> {
+    data: Vec<T>,
+    nbr_channels: usize,
+    frames_written: Vec<usize>,
+}
+
+impl<T> InterleavedSink<T>
+where
+    T: Copy + Default,
+{
+    /// Create a new empty buffer for `nbr_channels` channels.
+    pub fn new(nbr_channels: usize) -> Self {
+        InterleavedSink {
+            data: Vec::new(),
+            nbr_channels,
+            frames_written: vec![0; nbr_channels],
+        }
+    }
+
+    /// Get the number of frames that have been written to all channels.
+    pub fn nbr_complete_frames(&self) -> usize {
+        self.frames_written.iter().copied().min().unwrap_or(0)
+    }
+
+    /// Remove and return the interleaved samples of all complete frames.
+    pub fn take_frames(&mut self) -> Vec<T> {
+        let nbr_frames = self.nbr_complete_frames();
+        let rest = self.data.split_off(nbr_frames * self.nbr_channels);
+        for frames in self.frames_written.iter_mut() {
+            *frames -= nbr_frames;
+        }
+        std::mem::replace(&mut self.data, rest)
+    }
+}
+
+impl<T> AudioSink<T> for InterleavedSink<T>
+where
+    T: Copy + Default,
+{
+    /// Write frames of a channel. Channels beyond the number given when creating the buffer are ignored.
+    fn write_frames(&mut self, channel: usize, frames: &[T]) {
+        if channel >= self.nbr_channels {
+            return;
+        }
+        let start = self.frames_written[channel];
+        let end = start + frames.len();
+        if end * self.nbr_channels > self.data.len() {
+            self.data.resize(end * self.nbr_channels, T::default());
+        }
+        for (n, frame) in frames.iter().enumerate() {
+            self.data[(start + n) * self.nbr_channels + channel] = *frame;
+        }
+        self.frames_written[channel] = end;
+    }
+}
+
+/// A source of input for a resampler.
+///
+/// The resampler pulls the number of frames it needs for each chunk from the source,
+/// see `Resampler::pull_from`.
+pub trait AudioSource<T> {
+    /// Get the number of channels of the source.
+    fn nbr_channels(&self) -> usize;
+
+    /// Read up to `nbr_frames` frames into `wave`, that has one vector per channel.
+    /// The vectors are already `nbr_frames` long and filled with zeros,
+    /// and the frames are written from the start of each vector.
+    /// Returns the number of frames that were read.
+    /// This is less than `nbr_frames` when the source doesn't have enough data,
+    /// and zero when it has no data at all.
+    fn read_frames(&mut self, wave: &mut [Vec<T>], nbr_frames: usize) -> usize;
+}
+
+/// Non-interleaved ring buffers, with one `VecDeque` per channel.
+/// Frames are removed from the front of the queues, and only as many frames
+/// as are available in all channels are read.
+impl<T> AudioSource<T> for Vec<VecDeque<T>>
+where
+    T: Copy,
+{
+    fn nbr_channels(&self) -> usize {
+        self.len()
+    }
+
+    fn read_frames(&mut self, wave: &mut [Vec<T>], nbr_frames: usize) -> usize {
+        let available = self.iter().map(|queue| queue.len()).min().unwrap_or(0);
+        let nbr_read = available.min(nbr_frames);
+        for (queue, wave) in self.iter_mut().zip(wave.iter_mut()) {
+            for (value, sample) in wave.iter_mut().zip(queue.drain(..nbr_read)) {
+                *value = sample;
+            }
+        }
+        nbr_read
+    }
+}
+
+/// A source that reads from a slice of interleaved samples.
+#[derive(Debug, Clone)]
+pub struct InterleavedSource<'a, T> {
+    data: &'a [T],
+    nbr_channels: usize,
+    position: usize,
+}
+
+impl<'a, T> InterleavedSource<'a, T> {
+    /// Create a new source reading from `data`, that holds interleaved frames of `nbr_channels` channels.
+    /// Any incomplete frame at the end of the data is ignored.
+    pub fn new(data: &'a [T], nbr_channels: usize) -> Self {
+        InterleavedSource {
+            data,
+            nbr_channels,
+            position: 0,
+        }
+    }
+
+    /// Get the number of frames that have not been read yet.
+    pub fn frames_left(&self) -> usize {
+        if self.nbr_channels == 0 {
+            return 0;
+        }
+        self.data.len() / self.nbr_channels - self.position
+    }
+}
+
+impl<'a, T> AudioSource<T> for InterleavedSource<'a, T>
+where
+    T: Copy,
+{
+    fn nbr_channels(&self) -> usize {
+        self.nbr_channels
+    }
+
+    fn read_frames(&mut self, wave: &mut [Vec<T>], nbr_frames: usize) -> usize {
+        let nbr_read = self.frames_left().min(nbr_frames);
+        let start = self.position * self.nbr_channels;
+        let frames = &self.data[start..start + nbr_read * self.nbr_channels];
+        for (n, frame) in frames.chunks(self.nbr_channels).enumerate() {
+            for (wave, sample) in wave.iter_mut().zip(frame.iter()) {
+                wave[n] = *sample;
+            }
+        }
+        self.position += nbr_read;
+        nbr_read
+    }
+}
+
+/// Check the strides given to `Resampler::process_strided`.
+pub(crate) fn assert_strides(nbr_channels: usize, stride_in: usize, stride_out: usize) {
+    assert!(
+        stride_in >= nbr_channels.max(1) && stride_out >= nbr_channels.max(1),
+        "The strides must be at least the number of channels, and at least one"
+    );
+}
+
+/// Get the number of complete frames of `nbr_channels` channels in a buffer of length `len`,
+/// where consecutive frames start `stride` samples apart.
+pub(crate) fn strided_frames(len: usize, nbr_channels: usize, stride: usize) -> usize {
+    if len < nbr_channels {
+        0
+    } else {
+        (len - nbr_channels) / stride + 1
+    }
+}
+
+/// Check that a strided buffer of length `len` holds at least `nbr_frames` frames.
+pub(crate) fn check_strided_len(
+    len: usize,
+    nbr_channels: usize,
+    stride: usize,
+    nbr_frames: usize,
+) -> ResampleResult<()> {
+    let available = strided_frames(len, nbr_channels, stride);
+    if available < nbr_frames {
+        // the last channel is the first one to run out of frames
+        return Err(ResampleError::WrongNumberOfFrames {
+            channel: nbr_channels.saturating_sub(1),
+            expected: nbr_frames,
+            actual: available,
+            resampler: String::new(),
+        });
+    }
+    Ok(())
+}
+
+/// Check that every non-empty channel of `wave_in` holds at least `offset + len` frames,
+/// for `Resampler::process_range`.
+pub(crate) fn check_range<T>(wave_in: &[Vec<T>], offset: usize, len: usize) -> ResampleResult<()> {
+    for (chan, wave) in wave_in.iter().enumerate() {
+        if !wave.is_empty() && wave.len() < offset + len {
+            return Err(ResampleError::WrongNumberOfFrames {
+                channel: chan,
+                expected: offset + len,
+                actual: wave.len(),
+                resampler: String::new(),
+            });
+        }
+    }
+    Ok(())
+}
+
+/// Check that `wave_out` holds one vector per channel of `wave_in`,
+/// and that the vectors of the non-empty channels have room for `len` frames,
+/// for `Resampler::process_into_buffer`.
+pub(crate) fn check_output_buffers<T>(
+    wave_in: &[Vec<T>],
+    wave_out: &[Vec<T>],
+    len: usize,
+) -> ResampleResult<()> {
+    if wave_out.len() != wave_in.len() {
+        return Err(ResampleError::WrongNumberOfChannels {
+            expected: wave_in.len(),
+            actual: wave_out.len(),
+            resampler: String::new(),
+        });
+    }
+    for (chan, (wave, buffer)) in wave_in.iter().zip(wave_out.iter()).enumerate() {
+        if !wave.is_empty() && buffer.len() < len {
+            return Err(ResampleError::WrongNumberOfFrames {
+                channel: chan,
+                expected: len,
+                actual: buffer.len(),
+                resampler: String::new(),
+            });
+        }
+    }
+    Ok(())
+}
+
+/// Read `buffer.len()` frames of a channel from a strided buffer.
+pub(crate) fn read_strided<T: Copy>(data: &[T], channel: usize, stride: usize, buffer: &mut [T]) {
+    for (value, sample) in buffer
+        .iter_mut()
+        .zip(data[channel..].iter().step_by(stride))
+    {
+        *value = *sample;
+    }
+}
+
+/// Write the frames of a channel to a strided buffer.
+/// Frames that don't fit in the buffer are dropped.
+pub(crate) fn write_strided<T: Copy>(data: &mut [T], channel: usize, stride: usize, frames: &[T]) {
+    if channel >= data.len() {
+        return;
+    }
+    for (value, sample) in data[channel..]
+        .iter_mut()
+        .step_by(stride)
+        .zip(frames.iter())
+    {
+        *value = *sample;
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::io::{AudioSink, AudioSource, InterleavedSink, InterleavedSource};
+    use crate::{FftFixedIn, Resampler};
+    use crate::{InterpolationParameters, InterpolationType, SincFixedOut, WindowFunction};
+    use std::collections::VecDeque;
+
+    #[test]
+    fn vec_sinks() {
+        let mut sink: Vec<Vec<f64>> = Vec::new();
+        sink.write_frames(1, &[1.0, 2.0]);
+        sink.write_frames(1, &[3.0]);
+        assert_eq!(sink, vec![vec![], vec![1.0, 2.0, 3.0]]);
+
+        let mut ring: Vec<VecDeque<f64>> = vec![VecDeque::new()];
+        ring.write_frames(0, &[1.0, 2.0]);
+        assert_eq!(ring[0].pop_front(), Some(1.0));
+        ring.write_frames(0, &[3.0]);
+        assert_eq!(
+            ring[0].iter().copied().collect::<Vec<f64>>(),
+            vec![2.0, 3.0]
+        );
+    }
+
+    #[test]
+    fn interleaved_sink() {
+        let mut sink = InterleavedSink::<f32>::new(2);
+        sink.write_frames(0, &[1.0, 2.0, 3.0]);
+        sink.write_frames(1, &[-1.0, -2.0]);
+        sink.write_frames(2, &[5.0]);
+        assert_eq!(sink.nbr_complete_frames(), 2);
+        assert_eq!(sink.take_frames(), vec![1.0, -1.0, 2.0, -2.0]);
+        sink.write_frames(1, &[-3.0]);
+        assert_eq!(sink.take_frames(), vec![3.0, -3.0]);
+        assert!(sink.take_frames().is_empty());
+    }
+
+    #[test]
+    fn process_into_sink() {
+        let mut resampler = FftFixedIn::<f64>::new(44100, 48000, 1024, 2, 2).unwrap();
+        let mut reference = FftFixedIn::<f64>::new(44100, 48000, 1024, 2, 2).unwrap();
+        let mut sink = InterleavedSink::new(2);
+        let waves = vec![vec![0.5; 1024], vec![-0.5; 1024]];
+        for _ in 0..3 {
+            resampler.process_into_sink(&waves, &mut sink).unwrap();
+            let expected = reference.process(&waves).unwrap();
+            let interleaved = sink.take_frames();
+            assert_eq!(interleaved.len(), 2 * expected[0].len());
+            for (n, (left, right)) in expected[0].iter().zip(expected[1].iter()).enumerate() {
+                assert_eq!(interleaved[2 * n], *left);
+                assert_eq!(interleaved[2 * n + 1], *right);
+            }
+        }
+    }
+
+    #[test]
+    fn sources() {
+        let data = [1.0, -1.0, 2.0, -2.0, 3.0, -3.0, 4.0];
+        let mut source = InterleavedSource::new(&data, 2);
+        let mut wave = vec![vec![0.0; 2]; 2];
+        assert_eq!(source.read_frames(&mut wave, 2), 2);
+        assert_eq!(wave, vec![vec![1.0, 2.0], vec![-1.0, -2.0]]);
+        let mut wave = vec![vec![0.0; 2]; 2];
+        assert_eq!(source.read_frames(&mut wave, 2), 1);
+        assert_eq!(wave, vec![vec![3.0, 0.0], vec![-3.0, 0.0]]);
+        assert_eq!(source.frames_left(), 0);
+
+        let mut ring = vec![
+            (1..5).map(f64::from).collect::<VecDeque<f64>>(),
+            (1..4).map(f64::from).collect::<VecDeque<f64>>(),
+        ];
+        let mut wave = vec![vec![0.0; 4]; 2];
+        assert_eq!(ring.read_frames(&mut wave, 4), 3);
+        assert_eq!(wave[0], vec![1.0, 2.0, 3.0, 0.0]);
+        assert_eq!(ring[0].len(), 1);
+        assert!(ring[1].is_empty());
+    }
+
+    #[test]
+    fn pull_from() {
+        let params = InterpolationParameters {
+            sinc_len: 64,
+            f_cutoff: 0.95,
+            interpolation: InterpolationType::Linear,
+            oversampling_factor: 16,
+            window: WindowFunction::BlackmanHarris2,
+        };
+        let mut resampler = SincFixedOut::<f64>::new(1.5, params, 256, 2).unwrap();
+        let data = vec![0.25; 2 * 1000];
+        let mut source = InterleavedSource::new(&data, 2);
+        let mut nbr_chunks = 0;
+        while let Some(output) = resampler.pull_from(&mut source).unwrap() {
+            assert_eq!(output.len(), 2);
+            assert_eq!(output[0].len(), 256);
+            nbr_chunks += 1;
+        }
+        assert_eq!(nbr_chunks, 6);
+    }
+}
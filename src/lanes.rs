@@ -0,0 +1,294 @@
+use crate::asynchro::{index_steps, interp_cubic, interp_lin, read_sincs, SincInterpolator};
+use crate::interpolation::*;
+use crate::parallel::{for_each_channel, nbr_workers};
+use crate::{InterpolationType, Sample};
+use std::ops::Range;
+use std::sync::Arc;
+
+/// Number of channels that are interpolated together by the channel lane kernel.
+pub(crate) const LANES: usize = 8;
+
+/// One frame of a group of `LANES` channels.
+type Lanes<T> = [T; LANES];
+
+/// A sinc interpolation kernel that works on a group of channels at once.
+///
+/// The normal kernels vectorize along the taps of the sinc, and process one channel at a time.
+/// With many channels, it's more efficient to apply each tap to all channels of a group,
+/// with the channels in the lanes of the vectors. Then there is no horizontal sum at the end,
+/// and the sinc values are loaded once per group instead of once per channel.
+/// The loops over the lanes are written to be vectorized by the compiler.
+struct LaneInterpolator<T> {
+    sincs: Vec<Vec<T>>,
+    length: usize,
+    phases: PhaseIndex,
+}
+
+impl<T> LaneInterpolator<T>
+where
+    T: Sample,
+{
+    /// Read the sincs out of an interpolator, so that any kernel can be used.
+    fn from_interpolator(interpolator: &dyn SincInterpolator<T>) -> Self {
+        LaneInterpolator {
+            sincs: read_sincs(interpolator),
+            length: interpolator.len(),
+            phases: PhaseIndex::new(interpolator.nbr_sincs()),
+        }
+    }
+
+    /// Make the scalar products between the waveforms of the group starting at `index`,
+    /// and the sinc of `subindex`.
+    #[inline]
+    fn get_sinc_interpolated(&self, wave: &[Lanes<T>], index: usize, subindex: usize) -> Lanes<T> {
+        let sinc = &self.sincs[subindex];
+        let wave_cut = &wave[index..index + self.length];
+        let mut acc = [T::zero(); LANES];
+        for (frame, s) in wave_cut.iter().zip(sinc.iter()) {
+            for (a, w) in acc.iter_mut().zip(frame.iter()) {
+                *a += *w * *s;
+            }
+        }
+        acc
+    }
+
+    /// Interpolate the output frames of a group of channels, like `interpolate_channel`
+    /// does for a single channel.
+    fn interpolate(
+        &self,
+        interpolation: InterpolationType,
+        buf: &[Lanes<T>],
+        wave_out: &mut [Lanes<T>],
+        last_index: f64,
+        ratios: &[f64],
+    ) {
+        let oversampling_factor = self.sincs.len();
+        let phases = self.phases;
+        let offset = 2 * self.length as isize;
+        let frames = wave_out.iter_mut().zip(index_steps(ratios));
+        let mut idx = last_index;
+        match interpolation {
+            InterpolationType::Cubic => {
+                let mut points = [[T::zero(); LANES]; 4];
+                let mut nearest = [(0isize, 0isize); 4];
+                for (out, step) in frames {
+                    idx += step;
+                    phases.nearest_times_4(idx, &mut nearest);
+                    let pos = idx * oversampling_factor as f64;
+                    let frac_offset = T::coerce(pos - pos.floor());
+                    for (n, p) in nearest.iter().zip(points.iter_mut()) {
+                        *p = self.get_sinc_interpolated(buf, (n.0 + offset) as usize, n.1 as usize);
+                    }
+                    for (lane, value) in out.iter_mut().enumerate() {
+                        let yvals = [
+                            points[0][lane],
+                            points[1][lane],
+                            points[2][lane],
+                            points[3][lane],
+                        ];
+                        *value = interp_cubic(frac_offset, &yvals);
+                    }
+                }
+            }
+            InterpolationType::Linear => {
+                let mut points = [[T::zero(); LANES]; 2];
+                let mut nearest = [(0isize, 0isize); 2];
+                for (out, step) in frames {
+                    idx += step;
+                    phases.nearest_times_2(idx, &mut nearest);
+                    let pos = idx * oversampling_factor as f64;
+                    let frac_offset = T::coerce(pos - pos.floor());
+                    for (n, p) in nearest.iter().zip(points.iter_mut()) {
+                        *p = self.get_sinc_interpolated(buf, (n.0 + offset) as usize, n.1 as usize);
+                    }
+                    for (lane, value) in out.iter_mut().enumerate() {
+                        *value = interp_lin(frac_offset, &[points[0][lane], points[1][lane]]);
+                    }
+                }
+            }
+            InterpolationType::Nearest => {
+                for (out, step) in frames {
+                    idx += step;
+                    let nearest = phases.nearest_time(idx);
+                    *out = self.get_sinc_interpolated(
+                        buf,
+                        (nearest.0 + offset) as usize,
+                        nearest.1 as usize,
+                    );
+                }
+            }
+        }
+    }
+}
+
+/// The channel lane layout of a sinc resampler, used automatically when there are
+/// at least `LANES` channels.
+/// The channels are split into groups of `LANES`, and any remaining channels
+/// are left to the normal kernel.
+/// The clones of a resampler share the filters.
+#[derive(Clone)]
+pub(crate) struct ChannelLanes<T> {
+    interpolator: Arc<LaneInterpolator<T>>,
+    buffers: Vec<Vec<Lanes<T>>>,
+    outputs: Vec<Vec<Lanes<T>>>,
+    active_groups: Vec<bool>,
+}
+
+impl<T> ChannelLanes<T>
+where
+    T: Sample,
+{
+    /// Create the lane layout for `nbr_channels` channels,
+    /// or return `None` if there are too few channels to fill a group.
+    /// The buffers get room for `max_frames_in` input and `max_frames_out` output frames,
+    /// so that processing doesn't need to allocate.
+    pub fn new(
+        interpolator: &dyn SincInterpolator<T>,
+        nbr_channels: usize,
+        max_frames_in: usize,
+        max_frames_out: usize,
+    ) -> Option<Self> {
+        let nbr_groups = nbr_channels / LANES;
+        if nbr_groups == 0 {
+            return None;
+        }
+        debug!(
+            "Using channel lanes for {} groups of {} channels",
+            nbr_groups, LANES
+        );
+        Some(ChannelLanes {
+            interpolator: Arc::new(LaneInterpolator::from_interpolator(interpolator)),
+            buffers: (0..nbr_groups)
+                .map(|_| Vec::with_capacity(max_frames_in))
+                .collect(),
+            outputs: (0..nbr_groups)
+                .map(|_| Vec::with_capacity(max_frames_out))
+                .collect(),
+            active_groups: vec![false; nbr_groups],
+        })
+    }
+
+    /// Interpolate the groups where all channels are active, from the `window` of the buffers,
+    /// into the output buffers. These must already have the right length.
+    /// The active channels that are not part of such a group are stored in `remaining_channels`,
+    /// and must be interpolated by the normal kernel.
+    #[allow(clippy::too_many_arguments)]
+    pub fn process(
+        &mut self,
+        buffer: &[Vec<T>],
+        output_buffer: &mut [Vec<T>],
+        active_channels: &[usize],
+        window: Range<usize>,
+        interpolation: InterpolationType,
+        last_index: f64,
+        ratios: &[f64],
+        parallel_threshold: usize,
+        remaining_channels: &mut Vec<usize>,
+    ) {
+        let nbr_frames = active_channels
+            .first()
+            .map(|chan| output_buffer[*chan].len())
+            .unwrap_or(0);
+        for (group, active) in self.active_groups.iter_mut().enumerate() {
+            *active =
+                (group * LANES..(group + 1) * LANES).all(|chan| active_channels.contains(&chan));
+        }
+        let nbr_groups = self.active_groups.iter().filter(|active| **active).count();
+        let groups = self
+            .buffers
+            .iter_mut()
+            .zip(self.outputs.iter_mut())
+            .zip(self.active_groups.iter())
+            .enumerate()
+            .filter(|(_, (_, active))| **active)
+            .map(|(group, ((buf, out), _))| {
+                buf.clear();
+                buf.extend(window.clone().map(|n| {
+                    let mut frame = [T::zero(); LANES];
+                    for (lane, value) in frame.iter_mut().enumerate() {
+                        *value = buffer[group * LANES + lane][n];
+                    }
+                    frame
+                }));
+                out.clear();
+                out.resize(nbr_frames, [T::zero(); LANES]);
+                (&buf[..], &mut out[..])
+            });
+
+        let interpolator = &*self.interpolator;
+        let threshold = parallel_threshold / LANES;
+        // the workers carry no state, and a vector of them doesn't allocate
+        let mut workers = vec![(); nbr_workers(nbr_groups, threshold)];
+        for_each_channel(groups, &mut workers, threshold, |(buf, wave_out), _| {
+            interpolator.interpolate(interpolation, buf, wave_out, last_index, ratios)
+        });
+
+        for (group, output) in self.outputs.iter().enumerate() {
+            if !self.active_groups[group] {
+                continue;
+            }
+            for (n, frame) in output.iter().enumerate() {
+                for (lane, value) in frame.iter().enumerate() {
+                    output_buffer[group * LANES + lane][n] = *value;
+                }
+            }
+        }
+        remaining_channels.clear();
+        remaining_channels.extend(active_channels.iter().cloned().filter(|chan| {
+            self.active_groups
+                .get(chan / LANES)
+                .map(|active| !active)
+                .unwrap_or(true)
+        }));
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::asynchro::SincInterpolator;
+    use crate::lanes::LaneInterpolator;
+    use crate::{InterpolationParameters, InterpolationType, WindowFunction};
+    use crate::{Resampler, ScalarInterpolator, SincFixedIn};
+
+    #[test]
+    fn read_sincs() {
+        let interpolator = ScalarInterpolator::<f64>::new(64, 16, 0.95, WindowFunction::Hann);
+        let lanes = LaneInterpolator::from_interpolator(&interpolator);
+        let wave: Vec<f64> = (0..256).map(|n| (n as f64 * 0.3).sin()).collect();
+        let wave_lanes: Vec<[f64; 8]> = wave.iter().map(|value| [*value; 8]).collect();
+        let expected = interpolator.get_sinc_interpolated(&wave, 17, 5);
+        for value in lanes.get_sinc_interpolated(&wave_lanes, 17, 5).iter() {
+            assert!((value - expected).abs() < 1.0e-12);
+        }
+    }
+
+    #[test]
+    fn lanes_match_channels() {
+        let params = InterpolationParameters {
+            sinc_len: 64,
+            f_cutoff: 0.95,
+            interpolation: InterpolationType::Cubic,
+            oversampling_factor: 16,
+            window: WindowFunction::BlackmanHarris2,
+        };
+        // 8 channels in one group, and two left over
+        let mut grouped = SincFixedIn::<f64>::new(1.2, params.clone(), 256, 10).unwrap();
+        let mut single = SincFixedIn::<f64>::new(1.2, params, 256, 1).unwrap();
+        let waves: Vec<Vec<f64>> = (0..10)
+            .map(|chan| {
+                (0..256)
+                    .map(|n| (n as f64 * 0.02 * (chan + 1) as f64).sin())
+                    .collect()
+            })
+            .collect();
+        let out = grouped.process(&waves).unwrap();
+        for (chan, wave) in waves.iter().enumerate() {
+            single.reset();
+            let expected = single.process(std::slice::from_ref(wave)).unwrap();
+            assert_eq!(out[chan].len(), expected[0].len());
+            for (value, exp) in out[chan].iter().zip(expected[0].iter()) {
+                assert!((value - exp).abs() < 1.0e-12);
+            }
+        }
+    }
+}
@@ -16,6 +16,9 @@
 //! Synchronous resampling is implemented via FFT. The data is FFT:ed, the spectrum modified,
 //! and then inverse FFT:ed to get the resampled data.
 //! This type of resampler is considerably faster but doesn't support changing the resampling ratio.
+//! The exception is `FftFixedIn`, where the ratio can be trimmed by a small amount to follow clock drift.
+//! The trimming changes the number of output frames, so it is not available for `FftFixedOut`
+//! and `FftFixedInOut`, that always return the same number of frames.
 //!
 //! ## SIMD acceleration
 //! The asynchronous resampler is designed to benefit from auto-vectorization, meaning that the Rust compiler
@@ -24,12 +27,56 @@
 //! On x86_64 it will always use SSE3 if available. The speed benefit compared to auto-vectorization
 //! depends on the CPU, but tends to be in the range 20-30% for 64-bit data, and 50-100% for 32-bit data.
 //!
+//! The SIMD kernel is chosen at runtime when a resampler is created, so `SincFixedIn` and `SincFixedOut`
+//! always use the fastest instruction set that both the CPU and the enabled features support,
+//! and fall back to the auto-vectorized kernel otherwise.
+//! A custom kernel can be plugged in by implementing `SincInterpolator`,
+//! and creating the resampler with `new_with_interpolator`.
+//!
+//! For long sincs with a high oversampling factor, `interpolator_symmetric::SymmetricInterpolator`
+//! halves the memory used by the sinc tables, by storing the symmetric and antisymmetric halves
+//! of each mirrored pair of sincs, and folding the input around the center of the filter.
+//! This can pay off when the tables are too large for the caches,
+//! but it doesn't use SIMD instructions, so it's only used when created explicitly.
+//! The tables of 64-bit resamplers can also be halved by `interpolator_f32::F32Interpolator`,
+//! which stores the sincs as 32-bit floats and converts each tap as it's loaded,
+//! at the cost of a small loss of accuracy.
+//! For memory constrained targets, `interpolator_q15::Q15Interpolator` stores the sincs
+//! as 16-bit integers, which is a quarter of the memory of 64-bit floats,
+//! but limits the signal to noise ratio to about 80 dB.
+//! Sinc tables generated at compile time with `sinc_table_source` can be read in place
+//! by `interpolator_table::TableInterpolator`, so that creating a resampler doesn't copy them.
+//!
+//! With 8 or more channels, like for surround or ambisonics, the channels are instead interpolated
+//! in groups of 8, with one channel in each lane of the SIMD vectors.
+//! Each tap of the sinc is then applied to the whole group at once,
+//! which makes better use of the vectors than going through the taps of one channel at a time.
+//! This is chosen automatically, and the channels that don't fill a group use the normal kernel.
+//!
 //! ## Cargo features
 //! #### `avx`: AVX on x86_64
 //! The `avx` feature is enabled by default, and enables the use of AVX when it's available.
 //! The speed increase compared to SSE depends on the CPU, and tends to range from zero to 50%.
 //! On other architectures than x86_64 the `avx` feature does nothing.
 //!
+//! #### `avx512`: AVX-512 on x86_64
+//! The `avx512` feature adds `interpolator_avx512::Avx512Interpolator`, a kernel using the 512-bit
+//! AVX-512 instructions, which requires rustc 1.89 or newer.
+//! When the feature is enabled, the kernel is chosen on CPUs that support AVX-512.
+//! Many CPUs lower their clock frequency when running these instructions,
+//! which can make the rest of the application slower, so the feature is not enabled by default,
+//! and should only be enabled after measuring that it is faster on the target system.
+//!
+//! #### `const-generics`: Kernels for a fixed sinc length
+//! The `const-generics` feature adds `interpolator_fixed::FixedLengthInterpolator`,
+//! a kernel for sincs with a length that is fixed at compile time, and the `new_const`
+//! constructors of `SincFixedIn` and `SincFixedOut` that use it.
+//! Knowing the length lets the compiler unroll and vectorize the loop over the taps completely.
+//! Whether this is faster than the kernels chosen at runtime depends on the CPU,
+//! the sinc length and the enabled target features, so it should be measured.
+//! On x86_64 the AVX kernel is usually faster, so it's mostly useful on other architectures.
+//! This requires rustc 1.51 or newer.
+//!
 //! #### `neon`: Experimental Neon support on aarch64
 //! Experimental support for Neon is available for aarch64 (64-bit Arm) by enabling the `neon` feature.
 //! This requires the use of a nightly compiler, as the Neon support in Rust is still experimental.
@@ -37,6 +84,60 @@
 //! compared to the auto-vectorized implementation.
 //! Note that this only works on a full 64-bit operating system.
 //!
+//! #### `wasm-simd`: SIMD128 on WebAssembly
+//! The `wasm-simd` feature enables the use of the 128-bit SIMD instructions of WebAssembly,
+//! for example for resampling in a browser audio worklet.
+//! WebAssembly has no runtime feature detection, so the instructions must also be enabled
+//! when compiling, with `RUSTFLAGS="-C target-feature=+simd128"`.
+//! Without them, the feature does nothing and the auto-vectorized implementation is used.
+//! The SIMD128 intrinsics require rustc 1.54 or newer.
+//!
+//! #### `portable-simd`: Portable SIMD on any architecture
+//! The `portable-simd` feature replaces the auto-vectorized fallback kernel with
+//! `interpolator_portable::PortableInterpolator`, which uses the portable SIMD types of the standard library.
+//! The compiler translates these to the vector instructions of the target, which gives vectorization
+//! on architectures without a hand written kernel, like POWER and RISC-V.
+//! The cubic polynomial interpolation also uses the portable SIMD types.
+//! The hand written kernels are still preferred where they are available.
+//! This requires the use of a nightly compiler, as the portable SIMD support in Rust is still experimental.
+//!
+//! #### `parallel`: Processing channels in parallel
+//! The `parallel` feature makes the sinc and FFT resamplers process the channels concurrently,
+//! using the global thread pool of the rayon crate.
+//! This is only done when there are at least as many channels as the threshold,
+//! which is 8 by default and can be changed with `set_parallel_threshold`.
+//! For fewer channels, the cost of distributing the work tends to be larger than the gain.
+//! The output is identical to serial processing.
+//! Recent versions of rayon require a newer compiler than the rest of the crate.
+//!
+//! #### `realfft`, `rustfft` and `microfft`: FFT backends
+//! The FFT resamplers get their FFTs from an `FftBackend`, and the crate includes one backend
+//! for each of the `realfft`, `rustfft` and `microfft` crates, behind the feature with the same name.
+//! The `realfft` feature is enabled by default, and `RealFftBackend` is used by the constructors
+//! that don't take a backend. With only `rustfft`, `RustFftBackend` is used instead,
+//! which avoids the extra dependency for applications that already use `rustfft`.
+//! Other backends are given to the `new_with_backend` constructors.
+//! Without `realfft` and `rustfft`, the constructors without a backend return an error,
+//! the sinc resamplers don't use FFT convolution for long sincs,
+//! and the analysis functions like `measure_resampler_snr` are not available.
+//! `MicroFftBackend` supports `f32` and lengths that are powers of two from 4 to 4096,
+//! for embedded targets. The `microfft` feature requires rustc 1.61 or newer.
+//!
+//! The `microfft` feature also adds `MicroFftResampler`, a synchronous resampler
+//! with the FFT length and the number of channels as const generic parameters.
+//! It keeps all its state in fixed size arrays, a few kilobytes for short FFTs,
+//! and processes without allocating, for pairs of sample rates like 16 and 48 kHz.
+//! The rest of the crate still uses the standard library, so it doesn't make the crate `no_std`.
+//!
+//! #### `testing`: Reference resampler
+//! The `testing` feature enables `ReferenceResampler`, a slow but direct windowed sinc resampler
+//! that can be used as ground truth when testing resamplers and parameters.
+//!
+//! ## Thread safety
+//! All resamplers implement `Send` and `Sync`, and the `Resampler` trait requires `Send`.
+//! A resampler can therefore be moved to another thread,
+//! or shared between threads behind a `Mutex`.
+//!
 //! ## Documentation
 //!
 //! The full documentation can be generated by rustdoc. To generate and view it run:
@@ -61,7 +162,7 @@
 //!     params,
 //!     1024,
 //!     2,
-//! );
+//! ).unwrap();
 //!
 //! let waves_in = vec![vec![0.0f64; 1024];2];
 //! let waves_out = resampler.process(&waves_in).unwrap();
@@ -73,20 +174,103 @@
 
 #![cfg_attr(feature = "neon", feature(aarch64_target_feature))]
 #![cfg_attr(feature = "neon", feature(stdsimd))]
+#![cfg_attr(feature = "portable-simd", feature(portable_simd))]
 
+mod advisor;
+#[cfg(feature = "realfft")]
+mod analysis;
 mod asynchro;
+mod batch;
+mod complex;
+pub mod convert;
+mod convolution;
+mod delay;
+mod duplex;
 mod error;
+mod fft;
+mod group;
 mod interpolation;
+pub mod interpolator_f32;
+#[cfg(feature = "const-generics")]
+pub mod interpolator_fixed;
+pub mod interpolator_q15;
+pub mod interpolator_symmetric;
+pub mod interpolator_table;
+mod io;
+mod lanes;
+mod looping;
+mod markers;
+mod metering;
+#[cfg(feature = "microfft")]
+mod micro;
+mod modulation;
+mod normalization;
+mod observer;
+mod offline;
+mod oversampler;
+mod parallel;
+mod planner;
+mod pool;
+mod rational;
+#[cfg(any(test, feature = "testing"))]
+mod reference;
 mod sample;
 mod sinc;
 mod synchro;
+mod timing;
 mod windows;
+mod worklet;
 
-pub use crate::asynchro::{ScalarInterpolator, SincFixedIn, SincFixedOut};
+pub use crate::advisor::{
+    best_resampler, choose_engine, estimate_cost, suggest_parameters, suggest_variable_parameters,
+    CostReport, Engine, Quality,
+};
+#[cfg(feature = "realfft")]
+pub use crate::analysis::{
+    analyze_window, compare_resamplers, impulse_response, measure_resampler_rejection,
+    measure_resampler_snr, BandError, ResamplerComparison, WindowAnalysis,
+};
+pub use crate::asynchro::{
+    AliasingWarning, ScalarInterpolator, SincFixedIn, SincFixedOut, SincInterpolator,
+};
+pub use crate::batch::{BatchResampler, StreamId};
+pub use crate::complex::ComplexResampler;
+pub use crate::delay::FractionalDelay;
+pub use crate::duplex::{DriftController, DuplexResampler};
 pub use crate::error::{CpuFeature, MissingCpuFeature, ResampleError, ResampleResult};
+#[cfg(feature = "microfft")]
+pub use crate::fft::MicroFftBackend;
+#[cfg(feature = "realfft")]
+pub use crate::fft::RealFftBackend;
+#[cfg(feature = "rustfft")]
+pub use crate::fft::RustFftBackend;
+pub use crate::fft::{FftBackend, ForwardFft, InverseFft};
+pub use crate::group::ResamplerGroup;
+pub use crate::io::{AudioSink, AudioSource, InterleavedSink, InterleavedSource};
+pub use crate::looping::{resample_loop, LoopedWave};
+pub use crate::markers::MarkerMapper;
+pub use crate::metering::Metrics;
+#[cfg(feature = "microfft")]
+pub use crate::micro::MicroFftResampler;
+pub use crate::modulation::{Lfo, LfoWaveform};
+pub use crate::normalization::{NormalizationParameters, NormalizationTarget};
+pub use crate::observer::{ObservedResampler, Observer};
+pub use crate::offline::{
+    process_all, process_all_by_channels, process_all_cancellable, process_all_parallel,
+    process_all_with_progress,
+};
+pub use crate::oversampler::{Oversampler, Picker};
+pub use crate::planner::{plan_offline_chunks, OfflinePlan};
+pub use crate::pool::{PoolKey, PooledResampler, ResamplerPool};
+pub use crate::rational::{snap_ratio, RationalRatio};
+#[cfg(feature = "testing")]
+pub use crate::reference::ReferenceResampler;
 pub use crate::sample::Sample;
+pub use crate::sinc::sinc_table_source;
 pub use crate::synchro::{FftFixedIn, FftFixedInOut, FftFixedOut};
+pub use crate::timing::{ProcessingStats, TimedResampler};
 pub use crate::windows::WindowFunction;
+pub use crate::worklet::{QuantumResampler, RENDER_QUANTUM};
 
 /// Helper macro to define a dummy implementation of the sample trait if a
 /// feature is not supported.
@@ -120,6 +304,12 @@ interpolator! {
     trait AvxSample;
 }
 
+interpolator! {
+    #[cfg(all(target_arch = "x86_64", feature = "avx512"))]
+    mod interpolator_avx512;
+    trait Avx512Sample;
+}
+
 interpolator! {
     #[cfg(target_arch = "x86_64")]
     mod interpolator_sse;
@@ -132,11 +322,23 @@ interpolator! {
     trait NeonSample;
 }
 
+interpolator! {
+    #[cfg(feature = "portable-simd")]
+    mod interpolator_portable;
+    trait PortableSample;
+}
+
+interpolator! {
+    #[cfg(all(target_arch = "wasm32", feature = "wasm-simd"))]
+    mod interpolator_wasm;
+    trait WasmSample;
+}
+
 #[macro_use]
 extern crate log;
 
 /// A struct holding the parameters for interpolation.
-#[derive(Debug)]
+#[derive(Debug, Clone, PartialEq)]
 pub struct InterpolationParameters {
     /// Length of the windowed sinc interpolation filter.
     /// Higher values can allow a higher cut-off frequency leading to less high frequency roll-off
@@ -145,6 +347,8 @@ pub struct InterpolationParameters {
     pub sinc_len: usize,
     /// Relative cutoff frequency of the sinc interpolation filter
     /// (relative to the lowest one of fs_in/2 or fs_out/2). Start at 0.95, and increase if needed.
+    /// The `new_without_anti_aliasing` constructors of the asynchronous resamplers
+    /// instead use it relative to fs_in/2 also when downsampling.
     pub f_cutoff: f32,
     /// The number of intermediate points to use for interpolation.
     /// Higher values use more memory for storing the sinc filters.
@@ -166,7 +370,7 @@ pub struct InterpolationParameters {
 /// It's more efficient to combine the sinc filters with some other interpolation technique.
 /// Then sinc filters are used to provide a fixed number of interpolated points between input samples,
 /// and then the new value is calculated by interpolation between those points.
-#[derive(Debug)]
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
 pub enum InterpolationType {
     /// For cubic interpolation, the four nearest intermediate points are calculated
     /// using sinc interpolation.
@@ -191,9 +395,43 @@ pub enum InterpolationType {
     Nearest,
 }
 
+/// The result of "process_partial" of the `Resampler` trait.
+#[derive(Debug, Clone, PartialEq)]
+pub struct PartialOutput<T> {
+    /// The resampled chunk, one vector per channel.
+    pub wave_out: Vec<Vec<T>>,
+    /// The number of input frames that were consumed, before padding.
+    pub frames_consumed: usize,
+    /// The number of output frames that were produced from the consumed input,
+    /// which is the length of the channels of `wave_out`.
+    pub frames_produced: usize,
+}
+
+/// The latency of a resampler, as returned by the `latency` function of the resamplers.
+///
+/// An output frame at time `n / fs_out` corresponds to the input at time
+/// `n / fs_out - latency`, where the latency is in general not a whole number of frames.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct Latency {
+    /// The latency in input frames.
+    pub input_frames: f64,
+    /// The latency in output frames.
+    pub output_frames: f64,
+}
+
+impl Latency {
+    /// Get the latency in seconds, given the input sample rate.
+    pub fn seconds(&self, fs_in: f64) -> f64 {
+        self.input_frames / fs_in
+    }
+}
+
 /// A resampler that us used to resample a chunk of audio to a new sample rate.
 /// The rate can be adjusted as required.
-pub trait Resampler<T> {
+///
+/// Resamplers are required to be `Send`, so that a boxed `dyn Resampler` can be
+/// moved to another thread.
+pub trait Resampler<T>: Send {
     /// Resample a chunk of audio. Input and output data is stored in a vector,
     /// where each element contains a vector with all samples for a single channel.
     fn process(&mut self, wave_in: &[Vec<T>]) -> ResampleResult<Vec<Vec<T>>>;
@@ -201,9 +439,1177 @@ pub trait Resampler<T> {
     /// Query for the number of frames needed for the next call to "process".
     fn nbr_frames_needed(&self) -> usize;
 
+    /// Query for the number of frames that the next call to "process" will return.
+    /// The value reflects the current resample ratio, including any updates made
+    /// since the previous call.
+    fn nbr_output_frames_next(&self) -> usize;
+
     /// Update the resample ratio.
     fn set_resample_ratio(&mut self, new_ratio: f64) -> ResampleResult<()>;
 
     /// Update the resample ratio relative to the original one.
     fn set_resample_ratio_relative(&mut self, rel_ratio: f64) -> ResampleResult<()>;
+
+    /// Get the current resample ratio.
+    /// While the ratio is being smoothed or modulated, this is the ratio used at the end of the last chunk.
+    fn resample_ratio(&self) -> f64;
+
+    /// Get the original resample ratio, that the resampler was created with.
+    fn resample_ratio_original(&self) -> f64;
+
+    /// Get the chunk size of the resampler.
+    /// This is the fixed input size for the resamplers with fixed input,
+    /// and the fixed output size for the ones with fixed output.
+    /// It differs from the requested size if the resampler had to adjust it, like `FftFixedInOut` does.
+    fn chunk_size(&self) -> usize;
+
+    /// Get the number of channels.
+    fn nbr_channels(&self) -> usize;
+
+    /// Get the length of the sinc filters, after rounding up to a multiple of 8.
+    ///
+    /// The default implementation returns `None`, for resamplers that don't use sinc filters.
+    /// The sinc resamplers, and the wrappers around resamplers, override it.
+    fn sinc_len(&self) -> Option<usize> {
+        None
+    }
+
+    /// Check if the resample ratio has been changed so that the output may alias.
+    /// The default implementation returns `None`,
+    /// and the sinc resamplers override it, see `SincFixedIn::aliasing_warning`.
+    fn aliasing_warning(&self) -> Option<AliasingWarning> {
+        None
+    }
+
+    /// Get the delay of the output, in output frames, for example for keeping audio in sync with video.
+    /// An output frame at time `n / fs_out` corresponds to the input at time
+    /// `n / fs_out - output_delay / fs_out`. The delay is in general not a whole number of frames.
+    /// This is the output part of the latency returned by the `latency` function of the resamplers,
+    /// see `Latency`.
+    ///
+    /// The default implementation returns zero.
+    /// The resamplers of this crate, and the wrappers around them, override it.
+    fn output_delay(&self) -> f64 {
+        0.0
+    }
+
+    /// Clear the internal state, so that the resampler can be reused for a new, unrelated stream.
+    /// This clears the buffered input and output, and returns to the original resample ratio,
+    /// while the filters, which are expensive to calculate, and the settings are kept.
+    /// Afterwards the resampler behaves like a newly created one.
+    ///
+    /// The default implementation does nothing.
+    /// The resamplers of this crate, and the wrappers around them, override it.
+    fn reset(&mut self) {}
+
+    /// Emit the remaining output at the end of a stream.
+    /// The resamplers hold back some output, that depends on input that has not been given yet.
+    /// This processes silence until the output covers all of the input that was given,
+    /// and returns these frames, for all channels.
+    /// The FFT resamplers also return their latency, since their output is delayed.
+    ///
+    /// Afterwards the resampler continues as if silence had been given as input,
+    /// and should be reset before it is used for a new stream.
+    ///
+    /// The default implementation returns no frames.
+    /// The resamplers of this crate, and the wrappers around them, override it.
+    fn flush(&mut self) -> ResampleResult<Vec<Vec<T>>> {
+        Ok(Vec::new())
+    }
+
+    /// Resample a chunk of audio, and pass the output to the closure `f` instead of returning it.
+    /// The closure is called once for each channel, with the channel index and the output frames
+    /// of that channel, which are empty for channels that were ignored.
+    /// This lets the output be written directly to where it is needed.
+    ///
+    /// The default implementation calls "process" and passes on the returned vectors.
+    /// Resamplers that keep their output in an internal buffer override it
+    /// to avoid allocating output vectors.
+    fn process_with(
+        &mut self,
+        wave_in: &[Vec<T>],
+        f: &mut dyn FnMut(usize, &[T]),
+    ) -> ResampleResult<()> {
+        let wave_out = self.process(wave_in)?;
+        for (chan, wave) in wave_out.iter().enumerate() {
+            f(chan, wave);
+        }
+        Ok(())
+    }
+
+    /// Resample a chunk of audio, and write the output to a sink, see `AudioSink`.
+    /// This uses "process_with", and avoids allocating output vectors when the resampler supports it.
+    fn process_into_sink(
+        &mut self,
+        wave_in: &[Vec<T>],
+        sink: &mut dyn AudioSink<T>,
+    ) -> ResampleResult<()> {
+        self.process_with(wave_in, &mut |chan, frames| sink.write_frames(chan, frames))
+    }
+
+    /// Resample a chunk of audio, and write the output to the buffers in `wave_out`,
+    /// one vector per channel, starting at the first frame of each vector.
+    /// Returns the number of frames written.
+    /// The vectors are not resized, and must have room for at least the number of frames
+    /// given by "nbr_output_frames_next". Frames after the written ones are left untouched,
+    /// and so are the vectors of channels that are ignored because their input is empty.
+    ///
+    /// This uses "process_with", and so does not allocate any memory for the resamplers that override it,
+    /// which are the sinc resamplers and `FftFixedInOut`. This makes it suitable for real-time audio callbacks.
+    /// The exception is when the channels are processed in parallel with the `parallel` feature,
+    /// since the thread pool may allocate.
+    ///
+    /// # Errors
+    ///
+    /// The function returns an error if `wave_out` does not hold the same number of channels as `wave_in`,
+    /// if an output vector is too short, or if processing fails.
+    fn process_into_buffer(
+        &mut self,
+        wave_in: &[Vec<T>],
+        wave_out: &mut [Vec<T>],
+    ) -> ResampleResult<usize>
+    where
+        T: Sample,
+    {
+        io::check_output_buffers(wave_in, wave_out, self.nbr_output_frames_next())?;
+        let mut nbr_written = 0;
+        self.process_with(wave_in, &mut |chan, frames| {
+            wave_out[chan][..frames.len()].copy_from_slice(frames);
+            nbr_written = nbr_written.max(frames.len());
+        })?;
+        Ok(nbr_written)
+    }
+
+    /// Resample the last chunk of a stream, that may be shorter than the number of frames needed.
+    /// The channels are padded with zeros up to the number given by "nbr_frames_needed" and then processed.
+    /// Unlike in "process", empty channels are not ignored but processed as silence,
+    /// so that a chunk of empty channels can be given when the input has ended.
+    ///
+    /// The output held back by the resampler is then emitted like by "flush",
+    /// and the output frames that were produced from the padding are removed.
+    /// The result is the remaining output of the stream, so that the total output
+    /// is the input length multiplied by the resample ratio, plus the "output_delay".
+    /// Like after "flush", the resampler should be reset before it is used for a new stream.
+    ///
+    /// Returns the output together with the number of input frames consumed,
+    /// which is the length of the longest channel, and the number of output frames produced.
+    ///
+    /// # Errors
+    ///
+    /// The function returns an error if the number of channels doesn't match,
+    /// or if a channel holds more frames than given by "nbr_frames_needed".
+    fn process_partial(&mut self, wave_in: &[Vec<T>]) -> ResampleResult<PartialOutput<T>>
+    where
+        T: Sample,
+    {
+        let frames_needed = self.nbr_frames_needed();
+        for (chan, wave) in wave_in.iter().enumerate() {
+            if wave.len() > frames_needed {
+                return Err(ResampleError::WrongNumberOfFrames {
+                    channel: chan,
+                    expected: frames_needed,
+                    actual: wave.len(),
+                    resampler: String::new(),
+                });
+            }
+        }
+        let frames_consumed = wave_in.iter().map(|wave| wave.len()).max().unwrap_or(0);
+        let padded: Vec<Vec<T>> = wave_in
+            .iter()
+            .map(|wave| {
+                let mut padded = Vec::with_capacity(frames_needed);
+                padded.extend_from_slice(wave);
+                padded.resize(frames_needed, T::zero());
+                padded
+            })
+            .collect();
+        let padding_out =
+            ((frames_needed - frames_consumed) as f64 * self.resample_ratio()).round();
+        let mut wave_out = self.process(&padded)?;
+        let flushed = self.flush()?;
+        for (wave, tail) in wave_out.iter_mut().zip(flushed.iter()) {
+            wave.extend_from_slice(tail);
+        }
+        let frames_out = wave_out.iter().map(|wave| wave.len()).max().unwrap_or(0);
+        let frames_produced = frames_out.saturating_sub(padding_out as usize);
+        for wave in wave_out.iter_mut() {
+            wave.truncate(frames_produced);
+        }
+        Ok(PartialOutput {
+            wave_out,
+            frames_consumed,
+            frames_produced,
+        })
+    }
+
+    /// Read the number of frames needed for the next chunk from a source, see `AudioSource`,
+    /// and resample them.
+    /// If the source returns fewer frames than needed, the chunk is padded with zeros.
+    /// Returns `None` without processing anything when the source has no more data.
+    fn pull_from(&mut self, source: &mut dyn AudioSource<T>) -> ResampleResult<Option<Vec<Vec<T>>>>
+    where
+        T: Sample,
+    {
+        let nbr_frames = self.nbr_frames_needed();
+        let mut wave_in = vec![vec![T::zero(); nbr_frames]; source.nbr_channels()];
+        if source.read_frames(&mut wave_in, nbr_frames) == 0 {
+            return Ok(None);
+        }
+        self.process(&wave_in).map(Some)
+    }
+
+    /// Resample a chunk of audio like "process", but skip the validation of the input.
+    /// This saves the checks of the number of channels and frames, and the search for empty channels,
+    /// which can be noticeable when processing many small chunks.
+    ///
+    /// The default implementation calls "process", and the sinc resamplers override it.
+    ///
+    /// # Safety
+    ///
+    /// `wave_in` must hold exactly one vector per channel of the resampler,
+    /// and every vector must hold exactly the number of frames given by "nbr_frames_needed".
+    /// Empty channels are not allowed.
+    unsafe fn process_unchecked(&mut self, wave_in: &[Vec<T>]) -> ResampleResult<Vec<Vec<T>>> {
+        self.process(wave_in)
+    }
+
+    /// Resample a chunk of audio like "process", with the input given as one slice per channel.
+    /// This lets channels be passed that are borrowed from other buffers,
+    /// like arena or stack allocated arrays, without first copying them into vectors.
+    /// Empty slices are ignored, like empty vectors in "process".
+    ///
+    /// The default implementation copies the slices to new vectors and calls "process".
+    /// The sinc resamplers override it to read the slices directly into their internal buffers.
+    ///
+    /// # Errors
+    ///
+    /// The function returns an error if the number of channels or frames doesn't match,
+    /// like "process".
+    fn process_slices(&mut self, wave_in: &[&[T]]) -> ResampleResult<Vec<Vec<T>>>
+    where
+        T: Sample,
+    {
+        let wave_vecs: Vec<Vec<T>> = wave_in.iter().map(|wave| wave.to_vec()).collect();
+        self.process(&wave_vecs)
+    }
+
+    /// Resample the chunk of `len` frames starting at frame `offset` of each channel in `wave_in`.
+    /// This lets a long buffer be processed in successive windows,
+    /// without first slicing it into new vectors for each chunk.
+    /// Empty channels are ignored, like in "process".
+    ///
+    /// The default implementation copies the range to new vectors and calls "process".
+    /// The sinc resamplers override it to read the range directly into their internal buffers.
+    ///
+    /// # Errors
+    ///
+    /// The function returns an error if the number of channels doesn't match,
+    /// if `len` is not the number given by "nbr_frames_needed",
+    /// or if a channel ends before `offset + len`.
+    fn process_range(
+        &mut self,
+        wave_in: &[Vec<T>],
+        offset: usize,
+        len: usize,
+    ) -> ResampleResult<Vec<Vec<T>>>
+    where
+        T: Sample,
+    {
+        io::check_range(wave_in, offset, len)?;
+        let wave_range: Vec<Vec<T>> = wave_in
+            .iter()
+            .map(|wave| {
+                if wave.is_empty() {
+                    Vec::new()
+                } else {
+                    wave[offset..offset + len].to_vec()
+                }
+            })
+            .collect();
+        self.process(&wave_range)
+    }
+
+    /// Resample a chunk of audio read from a buffer with a stride,
+    /// and write the output to another buffer with a stride.
+    /// Frame `n` of channel `c` is read from `wave_in[n * stride_in + c]`,
+    /// and written to `wave_out[n * stride_out + c]`.
+    /// With strides equal to `nbr_channels` this processes interleaved buffers,
+    /// and with larger strides it processes a group of channels out of a wider interleaved buffer,
+    /// for example a single channel by passing the slice starting at that channel.
+    /// The samples in `wave_out` that belong to other channels are left untouched.
+    /// Returns the number of frames written.
+    ///
+    /// The default implementation copies the input to one vector per channel,
+    /// and writes the output with "process_with".
+    /// The sinc resamplers override it to read the input directly into their internal buffers,
+    /// so that no de-interleaving copy is needed.
+    ///
+    /// # Errors
+    ///
+    /// The function returns an error if `nbr_channels` doesn't match the resampler,
+    /// if `wave_in` holds fewer frames than given by "nbr_frames_needed",
+    /// or if `wave_out` has room for fewer frames than given by "nbr_output_frames_next".
+    ///
+    /// # Panics
+    ///
+    /// Panics if a stride is smaller than `nbr_channels`, or is zero.
+    fn process_strided(
+        &mut self,
+        nbr_channels: usize,
+        wave_in: &[T],
+        stride_in: usize,
+        wave_out: &mut [T],
+        stride_out: usize,
+    ) -> ResampleResult<usize>
+    where
+        T: Sample,
+    {
+        io::assert_strides(nbr_channels, stride_in, stride_out);
+        let nbr_frames = self.nbr_frames_needed();
+        io::check_strided_len(wave_in.len(), nbr_channels, stride_in, nbr_frames)?;
+        let nbr_frames_out = self.nbr_output_frames_next();
+        io::check_strided_len(wave_out.len(), nbr_channels, stride_out, nbr_frames_out)?;
+        let mut channels = vec![vec![T::zero(); nbr_frames]; nbr_channels];
+        for (chan, channel) in channels.iter_mut().enumerate() {
+            io::read_strided(wave_in, chan, stride_in, channel);
+        }
+        let mut nbr_written = 0;
+        self.process_with(&channels, &mut |chan, frames| {
+            io::write_strided(wave_out, chan, stride_out, frames);
+            nbr_written = nbr_written.max(frames.len());
+        })?;
+        Ok(nbr_written)
+    }
+
+    /// Resample a chunk of interleaved audio with `nbr_channels` channels,
+    /// and return the output as a new interleaved vector.
+    /// The input must hold the number of frames given by "nbr_frames_needed".
+    ///
+    /// This uses "process_strided", which lets the sinc resamplers read the input
+    /// without de-interleaving it first. To avoid allocating the output,
+    /// use "process_strided" directly with an output buffer and strides equal to `nbr_channels`.
+    ///
+    /// # Errors
+    ///
+    /// The function returns an error if `nbr_channels` doesn't match the resampler,
+    /// or if `wave_in` holds fewer frames than given by "nbr_frames_needed".
+    ///
+    /// # Panics
+    ///
+    /// Panics if `nbr_channels` is zero.
+    fn process_interleaved(&mut self, nbr_channels: usize, wave_in: &[T]) -> ResampleResult<Vec<T>>
+    where
+        T: Sample,
+    {
+        let mut wave_out = vec![T::zero(); nbr_channels * self.nbr_output_frames_next()];
+        let nbr_written = self.process_strided(
+            nbr_channels,
+            wave_in,
+            nbr_channels,
+            &mut wave_out,
+            nbr_channels,
+        )?;
+        wave_out.truncate(nbr_channels * nbr_written);
+        Ok(wave_out)
+    }
+
+    /// Resample a chunk of interleaved 16-bit audio with `nbr_channels` channels,
+    /// and write the output as interleaved 16-bit integers to `wave_out`.
+    /// The input must hold the number of frames given by "nbr_frames_needed",
+    /// and the samples are converted like `convert::from_i16` and `convert::to_i16`.
+    /// The output is rounded and clamped, and dithered if `dither` is given.
+    /// Returns the number of frames written.
+    ///
+    /// The default implementation converts the input to one vector per channel,
+    /// and converts the output while interleaving it in "process_with".
+    /// The sinc resamplers override it to convert the input directly into their internal buffers,
+    /// so that the audio is only copied once on the way in and once on the way out,
+    /// instead of separately de-interleaving, converting, converting back and interleaving.
+    ///
+    /// # Errors
+    ///
+    /// The function returns an error if `nbr_channels` doesn't match the resampler,
+    /// if `wave_in` holds fewer frames than given by "nbr_frames_needed",
+    /// or if `wave_out` has room for fewer frames than given by "nbr_output_frames_next".
+    ///
+    /// # Panics
+    ///
+    /// Panics if `nbr_channels` is zero.
+    fn process_interleaved_i16(
+        &mut self,
+        nbr_channels: usize,
+        wave_in: &[i16],
+        wave_out: &mut [i16],
+        mut dither: Option<&mut convert::Dither>,
+    ) -> ResampleResult<usize>
+    where
+        T: Sample,
+    {
+        io::assert_strides(nbr_channels, nbr_channels, nbr_channels);
+        let nbr_frames = self.nbr_frames_needed();
+        io::check_strided_len(wave_in.len(), nbr_channels, nbr_channels, nbr_frames)?;
+        let nbr_frames_out = self.nbr_output_frames_next();
+        io::check_strided_len(wave_out.len(), nbr_channels, nbr_channels, nbr_frames_out)?;
+        let mut channels = vec![vec![T::zero(); nbr_frames]; nbr_channels];
+        for (chan, channel) in channels.iter_mut().enumerate() {
+            convert::read_strided_i16(wave_in, chan, nbr_channels, channel);
+        }
+        let mut nbr_written = 0;
+        self.process_with(&channels, &mut |chan, frames| {
+            convert::write_strided_i16(wave_out, chan, nbr_channels, frames, dither.as_deref_mut());
+            nbr_written = nbr_written.max(frames.len());
+        })?;
+        Ok(nbr_written)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::{FftFixedIn, FftFixedInOut, FftFixedOut};
+    use crate::{ResampleError, Resampler};
+    use crate::{ScalarInterpolator, SincFixedIn, SincFixedOut};
+
+    fn assert_send<T: Send>() {}
+    fn assert_sync<T: Sync>() {}
+
+    #[test]
+    fn resamplers_are_send_and_sync() {
+        assert_send::<SincFixedIn<f32>>();
+        assert_sync::<SincFixedIn<f32>>();
+        assert_send::<SincFixedIn<f64>>();
+        assert_sync::<SincFixedIn<f64>>();
+        assert_send::<SincFixedOut<f32>>();
+        assert_sync::<SincFixedOut<f32>>();
+        assert_send::<SincFixedOut<f64>>();
+        assert_sync::<SincFixedOut<f64>>();
+        assert_send::<FftFixedIn<f64>>();
+        assert_sync::<FftFixedIn<f64>>();
+        assert_send::<FftFixedOut<f64>>();
+        assert_sync::<FftFixedOut<f64>>();
+        assert_send::<FftFixedInOut<f64>>();
+        assert_sync::<FftFixedInOut<f64>>();
+        assert_send::<ScalarInterpolator<f64>>();
+        assert_sync::<ScalarInterpolator<f64>>();
+        assert_send::<Box<dyn Resampler<f64>>>();
+    }
+
+    fn make_resamplers() -> Vec<Box<dyn Resampler<f64>>> {
+        let params = || crate::InterpolationParameters {
+            sinc_len: 64,
+            f_cutoff: 0.95,
+            interpolation: crate::InterpolationType::Cubic,
+            oversampling_factor: 16,
+            window: crate::WindowFunction::BlackmanHarris2,
+        };
+        vec![
+            Box::new(SincFixedIn::new(1.2, params(), 256, 2).unwrap()),
+            Box::new(SincFixedOut::new(0.8, params(), 256, 2).unwrap()),
+            Box::new(FftFixedInOut::new(44100, 48000, 256, 2).unwrap()),
+        ]
+    }
+
+    #[test]
+    fn process_with() {
+        for (mut resampler, mut streaming) in make_resamplers().into_iter().zip(make_resamplers()) {
+            for _ in 0..3 {
+                let frames = resampler.nbr_frames_needed();
+                let waves = vec![
+                    (0..frames).map(|n| (n as f64 * 0.1).sin()).collect(),
+                    Vec::new(),
+                ];
+                let expected = resampler.process(&waves).unwrap();
+                let mut output = vec![Vec::new(); 2];
+                streaming
+                    .process_with(&waves, &mut |chan, data| {
+                        output[chan].extend_from_slice(data)
+                    })
+                    .unwrap();
+                assert_eq!(output, expected);
+                assert!(output[1].is_empty());
+            }
+        }
+    }
+
+    #[test]
+    fn process_into_buffer() {
+        for (mut resampler, mut buffered) in make_resamplers().into_iter().zip(make_resamplers()) {
+            let mut output = vec![vec![-1.0; 512], vec![-1.0; 512]];
+            for _ in 0..3 {
+                let frames = resampler.nbr_frames_needed();
+                let waves = vec![
+                    (0..frames).map(|n| (n as f64 * 0.1).sin()).collect(),
+                    Vec::new(),
+                ];
+                let expected = resampler.process(&waves).unwrap();
+                let nbr_written = buffered.process_into_buffer(&waves, &mut output).unwrap();
+                assert_eq!(nbr_written, expected[0].len());
+                assert_eq!(output[0][..nbr_written], expected[0][..]);
+                assert!(output[1].iter().all(|value| *value == -1.0));
+            }
+            let waves = vec![vec![0.0; buffered.nbr_frames_needed()]; 2];
+            let mut short = vec![vec![0.0; buffered.nbr_output_frames_next() - 1]; 2];
+            assert!(buffered.process_into_buffer(&waves, &mut short).is_err());
+            assert!(buffered
+                .process_into_buffer(&waves, &mut output[..1])
+                .is_err());
+        }
+    }
+
+    #[test]
+    fn process_strided() {
+        for (mut resampler, mut strided) in make_resamplers().into_iter().zip(make_resamplers()) {
+            for _ in 0..3 {
+                let frames = resampler.nbr_frames_needed();
+                let waves: Vec<Vec<f64>> = vec![
+                    (0..frames).map(|n| (n as f64 * 0.1).sin()).collect(),
+                    (0..frames).map(|n| (n as f64 * 0.03).cos()).collect(),
+                ];
+                let expected = resampler.process(&waves).unwrap();
+                // two channels out of a buffer with three, and output with one unused slot per frame
+                let mut data = vec![9.0; 3 * frames];
+                for n in 0..frames {
+                    data[3 * n] = waves[0][n];
+                    data[3 * n + 1] = waves[1][n];
+                }
+                let frames_out = strided.nbr_output_frames_next();
+                let mut output = vec![-1.0; 3 * frames_out];
+                let nbr_written = strided
+                    .process_strided(2, &data, 3, &mut output, 3)
+                    .unwrap();
+                assert_eq!(nbr_written, expected[0].len());
+                for n in 0..nbr_written {
+                    assert_eq!(output[3 * n], expected[0][n]);
+                    assert_eq!(output[3 * n + 1], expected[1][n]);
+                    assert_eq!(output[3 * n + 2], -1.0);
+                }
+            }
+            let frames = strided.nbr_frames_needed();
+            let mut output = vec![0.0; 2 * strided.nbr_output_frames_next()];
+            let data = vec![0.0; 2 * frames - 1];
+            assert!(strided
+                .process_strided(2, &data, 2, &mut output, 2)
+                .is_err());
+            let data = vec![0.0; 2 * frames];
+            assert!(strided
+                .process_strided(2, &data, 2, &mut output[1..], 2)
+                .is_err());
+            assert!(strided
+                .process_strided(1, &data, 2, &mut output, 2)
+                .is_err());
+        }
+    }
+
+    #[test]
+    fn process_interleaved() {
+        for (mut resampler, mut interleaved) in make_resamplers().into_iter().zip(make_resamplers())
+        {
+            for _ in 0..3 {
+                let frames = resampler.nbr_frames_needed();
+                let waves: Vec<Vec<f64>> = vec![
+                    (0..frames).map(|n| (n as f64 * 0.1).sin()).collect(),
+                    (0..frames).map(|n| (n as f64 * 0.03).cos()).collect(),
+                ];
+                let expected = resampler.process(&waves).unwrap();
+                let data: Vec<f64> = (0..2 * frames).map(|n| waves[n % 2][n / 2]).collect();
+                let output = interleaved.process_interleaved(2, &data).unwrap();
+                assert_eq!(output.len(), 2 * expected[0].len());
+                for (n, frame) in output.chunks(2).enumerate() {
+                    assert_eq!(frame, &[expected[0][n], expected[1][n]][..]);
+                }
+            }
+            let data = vec![0.0; 3 * interleaved.nbr_frames_needed()];
+            assert!(interleaved.process_interleaved(3, &data).is_err());
+        }
+    }
+
+    #[test]
+    fn process_interleaved_i16() {
+        use crate::convert::{from_i16, to_i16, Dither};
+        for (mut resampler, mut fused) in make_resamplers().into_iter().zip(make_resamplers()) {
+            let mut dither = Dither::new(7);
+            let mut fused_dither = Dither::new(7);
+            for _ in 0..3 {
+                let frames = resampler.nbr_frames_needed();
+                let data: Vec<i16> = (0..2 * frames)
+                    .map(|n| ((n as f64 * 0.07).sin() * 30000.0) as i16)
+                    .collect();
+                // de-interleave, convert, resample, convert and interleave in separate steps
+                let mut waves = vec![vec![0.0; frames]; 2];
+                for (chan, wave) in waves.iter_mut().enumerate() {
+                    let samples: Vec<i16> = data.iter().skip(chan).step_by(2).copied().collect();
+                    from_i16(&samples, wave);
+                }
+                let expected: Vec<Vec<i16>> = resampler
+                    .process(&waves)
+                    .unwrap()
+                    .iter()
+                    .map(|wave| {
+                        let mut samples = vec![0; wave.len()];
+                        to_i16(wave, &mut samples, Some(&mut dither));
+                        samples
+                    })
+                    .collect();
+                let mut output = vec![0; 2 * fused.nbr_output_frames_next()];
+                let nbr_written = fused
+                    .process_interleaved_i16(2, &data, &mut output, Some(&mut fused_dither))
+                    .unwrap();
+                assert_eq!(nbr_written, expected[0].len());
+                for (n, frame) in output[..2 * nbr_written].chunks(2).enumerate() {
+                    assert_eq!(frame, &[expected[0][n], expected[1][n]][..]);
+                }
+            }
+            let mut output = vec![0; 2 * fused.nbr_output_frames_next()];
+            let data = vec![0; 2 * fused.nbr_frames_needed() - 2];
+            assert!(fused
+                .process_interleaved_i16(2, &data, &mut output, None)
+                .is_err());
+        }
+    }
+
+    #[test]
+    fn process_slices() {
+        for (mut resampler, mut sliced) in make_resamplers().into_iter().zip(make_resamplers()) {
+            let mut buffer = [0.0; 512];
+            for _ in 0..3 {
+                let frames = resampler.nbr_frames_needed();
+                for (n, value) in buffer[..frames].iter_mut().enumerate() {
+                    *value = (n as f64 * 0.1).sin();
+                }
+                let expected = resampler
+                    .process(&[buffer[..frames].to_vec(), Vec::new()])
+                    .unwrap();
+                let output = sliced.process_slices(&[&buffer[..frames], &[]]).unwrap();
+                assert_eq!(output, expected);
+            }
+            assert!(sliced.process_slices(&[&buffer[..1], &[]]).is_err());
+        }
+    }
+
+    #[test]
+    fn output_delay() {
+        let params = crate::InterpolationParameters {
+            sinc_len: 64,
+            f_cutoff: 0.95,
+            interpolation: crate::InterpolationType::Cubic,
+            oversampling_factor: 16,
+            window: crate::WindowFunction::BlackmanHarris2,
+        };
+        let sinc = SincFixedOut::<f64>::new(0.8, params, 256, 2).unwrap();
+        let expected = sinc.latency().output_frames;
+        let boxed: Box<dyn Resampler<f64>> = Box::new(sinc);
+        assert_eq!(boxed.output_delay(), expected);
+        assert!(expected.abs() < 1.0);
+
+        let fft = FftFixedIn::<f64>::new(44100, 48000, 300, 2, 2).unwrap();
+        let expected = fft.latency().output_frames;
+        let boxed: Box<dyn Resampler<f64>> = Box::new(fft);
+        assert_eq!(boxed.output_delay(), expected);
+        assert!(expected > 100.0);
+    }
+
+    #[test]
+    fn process_partial() {
+        for (mut resampler, mut partial) in make_resamplers().into_iter().zip(make_resamplers()) {
+            let frames = resampler.nbr_frames_needed();
+            let short: Vec<f64> = (0..frames / 3).map(|n| (n as f64 * 0.1).sin()).collect();
+            let mut padded = short.clone();
+            padded.resize(frames, 0.0);
+            let mut expected = resampler.process(&[padded, vec![0.0; frames]]).unwrap();
+            let flushed = resampler.flush().unwrap();
+            let output = partial.process_partial(&[short, Vec::new()]).unwrap();
+            assert_eq!(output.frames_consumed, frames / 3);
+            assert_eq!(output.wave_out[0].len(), output.frames_produced);
+            assert_eq!(output.wave_out[1].len(), output.frames_produced);
+            // the output is the start of the padded chunk followed by the flushed frames
+            expected[0].extend_from_slice(&flushed[0]);
+            assert_eq!(
+                output.wave_out[0][..],
+                expected[0][..output.frames_produced]
+            );
+
+            let too_long = vec![0.0; partial.nbr_frames_needed() + 1];
+            assert!(partial.process_partial(&[too_long, Vec::new()]).is_err());
+        }
+    }
+
+    #[test]
+    fn process_partial_length() {
+        for mut resampler in make_resamplers() {
+            let ratio = resampler.resample_ratio();
+            let input_len = 1000;
+            let mut frames_in = 0;
+            let mut frames_out = 0;
+            loop {
+                let frames = resampler.nbr_frames_needed();
+                if frames_in + frames > input_len {
+                    let last = vec![vec![0.5; input_len - frames_in]; 2];
+                    let output = resampler.process_partial(&last).unwrap();
+                    assert_eq!(output.frames_consumed, input_len - frames_in);
+                    frames_out += output.frames_produced;
+                    break;
+                }
+                frames_out += resampler.process(&vec![vec![0.5; frames]; 2]).unwrap()[0].len();
+                frames_in += frames;
+            }
+            let expected = input_len as f64 * ratio + resampler.output_delay();
+            assert!(
+                (frames_out as f64 - expected).abs() <= 2.0,
+                "{} frames, expected {}",
+                frames_out,
+                expected
+            );
+        }
+    }
+
+    #[test]
+    fn reset() {
+        let mut resamplers = make_resamplers();
+        resamplers.push(Box::new(FftFixedIn::new(44100, 48000, 300, 2, 2).unwrap()));
+        resamplers.push(Box::new(FftFixedOut::new(44100, 48000, 300, 2, 2).unwrap()));
+        for mut resampler in resamplers.into_iter() {
+            let mut first = Vec::new();
+            for _ in 0..3 {
+                let frames = resampler.nbr_frames_needed();
+                let waves = vec![
+                    (0..frames).map(|n| (n as f64 * 0.1).sin()).collect(),
+                    Vec::new(),
+                ];
+                first.push(resampler.process(&waves).unwrap());
+            }
+            let _ = resampler.set_resample_ratio_relative(1.05);
+            resampler
+                .process(&[vec![1.0; resampler.nbr_frames_needed()], Vec::new()])
+                .unwrap();
+            resampler.reset();
+            for expected in first.iter() {
+                let frames = resampler.nbr_frames_needed();
+                let waves = vec![
+                    (0..frames).map(|n| (n as f64 * 0.1).sin()).collect(),
+                    Vec::new(),
+                ];
+                assert_eq!(&resampler.process(&waves).unwrap(), expected);
+            }
+        }
+    }
+
+    #[test]
+    fn flush() {
+        let params = || crate::InterpolationParameters {
+            sinc_len: 64,
+            f_cutoff: 0.95,
+            interpolation: crate::InterpolationType::Cubic,
+            oversampling_factor: 16,
+            window: crate::WindowFunction::BlackmanHarris2,
+        };
+        let fft_in = FftFixedIn::<f64>::new(44100, 48000, 300, 2, 2).unwrap();
+        let fft_in_latency = fft_in.latency().output_frames;
+        let fft_out = FftFixedOut::<f64>::new(44100, 48000, 300, 2, 2).unwrap();
+        let fft_out_latency = fft_out.latency().output_frames;
+        let fft_inout = FftFixedInOut::<f64>::new(44100, 48000, 256, 2).unwrap();
+        let fft_inout_latency = fft_inout.latency().output_frames;
+        // the resamplers, with the ratio and the latency in output frames
+        let resamplers: Vec<(Box<dyn Resampler<f64>>, f64, f64)> = vec![
+            (
+                Box::new(SincFixedIn::new(1.2, params(), 256, 2).unwrap()),
+                1.2,
+                0.0,
+            ),
+            (
+                Box::new(SincFixedOut::new(0.8, params(), 256, 2).unwrap()),
+                0.8,
+                0.0,
+            ),
+            (
+                Box::new(SincFixedIn::new(0.5, params(), 256, 2).unwrap()),
+                0.5,
+                0.0,
+            ),
+            (Box::new(fft_in), 48000.0 / 44100.0, fft_in_latency),
+            (Box::new(fft_out), 48000.0 / 44100.0, fft_out_latency),
+            (Box::new(fft_inout), 48000.0 / 44100.0, fft_inout_latency),
+        ];
+        for (mut resampler, ratio, latency) in resamplers.into_iter() {
+            let mut frames_in = 0;
+            let mut frames_out = 0;
+            for _ in 0..5 {
+                let frames = resampler.nbr_frames_needed();
+                let waves = vec![vec![1.0; frames], Vec::new()];
+                frames_in += frames;
+                frames_out += resampler.process(&waves).unwrap()[0].len();
+            }
+            let tail = resampler.flush().unwrap();
+            assert_eq!(tail.len(), 2);
+            assert_eq!(tail[0].len(), tail[1].len());
+            assert!(tail[0].iter().any(|value| value.abs() > 0.5));
+            let expected = frames_in as f64 * ratio + latency;
+            let total = (frames_out + tail[0].len()) as f64;
+            assert!((total - expected).abs() <= 2.0, "{} != {}", total, expected);
+        }
+    }
+
+    #[test]
+    fn process_unchecked() {
+        for (mut resampler, mut unchecked) in make_resamplers().into_iter().zip(make_resamplers()) {
+            for _ in 0..3 {
+                let frames = resampler.nbr_frames_needed();
+                let waves: Vec<Vec<f64>> = vec![
+                    (0..frames).map(|n| (n as f64 * 0.1).sin()).collect(),
+                    (0..frames).map(|n| (n as f64 * 0.03).cos()).collect(),
+                ];
+                let expected = resampler.process(&waves).unwrap();
+                let output = unsafe { unchecked.process_unchecked(&waves).unwrap() };
+                assert_eq!(output, expected);
+            }
+        }
+    }
+
+    #[test]
+    fn process_range() {
+        for (mut resampler, mut ranged) in make_resamplers().into_iter().zip(make_resamplers()) {
+            let long: Vec<Vec<f64>> = vec![
+                (0..2000).map(|n| (n as f64 * 0.1).sin()).collect(),
+                Vec::new(),
+            ];
+            let mut offset = 7;
+            for _ in 0..3 {
+                let frames = resampler.nbr_frames_needed();
+                let waves = vec![long[0][offset..offset + frames].to_vec(), Vec::new()];
+                let expected = resampler.process(&waves).unwrap();
+                let output = ranged.process_range(&long, offset, frames).unwrap();
+                assert_eq!(output, expected);
+                offset += frames;
+            }
+            let frames = ranged.nbr_frames_needed();
+            assert!(ranged.process_range(&long, 2001 - frames, frames).is_err());
+            assert!(ranged.process_range(&long, 0, frames - 1).is_err());
+            assert!(ranged.process_range(&long[0..1], 0, frames).is_err());
+        }
+    }
+
+    #[test]
+    fn error_context() {
+        let names = ["SincFixedIn", "SincFixedOut", "FftFixedInOut"];
+        for (mut resampler, name) in make_resamplers().into_iter().zip(names.iter()) {
+            let frames = resampler.nbr_frames_needed();
+            let waves = vec![vec![0.0; frames], vec![0.0; frames - 1]];
+            match resampler.process(&waves) {
+                Err(ResampleError::WrongNumberOfFrames {
+                    channel,
+                    expected,
+                    actual,
+                    resampler,
+                }) => {
+                    assert_eq!((channel, expected, actual), (1, frames, frames - 1));
+                    assert!(resampler.starts_with(name), "{}", resampler);
+                }
+                other => panic!("unexpected result {:?}", other),
+            }
+            let err = resampler.process(&waves[0..1]).unwrap_err();
+            let message = format!("{}", err);
+            assert!(message.starts_with("Wrong number of channels 1 in input, expected 2, in "));
+            assert!(message.contains(name), "{}", message);
+        }
+    }
+
+    #[test]
+    fn ratio_out_of_bounds() {
+        let mut resamplers = make_resamplers();
+        match resamplers[0].set_resample_ratio(1.5) {
+            Err(ResampleError::RatioOutOfBounds {
+                provided,
+                original,
+                max_deviation,
+            }) => {
+                assert_eq!((provided, original, max_deviation), (1.5, 1.2, 0.1));
+            }
+            other => panic!("unexpected result {:?}", other),
+        }
+        let message = format!("{}", resamplers[1].set_resample_ratio(0.1).unwrap_err());
+        assert_eq!(
+            message,
+            "New resample ratio 0.1 is too far off from original 0.8, the limit is 10%"
+        );
+    }
+
+    #[test]
+    fn introspection() {
+        let mut resamplers = make_resamplers();
+        resamplers.push(Box::new(FftFixedIn::new(44100, 48000, 1024, 2, 1).unwrap()));
+        let summary: Vec<(f64, usize, usize, Option<usize>)> = resamplers
+            .iter()
+            .map(|r| {
+                assert_eq!(r.resample_ratio(), r.resample_ratio_original());
+                (
+                    r.resample_ratio_original(),
+                    r.chunk_size(),
+                    r.nbr_channels(),
+                    r.sinc_len(),
+                )
+            })
+            .collect();
+        assert_eq!(
+            summary,
+            vec![
+                (1.2, 256, 2, Some(64)),
+                (0.8, 256, 2, Some(64)),
+                (160.0 / 147.0, 294, 2, None),
+                (160.0 / 147.0, 1024, 1, None),
+            ]
+        );
+        resamplers[0].set_resample_ratio(1.25).unwrap();
+        resamplers[3].set_resample_ratio_relative(1.0005).unwrap();
+        assert_eq!(resamplers[0].resample_ratio(), 1.25);
+        assert_eq!(resamplers[3].resample_ratio(), 160.0 / 147.0 * 1.0005);
+        assert_eq!(resamplers[3].resample_ratio_original(), 160.0 / 147.0);
+    }
+
+    #[test]
+    fn parallel_channels() {
+        let params = || crate::InterpolationParameters {
+            sinc_len: 64,
+            f_cutoff: 0.95,
+            interpolation: crate::InterpolationType::Cubic,
+            oversampling_factor: 16,
+            window: crate::WindowFunction::BlackmanHarris2,
+        };
+        let mut sinc_in = SincFixedIn::<f64>::new(1.2, params(), 256, 4).unwrap();
+        let mut sinc_out = SincFixedOut::<f64>::new(0.8, params(), 256, 4).unwrap();
+        let mut fft_inout = FftFixedInOut::<f64>::new(44100, 48000, 256, 4).unwrap();
+        let mut fft_in = FftFixedIn::<f64>::new(44100, 48000, 256, 2, 4).unwrap();
+        let mut fft_out = FftFixedOut::<f64>::new(44100, 48000, 256, 2, 4).unwrap();
+        sinc_in.set_parallel_threshold(1);
+        sinc_out.set_parallel_threshold(1);
+        fft_inout.set_parallel_threshold(1);
+        fft_in.set_parallel_threshold(1);
+        fft_out.set_parallel_threshold(1);
+        let parallel: Vec<Box<dyn Resampler<f64>>> = vec![
+            Box::new(sinc_in),
+            Box::new(sinc_out),
+            Box::new(fft_inout),
+            Box::new(fft_in),
+            Box::new(fft_out),
+        ];
+        let serial: Vec<Box<dyn Resampler<f64>>> = vec![
+            Box::new(SincFixedIn::<f64>::new(1.2, params(), 256, 4).unwrap()),
+            Box::new(SincFixedOut::<f64>::new(0.8, params(), 256, 4).unwrap()),
+            Box::new(FftFixedInOut::<f64>::new(44100, 48000, 256, 4).unwrap()),
+            Box::new(FftFixedIn::<f64>::new(44100, 48000, 256, 2, 4).unwrap()),
+            Box::new(FftFixedOut::<f64>::new(44100, 48000, 256, 2, 4).unwrap()),
+        ];
+        for (mut parallel, mut serial) in parallel.into_iter().zip(serial) {
+            for chunk in 0..3 {
+                let frames = serial.nbr_frames_needed();
+                assert_eq!(parallel.nbr_frames_needed(), frames);
+                let waves: Vec<Vec<f64>> = (0..4)
+                    .map(|chan| match chan {
+                        // an ignored channel
+                        2 => Vec::new(),
+                        _ => (0..frames)
+                            .map(|n| ((chunk * frames + n) as f64 * 0.01 * (chan + 1) as f64).sin())
+                            .collect(),
+                    })
+                    .collect();
+                assert_eq!(
+                    parallel.process(&waves).unwrap(),
+                    serial.process(&waves).unwrap()
+                );
+            }
+        }
+    }
+
+    /// A kernel that counts the calls, and leaves the work to the scalar kernel.
+    struct CountingInterpolator {
+        inner: ScalarInterpolator<f64>,
+        calls: std::sync::Arc<std::sync::atomic::AtomicUsize>,
+    }
+
+    impl crate::SincInterpolator<f64> for CountingInterpolator {
+        fn get_sinc_interpolated(&self, wave: &[f64], index: usize, subindex: usize) -> f64 {
+            self.calls
+                .fetch_add(1, std::sync::atomic::Ordering::Relaxed);
+            self.inner.get_sinc_interpolated(wave, index, subindex)
+        }
+
+        fn len(&self) -> usize {
+            self.inner.len()
+        }
+
+        fn nbr_sincs(&self) -> usize {
+            self.inner.nbr_sincs()
+        }
+    }
+
+    #[test]
+    fn custom_kernel() {
+        let params = crate::InterpolationParameters {
+            sinc_len: 64,
+            f_cutoff: 0.95,
+            interpolation: crate::InterpolationType::Nearest,
+            oversampling_factor: 16,
+            window: crate::WindowFunction::BlackmanHarris2,
+        };
+        let calls = std::sync::Arc::new(std::sync::atomic::AtomicUsize::new(0));
+        let kernel = CountingInterpolator {
+            inner: ScalarInterpolator::new(64, 16, 0.95, crate::WindowFunction::BlackmanHarris2),
+            calls: calls.clone(),
+        };
+        let mut custom = SincFixedIn::<f64>::new_with_interpolator(
+            2.0,
+            crate::InterpolationType::Nearest,
+            Box::new(kernel),
+            256,
+            1,
+        )
+        .unwrap();
+        let mut reference = SincFixedIn::<f64>::new(2.0, params, 256, 1).unwrap();
+        let waves = vec![(0..256)
+            .map(|n| (n as f64 * 0.1).sin())
+            .collect::<Vec<f64>>()];
+        let output = custom.process(&waves).unwrap();
+        let expected = reference.process(&waves).unwrap();
+        assert!(calls.load(std::sync::atomic::Ordering::Relaxed) > 0);
+        for (value, exp) in output[0].iter().zip(expected[0].iter()) {
+            assert!((value - exp).abs() < 1.0e-12);
+        }
+    }
+
+    #[test]
+    fn invalid_parameters() {
+        let params = |oversampling_factor, f_cutoff| crate::InterpolationParameters {
+            sinc_len: 64,
+            f_cutoff,
+            interpolation: crate::InterpolationType::Cubic,
+            oversampling_factor,
+            window: crate::WindowFunction::BlackmanHarris2,
+        };
+        assert!(SincFixedIn::<f64>::new(1.2, params(16, 0.95), 256, 2).is_ok());
+        assert!(SincFixedIn::<f64>::new(1.2, params(0, 0.95), 256, 2).is_err());
+        assert!(SincFixedIn::<f64>::new(1.2, params(16, 0.95), 0, 2).is_err());
+        assert!(SincFixedOut::<f64>::new(-1.2, params(16, 0.95), 256, 2).is_err());
+        match SincFixedOut::<f64>::new(1.2, params(16, 1.5), 256, 2) {
+            Err(ResampleError::InvalidParameter { name, .. }) => assert_eq!(name, "f_cutoff"),
+            other => panic!("unexpected result {:?}", other.map(|_| ())),
+        }
+        for sigma in [0.0, -0.4, f64::NAN, f64::INFINITY].iter() {
+            let gaussian = crate::InterpolationParameters {
+                window: crate::WindowFunction::Gaussian(*sigma),
+                ..params(16, 0.95)
+            };
+            assert!(SincFixedIn::<f64>::new(1.2, gaussian, 256, 2).is_err());
+        }
+        let custom = crate::InterpolationParameters {
+            window: crate::WindowFunction::CustomTaps(vec![0.5; 64]),
+            ..params(16, 0.95)
+        };
+        assert!(SincFixedIn::<f64>::new(1.2, custom, 256, 2).is_ok());
+        let custom = crate::InterpolationParameters {
+            window: crate::WindowFunction::CustomTaps(vec![0.5; 60]),
+            ..params(16, 0.95)
+        };
+        match SincFixedIn::<f64>::new(1.2, custom, 256, 2) {
+            Err(ResampleError::InvalidParameter { value, .. }) => assert_eq!(value, "60"),
+            other => panic!("unexpected result {:?}", other.map(|_| ())),
+        }
+        // all constructors of the sinc resamplers validate their parameters
+        assert!(
+            SincFixedIn::<f64>::new_without_anti_aliasing(0.0, params(16, 0.95), 256, 2).is_err()
+        );
+        assert!(SincFixedOut::<f64>::new_with_filter(1.2, params(16, 0.95), &[], 256, 2).is_err());
+        assert!(
+            SincFixedIn::<f64>::new_rational(-0.5, params(16, 0.95), 16, 1.0e-6, 256, 2).is_err()
+        );
+        assert!(SincFixedOut::<f64>::new_from_rates(0, 48000, params(16, 0.95), 256, 2).is_err());
+        let prototype = vec![1.0; 64 * 16];
+        assert!(SincFixedIn::<f64>::new_from_prototype(
+            1.2,
+            &prototype,
+            16,
+            crate::InterpolationType::Cubic,
+            256,
+            2
+        )
+        .is_ok());
+        assert!(SincFixedIn::<f64>::new_from_prototype(
+            1.2,
+            &prototype[1..],
+            16,
+            crate::InterpolationType::Cubic,
+            256,
+            2
+        )
+        .is_err());
+        assert!(SincFixedOut::<f64>::new_from_table(
+            1.2,
+            &prototype,
+            60,
+            crate::InterpolationType::Cubic,
+            256,
+            2
+        )
+        .is_err());
+        let interpolator = Box::new(ScalarInterpolator::<f64>::new(
+            64,
+            16,
+            0.95,
+            crate::WindowFunction::BlackmanHarris2,
+        ));
+        assert!(SincFixedOut::<f64>::new_with_interpolator(
+            1.2,
+            crate::InterpolationType::Cubic,
+            interpolator,
+            0,
+            2
+        )
+        .is_err());
+        assert!(FftFixedInOut::<f64>::new(44100, 48000, 512, 2).is_ok());
+        assert!(FftFixedIn::<f64>::new(0, 48000, 512, 2, 2).is_err());
+        assert!(FftFixedOut::<f64>::new(44100, 48000, 512, 0, 2).is_err());
+    }
+
+    #[test]
+    fn debug_summary() {
+        let params = crate::InterpolationParameters {
+            sinc_len: 256,
+            f_cutoff: 0.95,
+            interpolation: crate::InterpolationType::Cubic,
+            oversampling_factor: 256,
+            window: crate::WindowFunction::BlackmanHarris2,
+        };
+        let state = (
+            SincFixedIn::<f64>::new(1.5, params, 1024, 2).unwrap(),
+            FftFixedInOut::<f64>::new(44100, 48000, 1024, 2).unwrap(),
+        );
+        let text = format!("{:?}", state);
+        assert!(
+            text.contains("SincFixedIn { nbr_channels: 2, chunk_size: 1024, resample_ratio: 1.5")
+        );
+        assert!(text.contains("sinc_len: 256, oversampling_factor: 256, interpolation: Cubic"));
+        assert!(text.contains("FftFixedInOut { nbr_channels: 2, chunk_size_in: 1029"));
+        assert!(text.len() < 500, "{}", text);
+    }
+
+    #[test]
+    fn parameter_derives() {
+        use std::collections::HashSet;
+        let params = crate::InterpolationParameters {
+            sinc_len: 256,
+            f_cutoff: 0.95,
+            interpolation: crate::InterpolationType::Cubic,
+            oversampling_factor: 256,
+            window: crate::WindowFunction::Gaussian(0.4),
+        };
+        let copy = params.clone();
+        assert_eq!(copy, params);
+        let other = crate::InterpolationParameters {
+            window: crate::WindowFunction::Gaussian(0.3),
+            ..params.clone()
+        };
+        assert_ne!(other, params);
+        let interpolation = params.interpolation;
+        let types: HashSet<_> = vec![interpolation, crate::InterpolationType::Cubic]
+            .into_iter()
+            .collect();
+        assert_eq!(types.len(), 1);
+        let _resampler = SincFixedIn::<f64>::new(1.5, params, 256, 1).unwrap();
+    }
 }
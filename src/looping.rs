@@ -0,0 +1,200 @@
+use crate::error::ResampleResult;
+use crate::offline::{check_lengths, read_frames};
+use crate::{InterpolationParameters, Resampler, Sample, SincFixedIn};
+
+/// Chunk size of the resampler used by `resample_loop`.
+const LOOP_CHUNK_SIZE: usize = 1024;
+
+/// A looped waveform, as returned by `resample_loop`.
+#[derive(Debug, Clone, PartialEq)]
+pub struct LoopedWave<T> {
+    /// The resampled waveform.
+    pub wave: Vec<Vec<T>>,
+    /// The first frame of the loop in the resampled waveform.
+    pub loop_start: usize,
+    /// The frame after the last frame of the loop in the resampled waveform.
+    pub loop_end: usize,
+    /// The resample ratio that was used, after adjusting it to the loop length.
+    pub resample_ratio: f64,
+}
+
+/// Resample a looped instrument sample, such that the loop stays free of clicks at the new rate.
+///
+/// The loop is the range `loop_start..loop_end` of input frames.
+/// The resample ratio is adjusted slightly, so that the loop becomes a whole number of output frames.
+/// The phase of the interpolation is then offset so that the new loop start falls exactly
+/// on the input loop start, and the new loop end on the input loop end.
+/// The frames up to the loop end are resampled as if the loop was repeated after the loop end,
+/// so that the filter sees the same signal there as when the sampler jumps back to the loop start.
+/// Any frames after the loop end, like a release tail, are resampled from the original waveform.
+/// The output is compensated for the latency of the resampler, and has the same duration as the input.
+///
+/// The channels must either have the same length, or be empty.
+///
+/// # Errors
+///
+/// The function returns an error if the channels have different lengths,
+/// or if the resampler returns an error.
+///
+/// # Panics
+///
+/// Panics if the loop is empty or extends past the end of the waveform.
+pub fn resample_loop<T>(
+    wave_in: &[Vec<T>],
+    loop_start: usize,
+    loop_end: usize,
+    resample_ratio: f64,
+    parameters: InterpolationParameters,
+) -> ResampleResult<LoopedWave<T>>
+where
+    T: Sample,
+{
+    let total = check_lengths(wave_in)?;
+    assert!(
+        loop_start < loop_end && loop_end <= total,
+        "Loop {}..{} is empty or out of range for {} frames",
+        loop_start,
+        loop_end,
+        total
+    );
+    let loop_len = loop_end - loop_start;
+    let loop_len_out = ((loop_len as f64 * resample_ratio).round() as usize).max(1);
+    let ratio = loop_len_out as f64 / loop_len as f64;
+    let loop_start_out = (loop_start as f64 * ratio).ceil() as usize;
+    let loop_end_out = loop_start_out + loop_len_out;
+    let total_out = loop_end_out + ((total - loop_end) as f64 * ratio).round() as usize;
+    debug!(
+        "Resample loop {}..{} to {}..{}, ratio adjusted from {} to {}",
+        loop_start, loop_end, loop_start_out, loop_end_out, resample_ratio, ratio
+    );
+
+    // unroll the loop far enough for the filter to reach past the loop end
+    let mut unrolled: Vec<Vec<T>> = wave_in
+        .iter()
+        .map(|wave| wave.iter().take(loop_end).cloned().collect())
+        .collect();
+    for (wave_unrolled, wave) in unrolled.iter_mut().zip(wave_in.iter()) {
+        if !wave.is_empty() {
+            while wave_unrolled.len() < loop_end + parameters.sinc_len {
+                wave_unrolled.extend_from_slice(&wave[loop_start..loop_end]);
+            }
+        }
+    }
+    let mut wave = resample_aligned(
+        &unrolled,
+        ratio,
+        parameters.clone(),
+        loop_start,
+        loop_start_out,
+        loop_end_out,
+    )?;
+    if total_out > loop_end_out {
+        let tail = resample_aligned(
+            wave_in,
+            ratio,
+            parameters,
+            loop_start,
+            loop_start_out,
+            total_out,
+        )?;
+        for (wave, tail) in wave.iter_mut().zip(tail.iter()) {
+            if !tail.is_empty() {
+                wave.extend_from_slice(&tail[loop_end_out..]);
+            }
+        }
+    }
+    Ok(LoopedWave {
+        wave,
+        loop_start: loop_start_out,
+        loop_end: loop_end_out,
+        resample_ratio: ratio,
+    })
+}
+
+/// Resample the first `nbr_frames` output frames, with output frame `start_out` at input frame `start`.
+fn resample_aligned<T>(
+    wave_in: &[Vec<T>],
+    ratio: f64,
+    parameters: InterpolationParameters,
+    start: usize,
+    start_out: usize,
+    nbr_frames: usize,
+) -> ResampleResult<Vec<Vec<T>>>
+where
+    T: Sample,
+{
+    let mut resampler = SincFixedIn::new(ratio, parameters, LOOP_CHUNK_SIZE, wave_in.len())?;
+    let latency = resampler.latency().input_frames;
+    resampler.shift_phase(start_out as f64 / ratio - start as f64 - latency);
+    let mut wave_out = vec![Vec::new(); wave_in.len()];
+    let mut frames_in = 0;
+    let mut frames_out = 0;
+    while frames_out < nbr_frames {
+        let chunk = read_frames(wave_in, frames_in, LOOP_CHUNK_SIZE);
+        let chunk_out = resampler.process(&chunk)?;
+        for (wave, chunk) in wave_out.iter_mut().zip(chunk_out.iter()) {
+            wave.extend_from_slice(chunk);
+        }
+        frames_in += LOOP_CHUNK_SIZE;
+        frames_out += chunk_len(&chunk_out);
+    }
+    for wave in wave_out.iter_mut() {
+        wave.truncate(nbr_frames);
+    }
+    Ok(wave_out)
+}
+
+/// Get the number of frames in a chunk, where some channels may be empty.
+fn chunk_len<T>(chunk: &[Vec<T>]) -> usize {
+    chunk.iter().map(|chan| chan.len()).max().unwrap_or(0)
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::looping::resample_loop;
+    use crate::{InterpolationParameters, InterpolationType, WindowFunction};
+    use std::f64::consts::PI;
+
+    #[test]
+    fn coherent_loop() {
+        let params = InterpolationParameters {
+            sinc_len: 128,
+            f_cutoff: 0.95,
+            interpolation: InterpolationType::Cubic,
+            oversampling_factor: 256,
+            window: WindowFunction::BlackmanHarris2,
+        };
+        // a loop of ten periods, followed by a silent tail
+        let period = 100.0;
+        let wave: Vec<f64> = (0..2500)
+            .map(|n| {
+                if n < 2000 {
+                    (2.0 * PI * n as f64 / period).sin()
+                } else {
+                    0.0
+                }
+            })
+            .collect();
+        let looped =
+            resample_loop(&[wave, Vec::new()], 1000, 2000, 48000.0 / 44100.0, params).unwrap();
+        assert_eq!(looped.loop_end - looped.loop_start, 1088);
+        assert!((looped.resample_ratio - 1.088).abs() < 1.0e-12);
+        assert_eq!(looped.loop_start, 1088);
+        assert_eq!(looped.wave[0].len(), 2720);
+        assert!(looped.wave[1].is_empty());
+
+        // the loop end continues like the loop start, without a step
+        let max_error = looped.wave[0][200..2176]
+            .iter()
+            .enumerate()
+            .map(|(n, value)| {
+                let pos = (n + 200) as f64 / looped.resample_ratio;
+                (value - (2.0 * PI * pos / period).sin()).abs()
+            })
+            .fold(0.0, f64::max);
+        assert!(max_error < 1.0e-3, "max error {}", max_error);
+        assert!(looped.wave[0][2300..]
+            .iter()
+            .all(|value| value.abs() < 1.0e-3));
+    }
+}
@@ -0,0 +1,206 @@
+use crate::Latency;
+
+/// A section of the stream where the resample ratio was constant.
+#[derive(Debug, Clone, Copy)]
+struct Segment {
+    input_start: f64,
+    output_start: f64,
+    ratio: f64,
+}
+
+/// Maps positions in the input of a resampler to positions in its output,
+/// for keeping markers like cue points and loop points aligned with the audio.
+///
+/// The mapper is told about each processed chunk with "record_chunk",
+/// and then maps input positions using the resample ratio of each chunk,
+/// together with the latency of the resampler.
+/// The result is a fractional output frame, which can be rounded as needed.
+/// For a constant ratio the mapping is exact, while a changing ratio
+/// gives an error of up to one frame after the points where the ratio changed,
+/// since the fractional phase at a chunk boundary isn't known.
+/// The frames are counted with 64-bit counters, so that streams running for days
+/// are counted correctly also on 32-bit targets.
+#[derive(Debug, Clone)]
+pub struct MarkerMapper {
+    latency: Latency,
+    segments: Vec<Segment>,
+    input_frames: u64,
+    output_frames: u64,
+}
+
+impl MarkerMapper {
+    /// Create a new mapper, for a resampler with the given latency.
+    pub fn new(latency: Latency) -> Self {
+        MarkerMapper {
+            latency,
+            segments: Vec::new(),
+            input_frames: 0,
+            output_frames: 0,
+        }
+    }
+
+    /// Record a processed chunk, with the number of input frames consumed,
+    /// the number of output frames produced, and the resample ratio used for the chunk.
+    pub fn record_chunk(&mut self, nbr_input_frames: usize, nbr_output_frames: usize, ratio: f64) {
+        // A new ratio applies from the first output frame of the chunk.
+        // The matching input position is found from the previous ratio,
+        // since the resampler may have consumed more input than it has output so far.
+        let segment = if let Some(last) = self.segments.last() {
+            if last.ratio != ratio {
+                let output_start = self.output_frames as f64;
+                Some(Segment {
+                    input_start: last.input_start + (output_start - last.output_start) / last.ratio,
+                    output_start,
+                    ratio,
+                })
+            } else {
+                None
+            }
+        } else {
+            Some(Segment {
+                input_start: 0.0,
+                output_start: 0.0,
+                ratio,
+            })
+        };
+        if let Some(segment) = segment {
+            self.segments.push(segment);
+        }
+        self.input_frames += nbr_input_frames as u64;
+        self.output_frames += nbr_output_frames as u64;
+    }
+
+    /// Get the total number of input frames recorded so far.
+    pub fn input_frames(&self) -> u64 {
+        self.input_frames
+    }
+
+    /// Get the total number of output frames recorded so far.
+    pub fn output_frames(&self) -> u64 {
+        self.output_frames
+    }
+
+    /// Map a position in the input to a position in the output.
+    /// Returns `None` if the position has not reached the output yet,
+    /// because the chunks containing it have not been processed, or it is still
+    /// delayed inside the resampler.
+    pub fn map(&self, input_position: f64) -> Option<f64> {
+        if self.segments.is_empty() {
+            return None;
+        }
+        let position = self.map_unchecked(input_position + self.latency.input_frames);
+        if position < self.output_frames as f64 {
+            Some(position)
+        } else {
+            None
+        }
+    }
+
+    /// Map an input position, after adding the latency, with the ratio of the segment
+    /// it belongs to. Positions after the last segment use the last ratio.
+    fn map_unchecked(&self, position: f64) -> f64 {
+        let segment = self
+            .segments
+            .iter()
+            .rev()
+            .find(|segment| segment.input_start <= position)
+            .or_else(|| self.segments.first());
+        if let Some(segment) = segment {
+            segment.output_start + (position - segment.input_start) * segment.ratio
+        } else {
+            position
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::markers::MarkerMapper;
+    use crate::{InterpolationParameters, InterpolationType, WindowFunction};
+    use crate::{Resampler, SincFixedIn};
+
+    fn peak_position(wave: &[f64], around: f64) -> usize {
+        let start = around as usize - 20;
+        let mut peak = start;
+        for idx in start..start + 40 {
+            if wave[idx].abs() > wave[peak].abs() {
+                peak = idx;
+            }
+        }
+        peak
+    }
+
+    #[test]
+    fn map_markers() {
+        let params = InterpolationParameters {
+            sinc_len: 64,
+            f_cutoff: 0.95,
+            interpolation: InterpolationType::Cubic,
+            oversampling_factor: 64,
+            window: WindowFunction::BlackmanHarris2,
+        };
+        let mut resampler = SincFixedIn::<f64>::new(1.5, params, 512, 1).unwrap();
+        let latency = resampler.latency();
+        let mut mapper = MarkerMapper::new(latency);
+        let markers = [700.0, 1800.0, 4000.0];
+        let mut wave = vec![0.0; 512 * 10];
+        for marker in markers.iter() {
+            wave[*marker as usize] = 1.0;
+        }
+        let mut output = Vec::new();
+        let mut ratio = 1.5;
+        for (chunk_idx, chunk) in wave.chunks(512).enumerate() {
+            if chunk_idx == 5 {
+                ratio = 1.6;
+                resampler.set_resample_ratio(ratio).unwrap();
+            }
+            if chunk_idx <= 4000 / 512 {
+                assert_eq!(mapper.map(4000.0), None);
+            }
+            let out = resampler.process(&[chunk.to_vec()]).unwrap();
+            mapper.record_chunk(chunk.len(), out[0].len(), ratio);
+            output.extend_from_slice(&out[0]);
+        }
+        assert_eq!(mapper.input_frames(), 5120);
+        assert_eq!(mapper.output_frames(), output.len() as u64);
+
+        // before the ratio change the mapping is exact
+        for marker in markers[0..2].iter() {
+            let mapped = mapper.map(*marker).unwrap();
+            assert!((mapped - marker * 1.5 - latency.output_frames).abs() < 1.0e-9);
+            let peak = peak_position(&output, mapped);
+            assert!((peak as f64 - mapped).abs() < 1.0);
+        }
+        let mapped = mapper.map(markers[2]).unwrap();
+        let peak = peak_position(&output, mapped);
+        assert!((peak as f64 - mapped).abs() < 1.0);
+        assert_eq!(mapper.map(6000.0), None);
+    }
+
+    #[test]
+    fn count_long_stream() {
+        let latency = SincFixedIn::<f64>::new(
+            1.0,
+            InterpolationParameters {
+                sinc_len: 64,
+                f_cutoff: 0.95,
+                interpolation: InterpolationType::Linear,
+                oversampling_factor: 16,
+                window: WindowFunction::BlackmanHarris2,
+            },
+            512,
+            1,
+        )
+        .unwrap()
+        .latency();
+        let mut mapper = MarkerMapper::new(latency);
+        // more frames than fit in a 32-bit usize
+        for _ in 0..3 {
+            mapper.record_chunk(2_000_000_000, 2_000_000_000, 1.0);
+        }
+        assert_eq!(mapper.input_frames(), 6_000_000_000);
+        assert_eq!(mapper.output_frames(), 6_000_000_000);
+        let mapped = mapper.map(5_000_000_000.0).unwrap();
+        assert!((mapped - 5_000_000_000.0 - latency.output_frames).abs() < 1.0e-3);
+    }
+}
@@ -0,0 +1,291 @@
+use crate::Sample;
+use std::f64::consts::PI;
+
+/// Level measurements of the input of a resampler, as returned by the `metrics` function.
+#[derive(Debug, Clone)]
+pub struct Metrics<T> {
+    /// Peak absolute value of each channel in the last processed chunk.
+    /// Zero when peak metering is disabled.
+    pub peak: Vec<T>,
+    /// RMS value of each channel in the last processed chunk.
+    /// Zero when RMS metering is disabled.
+    pub rms: Vec<T>,
+    /// K-weighted loudness in LUFS, accumulated over all chunks since the loudness metering was enabled.
+    /// This is a simple ungated measurement where all channels have the same weight.
+    /// `None` when loudness metering is disabled, or if nothing has been measured yet.
+    pub loudness: Option<f64>,
+}
+
+/// A second order IIR filter section, in transposed direct form II.
+#[derive(Clone)]
+struct Biquad {
+    b0: f64,
+    b1: f64,
+    b2: f64,
+    a1: f64,
+    a2: f64,
+    s1: f64,
+    s2: f64,
+}
+
+impl Biquad {
+    fn process(&mut self, x: f64) -> f64 {
+        let y = self.b0 * x + self.s1;
+        self.s1 = self.b1 * x - self.a1 * y + self.s2;
+        self.s2 = self.b2 * x - self.a2 * y;
+        y
+    }
+}
+
+/// The K-weighting filter of ITU-R BS.1770, a high shelf followed by a high pass filter.
+#[derive(Clone)]
+struct KWeighting {
+    shelf: Biquad,
+    highpass: Biquad,
+}
+
+impl KWeighting {
+    fn new(sample_rate: f64) -> Self {
+        let f0 = 1681.974450955533;
+        let gain = 3.999843853973347;
+        let q = 0.7071752369554196;
+        let k = (PI * f0 / sample_rate).tan();
+        let vh = 10.0f64.powf(gain / 20.0);
+        let vb = vh.powf(0.4996667741545416);
+        let a0 = 1.0 + k / q + k * k;
+        let shelf = Biquad {
+            b0: (vh + vb * k / q + k * k) / a0,
+            b1: 2.0 * (k * k - vh) / a0,
+            b2: (vh - vb * k / q + k * k) / a0,
+            a1: 2.0 * (k * k - 1.0) / a0,
+            a2: (1.0 - k / q + k * k) / a0,
+            s1: 0.0,
+            s2: 0.0,
+        };
+
+        let f0 = 38.13547087602444;
+        let q = 0.5003270373238773;
+        let k = (PI * f0 / sample_rate).tan();
+        let a0 = 1.0 + k / q + k * k;
+        let highpass = Biquad {
+            b0: 1.0,
+            b1: -2.0,
+            b2: 1.0,
+            a1: 2.0 * (k * k - 1.0) / a0,
+            a2: (1.0 - k / q + k * k) / a0,
+            s1: 0.0,
+            s2: 0.0,
+        };
+        KWeighting { shelf, highpass }
+    }
+
+    fn process(&mut self, x: f64) -> f64 {
+        self.highpass.process(self.shelf.process(x))
+    }
+}
+
+/// Per-channel level meter for the input of a resampler.
+/// The levels are measured while the input samples are copied into the internal buffers,
+/// to avoid an extra pass over the data.
+#[derive(Clone)]
+pub(crate) struct Meter<T> {
+    peak_enabled: bool,
+    rms_enabled: bool,
+    k_filters: Option<Vec<KWeighting>>,
+    loudness_sums: Vec<f64>,
+    loudness_frames: Vec<u64>,
+    metrics: Metrics<T>,
+}
+
+impl<T> Meter<T>
+where
+    T: Sample,
+{
+    /// Create a new meter, with all measurements disabled.
+    pub fn new(nbr_channels: usize) -> Self {
+        Meter {
+            peak_enabled: false,
+            rms_enabled: false,
+            k_filters: None,
+            loudness_sums: vec![0.0; nbr_channels],
+            loudness_frames: vec![0; nbr_channels],
+            metrics: Metrics {
+                peak: vec![T::zero(); nbr_channels],
+                rms: vec![T::zero(); nbr_channels],
+                loudness: None,
+            },
+        }
+    }
+
+    /// Enable or disable the peak meter.
+    pub fn set_peak_enabled(&mut self, enabled: bool) {
+        self.peak_enabled = enabled;
+        self.clear();
+    }
+
+    /// Enable or disable the RMS meter.
+    pub fn set_rms_enabled(&mut self, enabled: bool) {
+        self.rms_enabled = enabled;
+        self.clear();
+    }
+
+    /// Enable the loudness meter for the given sample rate, or disable it with `None`.
+    /// This also resets the accumulated loudness.
+    pub fn set_loudness_sample_rate(&mut self, sample_rate: Option<f64>) {
+        let nbr_channels = self.loudness_sums.len();
+        self.k_filters =
+            sample_rate.map(|rate| (0..nbr_channels).map(|_| KWeighting::new(rate)).collect());
+        for (sum, frames) in self
+            .loudness_sums
+            .iter_mut()
+            .zip(self.loudness_frames.iter_mut())
+        {
+            *sum = 0.0;
+            *frames = 0;
+        }
+        self.metrics.loudness = None;
+    }
+
+    /// Get the peak values of the last measured chunk, one per channel.
+    pub fn peaks(&self) -> &[T] {
+        &self.metrics.peak
+    }
+
+    /// Get all measurements.
+    pub fn metrics(&self) -> &Metrics<T> {
+        &self.metrics
+    }
+
+    /// Clear the per-chunk measurements before measuring a new chunk.
+    pub fn clear(&mut self) {
+        for (peak, rms) in self
+            .metrics
+            .peak
+            .iter_mut()
+            .zip(self.metrics.rms.iter_mut())
+        {
+            *peak = T::zero();
+            *rms = T::zero();
+        }
+    }
+
+    /// Check if any measurement is enabled.
+    fn is_enabled(&self) -> bool {
+        self.peak_enabled || self.rms_enabled || self.k_filters.is_some()
+    }
+
+    /// Measure a single sample, updating the peak and the sums of squares.
+    fn measure_sample(&mut self, chan: usize, sample: T, peak: &mut T, sum_sq: &mut f64) {
+        if self.peak_enabled {
+            let value = sample.abs();
+            if value > *peak {
+                *peak = value;
+            }
+        }
+        if self.rms_enabled {
+            let value = sample.to_f64().unwrap_or(0.0);
+            *sum_sq += value * value;
+        }
+        if let Some(filters) = self.k_filters.as_mut() {
+            let value = filters[chan].process(sample.to_f64().unwrap_or(0.0));
+            self.loudness_sums[chan] += value * value;
+        }
+    }
+
+    /// Store the results of measuring a chunk of `nbr_frames` frames.
+    fn store(&mut self, chan: usize, nbr_frames: usize, peak: T, sum_sq: f64) {
+        self.metrics.peak[chan] = peak;
+        if self.rms_enabled && nbr_frames > 0 {
+            self.metrics.rms[chan] = T::coerce((sum_sq / nbr_frames as f64).sqrt());
+        }
+        if self.k_filters.is_some() {
+            self.loudness_frames[chan] += nbr_frames as u64;
+            let power: f64 = self
+                .loudness_sums
+                .iter()
+                .zip(self.loudness_frames.iter())
+                .filter(|(_, frames)| **frames > 0)
+                .map(|(sum, frames)| sum / *frames as f64)
+                .sum();
+            if power > 0.0 {
+                self.metrics.loudness = Some(-0.691 + 10.0 * power.log10());
+            }
+        }
+    }
+
+    /// Measure a chunk of a channel.
+    pub fn measure(&mut self, chan: usize, wave: &[T]) {
+        if self.is_enabled() {
+            let mut peak = T::zero();
+            let mut sum_sq = 0.0;
+            for sample in wave.iter() {
+                self.measure_sample(chan, *sample, &mut peak, &mut sum_sq);
+            }
+            self.store(chan, wave.len(), peak, sum_sq);
+        }
+    }
+
+    /// Copy a chunk of a channel from `wave_in` to `buffer`, and measure it on the way.
+    pub fn copy_and_measure(&mut self, chan: usize, wave_in: &[T], buffer: &mut [T]) {
+        if self.is_enabled() {
+            let mut peak = T::zero();
+            let mut sum_sq = 0.0;
+            for (sample, buf) in wave_in.iter().zip(buffer.iter_mut()) {
+                *buf = *sample;
+                self.measure_sample(chan, *sample, &mut peak, &mut sum_sq);
+            }
+            self.store(chan, wave_in.len(), peak, sum_sq);
+        } else {
+            buffer.copy_from_slice(wave_in);
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::metering::Meter;
+
+    #[test]
+    fn peaks() {
+        let mut meter = Meter::<f64>::new(2);
+        let wave = vec![0.1, -0.7, 0.5];
+        let mut buffer = vec![0.0; 3];
+        meter.copy_and_measure(0, &wave, &mut buffer);
+        assert_eq!(buffer, wave);
+        assert_eq!(meter.peaks(), &[0.0, 0.0]);
+        meter.set_peak_enabled(true);
+        meter.copy_and_measure(0, &wave, &mut buffer);
+        meter.measure(1, &[0.2, 0.3]);
+        assert_eq!(meter.peaks(), &[0.7, 0.3]);
+        meter.clear();
+        assert_eq!(meter.peaks(), &[0.0, 0.0]);
+    }
+
+    #[test]
+    fn rms() {
+        let mut meter = Meter::<f32>::new(1);
+        meter.set_rms_enabled(true);
+        meter.measure(0, &[0.5, -0.5, 0.5, -0.5]);
+        assert!((meter.metrics().rms[0] - 0.5).abs() < 1.0e-6);
+        assert_eq!(meter.metrics().peak[0], 0.0);
+    }
+
+    #[test]
+    fn loudness() {
+        // A full scale 997 Hz sine in one channel should read -3.01 LUFS
+        let mut meter = Meter::<f64>::new(2);
+        assert!(meter.metrics().loudness.is_none());
+        meter.set_loudness_sample_rate(Some(48000.0));
+        let wave: Vec<f64> = (0..48000)
+            .map(|n| (2.0 * std::f64::consts::PI * 997.0 * n as f64 / 48000.0).sin())
+            .collect();
+        for chunk in wave.chunks(1000) {
+            meter.measure(0, chunk);
+            meter.measure(1, &vec![0.0; chunk.len()]);
+        }
+        let loudness = meter.metrics().loudness.unwrap();
+        assert!((loudness + 3.01).abs() < 0.05, "{}", loudness);
+        meter.set_loudness_sample_rate(None);
+        assert!(meter.metrics().loudness.is_none());
+    }
+}
@@ -0,0 +1,518 @@
+use crate::error::{ResampleError, ResampleResult};
+use crate::fft::{check_micro_fft_len, micro_cfft, micro_ifft};
+use crate::offline;
+use crate::sinc::sinc;
+use crate::{Latency, Resampler};
+use microfft::Complex32;
+use num_integer as integer;
+use std::f64::consts::PI;
+use std::fmt;
+
+/// A synchronous resampler for embedded targets, using the FFTs of the `microfft` crate.
+///
+/// The FFT length and the number of channels are given as const generic parameters,
+/// so that all the state is held in fixed size arrays inside the struct.
+/// Creating the resampler and processing don't allocate any memory,
+/// except for the vectors returned by "process" and the errors.
+/// Use "process_into_slices" to process without any vectors at all.
+/// The state takes `4 * FFT_LEN * (CHANNELS + 5)` bytes,
+/// which is 7 kB for two channels with an FFT length of 256.
+///
+/// The input is upsampled to a common multiple of the sample rates by inserting zeros,
+/// filtered by FFT convolution with the overlap-save method, and decimated to the output rate.
+/// Two channels are filtered by each complex FFT, one in the real and one in the imaginary part.
+/// The product of the sample rates divided by their greatest common divisor
+/// must be at most half the FFT length. This covers the common embedded pairs
+/// like 16 and 48 kHz, 8 and 16 kHz, or 32 and 48 kHz, but not 44.1 and 48 kHz.
+/// The anti-aliasing filter is a Blackman windowed sinc, as long as the FFT length allows,
+/// so a longer FFT gives a sharper filter, as well as longer chunks.
+///
+/// The numbers of input and output frames are fixed, like for `FftFixedInOut`.
+pub struct MicroFftResampler<const FFT_LEN: usize, const CHANNELS: usize> {
+    fs_in: usize,
+    fs_out: usize,
+    up: usize,
+    down: usize,
+    hop: usize,
+    filter_len: usize,
+    filter: [Complex32; FFT_LEN],
+    history: [[f32; FFT_LEN]; CHANNELS],
+    work: [Complex32; FFT_LEN],
+    output: [f32; FFT_LEN],
+}
+
+impl<const FFT_LEN: usize, const CHANNELS: usize> MicroFftResampler<FFT_LEN, CHANNELS> {
+    /// Create a new MicroFftResampler.
+    ///
+    /// Parameters are:
+    /// - `fs_in`: input sample rate.
+    /// - `fs_out`: output sample rate.
+    ///
+    /// # Errors
+    ///
+    /// The function returns `ResampleError::InvalidParameter` if a sample rate or the number
+    /// of channels is zero, if `microfft` has no FFT of the length `FFT_LEN`,
+    /// or if the FFT is too short for the ratio of the sample rates.
+    pub fn new(fs_in: usize, fs_out: usize) -> ResampleResult<Self> {
+        ResampleError::check(fs_in > 0, "fs_in", fs_in, "must be larger than zero")?;
+        ResampleError::check(fs_out > 0, "fs_out", fs_out, "must be larger than zero")?;
+        ResampleError::check(
+            CHANNELS > 0,
+            "nbr_channels",
+            CHANNELS,
+            "must be larger than zero",
+        )?;
+        check_micro_fft_len(FFT_LEN)?;
+        debug!(
+            "Create new MicroFftResampler, fs_in: {}, fs_out: {}, fft_len: {}, channels: {}",
+            fs_in, fs_out, FFT_LEN, CHANNELS
+        );
+
+        let gcd = integer::gcd(fs_in, fs_out);
+        let up = fs_out / gcd;
+        let down = fs_in / gcd;
+        // the hop is a multiple of both factors,
+        // so that the inserted zeros and the decimation line up in every FFT
+        let hop = FFT_LEN / 2 / (up * down) * (up * down);
+        ResampleError::check(
+            hop > 0,
+            "FFT length",
+            FFT_LEN,
+            "must be at least twice the product of the reduced sample rates",
+        )?;
+        let filter_len = FFT_LEN - hop + 1;
+        // place the transition band of the window just below the lowest Nyquist frequency
+        let band = 1.0 / up.max(down) as f64;
+        let cutoff = band - 5.5 / filter_len as f64;
+        ResampleError::check(
+            cutoff > 0.5 * band,
+            "FFT length",
+            FFT_LEN,
+            "is too short for the ratio of the sample rates",
+        )?;
+
+        let mut filter = [Complex32::new(0.0, 0.0); FFT_LEN];
+        let center = (filter_len - 1) as f64 / 2.0;
+        let mut sum = 0.0;
+        for (n, value) in filter.iter_mut().take(filter_len).enumerate() {
+            let phase = 2.0 * PI * n as f64 / (filter_len - 1) as f64;
+            let window = 0.42 - 0.5 * phase.cos() + 0.08 * (2.0 * phase).cos();
+            let tap = cutoff * sinc(cutoff * (n as f64 - center)) * window;
+            value.re = tap as f32;
+            sum += tap;
+        }
+        // the gain makes up for the zeros inserted when upsampling
+        let gain = (up as f64 / sum) as f32;
+        for value in filter.iter_mut() {
+            value.re *= gain;
+        }
+        micro_cfft(&mut filter);
+
+        Ok(MicroFftResampler {
+            fs_in,
+            fs_out,
+            up,
+            down,
+            hop,
+            filter_len,
+            filter,
+            history: [[0.0; FFT_LEN]; CHANNELS],
+            work: [Complex32::new(0.0, 0.0); FFT_LEN],
+            output: [0.0; FFT_LEN],
+        })
+    }
+
+    /// Get the latency of the resampler.
+    /// This is the delay of the anti-aliasing filter, which is half of its length.
+    pub fn latency(&self) -> Latency {
+        let delay = (self.filter_len - 1) as f64 / 2.0;
+        Latency {
+            input_frames: delay / self.up as f64,
+            output_frames: delay / self.down as f64,
+        }
+    }
+
+    /// Resample a chunk of audio, given as one slice per channel,
+    /// and write the output to the slices in `wave_out`, starting at the first frame of each slice.
+    /// Returns the number of frames written.
+    /// Empty input slices are ignored like in "process", and the output slices
+    /// of those channels are left untouched.
+    /// This doesn't use any vectors, so it also works where the application has no allocator.
+    ///
+    /// # Errors
+    ///
+    /// The function returns an error if the number of channels doesn't match,
+    /// if an input slice doesn't hold the number of frames given by "nbr_frames_needed",
+    /// or if an output slice is shorter than the number given by "nbr_output_frames_next".
+    pub fn process_into_slices(
+        &mut self,
+        wave_in: &[&[f32]],
+        wave_out: &mut [&mut [f32]],
+    ) -> ResampleResult<usize> {
+        self.check_input(wave_in)?;
+        let frames_out = self.hop / self.down;
+        if wave_out.len() != CHANNELS {
+            return Err(ResampleError::WrongNumberOfChannels {
+                expected: CHANNELS,
+                actual: wave_out.len(),
+                resampler: format!("{:?}", self),
+            });
+        }
+        for (chan, (wave, buffer)) in wave_in.iter().zip(wave_out.iter()).enumerate() {
+            if !wave.is_empty() && buffer.len() < frames_out {
+                return Err(ResampleError::WrongNumberOfFrames {
+                    channel: chan,
+                    expected: frames_out,
+                    actual: buffer.len(),
+                    resampler: format!("{:?}", self),
+                });
+            }
+        }
+        self.process_chunk(wave_in, &mut |chan, frames| {
+            wave_out[chan][..frames.len()].copy_from_slice(frames)
+        });
+        Ok(frames_out)
+    }
+
+    /// Check that there is one slice per channel, and that the non-empty ones hold one chunk.
+    fn check_input(&self, wave_in: &[&[f32]]) -> ResampleResult<()> {
+        if wave_in.len() != CHANNELS {
+            return Err(ResampleError::WrongNumberOfChannels {
+                expected: CHANNELS,
+                actual: wave_in.len(),
+                resampler: format!("{:?}", self),
+            });
+        }
+        for (chan, wave) in wave_in.iter().enumerate() {
+            if !wave.is_empty() && wave.len() != self.hop / self.up {
+                return Err(ResampleError::WrongNumberOfFrames {
+                    channel: chan,
+                    expected: self.hop / self.up,
+                    actual: wave.len(),
+                    resampler: format!("{:?}", self),
+                });
+            }
+        }
+        Ok(())
+    }
+
+    /// Resample the non-empty channels of a checked chunk,
+    /// and pass the output of each channel to `f`, in the order of the channels.
+    fn process_chunk(&mut self, wave_in: &[&[f32]], f: &mut dyn FnMut(usize, &[f32])) {
+        let offset = FFT_LEN - self.hop;
+        let frames_out = self.hop / self.down;
+        for (wave, history) in wave_in.iter().zip(self.history.iter_mut()) {
+            if !wave.is_empty() {
+                history.copy_within(self.hop.., 0);
+                let new_values = &mut history[offset..];
+                for value in new_values.iter_mut() {
+                    *value = 0.0;
+                }
+                for (value, sample) in new_values.iter_mut().step_by(self.up).zip(wave.iter()) {
+                    *value = *sample;
+                }
+            }
+        }
+        for first in (0..CHANNELS).step_by(2) {
+            let second = first + 1;
+            let use_first = !wave_in[first].is_empty();
+            let use_second = second < CHANNELS && !wave_in[second].is_empty();
+            if use_first || use_second {
+                for (n, value) in self.work.iter_mut().enumerate() {
+                    let re = if use_first {
+                        self.history[first][n]
+                    } else {
+                        0.0
+                    };
+                    let im = if use_second {
+                        self.history[second][n]
+                    } else {
+                        0.0
+                    };
+                    *value = Complex32::new(re, im);
+                }
+                micro_cfft(&mut self.work);
+                for (value, filter) in self.work.iter_mut().zip(self.filter.iter()) {
+                    *value *= *filter;
+                }
+                micro_ifft(&mut self.work);
+                // the filter is real, so the two channels stay in the real and imaginary parts
+                let (output_first, output_second) = self.output.split_at_mut(FFT_LEN / 2);
+                for ((value, first), second) in self.work[offset..]
+                    .iter()
+                    .step_by(self.down)
+                    .zip(output_first.iter_mut())
+                    .zip(output_second.iter_mut())
+                {
+                    *first = value.re;
+                    *second = value.im;
+                }
+            }
+            f(
+                first,
+                if use_first {
+                    &self.output[..frames_out]
+                } else {
+                    &[]
+                },
+            );
+            if second < CHANNELS {
+                let start = FFT_LEN / 2;
+                f(
+                    second,
+                    if use_second {
+                        &self.output[start..(start + frames_out)]
+                    } else {
+                        &[]
+                    },
+                );
+            }
+        }
+    }
+}
+
+/// Shows the configuration, without the filter and the contents of the buffers.
+impl<const FFT_LEN: usize, const CHANNELS: usize> fmt::Debug
+    for MicroFftResampler<FFT_LEN, CHANNELS>
+{
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        f.debug_struct("MicroFftResampler")
+            .field("fs_in", &self.fs_in)
+            .field("fs_out", &self.fs_out)
+            .field("fft_len", &FFT_LEN)
+            .field("nbr_channels", &CHANNELS)
+            .field("chunk_size_in", &(self.hop / self.up))
+            .field("chunk_size_out", &(self.hop / self.down))
+            .finish()
+    }
+}
+
+impl<const FFT_LEN: usize, const CHANNELS: usize> Resampler<f32>
+    for MicroFftResampler<FFT_LEN, CHANNELS>
+{
+    fn output_delay(&self) -> f64 {
+        self.latency().output_frames
+    }
+
+    /// Clear the history of the channels, see "reset" of the `Resampler` trait.
+    /// The filter is kept.
+    fn reset(&mut self) {
+        for value in self.history.iter_mut().flatten() {
+            *value = 0.0;
+        }
+    }
+
+    /// Emit the output frames that are held back at the end of a stream, see "flush"
+    /// of the `Resampler` trait. These are the latency of the resampler.
+    fn flush(&mut self) -> ResampleResult<Vec<Vec<f32>>> {
+        let nbr_frames = self.latency().output_frames.ceil() as usize;
+        offline::flush_with_silence(self, CHANNELS, nbr_frames)
+    }
+
+    /// Query for the number of frames needed for the next call to "process".
+    fn nbr_frames_needed(&self) -> usize {
+        self.hop / self.up
+    }
+
+    /// Query for the number of frames that the next call to "process" will return.
+    fn nbr_output_frames_next(&self) -> usize {
+        self.hop / self.down
+    }
+
+    /// Resample a chunk of audio. The input and output lengths are fixed.
+    /// If the waveform for a channel is empty, this channel will be ignored and produce a
+    /// corresponding empty output waveform.
+    /// # Errors
+    ///
+    /// The function returns an error if the size of the input data is not equal
+    /// to the number of channels and input size of the resampler.
+    fn process(&mut self, wave_in: &[Vec<f32>]) -> ResampleResult<Vec<Vec<f32>>> {
+        let mut wave_out = vec![Vec::new(); CHANNELS];
+        self.process_with(wave_in, &mut |chan, frames| {
+            wave_out[chan].extend_from_slice(frames)
+        })?;
+        Ok(wave_out)
+    }
+
+    /// Resample a chunk of audio like "process", but pass the output of each channel
+    /// to the closure `f` instead of returning it.
+    /// The output is taken directly from an internal buffer, without allocating any output vectors.
+    fn process_with(
+        &mut self,
+        wave_in: &[Vec<f32>],
+        f: &mut dyn FnMut(usize, &[f32]),
+    ) -> ResampleResult<()> {
+        if wave_in.len() != CHANNELS {
+            return Err(ResampleError::WrongNumberOfChannels {
+                expected: CHANNELS,
+                actual: wave_in.len(),
+                resampler: format!("{:?}", self),
+            });
+        }
+        let mut slices: [&[f32]; CHANNELS] = [&[]; CHANNELS];
+        for (slice, wave) in slices.iter_mut().zip(wave_in.iter()) {
+            *slice = wave;
+        }
+        self.check_input(&slices)?;
+        self.process_chunk(&slices, f);
+        Ok(())
+    }
+
+    /// Resample a chunk of audio like "process", with the input given as one slice per channel.
+    /// The slices are read directly, without copying them to vectors.
+    fn process_slices(&mut self, wave_in: &[&[f32]]) -> ResampleResult<Vec<Vec<f32>>> {
+        self.check_input(wave_in)?;
+        let mut wave_out = vec![Vec::new(); CHANNELS];
+        self.process_chunk(wave_in, &mut |chan, frames| {
+            wave_out[chan].extend_from_slice(frames)
+        });
+        Ok(wave_out)
+    }
+
+    /// Update the resample ratio. This is not supported by this resampler and
+    /// always returns an error.
+    fn set_resample_ratio(&mut self, _new_ratio: f64) -> ResampleResult<()> {
+        Err(ResampleError::SyncNotAdjustable)
+    }
+
+    /// Update the resample ratio relative to the original one. This is not
+    /// supported by this resampler and always returns an error.
+    fn set_resample_ratio_relative(&mut self, _rel_ratio: f64) -> ResampleResult<()> {
+        Err(ResampleError::SyncNotAdjustable)
+    }
+
+    fn resample_ratio(&self) -> f64 {
+        self.fs_out as f64 / self.fs_in as f64
+    }
+
+    fn resample_ratio_original(&self) -> f64 {
+        self.resample_ratio()
+    }
+
+    fn chunk_size(&self) -> usize {
+        self.hop / self.up
+    }
+
+    fn nbr_channels(&self) -> usize {
+        CHANNELS
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::micro::MicroFftResampler;
+    use crate::Resampler;
+    use std::f64::consts::PI;
+
+    /// Resample a tone of the frequency `freq` relative to the input sample rate,
+    /// and return the peak amplitude of the output after the transient,
+    /// together with the largest deviation from the ideal delayed tone.
+    fn resample_tone<const FFT_LEN: usize>(fs_in: usize, fs_out: usize, freq: f64) -> (f64, f64) {
+        let mut resampler = MicroFftResampler::<FFT_LEN, 1>::new(fs_in, fs_out).unwrap();
+        let ratio = fs_out as f64 / fs_in as f64;
+        let delay = resampler.output_delay();
+        let chunk = resampler.nbr_frames_needed();
+        let mut peak: f64 = 0.0;
+        let mut error: f64 = 0.0;
+        let mut frames_out = 0;
+        for n in 0..(8192 / chunk) {
+            let wave: Vec<f32> = (0..chunk)
+                .map(|m| (2.0 * PI * freq * (n * chunk + m) as f64).sin() as f32)
+                .collect();
+            for value in resampler.process(&[wave]).unwrap()[0].iter() {
+                if frames_out as f64 > delay + 2048.0 * ratio {
+                    let time = (frames_out as f64 - delay) / ratio;
+                    let expected = (2.0 * PI * freq * time).sin();
+                    peak = peak.max((*value as f64).abs());
+                    error = error.max((*value as f64 - expected).abs());
+                }
+                frames_out += 1;
+            }
+        }
+        (peak, error)
+    }
+
+    #[test]
+    fn upsample_16_to_48() {
+        let resampler = MicroFftResampler::<256, 2>::new(16000, 48000).unwrap();
+        assert_eq!(resampler.nbr_frames_needed(), 42);
+        assert_eq!(resampler.nbr_output_frames_next(), 126);
+        let (_, error) = resample_tone::<256>(16000, 48000, 0.1);
+        assert!(error < 1.0e-3, "error {}", error);
+    }
+
+    #[test]
+    fn downsample_48_to_16() {
+        let (_, error) = resample_tone::<512>(48000, 16000, 0.03);
+        assert!(error < 1.0e-3, "error {}", error);
+        // a tone above the output Nyquist frequency is removed
+        let (peak, _) = resample_tone::<512>(48000, 16000, 0.25);
+        assert!(peak < 1.0e-3, "peak {}", peak);
+    }
+
+    #[test]
+    fn fits_in_a_few_kilobytes() {
+        assert_eq!(
+            std::mem::size_of::<MicroFftResampler<256, 2>>(),
+            7 * 1024 + 48
+        );
+    }
+
+    #[test]
+    fn channels_are_independent() {
+        // three channels, so that the last one is filtered without a pair
+        let mut resampler = MicroFftResampler::<256, 3>::new(32000, 48000).unwrap();
+        let chunk = resampler.nbr_frames_needed();
+        let mut single = MicroFftResampler::<256, 1>::new(32000, 48000).unwrap();
+        for n in 0..4 {
+            let tone: Vec<f32> = (0..chunk)
+                .map(|m| ((n * chunk + m) as f32 * 0.3).sin())
+                .collect();
+            let noise: Vec<f32> = (0..chunk).map(|m| ((m * 7919) % 13) as f32 * 0.1).collect();
+            let wave_out = resampler
+                .process(&[tone.clone(), noise, tone.clone()])
+                .unwrap();
+            let expected = single.process(&[tone]).unwrap();
+            for (first, third) in wave_out[0].iter().zip(wave_out[2].iter()) {
+                assert!((first - third).abs() < 1.0e-5);
+            }
+            for (value, expected) in wave_out[0].iter().zip(expected[0].iter()) {
+                assert!((value - expected).abs() < 1.0e-5);
+            }
+        }
+    }
+
+    #[test]
+    fn process_into_slices() {
+        let mut resampler = MicroFftResampler::<256, 2>::new(48000, 16000).unwrap();
+        let mut reference = MicroFftResampler::<256, 2>::new(48000, 16000).unwrap();
+        let wave: [f32; 126] = [0.5; 126];
+        let mut first = [0.0; 64];
+        let mut second = [0.0; 64];
+        let frames = resampler
+            .process_into_slices(&[&wave, &[]], &mut [&mut first, &mut second])
+            .unwrap();
+        assert_eq!(frames, 42);
+        let expected = reference.process(&[wave.to_vec(), Vec::new()]).unwrap();
+        assert_eq!(&first[..42], &expected[0][..]);
+        assert!(expected[1].is_empty());
+        assert!(second.iter().all(|value| *value == 0.0));
+        assert!(resampler
+            .process_into_slices(&[&wave, &wave], &mut [&mut first, &mut second[..20]])
+            .is_err());
+        assert!(resampler
+            .process_into_slices(&[&wave], &mut [&mut first])
+            .is_err());
+    }
+
+    #[test]
+    fn unsupported_ratios() {
+        assert!(MicroFftResampler::<256, 2>::new(44100, 48000).is_err());
+        assert!(MicroFftResampler::<4096, 2>::new(44100, 48000).is_err());
+        assert!(MicroFftResampler::<100, 2>::new(16000, 48000).is_err());
+        assert!(MicroFftResampler::<256, 0>::new(16000, 48000).is_err());
+        let mut resampler = MicroFftResampler::<256, 2>::new(16000, 48000).unwrap();
+        assert!(resampler.set_resample_ratio(3.1).is_err());
+        assert!(resampler.process(&[vec![0.0; 41], vec![0.0; 41]]).is_err());
+    }
+}
@@ -0,0 +1,160 @@
+use std::f64::consts::PI;
+
+/// Waveform of a low frequency oscillator.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum LfoWaveform {
+    /// A sine wave, giving a regular and periodic speed variation.
+    Sine,
+    /// A smooth random walk. A new random point is picked once per period,
+    /// by taking a random step from the previous point,
+    /// and the oscillator glides between the points with cosine interpolation.
+    RandomWalk,
+}
+
+/// A low frequency oscillator used to modulate the resample ratio of an asynchronous resampler.
+/// This can be used to simulate the speed instabilities of analog tape and vinyl,
+/// often called wow (slow variations, below a few Hz) and flutter (faster variations).
+///
+/// The oscillator is advanced once per output frame,
+/// and scales the resample ratio by a factor of `2^(depth_cents * value / 1200)`,
+/// where `value` is the current oscillator value in the range -1 to +1.
+#[derive(Debug, Clone)]
+pub struct Lfo {
+    waveform: LfoWaveform,
+    rate: f64,
+    depth_cents: f64,
+    sample_rate: f64,
+    phase: f64,
+    seed: u64,
+    rng_state: u64,
+    previous_point: f64,
+    next_point: f64,
+}
+
+impl Lfo {
+    /// Create a new low frequency oscillator.
+    ///
+    /// Parameters are:
+    /// - `waveform`: Shape of the modulation.
+    /// - `rate`: Modulation rate in Hz.
+    /// - `depth_cents`: Peak deviation from the nominal speed, in cents.
+    /// - `sample_rate`: Output sample rate of the resampler, used to convert the rate to frames.
+    pub fn new(waveform: LfoWaveform, rate: f64, depth_cents: f64, sample_rate: f64) -> Self {
+        let mut lfo = Lfo {
+            waveform,
+            rate,
+            depth_cents,
+            sample_rate,
+            phase: 0.0,
+            seed: 0x2545_f491_4f6c_dd1d,
+            rng_state: 0,
+            previous_point: 0.0,
+            next_point: 0.0,
+        };
+        lfo.reset();
+        lfo
+    }
+
+    /// Set the seed of the random number generator used by the random walk, and reset the oscillator.
+    /// Two oscillators with the same settings and seed produce the same modulation.
+    pub fn set_seed(&mut self, seed: u64) {
+        self.seed = seed;
+        self.reset();
+    }
+
+    /// Reset the oscillator to its starting state.
+    pub fn reset(&mut self) {
+        self.phase = 0.0;
+        // xorshift must not be seeded with zero
+        self.rng_state = self.seed.max(1);
+        self.previous_point = 0.0;
+        self.next_point = self.random_step(0.0);
+    }
+
+    /// Get the current value of the oscillator, in the range -1 to +1.
+    fn value(&self) -> f64 {
+        match self.waveform {
+            LfoWaveform::Sine => (2.0 * PI * self.phase).sin(),
+            LfoWaveform::RandomWalk => {
+                let mix = 0.5 - 0.5 * (PI * self.phase).cos();
+                self.previous_point + mix * (self.next_point - self.previous_point)
+            }
+        }
+    }
+
+    /// Get the factor to scale the resample ratio with for the current frame.
+    pub fn factor(&self) -> f64 {
+        2.0f64.powf(self.depth_cents * self.value() / 1200.0)
+    }
+
+    /// Advance the oscillator by a number of frames.
+    pub fn advance(&mut self, nbr_frames: usize) {
+        self.phase += nbr_frames as f64 * self.rate / self.sample_rate;
+        while self.phase >= 1.0 {
+            self.phase -= 1.0;
+            if self.waveform == LfoWaveform::RandomWalk {
+                self.previous_point = self.next_point;
+                self.next_point = self.random_step(self.previous_point);
+            }
+        }
+    }
+
+    /// Calculate the ratio factors for the next `nbr_frames` frames, without advancing the oscillator.
+    pub(crate) fn factors(&self, nbr_frames: usize) -> impl Iterator<Item = f64> {
+        let mut lfo = self.clone();
+        (0..nbr_frames).map(move |_| {
+            let factor = lfo.factor();
+            lfo.advance(1);
+            factor
+        })
+    }
+
+    /// Take a random step of at most +-1 from the given point, staying within -1 to +1.
+    fn random_step(&mut self, point: f64) -> f64 {
+        self.rng_state ^= self.rng_state << 13;
+        self.rng_state ^= self.rng_state >> 7;
+        self.rng_state ^= self.rng_state << 17;
+        let step = 2.0 * (self.rng_state >> 11) as f64 / (1u64 << 53) as f64 - 1.0;
+        let next = point + step;
+        // reflect at the limits
+        if next > 1.0 {
+            2.0 - next
+        } else if next < -1.0 {
+            -2.0 - next
+        } else {
+            next
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::modulation::{Lfo, LfoWaveform};
+
+    #[test]
+    fn sine_lfo() {
+        let lfo = Lfo::new(LfoWaveform::Sine, 10.0, 100.0, 1000.0);
+        let factors: Vec<f64> = lfo.factors(100).collect();
+        assert!((factors[0] - 1.0).abs() < 1.0e-12);
+        assert!((factors[25] - 2.0f64.powf(100.0 / 1200.0)).abs() < 1.0e-9);
+        assert!((factors[75] - 2.0f64.powf(-100.0 / 1200.0)).abs() < 1.0e-9);
+    }
+
+    #[test]
+    fn random_walk_lfo() {
+        let mut lfo = Lfo::new(LfoWaveform::RandomWalk, 5.0, 50.0, 1000.0);
+        let max_factor = 2.0f64.powf(50.0 / 1200.0);
+        let factors: Vec<f64> = lfo.factors(2000).collect();
+        assert!(factors
+            .iter()
+            .all(|f| *f <= max_factor && *f >= 1.0 / max_factor));
+        assert!(factors
+            .windows(2)
+            .all(|pair| (pair[1] - pair[0]).abs() < 0.001));
+        lfo.advance(2000);
+        lfo.reset();
+        assert_eq!(lfo.factors(2000).collect::<Vec<f64>>(), factors);
+        lfo.set_seed(123);
+        assert_ne!(lfo.factors(2000).collect::<Vec<f64>>(), factors);
+    }
+}
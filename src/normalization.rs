@@ -0,0 +1,218 @@
+use crate::Sample;
+
+/// The level that the output is normalized to.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum NormalizationTarget {
+    /// Normalize the peak level to the given value.
+    Peak(f64),
+    /// Normalize the RMS level to the given value.
+    Rms(f64),
+}
+
+/// Parameters for the automatic gain of the output.
+#[derive(Debug, Clone, Copy)]
+pub struct NormalizationParameters {
+    /// The level to normalize to.
+    pub target: NormalizationTarget,
+    /// Time constant of the level measurement and gain changes, in output frames.
+    /// Use a long time constant, of at least a few chunks, to avoid audible pumping.
+    pub time_constant: usize,
+    /// Maximum gain. This prevents silence and noise from being amplified too much.
+    pub max_gain: f64,
+}
+
+/// Automatic gain stage for the output of a resampler.
+/// The gain is applied to each chunk after it has been resampled,
+/// and ramps linearly over the chunk to avoid steps.
+/// The level of each chunk is used to update the gain for the next one.
+/// All channels use the same gain, to keep the balance between them.
+#[derive(Clone)]
+pub(crate) struct Normalizer<T> {
+    parameters: Option<NormalizationParameters>,
+    level: f64,
+    gain: f64,
+    next_gain: f64,
+    gain_step: T,
+    gain_start: T,
+    chunk_peak: T,
+    chunk_sum_sq: f64,
+    chunk_samples: usize,
+}
+
+impl<T> Normalizer<T>
+where
+    T: Sample,
+{
+    /// Create a new disabled normalizer.
+    pub fn new() -> Self {
+        Normalizer {
+            parameters: None,
+            level: 0.0,
+            gain: 1.0,
+            next_gain: 1.0,
+            gain_step: T::zero(),
+            gain_start: T::one(),
+            chunk_peak: T::zero(),
+            chunk_sum_sq: 0.0,
+            chunk_samples: 0,
+        }
+    }
+
+    /// Enable the normalization with the given parameters, or disable it with `None`.
+    /// This resets the gain to unity.
+    pub fn set_parameters(&mut self, parameters: Option<NormalizationParameters>) {
+        self.parameters = parameters;
+        self.level = 0.0;
+        self.gain = 1.0;
+        self.next_gain = 1.0;
+    }
+
+    /// Reset the measured level and the gain to unity, keeping the parameters.
+    pub fn reset(&mut self) {
+        self.level = 0.0;
+        self.gain = 1.0;
+        self.next_gain = 1.0;
+    }
+
+    /// Get the gain used at the end of the last chunk.
+    pub fn gain(&self) -> f64 {
+        self.gain
+    }
+
+    /// Prepare for writing a chunk of `nbr_frames` frames.
+    pub fn start_chunk(&mut self, nbr_frames: usize) {
+        self.gain_start = T::coerce(self.gain);
+        self.gain_step = if nbr_frames > 0 {
+            T::coerce((self.next_gain - self.gain) / nbr_frames as f64)
+        } else {
+            T::zero()
+        };
+        self.chunk_peak = T::zero();
+        self.chunk_sum_sq = 0.0;
+        self.chunk_samples = 0;
+    }
+
+    /// Apply the gain to the output sample for frame `n` of the chunk,
+    /// and include the unscaled value in the level measurement.
+    #[inline]
+    pub fn apply(&mut self, n: usize, value: T) -> T {
+        match self.parameters {
+            None => value,
+            Some(NormalizationParameters {
+                target: NormalizationTarget::Peak(_),
+                ..
+            }) => {
+                let abs = value.abs();
+                if abs > self.chunk_peak {
+                    self.chunk_peak = abs;
+                }
+                value * (self.gain_start + self.gain_step * T::coerce(n))
+            }
+            Some(NormalizationParameters {
+                target: NormalizationTarget::Rms(_),
+                ..
+            }) => {
+                let val = value.to_f64().unwrap_or(0.0);
+                self.chunk_sum_sq += val * val;
+                self.chunk_samples += 1;
+                value * (self.gain_start + self.gain_step * T::coerce(n))
+            }
+        }
+    }
+
+    /// Apply the gain to the frames of one channel of the chunk, see "apply".
+    pub fn apply_wave(&mut self, wave: &mut [T]) {
+        if self.parameters.is_none() {
+            return;
+        }
+        for (n, value) in wave.iter_mut().enumerate() {
+            *value = self.apply(n, *value);
+        }
+    }
+
+    /// Update the level estimate and the gain after writing a chunk of `nbr_frames` frames.
+    pub fn finish_chunk(&mut self, nbr_frames: usize) {
+        let parameters = match self.parameters {
+            Some(parameters) => parameters,
+            None => return,
+        };
+        let decay = if parameters.time_constant > 0 {
+            (-(nbr_frames as f64) / parameters.time_constant as f64).exp()
+        } else {
+            0.0
+        };
+        self.gain = self.next_gain;
+        let desired = match parameters.target {
+            NormalizationTarget::Peak(target) => {
+                // peak hold with exponential release
+                let peak = self.chunk_peak.to_f64().unwrap_or(0.0);
+                self.level = peak.max(self.level * decay);
+                target / self.level
+            }
+            NormalizationTarget::Rms(target) => {
+                if self.chunk_samples > 0 {
+                    let mean_sq = self.chunk_sum_sq / self.chunk_samples as f64;
+                    self.level = mean_sq + (self.level - mean_sq) * decay;
+                }
+                target / self.level.sqrt()
+            }
+        };
+        let desired = if desired.is_finite() {
+            desired.min(parameters.max_gain)
+        } else {
+            parameters.max_gain
+        };
+        self.next_gain = desired + (self.next_gain - desired) * decay;
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::normalization::{NormalizationParameters, NormalizationTarget, Normalizer};
+
+    fn run_chunks(normalizer: &mut Normalizer<f64>, amplitude: f64, nbr_chunks: usize) -> f64 {
+        let mut out_peak = 0.0;
+        for _ in 0..nbr_chunks {
+            normalizer.start_chunk(100);
+            out_peak = 0.0;
+            for n in 0..100 {
+                let value = if n % 2 == 0 { amplitude } else { -amplitude };
+                let out = normalizer.apply(n, value);
+                out_peak = out.abs().max(out_peak);
+            }
+            normalizer.finish_chunk(100);
+        }
+        out_peak
+    }
+
+    #[test]
+    fn disabled() {
+        let mut normalizer = Normalizer::<f64>::new();
+        assert_eq!(run_chunks(&mut normalizer, 0.3, 10), 0.3);
+    }
+
+    #[test]
+    fn normalize_peak() {
+        let mut normalizer = Normalizer::<f64>::new();
+        normalizer.set_parameters(Some(NormalizationParameters {
+            target: NormalizationTarget::Peak(0.9),
+            time_constant: 200,
+            max_gain: 10.0,
+        }));
+        let peak = run_chunks(&mut normalizer, 0.3, 50);
+        assert!((peak - 0.9).abs() < 1.0e-3, "{}", peak);
+        assert!((normalizer.gain() - 3.0).abs() < 1.0e-3);
+    }
+
+    #[test]
+    fn normalize_rms_limited() {
+        let mut normalizer = Normalizer::<f64>::new();
+        normalizer.set_parameters(Some(NormalizationParameters {
+            target: NormalizationTarget::Rms(0.5),
+            time_constant: 200,
+            max_gain: 2.0,
+        }));
+        let peak = run_chunks(&mut normalizer, 0.1, 50);
+        assert!((peak - 0.2).abs() < 1.0e-3, "{}", peak);
+    }
+}
@@ -0,0 +1,255 @@
+use crate::error::ResampleResult;
+use crate::{AliasingWarning, Resampler};
+use std::fmt;
+
+/// Receives events from an `ObservedResampler`.
+///
+/// All methods have empty default implementations, so an observer only needs
+/// to implement the events it is interested in.
+/// The methods are called from the thread that uses the resampler,
+/// and should return quickly to not disturb the processing.
+pub trait Observer: Send {
+    /// A new resample ratio was accepted by "set_resample_ratio".
+    fn ratio_changed(&mut self, _new_ratio: f64) {}
+
+    /// A new relative resample ratio was accepted by "set_resample_ratio_relative".
+    fn relative_ratio_changed(&mut self, _rel_ratio: f64) {}
+
+    /// A chunk was processed, consuming `nbr_frames_in` and producing `nbr_frames_out` frames.
+    fn chunk_processed(&mut self, _nbr_frames_in: usize, _nbr_frames_out: usize) {}
+
+    /// The number of input frames needed for the next chunk has changed,
+    /// so the input buffer should be resized before the next call to "process".
+    fn buffer_resized(&mut self, _nbr_frames_needed: usize) {}
+
+    /// The resample ratio was changed so that the output may alias, see `AliasingWarning`.
+    fn aliasing_warning(&mut self, _warning: AliasingWarning) {}
+}
+
+/// A wrapper that reports what happens in the inner resampler to an `Observer`.
+///
+/// This is meant for monitoring and debugging, without relying on the global logger.
+/// The events are detected by the wrapper, and the inner resampler can be of any type.
+pub struct ObservedResampler<T> {
+    resampler: Box<dyn Resampler<T>>,
+    observer: Box<dyn Observer>,
+    frames_needed: usize,
+}
+
+impl<T> ObservedResampler<T> {
+    /// Wrap a resampler, and report its events to `observer`.
+    pub fn new(resampler: Box<dyn Resampler<T>>, observer: Box<dyn Observer>) -> Self {
+        let frames_needed = resampler.nbr_frames_needed();
+        ObservedResampler {
+            resampler,
+            observer,
+            frames_needed,
+        }
+    }
+
+    /// Get the inner resampler.
+    pub fn inner(&mut self) -> &mut dyn Resampler<T> {
+        self.resampler.as_mut()
+    }
+
+    /// Get the observer.
+    pub fn observer(&mut self) -> &mut dyn Observer {
+        self.observer.as_mut()
+    }
+
+    /// Report a change of the number of frames needed, if there is one.
+    fn check_frames_needed(&mut self) {
+        let frames_needed = self.resampler.nbr_frames_needed();
+        if frames_needed != self.frames_needed {
+            self.frames_needed = frames_needed;
+            self.observer.buffer_resized(frames_needed);
+        }
+    }
+
+    /// Report the events that may follow a ratio change.
+    fn after_ratio_change(&mut self) {
+        if let Some(warning) = self.resampler.aliasing_warning() {
+            self.observer.aliasing_warning(warning);
+        }
+        self.check_frames_needed();
+    }
+}
+
+/// Shows the number of frames needed, but not the inner resampler or the observer.
+impl<T> fmt::Debug for ObservedResampler<T> {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        f.debug_struct("ObservedResampler")
+            .field("frames_needed", &self.frames_needed)
+            .finish()
+    }
+}
+
+impl<T> Resampler<T> for ObservedResampler<T> {
+    fn process(&mut self, wave_in: &[Vec<T>]) -> ResampleResult<Vec<Vec<T>>> {
+        let wave_out = self.resampler.process(wave_in)?;
+        let nbr_frames_out = wave_out.iter().map(|chan| chan.len()).max().unwrap_or(0);
+        self.observer
+            .chunk_processed(self.frames_needed, nbr_frames_out);
+        self.check_frames_needed();
+        Ok(wave_out)
+    }
+
+    fn process_with(
+        &mut self,
+        wave_in: &[Vec<T>],
+        f: &mut dyn FnMut(usize, &[T]),
+    ) -> ResampleResult<()> {
+        let mut nbr_frames_out = 0;
+        self.resampler.process_with(wave_in, &mut |chan, frames| {
+            nbr_frames_out = nbr_frames_out.max(frames.len());
+            f(chan, frames);
+        })?;
+        self.observer
+            .chunk_processed(self.frames_needed, nbr_frames_out);
+        self.check_frames_needed();
+        Ok(())
+    }
+
+    fn output_delay(&self) -> f64 {
+        self.resampler.output_delay()
+    }
+
+    fn reset(&mut self) {
+        self.resampler.reset();
+        self.check_frames_needed();
+    }
+
+    /// The chunks of silence processed while flushing are not reported to the observer.
+    fn flush(&mut self) -> ResampleResult<Vec<Vec<T>>> {
+        let wave_out = self.resampler.flush()?;
+        self.check_frames_needed();
+        Ok(wave_out)
+    }
+
+    fn nbr_frames_needed(&self) -> usize {
+        self.resampler.nbr_frames_needed()
+    }
+
+    fn nbr_output_frames_next(&self) -> usize {
+        self.resampler.nbr_output_frames_next()
+    }
+
+    fn set_resample_ratio(&mut self, new_ratio: f64) -> ResampleResult<()> {
+        self.resampler.set_resample_ratio(new_ratio)?;
+        self.observer.ratio_changed(new_ratio);
+        self.after_ratio_change();
+        Ok(())
+    }
+
+    fn set_resample_ratio_relative(&mut self, rel_ratio: f64) -> ResampleResult<()> {
+        self.resampler.set_resample_ratio_relative(rel_ratio)?;
+        self.observer.relative_ratio_changed(rel_ratio);
+        self.after_ratio_change();
+        Ok(())
+    }
+
+    fn aliasing_warning(&self) -> Option<AliasingWarning> {
+        self.resampler.aliasing_warning()
+    }
+
+    fn resample_ratio(&self) -> f64 {
+        self.resampler.resample_ratio()
+    }
+
+    fn resample_ratio_original(&self) -> f64 {
+        self.resampler.resample_ratio_original()
+    }
+
+    fn chunk_size(&self) -> usize {
+        self.resampler.chunk_size()
+    }
+
+    fn nbr_channels(&self) -> usize {
+        self.resampler.nbr_channels()
+    }
+
+    fn sinc_len(&self) -> Option<usize> {
+        self.resampler.sinc_len()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::observer::{ObservedResampler, Observer};
+    use crate::{AliasingWarning, Resampler, SincFixedOut};
+    use crate::{InterpolationParameters, InterpolationType, WindowFunction};
+    use std::sync::{Arc, Mutex};
+
+    struct Recorder {
+        events: Arc<Mutex<Vec<String>>>,
+    }
+
+    impl Observer for Recorder {
+        fn ratio_changed(&mut self, new_ratio: f64) {
+            self.events
+                .lock()
+                .unwrap()
+                .push(format!("ratio {}", new_ratio));
+        }
+
+        fn chunk_processed(&mut self, nbr_frames_in: usize, nbr_frames_out: usize) {
+            self.events
+                .lock()
+                .unwrap()
+                .push(format!("chunk {} {}", nbr_frames_in, nbr_frames_out));
+        }
+
+        fn buffer_resized(&mut self, nbr_frames_needed: usize) {
+            self.events
+                .lock()
+                .unwrap()
+                .push(format!("resized {}", nbr_frames_needed));
+        }
+
+        fn aliasing_warning(&mut self, warning: AliasingWarning) {
+            self.events
+                .lock()
+                .unwrap()
+                .push(format!("aliasing {}", warning.ratio));
+        }
+    }
+
+    #[test]
+    fn observe_events() {
+        let params = InterpolationParameters {
+            sinc_len: 64,
+            f_cutoff: 0.95,
+            interpolation: InterpolationType::Linear,
+            oversampling_factor: 16,
+            window: WindowFunction::BlackmanHarris2,
+        };
+        let events = Arc::new(Mutex::new(Vec::new()));
+        let recorder = Recorder {
+            events: events.clone(),
+        };
+        let mut resampler = ObservedResampler::<f64>::new(
+            Box::new(SincFixedOut::new(0.5, params, 256, 1).unwrap()),
+            Box::new(recorder),
+        );
+        let frames = resampler.nbr_frames_needed();
+        resampler.process(&[vec![0.0; frames]]).unwrap();
+        // the first chunk needs extra frames to fill the buffer
+        let frames_next = resampler.nbr_frames_needed();
+        assert!(frames_next < frames);
+        resampler.set_resample_ratio(0.46).unwrap();
+        let frames_after = resampler.nbr_frames_needed();
+        assert!(frames_after > frames_next);
+        assert!(resampler.set_resample_ratio(0.2).is_err());
+        let events = events.lock().unwrap();
+        assert_eq!(
+            *events,
+            vec![
+                format!("chunk {} 256", frames),
+                format!("resized {}", frames_next),
+                "ratio 0.46".to_string(),
+                "aliasing 0.46".to_string(),
+                format!("resized {}", frames_after),
+            ]
+        );
+    }
+}
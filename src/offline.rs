@@ -0,0 +1,506 @@
+use crate::error::{ResampleError, ResampleResult};
+use crate::{Resampler, Sample};
+use std::mem;
+use std::panic;
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::thread;
+
+/// Number of chunks before each segment that are processed and discarded
+/// by `process_all_parallel`, to bring the resampler into the right state.
+const SEGMENT_OVERLAP_CHUNKS: usize = 2;
+
+/// Resample a complete waveform in one call, by feeding it to a resampler chunk by chunk.
+///
+/// The channels must either have the same length, or be empty.
+/// The last chunk is padded with zeros, and the output is the concatenation of the
+/// output of all chunks. It therefore includes the delay of the resampler,
+/// and may be slightly longer than the resampled input.
+///
+/// # Errors
+///
+/// The function returns an error if the number of channels doesn't match the resampler,
+/// if the channels have different lengths, or if the resampler returns an error.
+pub fn process_all<T, R>(resampler: &mut R, wave_in: &[Vec<T>]) -> ResampleResult<Vec<Vec<T>>>
+where
+    T: Sample,
+    R: Resampler<T> + ?Sized,
+{
+    process_all_with_progress(resampler, wave_in, |_, _| {})
+}
+
+/// Resample a complete waveform in one call, like `process_all`,
+/// and report the progress after each chunk.
+///
+/// The `progress` callback is called with the number of input frames processed so far,
+/// and the total number of input frames.
+///
+/// # Errors
+///
+/// The function returns an error if the number of channels doesn't match the resampler,
+/// if the channels have different lengths, or if the resampler returns an error.
+pub fn process_all_with_progress<T, R, F>(
+    resampler: &mut R,
+    wave_in: &[Vec<T>],
+    progress: F,
+) -> ResampleResult<Vec<Vec<T>>>
+where
+    T: Sample,
+    R: Resampler<T> + ?Sized,
+    F: FnMut(usize, usize),
+{
+    process_all_inner(resampler, wave_in, progress, None)
+}
+
+/// Resample a complete waveform in one call, like `process_all_with_progress`,
+/// with the possibility to cancel the processing.
+///
+/// The `cancel` flag is checked before each chunk, and the processing stops if it is set.
+/// Typically the flag is shared with another thread via an `Arc`.
+/// After a cancellation the resampler is left in the middle of the waveform,
+/// and should not be used to continue processing the same stream.
+///
+/// # Errors
+///
+/// The function returns `ResampleError::Cancelled` if it was cancelled.
+/// It also returns an error if the number of channels doesn't match the resampler,
+/// if the channels have different lengths, or if the resampler returns an error.
+pub fn process_all_cancellable<T, R, F>(
+    resampler: &mut R,
+    wave_in: &[Vec<T>],
+    progress: F,
+    cancel: &AtomicBool,
+) -> ResampleResult<Vec<Vec<T>>>
+where
+    T: Sample,
+    R: Resampler<T> + ?Sized,
+    F: FnMut(usize, usize),
+{
+    process_all_inner(resampler, wave_in, progress, Some(cancel))
+}
+
+/// Resample a complete waveform, with an optional cancellation flag.
+fn process_all_inner<T, R, F>(
+    resampler: &mut R,
+    wave_in: &[Vec<T>],
+    mut progress: F,
+    cancel: Option<&AtomicBool>,
+) -> ResampleResult<Vec<Vec<T>>>
+where
+    T: Sample,
+    R: Resampler<T> + ?Sized,
+    F: FnMut(usize, usize),
+{
+    let total = check_lengths(wave_in)?;
+    let mut wave_out = vec![Vec::new(); wave_in.len()];
+    let mut frames_done = 0;
+    while frames_done < total {
+        if let Some(cancel) = cancel {
+            if cancel.load(Ordering::Relaxed) {
+                debug!("Cancelled after {} of {} frames", frames_done, total);
+                return Err(ResampleError::Cancelled);
+            }
+        }
+        let frames_needed = resampler.nbr_frames_needed();
+        let end = (frames_done + frames_needed).min(total);
+        let chunk = read_frames(wave_in, frames_done, frames_needed);
+        let chunk_out = resampler.process(&chunk)?;
+        for (wave, chunk) in wave_out.iter_mut().zip(chunk_out.iter()) {
+            wave.extend_from_slice(chunk);
+        }
+        frames_done = end;
+        trace!("Processed {} of {} frames", frames_done, total);
+        progress(frames_done, total);
+    }
+    Ok(wave_out)
+}
+
+/// Resample a complete waveform like `process_all`, by splitting it into segments
+/// that are processed in parallel on `nbr_threads` threads.
+///
+/// A new resampler is created with `new_resampler` for each segment.
+/// Each segment starts a few chunks early, and the output of these overlapping chunks
+/// is discarded, so that the stitched result is the same as from a single resampler.
+/// This requires a resampler that takes the same number of input frames,
+/// and returns the same number of output frames, for every chunk, such as `FftFixedInOut`.
+///
+/// # Errors
+///
+/// The function returns an error if the number of channels doesn't match the resampler,
+/// if the channels have different lengths, or if a resampler returns an error.
+/// It also returns `ResampleError::WrongNumberOfFrames` if a resampler returns
+/// chunks of varying length.
+pub fn process_all_parallel<T, R, M>(
+    mut new_resampler: M,
+    wave_in: &[Vec<T>],
+    nbr_threads: usize,
+) -> ResampleResult<Vec<Vec<T>>>
+where
+    T: Sample + Send + 'static,
+    R: Resampler<T> + 'static,
+    M: FnMut() -> R,
+{
+    let total = check_lengths(wave_in)?;
+    let first_resampler = new_resampler();
+    let chunk_in = first_resampler.nbr_frames_needed();
+    let chunk_out = first_resampler.nbr_output_frames_next();
+    let mut first_resampler = Some(first_resampler);
+    let mut nbr_chunks = total / chunk_in;
+    if total % chunk_in > 0 {
+        nbr_chunks += 1;
+    }
+    let nbr_segments = nbr_threads.max(1).min(nbr_chunks);
+    debug!(
+        "Processing {} chunks in {} segments, chunk size in: {}, out: {}",
+        nbr_chunks, nbr_segments, chunk_in, chunk_out
+    );
+
+    let mut handles = Vec::with_capacity(nbr_segments);
+    for segment in 0..nbr_segments {
+        let first_chunk = segment * nbr_chunks / nbr_segments;
+        let end_chunk = (segment + 1) * nbr_chunks / nbr_segments;
+        let start_chunk = first_chunk.saturating_sub(SEGMENT_OVERLAP_CHUNKS);
+        let segment_in = read_frames(
+            wave_in,
+            start_chunk * chunk_in,
+            (end_chunk - start_chunk) * chunk_in,
+        );
+        let resampler = first_resampler.take().unwrap_or_else(&mut new_resampler);
+        let skip_chunks = first_chunk - start_chunk;
+        handles.push(thread::spawn(move || {
+            process_segment(resampler, &segment_in, chunk_in, chunk_out, skip_chunks)
+        }));
+    }
+
+    let mut wave_out = vec![Vec::new(); wave_in.len()];
+    for handle in handles {
+        let segment_out = handle
+            .join()
+            .unwrap_or_else(|err| panic::resume_unwind(err))?;
+        for (wave, segment) in wave_out.iter_mut().zip(segment_out.iter()) {
+            wave.extend_from_slice(segment);
+        }
+    }
+    Ok(wave_out)
+}
+
+/// Resample a complete waveform with many channels, a group of channels at a time,
+/// to limit the amount of memory needed.
+///
+/// The waveform is processed in several passes, each handling `channels_per_pass` channels.
+/// For each pass, a new resampler is created with `new_resampler`, for the full number of channels.
+/// The input of the channels in the pass is read with `read_channel`, that is called
+/// with the channel index, and the other channels are left empty so that the resampler skips them.
+/// When the pass is done, the output of each channel is given to `write_channel`,
+/// together with the channel index, before the next pass starts.
+/// The peak memory use then scales with `channels_per_pass` instead of the total number of channels.
+///
+/// Since each pass starts from a fresh resampler and processes the same number of frames,
+/// all channels follow the same timing, and the result is the same as from `process_all`.
+/// This requires that `new_resampler` always returns resamplers with identical settings.
+///
+/// # Errors
+///
+/// The function returns an error if the number of channels doesn't match the resampler,
+/// if the channels have different lengths, or if a resampler returns an error.
+pub fn process_all_by_channels<T, R, M, I, O>(
+    mut new_resampler: M,
+    nbr_channels: usize,
+    channels_per_pass: usize,
+    mut read_channel: I,
+    mut write_channel: O,
+) -> ResampleResult<()>
+where
+    T: Sample,
+    R: Resampler<T>,
+    M: FnMut() -> R,
+    I: FnMut(usize) -> Vec<T>,
+    O: FnMut(usize, Vec<T>),
+{
+    let channels_per_pass = channels_per_pass.max(1);
+    let mut total = None;
+    for first_chan in (0..nbr_channels).step_by(channels_per_pass) {
+        let end_chan = (first_chan + channels_per_pass).min(nbr_channels);
+        let mut wave_in = vec![Vec::new(); nbr_channels];
+        for (chan, wave) in wave_in
+            .iter_mut()
+            .enumerate()
+            .take(end_chan)
+            .skip(first_chan)
+        {
+            *wave = read_channel(chan);
+        }
+        let pass_total = check_lengths(&wave_in)?;
+        let total = *total.get_or_insert(pass_total);
+        if pass_total != total {
+            return Err(ResampleError::WrongNumberOfFrames {
+                channel: first_chan,
+                expected: total,
+                actual: pass_total,
+                resampler: String::new(),
+            });
+        }
+        debug!("Processing channels {} to {}", first_chan, end_chan - 1);
+        let mut resampler = new_resampler();
+        let mut wave_out = process_all(&mut resampler, &wave_in)?;
+        drop(wave_in);
+        for (chan, wave) in wave_out
+            .iter_mut()
+            .enumerate()
+            .take(end_chan)
+            .skip(first_chan)
+        {
+            write_channel(chan, mem::take(wave));
+        }
+    }
+    Ok(())
+}
+
+/// Resample one segment for `process_all_parallel`,
+/// and discard the output of the first `skip_chunks` chunks.
+fn process_segment<T, R>(
+    mut resampler: R,
+    segment_in: &[Vec<T>],
+    chunk_in: usize,
+    chunk_out: usize,
+    skip_chunks: usize,
+) -> ResampleResult<Vec<Vec<T>>>
+where
+    T: Sample,
+    R: Resampler<T>,
+{
+    let total = segment_in.iter().map(|wave| wave.len()).max().unwrap_or(0);
+    let mut wave_out = vec![Vec::new(); segment_in.len()];
+    for (idx, start) in (0..total).step_by(chunk_in).enumerate() {
+        let chunk = read_frames(segment_in, start, chunk_in);
+        let output = resampler.process(&chunk)?;
+        for (chan, wave) in output.iter().enumerate() {
+            if !wave.is_empty() && wave.len() != chunk_out {
+                return Err(ResampleError::WrongNumberOfFrames {
+                    channel: chan,
+                    expected: chunk_out,
+                    actual: wave.len(),
+                    resampler: String::new(),
+                });
+            }
+        }
+        if idx >= skip_chunks {
+            for (wave, chunk) in wave_out.iter_mut().zip(output.iter()) {
+                wave.extend_from_slice(chunk);
+            }
+        }
+    }
+    Ok(wave_out)
+}
+
+/// Process chunks of silence on all channels until `nbr_frames` frames have been output,
+/// and return these frames, for the "flush" of the resamplers.
+pub(crate) fn flush_with_silence<T, R>(
+    resampler: &mut R,
+    nbr_channels: usize,
+    nbr_frames: usize,
+) -> ResampleResult<Vec<Vec<T>>>
+where
+    T: Sample,
+    R: Resampler<T> + ?Sized,
+{
+    let mut wave_out = vec![Vec::with_capacity(nbr_frames); nbr_channels];
+    let mut frames_done = 0;
+    while frames_done < nbr_frames {
+        let silence = vec![vec![T::zero(); resampler.nbr_frames_needed()]; nbr_channels];
+        let chunk_out = resampler.process(&silence)?;
+        for (wave, chunk) in wave_out.iter_mut().zip(chunk_out.iter()) {
+            wave.extend_from_slice(chunk);
+        }
+        frames_done += chunk_out
+            .first()
+            .map(|chunk| chunk.len())
+            .unwrap_or(nbr_frames);
+    }
+    for wave in wave_out.iter_mut() {
+        wave.truncate(nbr_frames);
+    }
+    trace!("Flushed {} frames", nbr_frames);
+    Ok(wave_out)
+}
+
+/// Check that all non-empty channels have the same length, and return this length.
+pub(crate) fn check_lengths<T>(wave_in: &[Vec<T>]) -> ResampleResult<usize> {
+    let total = wave_in.iter().map(|wave| wave.len()).max().unwrap_or(0);
+    for (chan, wave) in wave_in.iter().enumerate() {
+        if !wave.is_empty() && wave.len() != total {
+            return Err(ResampleError::WrongNumberOfFrames {
+                channel: chan,
+                expected: total,
+                actual: wave.len(),
+                resampler: String::new(),
+            });
+        }
+    }
+    Ok(total)
+}
+
+/// Copy `nbr_frames` frames starting at `start` from the non-empty channels,
+/// padding with zeros past the end of the waveform.
+pub(crate) fn read_frames<T: Sample>(
+    wave_in: &[Vec<T>],
+    start: usize,
+    nbr_frames: usize,
+) -> Vec<Vec<T>> {
+    wave_in
+        .iter()
+        .map(|wave| {
+            if wave.is_empty() {
+                Vec::new()
+            } else {
+                let end = (start + nbr_frames).min(wave.len());
+                let mut chunk = wave[start.min(end)..end].to_vec();
+                chunk.resize(nbr_frames, T::zero());
+                chunk
+            }
+        })
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::offline::{
+        process_all, process_all_by_channels, process_all_cancellable, process_all_parallel,
+        process_all_with_progress,
+    };
+    use crate::{FftFixedIn, FftFixedInOut, ResampleError, Resampler, SincFixedIn, SincFixedOut};
+    use crate::{InterpolationParameters, InterpolationType, WindowFunction};
+    use std::sync::atomic::{AtomicBool, Ordering};
+    use std::sync::Arc;
+
+    #[test]
+    fn process_all_fft() {
+        let mut resampler = FftFixedIn::<f64>::new(44100, 48000, 1024, 2, 2).unwrap();
+        let waves = vec![vec![0.1f64; 10000], Vec::new()];
+        let out = process_all(&mut resampler, &waves).unwrap();
+        assert!(out[1].is_empty());
+        assert!(out[0].len() >= 10000 * 48000 / 44100 - 1024);
+        assert!(out[0].len() <= 10240 * 48000 / 44100 + 1);
+
+        let waves = vec![vec![0.1f64; 10000], vec![0.1f64; 9999]];
+        assert!(process_all(&mut resampler, &waves).is_err());
+    }
+
+    #[test]
+    fn progress() {
+        let params = InterpolationParameters {
+            sinc_len: 64,
+            f_cutoff: 0.95,
+            interpolation: InterpolationType::Linear,
+            oversampling_factor: 16,
+            window: WindowFunction::BlackmanHarris2,
+        };
+        let mut resampler = SincFixedOut::<f32>::new(0.5, params, 512, 1).unwrap();
+        let waves = vec![vec![0.0f32; 5000]];
+        let mut reports = Vec::new();
+        let out = process_all_with_progress(&mut resampler, &waves, |done, total| {
+            reports.push((done, total))
+        })
+        .unwrap();
+        assert_eq!(out[0].len() % 512, 0);
+        assert_eq!(reports.last(), Some(&(5000, 5000)));
+        assert!(reports.windows(2).all(|pair| pair[1].0 > pair[0].0));
+        assert_eq!(reports.len(), out[0].len() / 512);
+        let boxed: &mut dyn Resampler<f32> = &mut resampler;
+        assert!(process_all(boxed, &waves).is_ok());
+    }
+
+    #[test]
+    fn cancel() {
+        let mut resampler = FftFixedIn::<f64>::new(44100, 48000, 1024, 2, 1).unwrap();
+        let waves = vec![vec![0.0f64; 10000]];
+        let cancel = Arc::new(AtomicBool::new(false));
+        let flag = cancel.clone();
+        let mut calls = 0;
+        let result = process_all_cancellable(
+            &mut resampler,
+            &waves,
+            |done, _| {
+                calls += 1;
+                if done >= 3000 {
+                    flag.store(true, Ordering::Relaxed);
+                }
+            },
+            &cancel,
+        );
+        match result {
+            Err(ResampleError::Cancelled) => {}
+            _ => panic!("Expected a cancellation"),
+        }
+        assert_eq!(calls, 3);
+    }
+
+    #[test]
+    fn parallel() {
+        let waves: Vec<Vec<f64>> = vec![
+            (0..20000).map(|n| (n as f64 * 0.01).sin()).collect(),
+            (0..20000).map(|n| (n as f64 * 0.003).cos()).collect(),
+        ];
+        let mut resampler = FftFixedInOut::<f64>::new(44100, 48000, 512, 2).unwrap();
+        let expected = process_all(&mut resampler, &waves).unwrap();
+        for nbr_threads in &[1, 3, 100] {
+            let out = process_all_parallel(
+                || FftFixedInOut::new(44100, 48000, 512, 2).unwrap(),
+                &waves,
+                *nbr_threads,
+            )
+            .unwrap();
+            assert_eq!(out[0].len(), expected[0].len());
+            for (wave, expected_wave) in out.iter().zip(expected.iter()) {
+                for (value, expected_value) in wave.iter().zip(expected_wave.iter()) {
+                    assert!((value - expected_value).abs() < 1.0e-12);
+                }
+            }
+        }
+    }
+
+    #[test]
+    fn by_channels() {
+        let new_resampler = || {
+            let params = InterpolationParameters {
+                sinc_len: 64,
+                f_cutoff: 0.95,
+                interpolation: InterpolationType::Cubic,
+                oversampling_factor: 16,
+                window: WindowFunction::BlackmanHarris2,
+            };
+            SincFixedIn::<f64>::new(1.2, params, 512, 5).unwrap()
+        };
+        let waves: Vec<Vec<f64>> = (0..5)
+            .map(|chan| {
+                (0..3000)
+                    .map(|n| (n as f64 * 0.01 * (chan + 1) as f64).sin())
+                    .collect()
+            })
+            .collect();
+        let expected = process_all(&mut new_resampler(), &waves).unwrap();
+        let mut output = vec![Vec::new(); 5];
+        let mut reads = Vec::new();
+        process_all_by_channels(
+            new_resampler,
+            5,
+            2,
+            |chan| {
+                reads.push(chan);
+                waves[chan].clone()
+            },
+            |chan, wave| output[chan] = wave,
+        )
+        .unwrap();
+        assert_eq!(reads, vec![0, 1, 2, 3, 4]);
+        assert_eq!(output, expected);
+
+        let result = process_all_by_channels(
+            new_resampler,
+            5,
+            2,
+            |chan| vec![0.0; 3000 + chan],
+            |_, _| {},
+        );
+        assert!(result.is_err());
+    }
+}
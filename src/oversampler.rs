@@ -0,0 +1,393 @@
+use crate::asynchro::{
+    interp_cubic, interp_lin, make_interpolator, oversample_wave, SincInterpolator,
+};
+use crate::error::{ResampleError, ResampleResult};
+use crate::{InterpolationParameters, InterpolationType, Sample};
+use std::fmt;
+
+/// Number of oversampled points kept from the previous chunk by a `Picker`.
+const PICKER_HISTORY: usize = 3;
+
+/// The first stage of a two-stage resampler, that can feed several `Picker`s.
+///
+/// The oversampler upsamples the input by the oversampling factor, using the same
+/// sinc interpolation as the asynchronous resamplers.
+/// Each `Picker` then derives an output at its own ratio from the oversampled stream,
+/// using cheap polynomial interpolation.
+/// This avoids repeating the expensive sinc interpolation when the same input
+/// is resampled to several slightly different output clocks.
+///
+/// Note that this calculates every point of the oversampled stream,
+/// while a single resampler only calculates the points it needs.
+/// It is therefore only faster when the oversampled stream is shared by several pickers.
+pub struct Oversampler<T> {
+    nbr_channels: usize,
+    chunk_size: usize,
+    interpolator: Box<dyn SincInterpolator<T>>,
+    buffer: Vec<Vec<T>>,
+}
+
+/// The second stage of a two-stage resampler, see `Oversampler`.
+///
+/// A picker takes the oversampled stream and returns a variable number of frames
+/// at its own resample ratio.
+pub struct Picker<T> {
+    nbr_channels: usize,
+    oversampling_factor: usize,
+    interpolation: InterpolationType,
+    resample_ratio: f64,
+    position: f64,
+    history: Vec<Vec<T>>,
+}
+
+impl<T> Oversampler<T>
+where
+    T: Sample,
+{
+    /// Create a new Oversampler
+    ///
+    /// Parameters are:
+    /// - `lowest_ratio`: The lowest ratio between output and input sample rates of the pickers.
+    ///   This is used to set the cutoff of the anti-aliasing filter.
+    /// - `parameters`: Parameters for interpolation, see `InterpolationParameters`.
+    ///   The interpolation type is not used here, it is instead given to each `Picker`.
+    /// - `chunk_size`: size of input data in frames
+    /// - `nbr_channels`: number of channels in input/output
+    pub fn new(
+        lowest_ratio: f64,
+        parameters: InterpolationParameters,
+        chunk_size: usize,
+        nbr_channels: usize,
+    ) -> Self {
+        debug!(
+            "Create new Oversampler, lowest ratio: {}, chunk_size: {}, channels: {}, parameters: {:?}",
+            lowest_ratio, chunk_size, nbr_channels, parameters
+        );
+        let interpolator = make_interpolator(
+            parameters.sinc_len,
+            lowest_ratio,
+            parameters.f_cutoff,
+            parameters.oversampling_factor,
+            parameters.window,
+        );
+        let buffer = vec![vec![T::zero(); chunk_size + 2 * interpolator.len()]; nbr_channels];
+        Oversampler {
+            nbr_channels,
+            chunk_size,
+            interpolator,
+            buffer,
+        }
+    }
+
+    /// Get the oversampling factor, needed when creating the `Picker`s.
+    pub fn oversampling_factor(&self) -> usize {
+        self.interpolator.nbr_sincs()
+    }
+
+    /// Query for the number of frames needed for the next call to "process".
+    /// Will always return the chunk_size defined when creating the instance.
+    pub fn nbr_frames_needed(&self) -> usize {
+        self.chunk_size
+    }
+
+    /// Oversample a chunk of audio. The input length is fixed, and the output is
+    /// `chunk_size` times the oversampling factor points long.
+    /// If the waveform for a channel is empty, this channel will be ignored and produce a
+    /// corresponding empty output waveform.
+    ///
+    /// # Errors
+    ///
+    /// The function returns an error if the length of the input data is not equal
+    /// to the number of channels and chunk size defined when creating the instance.
+    pub fn process(&mut self, wave_in: &[Vec<T>]) -> ResampleResult<Vec<Vec<T>>> {
+        if wave_in.len() != self.nbr_channels {
+            return Err(ResampleError::WrongNumberOfChannels {
+                expected: self.nbr_channels,
+                actual: wave_in.len(),
+                resampler: format!("{:?}", self),
+            });
+        }
+        let mut used_channels = Vec::new();
+        for (chan, wave) in wave_in.iter().enumerate() {
+            if !wave.is_empty() {
+                used_channels.push(chan);
+                if wave.len() != self.chunk_size {
+                    return Err(ResampleError::WrongNumberOfFrames {
+                        channel: chan,
+                        expected: self.chunk_size,
+                        actual: wave.len(),
+                        resampler: format!("{:?}", self),
+                    });
+                }
+            }
+        }
+        let sinc_len = self.interpolator.len();
+        let factor = self.interpolator.nbr_sincs();
+
+        let mut wave_out = vec![Vec::new(); self.nbr_channels];
+        for chan in used_channels.iter() {
+            let buf = &mut self.buffer[*chan];
+            for idx in 0..(2 * sinc_len) {
+                buf[idx] = buf[idx + self.chunk_size];
+            }
+            buf[2 * sinc_len..].copy_from_slice(&wave_in[*chan]);
+
+            wave_out[*chan] = oversample_wave(self.interpolator.as_ref(), buf, self.chunk_size);
+        }
+        trace!(
+            "Oversampling channels {:?}, {} frames in, {} points out",
+            used_channels,
+            self.chunk_size,
+            self.chunk_size * factor,
+        );
+        Ok(wave_out)
+    }
+}
+
+impl<T> fmt::Debug for Oversampler<T> {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        f.debug_struct("Oversampler")
+            .field("nbr_channels", &self.nbr_channels)
+            .field("chunk_size", &self.chunk_size)
+            .field("sinc_len", &self.interpolator.len())
+            .field("oversampling_factor", &self.interpolator.nbr_sincs())
+            .finish()
+    }
+}
+
+impl<T> fmt::Debug for Picker<T> {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        f.debug_struct("Picker")
+            .field("nbr_channels", &self.nbr_channels)
+            .field("oversampling_factor", &self.oversampling_factor)
+            .field("interpolation", &self.interpolation)
+            .field("resample_ratio", &self.resample_ratio)
+            .field("position", &self.position)
+            .finish()
+    }
+}
+
+impl<T> Picker<T>
+where
+    T: Sample,
+{
+    /// Create a new Picker
+    ///
+    /// Parameters are:
+    /// - `resample_ratio`: Ratio between output and input sample rates.
+    /// - `oversampling_factor`: The oversampling factor of the `Oversampler` feeding this picker.
+    /// - `interpolation`: Interpolation type used to pick the output from the oversampled points.
+    /// - `nbr_channels`: number of channels in input/output
+    pub fn new(
+        resample_ratio: f64,
+        oversampling_factor: usize,
+        interpolation: InterpolationType,
+        nbr_channels: usize,
+    ) -> Self {
+        debug!(
+            "Create new Picker, ratio: {}, oversampling_factor: {}, interpolation: {:?}, channels: {}",
+            resample_ratio, oversampling_factor, interpolation, nbr_channels
+        );
+        Picker {
+            nbr_channels,
+            oversampling_factor,
+            interpolation,
+            resample_ratio,
+            position: 0.0,
+            history: vec![vec![T::zero(); PICKER_HISTORY]; nbr_channels],
+        }
+    }
+
+    /// Update the resample ratio. The new value must be positive,
+    /// and should not be lower than the lowest ratio given to the `Oversampler`.
+    ///
+    /// # Errors
+    ///
+    /// The function returns an error if the new ratio is not a positive number.
+    pub fn set_resample_ratio(&mut self, new_ratio: f64) -> ResampleResult<()> {
+        trace!("Change picker resample ratio to {}", new_ratio);
+        if new_ratio > 0.0 && new_ratio.is_finite() {
+            self.resample_ratio = new_ratio;
+            Ok(())
+        } else {
+            Err(ResampleError::RatioOutOfBounds {
+                provided: new_ratio,
+                original: self.resample_ratio,
+                max_deviation: f64::INFINITY,
+            })
+        }
+    }
+
+    /// Shift the position of the next output frame by a number of input frames.
+    /// This can be used to set the phase of the output clock relative to other pickers.
+    pub fn shift_phase(&mut self, frames: f64) {
+        self.position += frames * self.oversampling_factor as f64;
+    }
+
+    /// Derive the output from a chunk of the oversampled stream produced by an `Oversampler`.
+    /// The number of output frames varies between calls.
+    /// If the waveform for a channel is empty, this channel will be ignored and produce a
+    /// corresponding empty output waveform.
+    ///
+    /// # Errors
+    ///
+    /// The function returns an error if the number of channels is not equal to the one defined
+    /// when creating the instance, or if the channels have different lengths.
+    pub fn process(&mut self, oversampled: &[Vec<T>]) -> ResampleResult<Vec<Vec<T>>> {
+        if oversampled.len() != self.nbr_channels {
+            return Err(ResampleError::WrongNumberOfChannels {
+                expected: self.nbr_channels,
+                actual: oversampled.len(),
+                resampler: format!("{:?}", self),
+            });
+        }
+        let mut used_channels = Vec::new();
+        let mut len = None;
+        for (chan, wave) in oversampled.iter().enumerate() {
+            if !wave.is_empty() {
+                used_channels.push(chan);
+                let expected = *len.get_or_insert(wave.len());
+                if wave.len() != expected {
+                    return Err(ResampleError::WrongNumberOfFrames {
+                        channel: chan,
+                        expected,
+                        actual: wave.len(),
+                        resampler: format!("{:?}", self),
+                    });
+                }
+            }
+        }
+        let len = len.unwrap_or(0);
+
+        let mut buffers = vec![Vec::new(); self.nbr_channels];
+        for chan in used_channels.iter() {
+            let mut buffer = Vec::with_capacity(PICKER_HISTORY + len);
+            buffer.extend_from_slice(&self.history[*chan]);
+            buffer.extend_from_slice(&oversampled[*chan]);
+            buffers[*chan] = buffer;
+        }
+
+        let step = self.oversampling_factor as f64 / self.resample_ratio;
+        let mut wave_out = vec![Vec::new(); self.nbr_channels];
+        loop {
+            let base = self.position.floor();
+            let last_needed = match self.interpolation {
+                InterpolationType::Cubic => base as isize + 2,
+                InterpolationType::Linear => base as isize + 1,
+                InterpolationType::Nearest => self.position.round() as isize,
+            };
+            if last_needed >= len as isize {
+                break;
+            }
+            let offset = base as isize + PICKER_HISTORY as isize;
+            let frac = T::coerce(self.position - base);
+            for chan in used_channels.iter() {
+                let buf = &buffers[*chan];
+                let value = match self.interpolation {
+                    InterpolationType::Cubic => {
+                        let start = (offset - 1) as usize;
+                        let points = [buf[start], buf[start + 1], buf[start + 2], buf[start + 3]];
+                        interp_cubic(frac, &points)
+                    }
+                    InterpolationType::Linear => {
+                        let start = offset as usize;
+                        interp_lin(frac, &[buf[start], buf[start + 1]])
+                    }
+                    InterpolationType::Nearest => {
+                        buf[(self.position.round() as isize + PICKER_HISTORY as isize) as usize]
+                    }
+                };
+                wave_out[*chan].push(value);
+            }
+            self.position += step;
+        }
+        self.position -= len as f64;
+        for chan in used_channels.iter() {
+            let buf = &buffers[*chan];
+            self.history[*chan].copy_from_slice(&buf[buf.len() - PICKER_HISTORY..]);
+        }
+        Ok(wave_out)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::{InterpolationParameters, InterpolationType, WindowFunction};
+    use crate::{Oversampler, Picker, Resampler, SincFixedIn};
+
+    fn params(interpolation: InterpolationType) -> InterpolationParameters {
+        InterpolationParameters {
+            sinc_len: 64,
+            f_cutoff: 0.95,
+            interpolation,
+            oversampling_factor: 16,
+            window: WindowFunction::BlackmanHarris2,
+        }
+    }
+
+    #[test]
+    fn oversampler_lengths() {
+        let mut oversampler =
+            Oversampler::<f64>::new(1.0, params(InterpolationType::Cubic), 100, 2);
+        assert_eq!(oversampler.oversampling_factor(), 16);
+        let waves = vec![vec![0.0f64; 100], Vec::new()];
+        let out = oversampler.process(&waves).unwrap();
+        assert_eq!(out[0].len(), 1600);
+        assert!(out[1].is_empty());
+        let waves = vec![vec![0.0f64; 99], Vec::new()];
+        assert!(oversampler.process(&waves).is_err());
+    }
+
+    #[test]
+    fn picker_matches_resampler() {
+        let ratio = 1.07;
+        let mut oversampler =
+            Oversampler::<f64>::new(ratio, params(InterpolationType::Cubic), 256, 1);
+        let mut picker = Picker::<f64>::new(ratio, 16, InterpolationType::Cubic, 1);
+        let mut resampler =
+            SincFixedIn::<f64>::new(ratio, params(InterpolationType::Cubic), 256, 1).unwrap();
+        // The resampler starts at index -sinc_len/2, and steps before calculating each point
+        picker.shift_phase(32.0 + 1.0 / ratio);
+        let mut phase = 0.0f64;
+        let mut picked: Vec<f64> = Vec::new();
+        let mut resampled: Vec<f64> = Vec::new();
+        for _ in 0..10 {
+            let wave: Vec<f64> = (0..256)
+                .map(|_| {
+                    phase += 0.1;
+                    phase.sin()
+                })
+                .collect();
+            let waves = vec![wave];
+            let oversampled = oversampler.process(&waves).unwrap();
+            picked.extend(picker.process(&oversampled).unwrap()[0].iter());
+            resampled.extend(resampler.process(&waves).unwrap()[0].iter());
+        }
+        let nbr_frames = picked.len().min(resampled.len());
+        assert!(nbr_frames > 2000);
+        for (p, r) in picked.iter().zip(resampled.iter()).take(nbr_frames) {
+            assert!((p - r).abs() < 1.0e-9);
+        }
+    }
+
+    #[test]
+    fn several_pickers() {
+        let mut oversampler =
+            Oversampler::<f32>::new(0.9, params(InterpolationType::Linear), 512, 1);
+        let mut pickers: Vec<Picker<f32>> = [0.9, 1.0, 1.1]
+            .iter()
+            .map(|ratio| Picker::new(*ratio, 16, InterpolationType::Linear, 1))
+            .collect();
+        let mut lengths = [0; 3];
+        for _ in 0..20 {
+            let oversampled = oversampler.process(&[vec![0.0f32; 512]]).unwrap();
+            for (picker, length) in pickers.iter_mut().zip(lengths.iter_mut()) {
+                *length += picker.process(&oversampled).unwrap()[0].len();
+            }
+        }
+        assert!((lengths[0] as isize - 9216).abs() < 3);
+        assert!((lengths[1] as isize - 10240).abs() < 3);
+        assert!((lengths[2] as isize - 11264).abs() < 3);
+        assert!(pickers[0].set_resample_ratio(0.0).is_err());
+    }
+}
@@ -0,0 +1,74 @@
+/// Default number of channels from which the channels are processed in parallel,
+/// when the `parallel` feature is enabled.
+pub const DEFAULT_PARALLEL_THRESHOLD: usize = 8;
+
+/// Run `f` once for every item, where each item holds the data of one channel.
+/// Each call also gets a mutable worker, that holds scratch space or similar.
+///
+/// With the `parallel` feature, the items are processed concurrently on the rayon thread pool
+/// when there are at least `threshold` of them, and there is one worker per item.
+/// Otherwise they are processed one by one, all using the first worker.
+pub(crate) fn for_each_channel<I, W, F>(
+    items: impl Iterator<Item = I>,
+    workers: &mut [W],
+    threshold: usize,
+    f: F,
+) where
+    I: Send,
+    W: Send,
+    F: Fn(I, &mut W) + Sync,
+{
+    #[cfg(feature = "parallel")]
+    {
+        if workers.len() > 1 {
+            use rayon::prelude::*;
+            let items: Vec<I> = items.collect();
+            if items.len() >= threshold && items.len() <= workers.len() {
+                items
+                    .into_par_iter()
+                    .zip(workers.par_iter_mut())
+                    .for_each(|(item, worker)| f(item, worker));
+            } else {
+                for item in items {
+                    f(item, &mut workers[0]);
+                }
+            }
+            return;
+        }
+    }
+    #[cfg(not(feature = "parallel"))]
+    let _ = threshold;
+    for item in items {
+        f(item, &mut workers[0]);
+    }
+}
+
+/// Get the number of workers to use for `nbr_channels` channels.
+/// This is one per channel when the channels will be processed in parallel, and otherwise one.
+pub(crate) fn nbr_workers(nbr_channels: usize, threshold: usize) -> usize {
+    if cfg!(feature = "parallel") && nbr_channels >= threshold && nbr_channels > 1 {
+        nbr_channels
+    } else {
+        1
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::parallel::{for_each_channel, nbr_workers};
+
+    #[test]
+    fn process_all_channels() {
+        let mut data = vec![vec![1.0f64; 16]; 4];
+        let mut workers = vec![0usize; nbr_workers(4, 2)];
+        for_each_channel(data.iter_mut(), &mut workers, 2, |wave, count| {
+            for value in wave.iter_mut() {
+                *value *= 2.0;
+            }
+            *count += 1;
+        });
+        assert!(data.iter().flatten().all(|value| *value == 2.0));
+        assert_eq!(workers.iter().sum::<usize>(), 4);
+        assert_eq!(workers.len() == 4, cfg!(feature = "parallel"));
+    }
+}
@@ -0,0 +1,256 @@
+use crate::error::ResampleResult;
+use crate::{FftFixedIn, FftFixedInOut, Sample};
+use num_integer as integer;
+
+/// Smallest FFT input size considered by the planner.
+/// Shorter FFTs give too short anti-aliasing filters.
+const MIN_FFT_SIZE: usize = 256;
+
+/// Largest FFT input size considered by the planner.
+const MAX_FFT_SIZE: usize = 1 << 18;
+
+/// Estimated fixed cost of processing one chunk, in the same unit as the FFT cost,
+/// covering the function calls, buffer copying and the overlap handling.
+const CHUNK_OVERHEAD: f64 = 4096.0;
+
+/// The result of `plan_offline_chunks`, describing the FFT sizes to use
+/// when resampling a complete waveform with a synchronous resampler.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct OfflinePlan {
+    /// Sample rate of the input.
+    pub fs_in: usize,
+    /// Sample rate of the output.
+    pub fs_out: usize,
+    /// Number of input frames per FFT.
+    pub fft_size_in: usize,
+    /// Number of output frames per FFT.
+    pub fft_size_out: usize,
+    /// Number of chunks needed to process the complete input.
+    pub nbr_chunks: usize,
+    /// The estimated total cost, in arbitrary units.
+    /// Only useful for comparing plans for the same input.
+    pub cost: f64,
+}
+
+impl OfflinePlan {
+    /// Create an `FftFixedIn` resampler that uses the planned FFT sizes,
+    /// with one FFT per chunk.
+    ///
+    /// # Errors
+    ///
+    /// The function returns `ResampleError::InvalidParameter` for the same parameters as `FftFixedIn::new`.
+    pub fn fixed_in<T: Sample>(&self, nbr_channels: usize) -> ResampleResult<FftFixedIn<T>> {
+        FftFixedIn::new(self.fs_in, self.fs_out, self.fft_size_in, 1, nbr_channels)
+    }
+
+    /// Create an `FftFixedInOut` resampler that uses the planned FFT sizes.
+    ///
+    /// # Errors
+    ///
+    /// The function returns `ResampleError::InvalidParameter` for the same parameters as `FftFixedInOut::new`.
+    pub fn fixed_in_out<T: Sample>(&self, nbr_channels: usize) -> ResampleResult<FftFixedInOut<T>> {
+        // FftFixedInOut rounds the chunk size to a multiple of the reduced output rate
+        let gcd = integer::gcd(self.fs_in, self.fs_out);
+        let fft_chunks = self.fft_size_in / (self.fs_in / gcd);
+        FftFixedInOut::new(
+            self.fs_in,
+            self.fs_out,
+            fft_chunks * self.fs_out / gcd,
+            nbr_channels,
+        )
+    }
+}
+
+/// Choose the FFT sizes that minimize the total amount of computation
+/// for resampling `total_frames` frames from `fs_in` to `fs_out` in one go.
+///
+/// The default sizes of the synchronous resamplers are chosen for a low latency.
+/// For offline jobs the latency doesn't matter, and the sizes can instead be chosen for speed.
+/// The planner compares the possible sizes using an estimate of the FFT cost,
+/// that accounts for both the length and how well it factorizes into small primes,
+/// together with a fixed cost per chunk and the waste of zero padding the last chunk.
+/// Larger FFTs save on the per chunk cost, but the cost per frame of the FFT itself
+/// grows slowly with the length, so the best size is often only moderately large.
+/// The main gain is to avoid lengths with large prime factors, that are much slower.
+pub fn plan_offline_chunks(fs_in: usize, fs_out: usize, total_frames: usize) -> OfflinePlan {
+    let gcd = integer::gcd(fs_in, fs_out);
+    let unit_in = fs_in / gcd;
+    let unit_out = fs_out / gcd;
+    let mut first_chunks = MIN_FFT_SIZE / unit_in;
+    if first_chunks * unit_in < MIN_FFT_SIZE {
+        first_chunks += 1;
+    }
+    let mut best: Option<OfflinePlan> = None;
+    let mut fft_chunks = first_chunks;
+    loop {
+        let fft_size_in = fft_chunks * unit_in;
+        let fft_size_out = fft_chunks * unit_out;
+        let mut nbr_chunks = total_frames / fft_size_in;
+        if nbr_chunks * fft_size_in < total_frames {
+            nbr_chunks += 1;
+        }
+        let chunk_cost = fft_cost(2 * fft_size_in)
+            + fft_cost(2 * fft_size_out)
+            + 2.0 * (fft_size_in + fft_size_out) as f64
+            + CHUNK_OVERHEAD;
+        let cost = nbr_chunks as f64 * chunk_cost;
+        let is_better = if let Some(plan) = best.as_ref() {
+            cost < plan.cost
+        } else {
+            true
+        };
+        if is_better {
+            best = Some(OfflinePlan {
+                fs_in,
+                fs_out,
+                fft_size_in,
+                fft_size_out,
+                nbr_chunks,
+                cost,
+            });
+        }
+        // the first size is always tried, even if it is larger than the limits
+        let next_size = (fft_chunks + 1) * unit_in;
+        if next_size > MAX_FFT_SIZE || (next_size > total_frames && next_size > MIN_FFT_SIZE) {
+            break;
+        }
+        fft_chunks += 1;
+    }
+    let plan = best.unwrap();
+    debug!(
+        "Planned offline resampling of {} frames, fft_size_in: {}, fft_size_out: {}, chunks: {}",
+        total_frames, plan.fft_size_in, plan.fft_size_out, plan.nbr_chunks
+    );
+    plan
+}
+
+/// Largest prime factor allowed in the FFT lengths chosen by `smooth_fft_chunks`.
+const MAX_SMOOTH_FACTOR: usize = 7;
+
+/// Choose the number of reduced-rate units per FFT for the synchronous resamplers.
+/// The FFT lengths are `2 * fft_chunks * unit_in` and `2 * fft_chunks * unit_out`.
+///
+/// If the wanted number gives lengths with a prime factor larger than 7,
+/// the smallest number up to 25% larger that gives only small factors is used instead.
+/// Otherwise, or if there is no such number, the wanted number is returned unchanged.
+pub(crate) fn smooth_fft_chunks(unit_in: usize, unit_out: usize, wanted: usize) -> usize {
+    if largest_prime_factor(unit_in) > MAX_SMOOTH_FACTOR
+        || largest_prime_factor(unit_out) > MAX_SMOOTH_FACTOR
+    {
+        return wanted;
+    }
+    let max_chunks = wanted + (wanted / 4).max(1);
+    for fft_chunks in wanted..=max_chunks {
+        if largest_prime_factor(fft_chunks) <= MAX_SMOOTH_FACTOR {
+            if fft_chunks != wanted {
+                debug!(
+                    "Using {} instead of {} units per FFT to avoid large prime factors",
+                    fft_chunks, wanted
+                );
+            }
+            return fft_chunks;
+        }
+    }
+    wanted
+}
+
+/// Get the largest prime factor of `value`, or 1 if the value is 0 or 1.
+fn largest_prime_factor(value: usize) -> usize {
+    let mut rest = value;
+    let mut largest = 1;
+    let mut factor = 2;
+    while rest > 1 && factor * factor <= rest {
+        while integer::Integer::is_multiple_of(&rest, &factor) {
+            largest = factor;
+            rest /= factor;
+        }
+        factor += 1;
+    }
+    if rest > 1 {
+        largest = rest;
+    }
+    largest
+}
+
+/// Estimate the cost of a mixed radix FFT of length `len`,
+/// as the length times the sum of its prime factors.
+fn fft_cost(len: usize) -> f64 {
+    let mut rest = len;
+    let mut sum = 0;
+    let mut factor = 2;
+    while factor * factor <= rest {
+        while integer::Integer::is_multiple_of(&rest, &factor) {
+            sum += factor;
+            rest /= factor;
+        }
+        factor += 1;
+    }
+    if rest > 1 {
+        sum += rest;
+    }
+    len as f64 * sum as f64
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::planner::{fft_cost, largest_prime_factor, plan_offline_chunks, smooth_fft_chunks};
+    use crate::FftFixedInOut;
+    use crate::Resampler;
+
+    #[test]
+    fn cost_estimate() {
+        assert_eq!(fft_cost(8), 48.0);
+        assert_eq!(fft_cost(12), 84.0);
+        // a prime length is much slower than a power of two of similar size
+        assert!(fft_cost(1031) > 10.0 * fft_cost(1024));
+    }
+
+    #[test]
+    fn smooth_sizes() {
+        assert_eq!(largest_prime_factor(0), 1);
+        assert_eq!(largest_prime_factor(1), 1);
+        assert_eq!(largest_prime_factor(1029), 7);
+        assert_eq!(largest_prime_factor(2 * 37), 37);
+        // already smooth
+        assert_eq!(smooth_fft_chunks(147, 160, 7), 7);
+        assert_eq!(smooth_fft_chunks(1, 2, 512), 512);
+        // 37 is replaced by 40
+        assert_eq!(smooth_fft_chunks(147, 160, 37), 40);
+        assert_eq!(smooth_fft_chunks(147, 160, 11), 12);
+        // nothing to gain when the units have large factors
+        assert_eq!(smooth_fft_chunks(441, 443, 37), 37);
+
+        // asking for 5900 frames would give 37 units, with a factor 37 in the FFT lengths
+        let resampler = FftFixedInOut::<f64>::new(44100, 48000, 5900, 1).unwrap();
+        assert_eq!(resampler.fft_sizes(), (40 * 147, 40 * 160));
+    }
+
+    #[test]
+    fn offline_plan() {
+        let plan = plan_offline_chunks(44100, 48000, 441000);
+        assert_eq!(plan.fft_size_in % 147, 0);
+        assert_eq!(plan.fft_size_out, plan.fft_size_in / 147 * 160);
+        assert!(plan.fft_size_in >= 256);
+        assert!(plan.nbr_chunks * plan.fft_size_in >= 441000);
+        assert!((plan.nbr_chunks - 1) * plan.fft_size_in < 441000);
+        // no other size is estimated to be cheaper
+        for other in &[441000, 44100, 4410, 294] {
+            let other_plan = plan_offline_chunks(44100, 48000, *other);
+            let nbr_chunks = (441000 - 1) / other_plan.fft_size_in + 1;
+            let scale = nbr_chunks as f64 / other_plan.nbr_chunks as f64;
+            assert!(other_plan.cost * scale >= plan.cost);
+        }
+
+        let resampler = plan.fixed_in::<f64>(2).unwrap();
+        assert_eq!(resampler.nbr_frames_needed(), plan.fft_size_in);
+        assert_eq!(resampler.nbr_output_frames_next(), plan.fft_size_out);
+        let resampler = plan.fixed_in_out::<f64>(2).unwrap();
+        assert_eq!(resampler.nbr_frames_needed(), plan.fft_size_in);
+        assert_eq!(resampler.nbr_output_frames_next(), plan.fft_size_out);
+
+        // a short input only allows the smallest size
+        let plan = plan_offline_chunks(44100, 48000, 300);
+        assert_eq!(plan.fft_size_in, 294);
+        assert_eq!(plan.nbr_chunks, 2);
+    }
+}
@@ -0,0 +1,321 @@
+use crate::advisor::{suggest_parameters, Quality};
+use crate::convert::Dither;
+use crate::error::ResampleResult;
+use crate::{AliasingWarning, Resampler, Sample, SincFixedIn};
+use std::collections::HashMap;
+
+/// The key of a group of interchangeable resamplers in a `ResamplerPool`.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub struct PoolKey {
+    /// Input sample rate.
+    pub fs_in: usize,
+    /// Output sample rate.
+    pub fs_out: usize,
+    /// Quality used for choosing the parameters.
+    pub quality: Quality,
+}
+
+/// A resampler checked out from a `ResamplerPool`.
+/// It is used like any other resampler, and is given back to the pool with `ResamplerPool::checkin`.
+#[derive(Debug)]
+pub struct PooledResampler<T> {
+    key: PoolKey,
+    resampler: SincFixedIn<T>,
+}
+
+impl<T> PooledResampler<T> {
+    /// Get the key of the group this resampler belongs to.
+    pub fn key(&self) -> PoolKey {
+        self.key
+    }
+
+    /// Get the inner resampler.
+    pub fn inner(&mut self) -> &mut SincFixedIn<T> {
+        &mut self.resampler
+    }
+}
+
+impl<T> Resampler<T> for PooledResampler<T>
+where
+    T: Sample,
+{
+    fn process(&mut self, wave_in: &[Vec<T>]) -> ResampleResult<Vec<Vec<T>>> {
+        self.resampler.process(wave_in)
+    }
+
+    fn process_with(
+        &mut self,
+        wave_in: &[Vec<T>],
+        f: &mut dyn FnMut(usize, &[T]),
+    ) -> ResampleResult<()> {
+        self.resampler.process_with(wave_in, f)
+    }
+
+    unsafe fn process_unchecked(&mut self, wave_in: &[Vec<T>]) -> ResampleResult<Vec<Vec<T>>> {
+        self.resampler.process_unchecked(wave_in)
+    }
+
+    fn process_slices(&mut self, wave_in: &[&[T]]) -> ResampleResult<Vec<Vec<T>>> {
+        self.resampler.process_slices(wave_in)
+    }
+
+    fn process_range(
+        &mut self,
+        wave_in: &[Vec<T>],
+        offset: usize,
+        len: usize,
+    ) -> ResampleResult<Vec<Vec<T>>> {
+        self.resampler.process_range(wave_in, offset, len)
+    }
+
+    fn process_strided(
+        &mut self,
+        nbr_channels: usize,
+        wave_in: &[T],
+        stride_in: usize,
+        wave_out: &mut [T],
+        stride_out: usize,
+    ) -> ResampleResult<usize> {
+        self.resampler
+            .process_strided(nbr_channels, wave_in, stride_in, wave_out, stride_out)
+    }
+
+    fn process_interleaved_i16(
+        &mut self,
+        nbr_channels: usize,
+        wave_in: &[i16],
+        wave_out: &mut [i16],
+        dither: Option<&mut Dither>,
+    ) -> ResampleResult<usize> {
+        self.resampler
+            .process_interleaved_i16(nbr_channels, wave_in, wave_out, dither)
+    }
+
+    fn output_delay(&self) -> f64 {
+        self.resampler.output_delay()
+    }
+
+    fn reset(&mut self) {
+        self.resampler.reset();
+    }
+
+    fn flush(&mut self) -> ResampleResult<Vec<Vec<T>>> {
+        self.resampler.flush()
+    }
+
+    fn nbr_frames_needed(&self) -> usize {
+        self.resampler.nbr_frames_needed()
+    }
+
+    fn nbr_output_frames_next(&self) -> usize {
+        self.resampler.nbr_output_frames_next()
+    }
+
+    fn set_resample_ratio(&mut self, new_ratio: f64) -> ResampleResult<()> {
+        self.resampler.set_resample_ratio(new_ratio)
+    }
+
+    fn set_resample_ratio_relative(&mut self, rel_ratio: f64) -> ResampleResult<()> {
+        self.resampler.set_resample_ratio_relative(rel_ratio)
+    }
+
+    fn aliasing_warning(&self) -> Option<AliasingWarning> {
+        self.resampler.aliasing_warning()
+    }
+
+    fn resample_ratio(&self) -> f64 {
+        self.resampler.resample_ratio()
+    }
+
+    fn resample_ratio_original(&self) -> f64 {
+        self.resampler.resample_ratio_original()
+    }
+
+    fn chunk_size(&self) -> usize {
+        self.resampler.chunk_size()
+    }
+
+    fn nbr_channels(&self) -> usize {
+        self.resampler.nbr_channels()
+    }
+
+    fn sinc_len(&self) -> Option<usize> {
+        self.resampler.sinc_len()
+    }
+}
+
+/// A pool of preallocated resamplers, for applications that frequently start and stop streams,
+/// like a voice server creating a resampler for each call.
+///
+/// The resamplers are `SincFixedIn` instances, grouped by input and output sample rate and quality,
+/// all using the same chunk size and number of channels.
+/// The pool calculates the sinc tables once for each group, and keeps them as a filter bank
+/// that is shared by all resamplers of the group.
+/// A new resampler then only needs its own buffers and state,
+/// and even that is avoided by taking an idle one from the pool with `checkout`,
+/// and returning it with `checkin` when the stream ends.
+/// Returned resamplers are reset, so that each checkout starts with a clean state
+/// and the original resample ratio. Settings like metering and normalization are kept.
+///
+/// The pool itself is not synchronized, and must be wrapped in for example a `Mutex`
+/// to be shared between threads.
+#[derive(Debug)]
+pub struct ResamplerPool<T> {
+    chunk_size: usize,
+    nbr_channels: usize,
+    idle: HashMap<PoolKey, Vec<SincFixedIn<T>>>,
+    banks: HashMap<PoolKey, SincFixedIn<T>>,
+}
+
+impl<T> ResamplerPool<T>
+where
+    T: Sample,
+{
+    /// Create a new empty pool, for resamplers with the given chunk size and number of channels.
+    pub fn new(chunk_size: usize, nbr_channels: usize) -> Self {
+        ResamplerPool {
+            chunk_size,
+            nbr_channels,
+            idle: HashMap::new(),
+            banks: HashMap::new(),
+        }
+    }
+
+    /// Create `count` idle resamplers for the given sample rates and quality.
+    ///
+    /// # Errors
+    ///
+    /// The function returns `ResampleError::InvalidParameter` if a sample rate is zero.
+    pub fn preallocate(
+        &mut self,
+        fs_in: usize,
+        fs_out: usize,
+        quality: Quality,
+        count: usize,
+    ) -> ResampleResult<()> {
+        debug!(
+            "Preallocate {} resamplers from {} to {} with quality {:?}",
+            count, fs_in, fs_out, quality
+        );
+        let key = PoolKey {
+            fs_in,
+            fs_out,
+            quality,
+        };
+        for _ in 0..count {
+            let resampler = self.create(key)?;
+            self.idle.entry(key).or_default().push(resampler);
+        }
+        Ok(())
+    }
+
+    /// Get a resampler for the given sample rates and quality.
+    /// An idle one is used if there is one, otherwise a new one is created.
+    ///
+    /// # Errors
+    ///
+    /// The function returns `ResampleError::InvalidParameter` if a sample rate is zero.
+    pub fn checkout(
+        &mut self,
+        fs_in: usize,
+        fs_out: usize,
+        quality: Quality,
+    ) -> ResampleResult<PooledResampler<T>> {
+        let key = PoolKey {
+            fs_in,
+            fs_out,
+            quality,
+        };
+        let resampler = match self.idle.get_mut(&key).and_then(|idle| idle.pop()) {
+            Some(resampler) => resampler,
+            None => {
+                debug!("No idle resampler for {:?}, creating a new one", key);
+                self.create(key)?
+            }
+        };
+        Ok(PooledResampler { key, resampler })
+    }
+
+    /// Return a resampler to the pool. It is reset, and then kept for the next checkout.
+    pub fn checkin(&mut self, resampler: PooledResampler<T>) {
+        let PooledResampler { key, mut resampler } = resampler;
+        resampler.reset();
+        self.idle.entry(key).or_default().push(resampler);
+    }
+
+    /// Get the number of idle resamplers for the given sample rates and quality.
+    pub fn nbr_idle(&self, fs_in: usize, fs_out: usize, quality: Quality) -> usize {
+        let key = PoolKey {
+            fs_in,
+            fs_out,
+            quality,
+        };
+        self.idle.get(&key).map(|idle| idle.len()).unwrap_or(0)
+    }
+
+    /// Get the number of filter banks, one for each combination of sample rates and quality
+    /// that has been used.
+    pub fn nbr_filter_banks(&self) -> usize {
+        self.banks.len()
+    }
+
+    /// Create a new resampler, as a clone of an unused prototype that holds the filter bank.
+    /// The prototype is created the first time a key is used.
+    fn create(&mut self, key: PoolKey) -> ResampleResult<SincFixedIn<T>> {
+        if let Some(bank) = self.banks.get(&key) {
+            return Ok(bank.clone());
+        }
+        debug!("Create filter bank for {:?}", key);
+        let parameters = suggest_parameters(key.fs_in, key.fs_out, key.quality);
+        let bank = SincFixedIn::new(
+            key.fs_out as f64 / key.fs_in as f64,
+            parameters,
+            self.chunk_size,
+            self.nbr_channels,
+        )?;
+        self.banks.insert(key, bank.clone());
+        Ok(bank)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::pool::ResamplerPool;
+    use crate::{Quality, Resampler};
+
+    #[test]
+    fn recycle() {
+        let mut pool = ResamplerPool::<f64>::new(256, 1);
+        pool.preallocate(8000, 16000, Quality::Low, 2).unwrap();
+        assert_eq!(pool.nbr_idle(8000, 16000, Quality::Low), 2);
+        assert_eq!(pool.nbr_idle(8000, 16000, Quality::High), 0);
+
+        let input = vec![(0..256)
+            .map(|n| (n as f64 * 0.1).sin())
+            .collect::<Vec<f64>>()];
+        let mut first = pool.checkout(8000, 16000, Quality::Low).unwrap();
+        assert_eq!(pool.nbr_idle(8000, 16000, Quality::Low), 1);
+        let expected = first.process(&input).unwrap();
+        first.process(&input).unwrap();
+        first.set_resample_ratio(2.1).unwrap();
+        pool.checkin(first);
+        assert_eq!(pool.nbr_idle(8000, 16000, Quality::Low), 2);
+
+        // a recycled resampler behaves like a new one
+        let mut second = pool.checkout(8000, 16000, Quality::Low).unwrap();
+        let mut third = pool.checkout(8000, 16000, Quality::Low).unwrap();
+        assert_eq!(second.process(&input).unwrap(), expected);
+        assert_eq!(third.process(&input).unwrap(), expected);
+
+        // an empty group creates new resamplers
+        let other = pool.checkout(48000, 16000, Quality::High).unwrap();
+        assert_eq!(other.nbr_frames_needed(), 256);
+        pool.checkin(other);
+        assert_eq!(pool.nbr_idle(48000, 16000, Quality::High), 1);
+
+        // all resamplers of a group share one filter bank
+        let mut extra = pool.checkout(8000, 16000, Quality::Low).unwrap();
+        assert_eq!(extra.process(&input).unwrap(), expected);
+        assert_eq!(pool.nbr_filter_banks(), 2);
+    }
+}
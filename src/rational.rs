@@ -0,0 +1,170 @@
+use num_integer as integer;
+
+/// A resample ratio expressed as a fraction, as returned by `snap_ratio`.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub struct RationalRatio {
+    /// Number of output frames produced per `denominator` input frames.
+    /// This is also the oversampling factor needed to resample exactly at this ratio.
+    pub numerator: usize,
+    /// Number of input frames consumed per `numerator` output frames.
+    pub denominator: usize,
+}
+
+impl RationalRatio {
+    /// Get the value of the fraction.
+    pub fn value(&self) -> f64 {
+        self.numerator as f64 / self.denominator as f64
+    }
+
+    /// Get the ratio between two sample rates as a reduced fraction.
+    ///
+    /// # Panics
+    ///
+    /// Panics if a sample rate is zero.
+    pub fn from_rates(fs_in: usize, fs_out: usize) -> Self {
+        assert!(
+            fs_in > 0 && fs_out > 0,
+            "Sample rates must be larger than zero"
+        );
+        let gcd = integer::gcd(fs_in, fs_out);
+        RationalRatio {
+            numerator: fs_out / gcd,
+            denominator: fs_in / gcd,
+        }
+    }
+}
+
+/// Find the fraction closest to `ratio` with a numerator of at most `max_numerator`.
+///
+/// An asynchronous resampler using `Nearest` interpolation gives the same result
+/// as a synchronous one when the ratio is a fraction L/M, and the oversampling factor is L.
+/// The step between output frames is then M/L input frames, so every output frame
+/// falls exactly on one of the intermediate points.
+/// Since the memory for the sinc filters grows with the oversampling factor,
+/// the numerator must be kept reasonably small, a few hundred at most.
+///
+/// The best fraction is found from the continued fraction expansion of the step, 1/ratio.
+/// Returns `None` if the relative difference between the fraction and `ratio`
+/// is larger than `tolerance`.
+pub fn snap_ratio(ratio: f64, max_numerator: usize, tolerance: f64) -> Option<RationalRatio> {
+    if ratio.is_nan() || ratio <= 0.0 || max_numerator == 0 {
+        return None;
+    }
+    let step = 1.0 / ratio;
+    // convergents p/q of the step, where q is the numerator of the ratio
+    let (mut p0, mut q0, mut p1, mut q1) = (0usize, 1usize, 1usize, 0usize);
+    let mut rest = step;
+    loop {
+        let whole = rest.floor();
+        if whole > (usize::MAX / 2) as f64 {
+            break;
+        }
+        let whole = whole as usize;
+        let q2 = whole * q1 + q0;
+        if q2 > max_numerator {
+            // the best semiconvergent within the limit may be closer than the last convergent
+            if let Some(k) = (max_numerator - q0).checked_div(q1) {
+                let (p_semi, q_semi) = (p0 + k * p1, q0 + k * q1);
+                if (p_semi as f64 / q_semi as f64 - step).abs()
+                    < (p1 as f64 / q1 as f64 - step).abs()
+                {
+                    p1 = p_semi;
+                    q1 = q_semi;
+                }
+            }
+            break;
+        }
+        let p2 = whole * p1 + p0;
+        p0 = p1;
+        q0 = q1;
+        p1 = p2;
+        q1 = q2;
+        let fraction = rest - rest.floor();
+        if fraction < 1.0e-12 {
+            break;
+        }
+        rest = 1.0 / fraction;
+    }
+    if p1 == 0 || q1 == 0 {
+        return None;
+    }
+    let snapped = RationalRatio {
+        numerator: q1,
+        denominator: p1,
+    };
+    let deviation = (snapped.value() - ratio).abs() / ratio;
+    if deviation > tolerance {
+        debug!(
+            "No fraction with a numerator up to {} within {} of ratio {}",
+            max_numerator, tolerance, ratio
+        );
+        return None;
+    }
+    debug!(
+        "Snapped ratio {} to {}/{}",
+        ratio, snapped.numerator, snapped.denominator
+    );
+    Some(snapped)
+}
+
+/// Get the ratio as a reduced fraction with a numerator that divides `oversampling_factor`,
+/// if there is one. This is the case when `Nearest` interpolation gives an exact result.
+pub(crate) fn exact_fraction(ratio: f64, oversampling_factor: usize) -> Option<RationalRatio> {
+    let denominator = (oversampling_factor as f64 / ratio).round();
+    if denominator < 1.0 {
+        return None;
+    }
+    let fraction = oversampling_factor as f64 / denominator;
+    if (fraction - ratio).abs() > 1.0e-9 * ratio {
+        return None;
+    }
+    let denominator = denominator as usize;
+    let gcd = integer::gcd(oversampling_factor, denominator);
+    Some(RationalRatio {
+        numerator: oversampling_factor / gcd,
+        denominator: denominator / gcd,
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::rational::{exact_fraction, snap_ratio, RationalRatio};
+
+    #[test]
+    fn from_rates() {
+        let fraction = RationalRatio::from_rates(44100, 48000);
+        assert_eq!((fraction.numerator, fraction.denominator), (160, 147));
+        assert_eq!(fraction.value(), 160.0 / 147.0);
+    }
+
+    #[test]
+    fn snap_ratios() {
+        let snapped = snap_ratio(48000.0 / 44100.0 * (1.0 + 1.0e-9), 256, 1.0e-6).unwrap();
+        assert_eq!(
+            snapped,
+            RationalRatio {
+                numerator: 160,
+                denominator: 147
+            }
+        );
+        let snapped = snap_ratio(0.5, 256, 1.0e-6).unwrap();
+        assert_eq!((snapped.numerator, snapped.denominator), (1, 2));
+        let snapped = snap_ratio(3.0, 256, 1.0e-6).unwrap();
+        assert_eq!((snapped.numerator, snapped.denominator), (3, 1));
+        // pi is close to 22/7, but the best with a numerator up to 8 is 3/1
+        assert_eq!(snap_ratio(std::f64::consts::PI, 8, 1.0e-2), None);
+        let snapped = snap_ratio(std::f64::consts::PI, 8, 0.05).unwrap();
+        assert_eq!((snapped.numerator, snapped.denominator), (3, 1));
+        let snapped = snap_ratio(std::f64::consts::PI, 22, 1.0e-3).unwrap();
+        assert_eq!((snapped.numerator, snapped.denominator), (22, 7));
+        assert_eq!(snap_ratio(0.0, 256, 1.0), None);
+    }
+
+    #[test]
+    fn exact_fractions() {
+        let fraction = exact_fraction(160.0 / 147.0, 320).unwrap();
+        assert_eq!((fraction.numerator, fraction.denominator), (160, 147));
+        assert_eq!(exact_fraction(160.0 / 147.0, 256), None);
+        assert_eq!(exact_fraction(0.3, 4), None);
+    }
+}
@@ -0,0 +1,142 @@
+use crate::asynchro::design_cutoff;
+use crate::sinc::sinc;
+use crate::windows::make_window;
+use crate::InterpolationParameters;
+
+/// Smallest number of window points per sinc tap, used for evaluating the window
+/// between the points.
+const MIN_WINDOW_RESOLUTION: usize = 256;
+
+/// A direct windowed sinc resampler, that is intentionally simple and slow.
+///
+/// Each output sample is calculated by evaluating the continuous-time band-limited
+/// reconstruction of the input at the exact output instant, without any oversampled tables
+/// or interpolation between sincs. This makes it useful as a ground truth when testing
+/// the real resamplers, or when validating a set of parameters.
+/// The filter uses the same sinc length, cutoff and window as the asynchronous resamplers
+/// created with the same parameters, while the oversampling factor and interpolation type
+/// only control how finely the window is sampled.
+///
+/// The calculation is done in `f64`, and works on a complete channel at a time.
+/// Available with the `testing` feature.
+#[derive(Debug, Clone)]
+pub struct ReferenceResampler {
+    resample_ratio: f64,
+    sinc_len: usize,
+    cutoff: f64,
+    resolution: usize,
+    window: Vec<f64>,
+}
+
+impl ReferenceResampler {
+    /// Create a new reference resampler, for the given ratio and parameters.
+    pub fn new(resample_ratio: f64, parameters: &InterpolationParameters) -> Self {
+        let sinc_len = 8 * (((parameters.sinc_len as f32) / 8.0).ceil() as usize);
+        let factor = parameters.oversampling_factor.max(1);
+        // a multiple of the oversampling factor, so that custom taps can be used
+        let mut resolution = factor;
+        while resolution < MIN_WINDOW_RESOLUTION {
+            resolution += factor;
+        }
+        let window = make_window::<f64>(sinc_len * resolution, parameters.window.clone());
+        let cutoff = design_cutoff(resample_ratio, parameters.f_cutoff) as f64;
+        debug!(
+            "Create new ReferenceResampler, ratio: {}, sinc_len: {}, cutoff: {}",
+            resample_ratio, sinc_len, cutoff
+        );
+        ReferenceResampler {
+            resample_ratio,
+            sinc_len,
+            cutoff,
+            resolution,
+            window,
+        }
+    }
+
+    /// Get the value of the window at `offset` input frames from the center of the filter.
+    fn window_at(&self, offset: f64) -> f64 {
+        let position = (offset + (self.sinc_len / 2) as f64) * self.resolution as f64;
+        if position < 0.0 {
+            return 0.0;
+        }
+        let idx = position.floor() as usize;
+        let frac = position - idx as f64;
+        if idx + 1 >= self.window.len() {
+            return 0.0;
+        }
+        (1.0 - frac) * self.window[idx] + frac * self.window[idx + 1]
+    }
+
+    /// Evaluate the band-limited reconstruction of `wave` at `position`,
+    /// given in input frames. Samples outside of `wave` are taken to be zero.
+    pub fn value_at(&self, wave: &[f64], position: f64) -> f64 {
+        let half_len = (self.sinc_len / 2) as f64;
+        let first = (position - half_len).ceil().max(0.0) as usize;
+        let last = (position + half_len).floor();
+        if last < 0.0 {
+            return 0.0;
+        }
+        let last = (last as usize).min(wave.len().saturating_sub(1));
+        let mut value = 0.0;
+        for (idx, sample) in wave.iter().enumerate().take(last + 1).skip(first) {
+            let offset = position - idx as f64;
+            value += sample * self.cutoff * sinc(offset * self.cutoff) * self.window_at(offset);
+        }
+        value
+    }
+
+    /// Resample a complete channel.
+    /// Output frame `n` is the reconstruction at input position `n / resample_ratio - delay`,
+    /// where `delay` is in input frames. Use the `input_frames` of the latency of
+    /// another resampler as delay to line up the outputs.
+    /// The output has `len * resample_ratio` frames, rounded up.
+    pub fn resample(&self, wave: &[f64], delay: f64) -> Vec<f64> {
+        let nbr_frames = (wave.len() as f64 * self.resample_ratio).ceil() as usize;
+        (0..nbr_frames)
+            .map(|n| self.value_at(wave, n as f64 / self.resample_ratio - delay))
+            .collect()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::reference::ReferenceResampler;
+    use crate::{InterpolationParameters, InterpolationType, WindowFunction};
+    use crate::{Resampler, SincFixedIn};
+
+    #[test]
+    fn match_sinc_resampler() {
+        let params = InterpolationParameters {
+            sinc_len: 128,
+            f_cutoff: 0.95,
+            interpolation: InterpolationType::Cubic,
+            oversampling_factor: 256,
+            window: WindowFunction::BlackmanHarris2,
+        };
+        let reference = ReferenceResampler::new(1.5, &params);
+        // a constant is reconstructed as the same constant
+        let ones = vec![1.0; 400];
+        assert!((reference.value_at(&ones, 200.3) - 1.0).abs() < 1.0e-3);
+        assert_eq!(reference.value_at(&ones, -100.0), 0.0);
+
+        let wave: Vec<f64> = (0..4096)
+            .map(|n| (n as f64 * 0.05).sin() + 0.5 * (n as f64 * 0.71).sin())
+            .collect();
+        let mut resampler = SincFixedIn::<f64>::new(1.5, params.clone(), 1024, 1).unwrap();
+        let delay = resampler.latency().input_frames;
+        let mut output = Vec::new();
+        for chunk in wave.chunks(1024) {
+            output.extend(resampler.process(&[chunk.to_vec()]).unwrap().remove(0));
+        }
+        let expected = reference.resample(&wave, delay);
+        // skip the start, where the resampler is filling its buffer
+        let start = 3 * params.sinc_len;
+        let end = output.len().min(expected.len()) - params.sinc_len;
+        let max_error = output[start..end]
+            .iter()
+            .zip(expected[start..end].iter())
+            .map(|(out, exp)| (out - exp).abs())
+            .fold(0.0, f64::max);
+        assert!(max_error < 1.0e-6, "max error {}", max_error);
+    }
+}
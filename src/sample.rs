@@ -1,17 +1,32 @@
-use crate::{AvxSample, NeonSample, SseSample};
+use crate::{Avx512Sample, AvxSample, NeonSample, PortableSample, SseSample, WasmSample};
 
 /// The trait governing a single sample.
 ///
+/// The resamplers, the interpolation kernels and the sinc generation are all generic over this trait.
+/// It extends `num_traits::Float`, so that generic code can use the usual floating point functions,
+/// like `sin`, `abs` and `max`, on any sample type.
+///
 /// There are two types which implements this trait so far:
 /// * [f32]
 /// * [f64]
+///
+/// A new type also needs implementations of the marker traits for the SIMD interpolators
+/// of the enabled features, which are used to pick an accelerated kernel when one exists.
 pub trait Sample
 where
     Self: Copy
         + CoerceFrom<usize>
         + CoerceFrom<f64>
         + CoerceFrom<f32>
-        + realfft::FftNum
+        + num_traits::Float
+        + num_traits::FromPrimitive
+        + num_traits::Signed
+        + std::fmt::Debug
+        + Send
+        + Sync
+        + 'static
+        + std::cmp::PartialOrd
+        + num_traits::ToPrimitive
         + std::ops::Mul
         + std::ops::Div
         + std::ops::Add
@@ -22,17 +37,14 @@ where
         + std::ops::SubAssign
         + std::ops::AddAssign
         + AvxSample
+        + Avx512Sample
         + SseSample
-        + NeonSample,
+        + NeonSample
+        + PortableSample
+        + WasmSample,
 {
     const PI: Self;
 
-    /// Calculate the sine of `self`.
-    fn sin(self) -> Self;
-
-    /// Calculate the cosine of `self`.
-    fn cos(self) -> Self;
-
     /// Coerce `value` into the current type.
     ///
     /// Coercions are governed through the [CoerceFrom] trait.
@@ -46,26 +58,10 @@ where
 
 impl Sample for f32 {
     const PI: Self = std::f32::consts::PI;
-
-    fn sin(self) -> Self {
-        f32::sin(self)
-    }
-
-    fn cos(self) -> Self {
-        f32::cos(self)
-    }
 }
 
 impl Sample for f64 {
     const PI: Self = std::f64::consts::PI;
-
-    fn sin(self) -> Self {
-        f64::sin(self)
-    }
-
-    fn cos(self) -> Self {
-        f64::cos(self)
-    }
 }
 
 /// The trait used to coerce a value infallibly from one type to another.
@@ -1,5 +1,6 @@
 use crate::windows::{make_window, WindowFunction};
-use crate::Sample;
+use crate::{InterpolationParameters, Sample};
+use std::fmt::Write;
 
 /// Helper function: sinc(x) = sin(pi*x)/(pi*x)
 pub fn sinc<T>(value: T) -> T
@@ -13,6 +14,23 @@ where
     }
 }
 
+/// Helper function. Calculate a checksum of a sequence of filter coefficients.
+/// This is a 64-bit FNV-1a hash of the bits of the values converted to `f64`,
+/// so it gives the same result for the same coefficients, on all platforms.
+pub fn checksum_values<I>(values: I) -> u64
+where
+    I: IntoIterator<Item = f64>,
+{
+    let mut hash: u64 = 0xcbf2_9ce4_8422_2325;
+    for value in values {
+        for byte in value.to_bits().to_le_bytes().iter() {
+            hash ^= u64::from(*byte);
+            hash = hash.wrapping_mul(0x0100_0000_01b3);
+        }
+    }
+    hash
+}
+
 /// Helper function. Make a set of windowed sincs.  
 pub fn make_sincs<T>(
     npoints: usize,
@@ -20,35 +38,156 @@ pub fn make_sincs<T>(
     f_cutoff: f32,
     windowfunc: WindowFunction,
 ) -> Vec<Vec<T>>
+where
+    T: Sample,
+{
+    let y = make_prototype(npoints, factor, f_cutoff, windowfunc);
+    debug!(
+        "Generate sincs, length: {}, oversampling: {}",
+        npoints, factor
+    );
+    make_sincs_from_prototype(&y, factor)
+}
+
+/// Helper function. Make a windowed sinc prototype filter, with `npoints * factor` taps.
+pub fn make_prototype<T>(
+    npoints: usize,
+    factor: usize,
+    f_cutoff: f32,
+    windowfunc: WindowFunction,
+) -> Vec<T>
 where
     T: Sample,
 {
     let totpoints = npoints * factor;
+    if let WindowFunction::CustomTaps(taps) = &windowfunc {
+        assert!(
+            taps.len() == npoints || taps.len() == totpoints,
+            "Custom window has {} taps, expected {} or {}",
+            taps.len(),
+            npoints,
+            totpoints
+        );
+    }
     let mut y = Vec::with_capacity(totpoints);
     let window = make_window::<T>(totpoints, windowfunc);
-    let mut sum = T::zero();
     for (x, w) in window.iter().enumerate().take(totpoints) {
         let val = *w
             * sinc(
                 (T::coerce(x) - T::coerce(totpoints / 2)) * T::coerce(f_cutoff) / T::coerce(factor),
             );
-        sum += val;
         y.push(val);
     }
-    sum /= T::coerce(factor);
+    y
+}
+
+/// Helper function. Convolve a prototype filter with `factor` taps per frame
+/// with a user filter at the input rate, given by `taps`.
+/// The result is padded with zeros to a multiple of 8 frames, split evenly on both sides,
+/// so that the center of the result lines up with the center of the padded length.
+/// A symmetric user filter then adds no delay.
+pub fn fuse_filter<T>(prototype: &[T], factor: usize, taps: &[T]) -> Vec<T>
+where
+    T: Sample,
+{
+    let fused_len = prototype.len() + factor * taps.len().saturating_sub(1);
+    let npoints = 8 * (((fused_len / factor) as f32 / 8.0).ceil() as usize);
+    let front = (npoints * factor - fused_len) / 2;
+    let mut fused = vec![T::zero(); npoints * factor];
+    for (m, tap) in taps.iter().enumerate() {
+        for (value, proto) in fused[front + m * factor..].iter_mut().zip(prototype.iter()) {
+            *value += *tap * *proto;
+        }
+    }
     debug!(
-        "Generate sincs, length: {}, oversampling: {}, normalized by: {:?}",
-        npoints, factor, sum
+        "Fuse {} taps into prototype, new length: {}",
+        taps.len(),
+        npoints
+    );
+    fused
+}
+
+/// Helper function. Split a prototype filter into a set of sincs, one per oversampling step.
+/// The prototype is normalized to give unity gain at zero frequency.
+pub fn make_sincs_from_prototype<T>(prototype: &[T], factor: usize) -> Vec<Vec<T>>
+where
+    T: Sample,
+{
+    let npoints = prototype.len() / factor;
+    assert!(
+        npoints * factor == prototype.len(),
+        "Prototype has {} taps, which is not a multiple of the oversampling factor {}",
+        prototype.len(),
+        factor
     );
+    let mut sum = T::zero();
+    for val in prototype.iter() {
+        sum += *val;
+    }
+    sum /= T::coerce(factor);
+    debug!("Normalize prototype filter by: {:?}", sum);
     let mut sincs = vec![vec![T::zero(); npoints]; factor];
     for p in 0..npoints {
         for n in 0..factor {
-            sincs[factor - n - 1][p] = y[factor * p + n] / sum;
+            sincs[factor - n - 1][p] = prototype[factor * p + n] / sum;
         }
     }
     sincs
 }
 
+/// Generate Rust source code for a static sinc table, for building the tables at compile time.
+///
+/// This is meant to be called from a build script, with the output written to a file in `OUT_DIR`
+/// and included with `include!`. The source defines two items, where `name` is the given name:
+/// - `name`: a `[T; N]` static with the sincs for the given parameters and ratio,
+///   stored one after the other, as expected by `SincFixedIn::new_from_table`
+///   and `SincFixedOut::new_from_table`, or by `interpolator_table::TableInterpolator`.
+/// - `name_SINC_LEN`: a `usize` constant with the length of each sinc.
+///
+/// The type `T` is the sample type of the resamplers that will use the table, `f32` or `f64`.
+///
+/// The sinc length is rounded up to a multiple of 8, and the cutoff is adjusted for
+/// the resample ratio, in the same way as when the tables are made by `SincFixedIn::new`.
+/// A resampler made from the table therefore only matches the ratio it was generated for.
+pub fn sinc_table_source<T>(
+    name: &str,
+    parameters: &InterpolationParameters,
+    resample_ratio: f64,
+) -> String
+where
+    T: Sample,
+{
+    let sinc_len = 8 * (((parameters.sinc_len as f32) / 8.0).ceil() as usize);
+    let f_cutoff = if resample_ratio >= 1.0 {
+        parameters.f_cutoff
+    } else {
+        parameters.f_cutoff * resample_ratio as f32
+    };
+    let sincs = make_sincs::<f64>(
+        sinc_len,
+        parameters.oversampling_factor,
+        f_cutoff,
+        parameters.window.clone(),
+    );
+    let mut source = String::new();
+    writeln!(source, "pub const {}_SINC_LEN: usize = {};", name, sinc_len).unwrap();
+    writeln!(
+        source,
+        "pub static {}: [{}; {}] = [",
+        name,
+        std::any::type_name::<T>(),
+        sinc_len * parameters.oversampling_factor
+    )
+    .unwrap();
+    for sinc in sincs.iter() {
+        for value in sinc.iter() {
+            writeln!(source, "    {:?},", T::coerce(*value)).unwrap();
+        }
+    }
+    source.push_str("];\n");
+    source
+}
+
 #[cfg(test)]
 mod tests {
     use crate::sinc::make_sincs;
@@ -61,4 +200,12 @@ mod tests {
         let sum: f64 = sincs.iter().map(|v| v.iter().sum::<f64>()).sum();
         assert!((sum - 8.0).abs() < 0.00001);
     }
+
+    #[test]
+    fn sincs_gaussian() {
+        let sincs = make_sincs::<f64>(32, 8, 0.9, WindowFunction::Gaussian(0.4));
+        assert!((sincs[7][16] - 1.0).abs() < 0.2);
+        let sum: f64 = sincs.iter().map(|v| v.iter().sum::<f64>()).sum();
+        assert!((sum - 8.0).abs() < 0.00001);
+    }
 }
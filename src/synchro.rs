@@ -1,21 +1,38 @@
-use crate::sinc::make_sincs;
+use crate::asynchro::interp_cubic;
+use crate::sinc::{checksum_values, make_sincs};
 use crate::windows::WindowFunction;
 use num_complex::Complex;
 use num_integer as integer;
 use num_traits::Zero;
+use std::fmt;
 use std::sync::Arc;
 
 use crate::error::{ResampleError, ResampleResult};
-use crate::{Resampler, Sample};
-use realfft::{ComplexToReal, RealFftPlanner, RealToComplex};
+use crate::fft::{default_backend, FftBackend, ForwardFft, InverseFft};
+use crate::metering::{Meter, Metrics};
+use crate::offline;
+use crate::parallel::{for_each_channel, nbr_workers, DEFAULT_PARALLEL_THRESHOLD};
+use crate::planner::smooth_fft_chunks;
+use crate::{Latency, Resampler, Sample};
 
 /// A helper for resampling a single chunk of data.
 struct FftResampler<T> {
+    filter: FftFilter<T>,
+    scratches: Vec<FftScratch<T>>,
+}
+
+/// The anti-aliasing filter and the FFT plans, which are shared by all channels.
+struct FftFilter<T> {
     fft_size_in: usize,
     fft_size_out: usize,
     filter_f: Vec<Complex<T>>,
-    fft: Arc<dyn RealToComplex<T>>,
-    ifft: Arc<dyn ComplexToReal<T>>,
+    fft: Arc<dyn ForwardFft<T>>,
+    ifft: Arc<dyn InverseFft<T>>,
+}
+
+/// The buffers used while resampling a chunk of one channel.
+/// There is one of these per channel when the channels are processed in parallel.
+struct FftScratch<T> {
     scratch_fw: Vec<Complex<T>>,
     scratch_inv: Vec<Complex<T>>,
     input_buf: Vec<T>,
@@ -24,12 +41,30 @@ struct FftResampler<T> {
     output_buf: Vec<T>,
 }
 
+/// Largest relative deviation from the original ratio that `FftFixedIn` accepts.
+const MAX_TRIM: f64 = 0.001;
+
+/// A fine adjustment of the resample ratio, made by cubic interpolation of the output
+/// of an FFT resampler. This is only meant for very small changes, to follow clock drift.
+/// The trimmer passes the output through unchanged until the first time the ratio is changed.
+struct Trimmer<T> {
+    active: bool,
+    rel_ratio: f64,
+    phase: f64,
+    pending: Vec<Vec<T>>,
+    last: Vec<T>,
+}
+
 /// A synchronous resampler that needs a fixed number of audio frames for input
 /// and returns a variable number of frames.
 ///
 /// The resampling is done by FFT:ing the input data. The spectrum is then extended or
 /// truncated as well as multiplied with an antialiasing filter
 /// before it's inverse transformed to get the resampled waveforms.
+///
+/// Unlike the other synchronous resamplers, the ratio can be trimmed by up to +-0.1%
+/// with "set_resample_ratio", to compensate for clock drift.
+/// This is done by cubic interpolation of the output, see "set_resample_ratio_relative".
 pub struct FftFixedIn<T> {
     nbr_channels: usize,
     chunk_size_in: usize,
@@ -39,6 +74,9 @@ pub struct FftFixedIn<T> {
     input_buffers: Vec<Vec<T>>,
     saved_frames: usize,
     resampler: FftResampler<T>,
+    parallel_threshold: usize,
+    meter: Meter<T>,
+    trimmer: Trimmer<T>,
 }
 
 /// A synchronous resampler that needs a varying number of audio frames for input
@@ -57,6 +95,8 @@ pub struct FftFixedOut<T> {
     saved_frames: usize,
     frames_needed: usize,
     resampler: FftResampler<T>,
+    parallel_threshold: usize,
+    meter: Meter<T>,
 }
 
 /// A synchronous resampler that accepts a fixed number of audio frames for input
@@ -71,7 +111,99 @@ pub struct FftFixedInOut<T> {
     chunk_size_out: usize,
     fft_size_in: usize,
     overlaps: Vec<Vec<T>>,
+    output_buffer: Vec<Vec<T>>,
     resampler: FftResampler<T>,
+    parallel_threshold: usize,
+    meter: Meter<T>,
+}
+
+impl<T> Trimmer<T>
+where
+    T: Sample,
+{
+    fn new(nbr_channels: usize) -> Self {
+        Trimmer {
+            active: false,
+            rel_ratio: 1.0,
+            phase: 0.0,
+            pending: vec![Vec::new(); nbr_channels],
+            last: vec![T::zero(); nbr_channels],
+        }
+    }
+
+    /// Set a new relative ratio.
+    /// When called for the first time, the interpolation starts from the last output frame,
+    /// so that there is no discontinuity.
+    fn set_ratio(&mut self, rel_ratio: f64) {
+        if !self.active {
+            self.active = true;
+            self.phase = 1.0;
+            for (pending, last) in self.pending.iter_mut().zip(self.last.iter()) {
+                pending.clear();
+                pending.push(*last);
+            }
+        }
+        self.rel_ratio = rel_ratio;
+    }
+
+    /// Get the number of frames that are returned when `nbr_new` new frames are added.
+    fn output_len(&self, nbr_new: usize) -> usize {
+        if !self.active {
+            return nbr_new;
+        }
+        let available = self.pending.first().map(|p| p.len()).unwrap_or(0) + nbr_new;
+        let step = 1.0 / self.rel_ratio;
+        let mut nbr_frames = 0;
+        // the cubic interpolation needs two frames after the interpolated point
+        while (self.phase + nbr_frames as f64 * step).floor() as usize + 2 < available {
+            nbr_frames += 1;
+        }
+        nbr_frames
+    }
+
+    /// Trim the output of the FFT resampler.
+    /// Ignored channels must be empty, and the other channels must have the same length.
+    fn process(&mut self, mut wave_out: Vec<Vec<T>>) -> Vec<Vec<T>> {
+        if !self.active {
+            for (wave, last) in wave_out.iter().zip(self.last.iter_mut()) {
+                if let Some(value) = wave.last() {
+                    *last = *value;
+                }
+            }
+            return wave_out;
+        }
+        let nbr_new = wave_out.iter().map(|wave| wave.len()).max().unwrap_or(0);
+        let nbr_frames = self.output_len(nbr_new);
+        let step = 1.0 / self.rel_ratio;
+        for (wave, pending) in wave_out.iter_mut().zip(self.pending.iter_mut()) {
+            if wave.is_empty() {
+                // keep ignored channels aligned with the others
+                pending.resize(pending.len() + nbr_new, T::zero());
+                continue;
+            }
+            pending.extend_from_slice(wave);
+            wave.clear();
+            for n in 0..nbr_frames {
+                let position = self.phase + n as f64 * step;
+                let index = position.floor() as usize;
+                let frac = T::coerce(position - position.floor());
+                let yvals = [
+                    pending[index - 1],
+                    pending[index],
+                    pending[index + 1],
+                    pending[index + 2],
+                ];
+                wave.push(interp_cubic(frac, &yvals));
+            }
+        }
+        let end = self.phase + nbr_frames as f64 * step;
+        let consumed = end.floor() as usize - 1;
+        for pending in self.pending.iter_mut() {
+            pending.drain(..consumed);
+        }
+        self.phase = end - consumed as f64;
+        wave_out
+    }
 }
 
 impl<T> FftResampler<T>
@@ -79,7 +211,11 @@ where
     T: Sample,
 {
     //
-    pub fn new(fft_size_in: usize, fft_size_out: usize) -> Self {
+    pub fn new(
+        fft_size_in: usize,
+        fft_size_out: usize,
+        backend: &mut dyn FftBackend<T>,
+    ) -> ResampleResult<Self> {
         // calculate antialiasing cutoff
         let cutoff = if fft_size_in > fft_size_out {
             0.4f32.powf(16.0 / fft_size_in as f32) * fft_size_out as f32 / fft_size_in as f32
@@ -97,37 +233,103 @@ where
             *f = sinc[0][n] / T::coerce(2 * fft_size_in);
         }
 
-        let input_f: Vec<Complex<T>> = vec![Complex::zero(); fft_size_in + 1];
-        let input_buf: Vec<T> = vec![T::zero(); 2 * fft_size_in];
-        let output_f: Vec<Complex<T>> = vec![Complex::zero(); fft_size_out + 1];
-        let output_buf: Vec<T> = vec![T::zero(); 2 * fft_size_out];
-        let mut planner = RealFftPlanner::<T>::new();
-        let fft = planner.plan_fft_forward(2 * fft_size_in);
-        let ifft = planner.plan_fft_inverse(2 * fft_size_out);
-        fft.process(&mut filter_t, &mut filter_f).unwrap();
-        let scratch_fw = fft.make_scratch_vec();
-        let scratch_inv = ifft.make_scratch_vec();
+        let fft = backend.plan_forward(2 * fft_size_in)?;
+        let ifft = backend.plan_inverse(2 * fft_size_out)?;
+        let mut scratch_fw = vec![Complex::zero(); fft.scratch_len()];
+        fft.process(&mut filter_t, &mut filter_f, &mut scratch_fw);
 
-        FftResampler {
+        let filter = FftFilter {
             fft_size_in,
             fft_size_out,
             filter_f,
             fft,
             ifft,
-            scratch_fw,
-            scratch_inv,
-            input_buf,
-            input_f,
-            output_f,
-            output_buf,
+        };
+        let scratches = vec![FftScratch::new(&filter)];
+        Ok(FftResampler { filter, scratches })
+    }
+
+    /// Get a checksum of the filter spectrum.
+    fn checksum(&self) -> u64 {
+        let filter = &self.filter;
+        let mut values = vec![filter.fft_size_in as f64, filter.fft_size_out as f64];
+        for value in filter.filter_f.iter() {
+            values.push(value.re.to_f64().unwrap_or(0.0));
+            values.push(value.im.to_f64().unwrap_or(0.0));
         }
+        checksum_values(values)
+    }
+
+    /// Set the number of sets of buffers, one for each channel that may be processed in parallel.
+    fn set_nbr_workers(&mut self, nbr_workers: usize) {
+        let filter = &self.filter;
+        self.scratches
+            .resize_with(nbr_workers, || FftScratch::new(filter));
     }
 
     /// Resample a small chunk
+    #[cfg(test)]
     fn resample_unit(&mut self, wave_in: &[T], wave_out: &mut [T], overlap: &mut [T]) {
+        self.filter
+            .resample_unit(&mut self.scratches[0], wave_in, wave_out, overlap);
+    }
+
+    /// Resample a number of channels, each given as a tuple of the input, the output and the overlap.
+    /// The input and output are split into chunks of the FFT sizes,
+    /// and the channels are processed in parallel when there are at least `threshold` of them.
+    fn resample_channels<'a, I>(&mut self, channels: I, threshold: usize)
+    where
+        I: Iterator<Item = (&'a [T], &'a mut [T], &'a mut [T])>,
+        T: 'a,
+    {
+        let filter = &self.filter;
+        for_each_channel(
+            channels,
+            &mut self.scratches,
+            threshold,
+            |(wave_in, wave_out, overlap), scratch| {
+                for (in_chunk, out_chunk) in wave_in
+                    .chunks(filter.fft_size_in)
+                    .zip(wave_out.chunks_mut(filter.fft_size_out))
+                {
+                    filter.resample_unit(scratch, in_chunk, out_chunk, overlap);
+                }
+            },
+        );
+    }
+}
+
+impl<T> FftScratch<T>
+where
+    T: Sample,
+{
+    fn new(filter: &FftFilter<T>) -> Self {
+        FftScratch {
+            scratch_fw: vec![Complex::zero(); filter.fft.scratch_len()],
+            scratch_inv: vec![Complex::zero(); filter.ifft.scratch_len()],
+            input_buf: vec![T::zero(); 2 * filter.fft_size_in],
+            input_f: vec![Complex::zero(); filter.fft_size_in + 1],
+            output_f: vec![Complex::zero(); filter.fft_size_out + 1],
+            output_buf: vec![T::zero(); 2 * filter.fft_size_out],
+        }
+    }
+}
+
+impl<T> FftFilter<T>
+where
+    T: Sample,
+{
+    /// Resample a small chunk, using the given buffers.
+    fn resample_unit(
+        &self,
+        scratch: &mut FftScratch<T>,
+        wave_in: &[T],
+        wave_out: &mut [T],
+        overlap: &mut [T],
+    ) {
         // Copy to input buffer and clear padding area
-        self.input_buf[0..self.fft_size_in].copy_from_slice(wave_in);
-        for item in self
+        scratch.input_buf[0..self.fft_size_in].copy_from_slice(wave_in);
+        for item in scratch
             .input_buf
             .iter_mut()
             .skip(self.fft_size_in)
@@ -137,44 +339,75 @@ where
         }
 
         // FFT and store result in history, update index
-        self.fft
-            .process_with_scratch(&mut self.input_buf, &mut self.input_f, &mut self.scratch_fw)
-            .unwrap();
+        self.fft.process(
+            &mut scratch.input_buf,
+            &mut scratch.input_f,
+            &mut scratch.scratch_fw,
+        );
 
-        // multiply with filter FT
-        self.input_f
-            .iter_mut()
-            .take(self.fft_size_in + 1)
-            .zip(self.filter_f.iter())
-            .for_each(|(spec, filt)| *spec *= filt);
+        // only the bins that fit in the output spectrum are used,
+        // so when downsampling the upper part is neither filtered nor copied
         let new_len = if self.fft_size_in < self.fft_size_out {
             self.fft_size_in + 1
         } else {
             self.fft_size_out
         };
 
-        // copy to modified spectrum
-        self.output_f[0..new_len].copy_from_slice(&self.input_f[0..new_len]);
-        for val in self.output_f[new_len..].iter_mut() {
+        // multiply with filter FT, and store in the modified spectrum
+        scratch
+            .output_f
+            .iter_mut()
+            .take(new_len)
+            .zip(scratch.input_f.iter().zip(self.filter_f.iter()))
+            .for_each(|(out, (spec, filt))| *out = *spec * filt);
+        for val in scratch.output_f[new_len..].iter_mut() {
             *val = Complex::zero();
         }
-        //self.output_f[self.fft_size_out] = self.input_f[self.fft_size_in];
 
         // IFFT result, store result and overlap
-        self.ifft
-            .process_with_scratch(
-                &mut self.output_f,
-                &mut self.output_buf,
-                &mut self.scratch_inv,
-            )
-            .unwrap();
+        self.ifft.process(
+            &mut scratch.output_f,
+            &mut scratch.output_buf,
+            &mut scratch.scratch_inv,
+        );
         for (n, item) in wave_out.iter_mut().enumerate().take(self.fft_size_out) {
-            *item = self.output_buf[n] + overlap[n];
+            *item = scratch.output_buf[n] + overlap[n];
         }
-        overlap.copy_from_slice(&self.output_buf[self.fft_size_out..]);
+        overlap.copy_from_slice(&scratch.output_buf[self.fft_size_out..]);
     }
 }
 
+/// Check the parameters given to the constructors of the FFT resamplers.
+/// The number of sub chunks is one for `FftFixedInOut`, which doesn't take this parameter.
+fn validate_parameters(
+    fs_in: usize,
+    fs_out: usize,
+    chunk_size: usize,
+    sub_chunks: usize,
+    nbr_channels: usize,
+) -> ResampleResult<()> {
+    ResampleError::check(fs_in > 0, "fs_in", fs_in, "must be larger than zero")?;
+    ResampleError::check(fs_out > 0, "fs_out", fs_out, "must be larger than zero")?;
+    ResampleError::check(
+        chunk_size > 0,
+        "chunk_size",
+        chunk_size,
+        "must be larger than zero",
+    )?;
+    ResampleError::check(
+        sub_chunks > 0,
+        "sub_chunks",
+        sub_chunks,
+        "must be larger than zero",
+    )?;
+    ResampleError::check(
+        nbr_channels > 0,
+        "nbr_channels",
+        nbr_channels,
+        "must be larger than zero",
+    )
+}
+
 impl<T> FftFixedInOut<T>
 where
     T: Sample,
@@ -186,7 +419,44 @@ where
     /// - `fs_out`: Output sample rate.
     /// - `chunk_size_in`: desired length of input data in frames, actual value may be different.
     /// - `nbr_channels`: number of channels in input/output.
-    pub fn new(fs_in: usize, fs_out: usize, chunk_size_in: usize, nbr_channels: usize) -> Self {
+    ///
+    /// # Errors
+    ///
+    /// The function returns `ResampleError::InvalidParameter` if a sample rate, the chunk size,
+    /// or the number of channels is zero, and `ResampleError::NoFftBackend` if neither
+    /// the `realfft` nor the `rustfft` feature is enabled.
+    pub fn new(
+        fs_in: usize,
+        fs_out: usize,
+        chunk_size_in: usize,
+        nbr_channels: usize,
+    ) -> ResampleResult<Self> {
+        Self::new_with_backend(
+            fs_in,
+            fs_out,
+            chunk_size_in,
+            nbr_channels,
+            &mut *default_backend()?,
+        )
+    }
+
+    /// Create a new FftFixedInOut, using the FFT implementation of the given backend.
+    ///
+    /// Parameters are the same as for `new`, with the addition of:
+    /// - `backend`: the FFT backend, see `FftBackend`.
+    ///
+    /// # Errors
+    ///
+    /// The function returns `ResampleError::InvalidParameter` for the same parameters as `new`,
+    /// or the error of the backend if it doesn't support the FFT lengths.
+    pub fn new_with_backend(
+        fs_in: usize,
+        fs_out: usize,
+        chunk_size_in: usize,
+        nbr_channels: usize,
+        backend: &mut dyn FftBackend<T>,
+    ) -> ResampleResult<Self> {
+        validate_parameters(fs_in, fs_out, chunk_size_in, 1, nbr_channels)?;
         debug!(
             "Create new FftFixedInOut, fs_in: {}, fs_out: {} chunk_size_in: {}, channels: {}",
             fs_in, fs_out, chunk_size_in, nbr_channels
@@ -195,34 +465,186 @@ where
         let gcd = integer::gcd(fs_in, fs_out);
         let min_chunk_out = fs_out / gcd;
         let wanted = chunk_size_in;
-        let fft_chunks = (wanted as f32 / min_chunk_out as f32).ceil() as usize;
+        let fft_chunks = smooth_fft_chunks(
+            fs_in / gcd,
+            fs_out / gcd,
+            (wanted as f32 / min_chunk_out as f32).ceil() as usize,
+        );
         let fft_size_out = fft_chunks * fs_out / gcd;
         let fft_size_in = fft_chunks * fs_in / gcd;
 
-        let resampler = FftResampler::<T>::new(fft_size_in, fft_size_out);
+        let mut resampler = FftResampler::<T>::new(fft_size_in, fft_size_out, backend)?;
+        resampler.set_nbr_workers(nbr_workers(nbr_channels, DEFAULT_PARALLEL_THRESHOLD));
 
         let overlaps: Vec<Vec<T>> = vec![vec![T::zero(); fft_size_out]; nbr_channels];
+        let output_buffer: Vec<Vec<T>> = vec![vec![T::zero(); fft_size_out]; nbr_channels];
 
-        FftFixedInOut {
+        Ok(FftFixedInOut {
             nbr_channels,
             chunk_size_in: fft_size_in,
             chunk_size_out: fft_size_out,
             fft_size_in,
             overlaps,
+            output_buffer,
             resampler,
+            parallel_threshold: DEFAULT_PARALLEL_THRESHOLD,
+            meter: Meter::new(nbr_channels),
+        })
+    }
+
+    /// Enable or disable peak metering of the input.
+    /// When enabled, the peak levels are measured during processing,
+    /// and can be read with "input_peaks" after each call to "process".
+    pub fn set_peak_metering(&mut self, enabled: bool) {
+        trace!("Set peak metering to {}", enabled);
+        self.meter.set_peak_enabled(enabled);
+    }
+
+    /// Set the number of channels from which the channels are resampled in parallel,
+    /// on the rayon thread pool. The default is 8 channels.
+    /// This only has an effect when the `parallel` feature is enabled.
+    pub fn set_parallel_threshold(&mut self, nbr_channels: usize) {
+        trace!("Set parallel threshold to {} channels", nbr_channels);
+        self.parallel_threshold = nbr_channels;
+        self.resampler
+            .set_nbr_workers(nbr_workers(self.nbr_channels, nbr_channels));
+    }
+
+    /// Get the peak absolute values of the input of the last call to "process", one per channel.
+    /// The values are zero for channels that were not processed, and when metering is disabled.
+    pub fn input_peaks(&self) -> &[T] {
+        self.meter.peaks()
+    }
+
+    /// Enable or disable RMS metering of the input.
+    /// The RMS value of each channel is calculated per call to "process", and can be read with "metrics".
+    pub fn set_rms_metering(&mut self, enabled: bool) {
+        trace!("Set RMS metering to {}", enabled);
+        self.meter.set_rms_enabled(enabled);
+    }
+
+    /// Enable loudness metering of the input, or disable it by passing `None`.
+    /// The value is the sample rate of the input, needed for the K-weighting filter.
+    /// The loudness is accumulated over all calls to "process" until the metering
+    /// is disabled or enabled again, and can be read with "metrics".
+    pub fn set_loudness_metering(&mut self, sample_rate: Option<f64>) {
+        trace!("Set loudness metering to {:?}", sample_rate);
+        self.meter.set_loudness_sample_rate(sample_rate);
+    }
+
+    /// Get the measurements from the enabled input meters.
+    pub fn metrics(&self) -> &Metrics<T> {
+        self.meter.metrics()
+    }
+
+    /// Get the latency of the resampler.
+    /// This is the delay of the anti-aliasing filter, which is half of the FFT length.
+    pub fn latency(&self) -> Latency {
+        Latency {
+            input_frames: self.fft_size_in as f64 / 2.0,
+            output_frames: self.chunk_size_out as f64 / 2.0,
         }
     }
+
+    /// Get the lengths of the input and output of each FFT, in frames.
+    /// These are multiples of the reduced sample rates, chosen to be near the requested
+    /// chunk size while avoiding lengths with large prime factors, that are slow to transform.
+    pub fn fft_sizes(&self) -> (usize, usize) {
+        (self.fft_size_in, self.chunk_size_out)
+    }
+
+    /// Get a checksum of the anti-aliasing filter used by this resampler.
+    /// Two resamplers with the same checksum use identical filters,
+    /// which can be used to verify that different machines or versions of the library
+    /// will give the same output.
+    pub fn filter_checksum(&self) -> u64 {
+        self.resampler.checksum()
+    }
+
+    /// Resample the non-empty channels of a chunk into the output buffer.
+    fn process_chunk(&mut self, wave_in: &[Vec<T>]) -> ResampleResult<()> {
+        if wave_in.len() != self.nbr_channels {
+            return Err(ResampleError::WrongNumberOfChannels {
+                expected: self.nbr_channels,
+                actual: wave_in.len(),
+                resampler: format!("{:?}", self),
+            });
+        }
+        for (chan, wave) in wave_in.iter().enumerate() {
+            if !wave.is_empty() && wave.len() != self.chunk_size_in {
+                return Err(ResampleError::WrongNumberOfFrames {
+                    channel: chan,
+                    expected: self.chunk_size_in,
+                    actual: wave.len(),
+                    resampler: format!("{:?}", self),
+                });
+            }
+        }
+
+        self.meter.clear();
+        for (chan, wave) in wave_in.iter().enumerate() {
+            if !wave.is_empty() {
+                self.meter.measure(chan, wave);
+            }
+        }
+        let channels = wave_in
+            .iter()
+            .zip(self.output_buffer.iter_mut())
+            .zip(self.overlaps.iter_mut())
+            .filter(|((wave, _), _)| !wave.is_empty())
+            .map(|((wave, wave_out), overlap)| (&wave[..], &mut wave_out[..], &mut overlap[..]));
+        self.resampler
+            .resample_channels(channels, self.parallel_threshold);
+        Ok(())
+    }
+}
+
+/// Shows the configuration, without the filter and the contents of the buffers.
+impl<T> fmt::Debug for FftFixedInOut<T> {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        f.debug_struct("FftFixedInOut")
+            .field("nbr_channels", &self.nbr_channels)
+            .field("chunk_size_in", &self.chunk_size_in)
+            .field("chunk_size_out", &self.chunk_size_out)
+            .field("fft_size_in", &self.fft_size_in)
+            .finish()
+    }
 }
 
 impl<T> Resampler<T> for FftFixedInOut<T>
 where
     T: Sample,
 {
+    fn output_delay(&self) -> f64 {
+        self.latency().output_frames
+    }
+
+    /// Clear the overlaps between the FFTs, see "reset" of the `Resampler` trait.
+    /// The filter is kept.
+    fn reset(&mut self) {
+        for value in self.overlaps.iter_mut().flatten() {
+            *value = T::zero();
+        }
+        self.meter.clear();
+    }
+
+    /// Emit the output frames that are held back at the end of a stream, see "flush"
+    /// of the `Resampler` trait. These are the latency of the resampler, half of the FFT length.
+    fn flush(&mut self) -> ResampleResult<Vec<Vec<T>>> {
+        let nbr_frames = self.latency().output_frames.ceil() as usize;
+        offline::flush_with_silence(self, self.nbr_channels, nbr_frames)
+    }
+
     /// Query for the number of frames needed for the next call to "process".
     fn nbr_frames_needed(&self) -> usize {
         self.fft_size_in
     }
 
+    /// Query for the number of frames that the next call to "process" will return.
+    fn nbr_output_frames_next(&self) -> usize {
+        self.chunk_size_out
+    }
+
     /// Resample a chunk of audio. The input and output lengths are fixed.
     /// If the waveform for a channel is empty, this channel will be ignored and produce a
     /// corresponding empty output waveform.
@@ -231,39 +653,42 @@ where
     /// The function returns an error if the size of the input data is not equal
     /// to the number of channels and input size defined when creating the instance.
     fn process(&mut self, wave_in: &[Vec<T>]) -> ResampleResult<Vec<Vec<T>>> {
-        if wave_in.len() != self.nbr_channels {
-            return Err(ResampleError::WrongNumberOfChannels {
-                expected: self.nbr_channels,
-                actual: wave_in.len(),
-            });
-        }
-        let mut used_channels = Vec::new();
-        for (chan, wave) in wave_in.iter().enumerate() {
-            if !wave.is_empty() {
-                used_channels.push(chan);
-                if wave.len() != self.chunk_size_in {
-                    return Err(ResampleError::WrongNumberOfFrames {
-                        channel: chan,
-                        expected: self.chunk_size_in,
-                        actual: wave.len(),
-                    });
+        self.process_chunk(wave_in)?;
+        Ok(wave_in
+            .iter()
+            .zip(self.output_buffer.iter())
+            .map(|(wave, output)| {
+                if wave.is_empty() {
+                    Vec::new()
+                } else {
+                    output.clone()
                 }
-            }
-        }
-        let mut wave_out = vec![Vec::new(); self.nbr_channels];
-        for chan in used_channels.iter() {
-            wave_out[*chan] = vec![T::zero(); self.chunk_size_out];
-        }
+            })
+            .collect())
+    }
 
-        for n in used_channels.iter() {
-            self.resampler
-                .resample_unit(&wave_in[*n], &mut wave_out[*n], &mut self.overlaps[*n])
+    /// Resample a chunk of audio like "process", but pass the output of each channel
+    /// to the closure `f` instead of returning it.
+    /// The output is taken directly from an internal buffer, without allocating any output vectors.
+    fn process_with(
+        &mut self,
+        wave_in: &[Vec<T>],
+        f: &mut dyn FnMut(usize, &[T]),
+    ) -> ResampleResult<()> {
+        self.process_chunk(wave_in)?;
+        for (chan, (wave, output)) in wave_in.iter().zip(self.output_buffer.iter()).enumerate() {
+            if wave.is_empty() {
+                f(chan, &[]);
+            } else {
+                f(chan, output);
+            }
         }
-        Ok(wave_out)
+        Ok(())
     }
 
     /// Update the resample ratio. This is not supported by this resampler and
-    /// always returns an error.
+    /// always returns an error. Trimming the ratio would change the fixed number of output frames,
+    /// use `FftFixedIn` to follow clock drift.
     fn set_resample_ratio(&mut self, _new_ratio: f64) -> ResampleResult<()> {
         Err(ResampleError::SyncNotAdjustable)
     }
@@ -273,6 +698,22 @@ where
     fn set_resample_ratio_relative(&mut self, _rel_ratio: f64) -> ResampleResult<()> {
         Err(ResampleError::SyncNotAdjustable)
     }
+
+    fn resample_ratio(&self) -> f64 {
+        self.resample_ratio_original()
+    }
+
+    fn resample_ratio_original(&self) -> f64 {
+        self.chunk_size_out as f64 / self.chunk_size_in as f64
+    }
+
+    fn chunk_size(&self) -> usize {
+        self.chunk_size_in
+    }
+
+    fn nbr_channels(&self) -> usize {
+        self.nbr_channels
+    }
 }
 
 impl<T> FftFixedOut<T>
@@ -287,21 +728,60 @@ where
     /// - `chunk_size_out`: length of output data in frames.
     /// - `sub_chunks`: desired number of subchunks for processing, actual number may be different.
     /// - `nbr_channels`: number of channels in input/output.
+    ///
+    /// # Errors
+    ///
+    /// The function returns `ResampleError::InvalidParameter` if a sample rate, the chunk size, the number of sub chunks
+    /// or the number of channels is zero, and `ResampleError::NoFftBackend` if neither
+    /// the `realfft` nor the `rustfft` feature is enabled.
     pub fn new(
         fs_in: usize,
         fs_out: usize,
         chunk_size_out: usize,
         sub_chunks: usize,
         nbr_channels: usize,
-    ) -> Self {
+    ) -> ResampleResult<Self> {
+        Self::new_with_backend(
+            fs_in,
+            fs_out,
+            chunk_size_out,
+            sub_chunks,
+            nbr_channels,
+            &mut *default_backend()?,
+        )
+    }
+
+    /// Create a new FftFixedOut, using the FFT implementation of the given backend.
+    ///
+    /// Parameters are the same as for `new`, with the addition of:
+    /// - `backend`: the FFT backend, see `FftBackend`.
+    ///
+    /// # Errors
+    ///
+    /// The function returns `ResampleError::InvalidParameter` for the same parameters as `new`,
+    /// or the error of the backend if it doesn't support the FFT lengths.
+    pub fn new_with_backend(
+        fs_in: usize,
+        fs_out: usize,
+        chunk_size_out: usize,
+        sub_chunks: usize,
+        nbr_channels: usize,
+        backend: &mut dyn FftBackend<T>,
+    ) -> ResampleResult<Self> {
+        validate_parameters(fs_in, fs_out, chunk_size_out, sub_chunks, nbr_channels)?;
         let gcd = integer::gcd(fs_in, fs_out);
         let min_chunk_out = fs_out / gcd;
         let wanted_subsize = chunk_size_out / sub_chunks;
-        let fft_chunks = (wanted_subsize as f32 / min_chunk_out as f32).ceil() as usize;
+        let fft_chunks = smooth_fft_chunks(
+            fs_in / gcd,
+            fs_out / gcd,
+            (wanted_subsize as f32 / min_chunk_out as f32).ceil() as usize,
+        );
         let fft_size_out = fft_chunks * fs_out / gcd;
         let fft_size_in = fft_chunks * fs_in / gcd;
 
-        let resampler = FftResampler::<T>::new(fft_size_in, fft_size_out);
+        let mut resampler = FftResampler::<T>::new(fft_size_in, fft_size_out, backend)?;
+        resampler.set_nbr_workers(nbr_workers(nbr_channels, DEFAULT_PARALLEL_THRESHOLD));
 
         debug!(
             "Create new FftFixedOut, fs_in: {}, fs_out: {} chunk_size_in: {}, channels: {}, fft_size_in: {}, fft_size_out: {}",
@@ -316,7 +796,7 @@ where
         let chunks_needed = (chunk_size_out as f32 / fft_size_out as f32).ceil() as usize;
         let frames_needed = chunks_needed * fft_size_in;
 
-        FftFixedOut {
+        Ok(FftFixedOut {
             nbr_channels,
             chunk_size_out,
             fft_size_in,
@@ -326,19 +806,136 @@ where
             saved_frames,
             frames_needed,
             resampler,
+            parallel_threshold: DEFAULT_PARALLEL_THRESHOLD,
+            meter: Meter::new(nbr_channels),
+        })
+    }
+
+    /// Enable or disable peak metering of the input.
+    /// When enabled, the peak levels are measured during processing,
+    /// and can be read with "input_peaks" after each call to "process".
+    pub fn set_peak_metering(&mut self, enabled: bool) {
+        trace!("Set peak metering to {}", enabled);
+        self.meter.set_peak_enabled(enabled);
+    }
+
+    /// Set the number of channels from which the channels are resampled in parallel,
+    /// on the rayon thread pool. The default is 8 channels.
+    /// This only has an effect when the `parallel` feature is enabled.
+    pub fn set_parallel_threshold(&mut self, nbr_channels: usize) {
+        trace!("Set parallel threshold to {} channels", nbr_channels);
+        self.parallel_threshold = nbr_channels;
+        self.resampler
+            .set_nbr_workers(nbr_workers(self.nbr_channels, nbr_channels));
+    }
+
+    /// Get the peak absolute values of the input of the last call to "process", one per channel.
+    /// The values are zero for channels that were not processed, and when metering is disabled.
+    pub fn input_peaks(&self) -> &[T] {
+        self.meter.peaks()
+    }
+
+    /// Enable or disable RMS metering of the input.
+    /// The RMS value of each channel is calculated per call to "process", and can be read with "metrics".
+    pub fn set_rms_metering(&mut self, enabled: bool) {
+        trace!("Set RMS metering to {}", enabled);
+        self.meter.set_rms_enabled(enabled);
+    }
+
+    /// Enable loudness metering of the input, or disable it by passing `None`.
+    /// The value is the sample rate of the input, needed for the K-weighting filter.
+    /// The loudness is accumulated over all calls to "process" until the metering
+    /// is disabled or enabled again, and can be read with "metrics".
+    pub fn set_loudness_metering(&mut self, sample_rate: Option<f64>) {
+        trace!("Set loudness metering to {:?}", sample_rate);
+        self.meter.set_loudness_sample_rate(sample_rate);
+    }
+
+    /// Get the measurements from the enabled input meters.
+    pub fn metrics(&self) -> &Metrics<T> {
+        self.meter.metrics()
+    }
+
+    /// Get the latency of the resampler.
+    /// This is the delay of the anti-aliasing filter, which is half of the FFT length.
+    pub fn latency(&self) -> Latency {
+        Latency {
+            input_frames: self.fft_size_in as f64 / 2.0,
+            output_frames: self.fft_size_out as f64 / 2.0,
         }
     }
+
+    /// Get the lengths of the input and output of each FFT, in frames.
+    /// These are multiples of the reduced sample rates, chosen to be near the requested
+    /// chunk size while avoiding lengths with large prime factors, that are slow to transform.
+    pub fn fft_sizes(&self) -> (usize, usize) {
+        (self.fft_size_in, self.fft_size_out)
+    }
+
+    /// Get a checksum of the anti-aliasing filter used by this resampler.
+    /// Two resamplers with the same checksum use identical filters,
+    /// which can be used to verify that different machines or versions of the library
+    /// will give the same output.
+    pub fn filter_checksum(&self) -> u64 {
+        self.resampler.checksum()
+    }
+}
+
+/// Shows the configuration and the current state, without the filter and the contents of the buffers.
+impl<T> fmt::Debug for FftFixedOut<T> {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        f.debug_struct("FftFixedOut")
+            .field("nbr_channels", &self.nbr_channels)
+            .field("chunk_size_out", &self.chunk_size_out)
+            .field("fft_size_in", &self.fft_size_in)
+            .field("fft_size_out", &self.fft_size_out)
+            .field("saved_frames", &self.saved_frames)
+            .field("frames_needed", &self.frames_needed)
+            .finish()
+    }
 }
 
 impl<T> Resampler<T> for FftFixedOut<T>
 where
     T: Sample,
 {
+    fn output_delay(&self) -> f64 {
+        self.latency().output_frames
+    }
+
+    /// Clear the overlaps between the FFTs and the saved output frames,
+    /// see "reset" of the `Resampler` trait. The filter is kept.
+    fn reset(&mut self) {
+        for value in self.overlaps.iter_mut().flatten() {
+            *value = T::zero();
+        }
+        for value in self.output_buffers.iter_mut().flatten() {
+            *value = T::zero();
+        }
+        self.saved_frames = 0;
+        let chunks_needed = (self.chunk_size_out as f32 / self.fft_size_out as f32).ceil() as usize;
+        self.frames_needed = chunks_needed * self.fft_size_in;
+        self.meter.clear();
+    }
+
+    /// Emit the output frames that are held back at the end of a stream, see "flush"
+    /// of the `Resampler` trait. These are the frames already resampled but not yet returned,
+    /// and the latency of the resampler, half of the FFT length.
+    fn flush(&mut self) -> ResampleResult<Vec<Vec<T>>> {
+        let nbr_frames = self.saved_frames + self.latency().output_frames.ceil() as usize;
+        offline::flush_with_silence(self, self.nbr_channels, nbr_frames)
+    }
+
     /// Query for the number of frames needed for the next call to "process".
     fn nbr_frames_needed(&self) -> usize {
         self.frames_needed
     }
 
+    /// Query for the number of frames that the next call to "process" will return.
+    fn nbr_output_frames_next(&self) -> usize {
+        self.chunk_size_out
+    }
+
     /// Resample a chunk of audio. The required input length is provided by
     /// the "nbr_frames_needed" function, and the output length is fixed.
     /// If the waveform for a channel is empty, this channel will be ignored and produce a
@@ -353,6 +950,7 @@ where
             return Err(ResampleError::WrongNumberOfChannels {
                 expected: self.nbr_channels,
                 actual: wave_in.len(),
+                resampler: format!("{:?}", self),
             });
         }
         let mut used_channels = Vec::new();
@@ -364,6 +962,7 @@ where
                         channel: chan,
                         expected: self.frames_needed,
                         actual: wave.len(),
+                        resampler: format!("{:?}", self),
                     });
                 }
             }
@@ -374,15 +973,21 @@ where
             wave_out[*chan] = self.output_buffers[*chan].clone();
         }
 
+        self.meter.clear();
         for n in used_channels.iter() {
-            for (in_chunk, out_chunk) in wave_in[*n]
-                .chunks(self.fft_size_in)
-                .zip(wave_out[*n][self.saved_frames..].chunks_mut(self.fft_size_out))
-            {
-                self.resampler
-                    .resample_unit(in_chunk, out_chunk, &mut self.overlaps[*n]);
-            }
+            self.meter.measure(*n, &wave_in[*n]);
         }
+        let saved_frames = self.saved_frames;
+        let channels = wave_in
+            .iter()
+            .zip(wave_out.iter_mut())
+            .zip(self.overlaps.iter_mut())
+            .filter(|((wave, _), _)| !wave.is_empty())
+            .map(|((wave, wave_out), overlap)| {
+                (&wave[..], &mut wave_out[saved_frames..], &mut overlap[..])
+            });
+        self.resampler
+            .resample_channels(channels, self.parallel_threshold);
         let processed_frames =
             self.saved_frames + self.fft_size_out * (self.frames_needed / self.fft_size_in);
 
@@ -410,7 +1015,8 @@ where
     }
 
     /// Update the resample ratio. This is not supported by this resampler and
-    /// always returns an error.
+    /// always returns an error. Trimming the ratio would change the fixed number of output frames,
+    /// use `FftFixedIn` to follow clock drift.
     fn set_resample_ratio(&mut self, _new_ratio: f64) -> ResampleResult<()> {
         Err(ResampleError::SyncNotAdjustable)
     }
@@ -420,6 +1026,22 @@ where
     fn set_resample_ratio_relative(&mut self, _rel_ratio: f64) -> ResampleResult<()> {
         Err(ResampleError::SyncNotAdjustable)
     }
+
+    fn resample_ratio(&self) -> f64 {
+        self.resample_ratio_original()
+    }
+
+    fn resample_ratio_original(&self) -> f64 {
+        self.fft_size_out as f64 / self.fft_size_in as f64
+    }
+
+    fn chunk_size(&self) -> usize {
+        self.chunk_size_out
+    }
+
+    fn nbr_channels(&self) -> usize {
+        self.nbr_channels
+    }
 }
 
 impl<T> FftFixedIn<T>
@@ -434,21 +1056,60 @@ where
     /// - `chunk_size_out`: length of output data in frames.
     /// - `sub_chunks`: desired number of subchunks for processing, actual number used may be different.
     /// - `nbr_channels`: number of channels in input/output.
+    ///
+    /// # Errors
+    ///
+    /// The function returns `ResampleError::InvalidParameter` if a sample rate, the chunk size, the number of sub chunks
+    /// or the number of channels is zero, and `ResampleError::NoFftBackend` if neither
+    /// the `realfft` nor the `rustfft` feature is enabled.
     pub fn new(
         fs_in: usize,
         fs_out: usize,
         chunk_size_in: usize,
         sub_chunks: usize,
         nbr_channels: usize,
-    ) -> Self {
+    ) -> ResampleResult<Self> {
+        Self::new_with_backend(
+            fs_in,
+            fs_out,
+            chunk_size_in,
+            sub_chunks,
+            nbr_channels,
+            &mut *default_backend()?,
+        )
+    }
+
+    /// Create a new FftFixedIn, using the FFT implementation of the given backend.
+    ///
+    /// Parameters are the same as for `new`, with the addition of:
+    /// - `backend`: the FFT backend, see `FftBackend`.
+    ///
+    /// # Errors
+    ///
+    /// The function returns `ResampleError::InvalidParameter` for the same parameters as `new`,
+    /// or the error of the backend if it doesn't support the FFT lengths.
+    pub fn new_with_backend(
+        fs_in: usize,
+        fs_out: usize,
+        chunk_size_in: usize,
+        sub_chunks: usize,
+        nbr_channels: usize,
+        backend: &mut dyn FftBackend<T>,
+    ) -> ResampleResult<Self> {
+        validate_parameters(fs_in, fs_out, chunk_size_in, sub_chunks, nbr_channels)?;
         let gcd = integer::gcd(fs_in, fs_out);
         let min_chunk_in = fs_in / gcd;
         let wanted_subsize = chunk_size_in / sub_chunks;
-        let fft_chunks = (wanted_subsize as f32 / min_chunk_in as f32).ceil() as usize;
+        let fft_chunks = smooth_fft_chunks(
+            fs_in / gcd,
+            fs_out / gcd,
+            (wanted_subsize as f32 / min_chunk_in as f32).ceil() as usize,
+        );
         let fft_size_out = fft_chunks * fs_out / gcd;
         let fft_size_in = fft_chunks * fs_in / gcd;
 
-        let resampler = FftResampler::<T>::new(fft_size_in, fft_size_out);
+        let mut resampler = FftResampler::<T>::new(fft_size_in, fft_size_out, backend)?;
+        resampler.set_nbr_workers(nbr_workers(nbr_channels, DEFAULT_PARALLEL_THRESHOLD));
         debug!(
             "Create new FftFixedOut, fs_in: {}, fs_out: {} chunk_size_in: {}, channels: {}, fft_size_in: {}, fft_size_out: {}",
             fs_in, fs_out, chunk_size_in, nbr_channels, fft_size_in, fft_size_out
@@ -460,7 +1121,7 @@ where
 
         let saved_frames = 0;
 
-        FftFixedIn {
+        Ok(FftFixedIn {
             nbr_channels,
             chunk_size_in,
             fft_size_in,
@@ -469,19 +1130,143 @@ where
             input_buffers,
             saved_frames,
             resampler,
+            parallel_threshold: DEFAULT_PARALLEL_THRESHOLD,
+            meter: Meter::new(nbr_channels),
+            trimmer: Trimmer::new(nbr_channels),
+        })
+    }
+
+    /// Enable or disable peak metering of the input.
+    /// When enabled, the peak levels are measured during processing,
+    /// and can be read with "input_peaks" after each call to "process".
+    pub fn set_peak_metering(&mut self, enabled: bool) {
+        trace!("Set peak metering to {}", enabled);
+        self.meter.set_peak_enabled(enabled);
+    }
+
+    /// Set the number of channels from which the channels are resampled in parallel,
+    /// on the rayon thread pool. The default is 8 channels.
+    /// This only has an effect when the `parallel` feature is enabled.
+    pub fn set_parallel_threshold(&mut self, nbr_channels: usize) {
+        trace!("Set parallel threshold to {} channels", nbr_channels);
+        self.parallel_threshold = nbr_channels;
+        self.resampler
+            .set_nbr_workers(nbr_workers(self.nbr_channels, nbr_channels));
+    }
+
+    /// Get the peak absolute values of the input of the last call to "process", one per channel.
+    /// The values are zero for channels that were not processed, and when metering is disabled.
+    pub fn input_peaks(&self) -> &[T] {
+        self.meter.peaks()
+    }
+
+    /// Enable or disable RMS metering of the input.
+    /// The RMS value of each channel is calculated per call to "process", and can be read with "metrics".
+    pub fn set_rms_metering(&mut self, enabled: bool) {
+        trace!("Set RMS metering to {}", enabled);
+        self.meter.set_rms_enabled(enabled);
+    }
+
+    /// Enable loudness metering of the input, or disable it by passing `None`.
+    /// The value is the sample rate of the input, needed for the K-weighting filter.
+    /// The loudness is accumulated over all calls to "process" until the metering
+    /// is disabled or enabled again, and can be read with "metrics".
+    pub fn set_loudness_metering(&mut self, sample_rate: Option<f64>) {
+        trace!("Set loudness metering to {:?}", sample_rate);
+        self.meter.set_loudness_sample_rate(sample_rate);
+    }
+
+    /// Get the measurements from the enabled input meters.
+    pub fn metrics(&self) -> &Metrics<T> {
+        self.meter.metrics()
+    }
+
+    /// Get the latency of the resampler.
+    /// This is the delay of the anti-aliasing filter, which is half of the FFT length.
+    pub fn latency(&self) -> Latency {
+        Latency {
+            input_frames: self.fft_size_in as f64 / 2.0,
+            output_frames: self.fft_size_out as f64 / 2.0,
         }
     }
+
+    /// Get the lengths of the input and output of each FFT, in frames.
+    /// These are multiples of the reduced sample rates, chosen to be near the requested
+    /// chunk size while avoiding lengths with large prime factors, that are slow to transform.
+    pub fn fft_sizes(&self) -> (usize, usize) {
+        (self.fft_size_in, self.fft_size_out)
+    }
+
+    /// Get a checksum of the anti-aliasing filter used by this resampler.
+    /// Two resamplers with the same checksum use identical filters,
+    /// which can be used to verify that different machines or versions of the library
+    /// will give the same output.
+    pub fn filter_checksum(&self) -> u64 {
+        self.resampler.checksum()
+    }
+}
+
+/// Shows the configuration and the current state, without the filter and the contents of the buffers.
+impl<T> fmt::Debug for FftFixedIn<T> {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        f.debug_struct("FftFixedIn")
+            .field("nbr_channels", &self.nbr_channels)
+            .field("chunk_size_in", &self.chunk_size_in)
+            .field("fft_size_in", &self.fft_size_in)
+            .field("fft_size_out", &self.fft_size_out)
+            .field("saved_frames", &self.saved_frames)
+            .field("trim_ratio", &self.trimmer.rel_ratio)
+            .finish()
+    }
 }
 
 impl<T> Resampler<T> for FftFixedIn<T>
 where
     T: Sample,
 {
+    fn output_delay(&self) -> f64 {
+        self.latency().output_frames
+    }
+
+    /// Clear the overlaps between the FFTs and the saved input frames,
+    /// and return to the untrimmed ratio, see "reset" of the `Resampler` trait. The filter is kept.
+    fn reset(&mut self) {
+        for value in self.overlaps.iter_mut().flatten() {
+            *value = T::zero();
+        }
+        for value in self.input_buffers.iter_mut().flatten() {
+            *value = T::zero();
+        }
+        self.saved_frames = 0;
+        self.trimmer = Trimmer::new(self.nbr_channels);
+        self.meter.clear();
+    }
+
+    /// Emit the output frames that are held back at the end of a stream, see "flush"
+    /// of the `Resampler` trait. These are the output of the input frames that are saved
+    /// until there is enough for an FFT, and the latency of the resampler, half of the FFT length.
+    /// When the ratio is trimmed, the number of frames is calculated for the untrimmed ratio.
+    fn flush(&mut self) -> ResampleResult<Vec<Vec<T>>> {
+        let ratio = self.fft_size_out as f64 / self.fft_size_in as f64;
+        let nbr_frames =
+            ((self.saved_frames as f64 + self.latency().input_frames) * ratio).ceil() as usize;
+        offline::flush_with_silence(self, self.nbr_channels, nbr_frames)
+    }
+
     /// Query for the number of frames needed for the next call to "process".
     fn nbr_frames_needed(&self) -> usize {
         self.chunk_size_in
     }
 
+    /// Query for the number of frames that the next call to "process" will return.
+    /// This varies between calls, depending on how many frames are saved from the previous call,
+    /// and on the trimming of the ratio.
+    fn nbr_output_frames_next(&self) -> usize {
+        let nbr_chunks_ready = (self.saved_frames + self.chunk_size_in) / self.fft_size_in;
+        self.trimmer
+            .output_len(nbr_chunks_ready * self.fft_size_out)
+    }
+
     /// Resample a chunk of audio. The required input length is provided by
     /// the "nbr_frames_needed" function, and the output length is fixed.
     /// If the waveform for a channel is empty, this channel will be ignored and produce a
@@ -496,6 +1281,7 @@ where
             return Err(ResampleError::WrongNumberOfChannels {
                 expected: self.nbr_channels,
                 actual: wave_in.len(),
+                resampler: format!("{:?}", self),
             });
         }
         let mut used_channels = Vec::new();
@@ -507,6 +1293,7 @@ where
                         channel: chan,
                         expected: self.chunk_size_in,
                         actual: wave.len(),
+                        resampler: format!("{:?}", self),
                     });
                 }
             }
@@ -527,34 +1314,32 @@ where
                 *buffer = *input;
             }
         }
+        self.meter.clear();
         for n in used_channels.iter() {
-            for (input, buffer) in wave_in[*n].iter().zip(
-                input_temp[*n]
-                    .iter_mut()
-                    .skip(self.saved_frames)
-                    .take(self.chunk_size_in),
-            ) {
-                *buffer = *input;
-            }
+            self.meter.copy_and_measure(
+                *n,
+                &wave_in[*n],
+                &mut input_temp[*n][self.saved_frames..self.saved_frames + self.chunk_size_in],
+            );
         }
         self.saved_frames += self.chunk_size_in;
 
-        let nbr_chunks_ready =
-            (self.saved_frames as f32 / self.fft_size_in as f32).floor() as usize;
+        let nbr_chunks_ready = self.saved_frames / self.fft_size_in;
         let mut wave_out = vec![Vec::new(); self.nbr_channels];
         for chan in used_channels.iter() {
             wave_out[*chan] = vec![T::zero(); nbr_chunks_ready * self.fft_size_out];
         }
-        for n in used_channels.iter() {
-            for (in_chunk, out_chunk) in input_temp[*n]
-                .chunks(self.fft_size_in)
-                .take(nbr_chunks_ready)
-                .zip(wave_out[*n].chunks_mut(self.fft_size_out))
-            {
-                self.resampler
-                    .resample_unit(in_chunk, out_chunk, &mut self.overlaps[*n]);
-            }
-        }
+        let channels = input_temp
+            .iter()
+            .zip(wave_out.iter_mut())
+            .zip(self.overlaps.iter_mut())
+            .enumerate()
+            .filter(|(chan, _)| used_channels.contains(chan))
+            .map(|(_, ((wave, wave_out), overlap))| {
+                (&wave[..], &mut wave_out[..], &mut overlap[..])
+            });
+        self.resampler
+            .resample_channels(channels, self.parallel_threshold);
 
         // save extra frames for next round
         let frames_in_used = nbr_chunks_ready * self.fft_size_in;
@@ -573,30 +1358,63 @@ where
             }
         }
         self.saved_frames = extra;
-        Ok(wave_out)
+        Ok(self.trimmer.process(wave_out))
     }
 
-    /// Update the resample ratio. This is not supported by this resampler and
-    /// always returns an error.
-    fn set_resample_ratio(&mut self, _new_ratio: f64) -> ResampleResult<()> {
-        Err(ResampleError::SyncNotAdjustable)
+    /// Trim the resample ratio. The new value must be within +-0.1% of the original one.
+    fn set_resample_ratio(&mut self, new_ratio: f64) -> ResampleResult<()> {
+        let original = self.fft_size_out as f64 / self.fft_size_in as f64;
+        self.set_resample_ratio_relative(new_ratio / original)
     }
 
-    /// Update the resample ratio relative to the original one. This is not
-    /// supported by this resampler and always returns an error.
-    fn set_resample_ratio_relative(&mut self, _rel_ratio: f64) -> ResampleResult<()> {
-        Err(ResampleError::SyncNotAdjustable)
+    /// Trim the resample ratio relative to the original one.
+    /// The new value must be within +-0.1% of the original one.
+    ///
+    /// The trimming is done by cubic interpolation of the resampled output.
+    /// This gives a small loss of quality at high frequencies, and the first call
+    /// holds back two output frames, that are then delivered by the following calls.
+    /// Once the ratio has been trimmed, the interpolation stays enabled
+    /// even if the ratio is set back to the original.
+    fn set_resample_ratio_relative(&mut self, rel_ratio: f64) -> ResampleResult<()> {
+        trace!("Trim resample ratio to {}", rel_ratio);
+        if !rel_ratio.is_finite() || (rel_ratio - 1.0).abs() > MAX_TRIM {
+            return Err(ResampleError::RatioOutOfBounds {
+                provided: self.fft_size_out as f64 / self.fft_size_in as f64 * rel_ratio,
+                original: self.fft_size_out as f64 / self.fft_size_in as f64,
+                max_deviation: MAX_TRIM,
+            });
+        }
+        self.trimmer.set_ratio(rel_ratio);
+        Ok(())
+    }
+
+    fn resample_ratio(&self) -> f64 {
+        self.resample_ratio_original() * self.trimmer.rel_ratio
+    }
+
+    fn resample_ratio_original(&self) -> f64 {
+        self.fft_size_out as f64 / self.fft_size_in as f64
+    }
+
+    fn chunk_size(&self) -> usize {
+        self.chunk_size_in
+    }
+
+    fn nbr_channels(&self) -> usize {
+        self.nbr_channels
     }
 }
 
 #[cfg(test)]
 mod tests {
+    use crate::fft::RealFftBackend;
     use crate::synchro::{FftFixedIn, FftFixedInOut, FftFixedOut, FftResampler};
     use crate::Resampler;
 
     #[test]
     fn resample_unit() {
-        let mut resampler = FftResampler::<f64>::new(147, 1000);
+        let mut resampler =
+            FftResampler::<f64>::new(147, 1000, &mut RealFftBackend::new()).unwrap();
         let mut wave_in = vec![0.0; 147];
 
         wave_in[0] = 0.3;
@@ -615,10 +1433,25 @@ mod tests {
         assert!((maxval - 1.0).abs() < 0.1);
     }
 
+    #[test]
+    fn resample_unit_down() {
+        let mut resampler =
+            FftResampler::<f64>::new(1000, 147, &mut RealFftBackend::new()).unwrap();
+        let mut overlap = vec![0.0; 147];
+        let mut wave_out = vec![0.0; 147];
+        // a constant signal needs a couple of chunks to settle
+        for _ in 0..3 {
+            resampler.resample_unit(&[0.5; 1000], &mut wave_out, &mut overlap);
+        }
+        for value in wave_out.iter() {
+            assert!((value - 0.5).abs() < 1.0e-3);
+        }
+    }
+
     #[test]
     fn make_resampler_fio() {
         // asking for 1024 give the nearest which is 1029 -> 1120
-        let mut resampler = FftFixedInOut::<f64>::new(44100, 48000, 1024, 2);
+        let mut resampler = FftFixedInOut::<f64>::new(44100, 48000, 1024, 2).unwrap();
         let frames = resampler.nbr_frames_needed();
         let waves = vec![vec![0.0f64; frames]; 2];
         let out = resampler.process(&waves).unwrap();
@@ -629,7 +1462,7 @@ mod tests {
     #[test]
     fn make_resampler_fio_skipped() {
         // asking for 1024 give the nearest which is 1029 -> 1120
-        let mut resampler = FftFixedInOut::<f64>::new(44100, 48000, 1024, 2);
+        let mut resampler = FftFixedInOut::<f64>::new(44100, 48000, 1024, 2).unwrap();
         let frames = resampler.nbr_frames_needed();
         let waves = vec![vec![0.0f64; frames], Vec::new()];
         let out = resampler.process(&waves).unwrap();
@@ -638,9 +1471,48 @@ mod tests {
         assert!(out[1].is_empty());
     }
 
+    #[test]
+    fn trim_fi() {
+        let mut resampler = FftFixedIn::<f64>::new(44100, 48000, 1024, 2, 2).unwrap();
+        let mut reference = FftFixedIn::<f64>::new(44100, 48000, 1024, 2, 2).unwrap();
+        assert!(resampler.set_resample_ratio_relative(1.01).is_err());
+        assert!(resampler.set_resample_ratio_relative(f64::NAN).is_err());
+        assert!(resampler.set_resample_ratio(f64::INFINITY).is_err());
+        let mut output = Vec::new();
+        let mut nbr_reference = 0;
+        let mut position = 0;
+        for chunk in 0..200 {
+            if chunk == 20 {
+                resampler
+                    .set_resample_ratio(48000.0 / 44100.0 * 1.0005)
+                    .unwrap();
+            }
+            let wave = (position..position + 1024)
+                .map(|n| (2.0 * std::f64::consts::PI * 1000.0 * n as f64 / 44100.0).sin())
+                .collect::<Vec<f64>>();
+            position += 1024;
+            let waves = vec![wave, Vec::new()];
+            let expected_len = resampler.nbr_output_frames_next();
+            let out = resampler.process(&waves).unwrap();
+            assert_eq!(out[0].len(), expected_len);
+            assert!(out[1].is_empty());
+            output.extend_from_slice(&out[0]);
+            nbr_reference += reference.process(&waves).unwrap()[0].len();
+        }
+        // 180 chunks of about 1114.6 output frames each were trimmed
+        let expected_extra = 180.0 * 1024.0 * 48000.0 / 44100.0 * 0.0005;
+        let extra = output.len() as f64 - nbr_reference as f64;
+        assert!((extra - expected_extra).abs() < 3.0);
+        // the 1 kHz sine should stay continuous, also where the trimming starts
+        let max_step = 2.0 * std::f64::consts::PI * 1000.0 / 48000.0;
+        for pair in output[2000..].windows(2) {
+            assert!((pair[1] - pair[0]).abs() < 1.05 * max_step);
+        }
+    }
+
     #[test]
     fn make_resampler_fo() {
-        let mut resampler = FftFixedOut::<f64>::new(44100, 192000, 1024, 2, 2);
+        let mut resampler = FftFixedOut::<f64>::new(44100, 192000, 1024, 2, 2).unwrap();
         let frames = resampler.nbr_frames_needed();
         assert_eq!(frames, 294);
         let waves = vec![vec![0.0f64; frames]; 2];
@@ -651,7 +1523,7 @@ mod tests {
 
     #[test]
     fn make_resampler_fo_skipped() {
-        let mut resampler = FftFixedOut::<f64>::new(44100, 192000, 1024, 2, 2);
+        let mut resampler = FftFixedOut::<f64>::new(44100, 192000, 1024, 2, 2).unwrap();
         let frames = resampler.nbr_frames_needed();
         assert_eq!(frames, 294);
         let waves = vec![vec![0.0f64; frames], Vec::new()];
@@ -663,7 +1535,7 @@ mod tests {
 
     #[test]
     fn make_resampler_fo_empty() {
-        let mut resampler = FftFixedOut::<f64>::new(44100, 192000, 1024, 2, 2);
+        let mut resampler = FftFixedOut::<f64>::new(44100, 192000, 1024, 2, 2).unwrap();
         let frames = resampler.nbr_frames_needed();
         assert_eq!(frames, 294);
         let waves = vec![Vec::new(); 2];
@@ -675,7 +1547,7 @@ mod tests {
 
     #[test]
     fn make_resampler_fi() {
-        let mut resampler = FftFixedIn::<f64>::new(44100, 48000, 1024, 2, 2);
+        let mut resampler = FftFixedIn::<f64>::new(44100, 48000, 1024, 2, 2).unwrap();
         let frames = resampler.nbr_frames_needed();
         assert_eq!(frames, 1024);
         let waves = vec![vec![0.0f64; frames]; 2];
@@ -684,9 +1556,20 @@ mod tests {
         assert_eq!(out[0].len(), 640);
     }
 
+    #[test]
+    fn check_output_frames_fi() {
+        let mut resampler = FftFixedIn::<f64>::new(44100, 48000, 1024, 2, 2).unwrap();
+        let waves = vec![vec![0.0f64; 1024]; 2];
+        for _ in 0..5 {
+            let expected = resampler.nbr_output_frames_next();
+            let out = resampler.process(&waves).unwrap();
+            assert_eq!(out[0].len(), expected);
+        }
+    }
+
     #[test]
     fn make_resampler_fi_downsample() {
-        let mut resampler = FftFixedIn::<f64>::new(48000, 16000, 1200, 2, 2);
+        let mut resampler = FftFixedIn::<f64>::new(48000, 16000, 1200, 2, 2).unwrap();
         let frames = resampler.nbr_frames_needed();
         assert_eq!(frames, 1200);
         let waves = vec![vec![0.0f64; frames]; 2];
@@ -697,7 +1580,7 @@ mod tests {
 
     #[test]
     fn make_resampler_fi_skipped() {
-        let mut resampler = FftFixedIn::<f64>::new(44100, 48000, 1024, 2, 2);
+        let mut resampler = FftFixedIn::<f64>::new(44100, 48000, 1024, 2, 2).unwrap();
         let frames = resampler.nbr_frames_needed();
         assert_eq!(frames, 1024);
         let waves = vec![vec![0.0f64; frames], Vec::new()];
@@ -709,7 +1592,7 @@ mod tests {
 
     #[test]
     fn make_resampler_fi_empty() {
-        let mut resampler = FftFixedIn::<f64>::new(44100, 48000, 1024, 2, 2);
+        let mut resampler = FftFixedIn::<f64>::new(44100, 48000, 1024, 2, 2).unwrap();
         let frames = resampler.nbr_frames_needed();
         assert_eq!(frames, 1024);
         let waves = vec![Vec::new(); 2];
@@ -722,7 +1605,7 @@ mod tests {
     #[test]
     fn make_resampler_fio_unusualratio() {
         // asking for 1024 give the nearest which is 1029 -> 1120
-        let mut resampler = FftFixedInOut::<f64>::new(44100, 44110, 1024, 2);
+        let mut resampler = FftFixedInOut::<f64>::new(44100, 44110, 1024, 2).unwrap();
         let frames = resampler.nbr_frames_needed();
         let waves = vec![vec![0.0f64; frames]; 2];
         let out = resampler.process(&waves).unwrap();
@@ -732,7 +1615,7 @@ mod tests {
 
     #[test]
     fn make_resampler_fo_unusualratio() {
-        let mut resampler = FftFixedOut::<f64>::new(44100, 44110, 1024, 2, 2);
+        let mut resampler = FftFixedOut::<f64>::new(44100, 44110, 1024, 2, 2).unwrap();
         let frames = resampler.nbr_frames_needed();
         assert_eq!(frames, 4410);
         let waves = vec![vec![0.0f64; frames]; 2];
@@ -740,4 +1623,54 @@ mod tests {
         assert_eq!(out.len(), 2);
         assert_eq!(out[0].len(), 1024);
     }
+
+    #[test]
+    fn peak_metering() {
+        let mut resampler = FftFixedIn::<f64>::new(44100, 48000, 1024, 2, 2).unwrap();
+        let mut waves = vec![vec![0.0f64; 1024]; 2];
+        waves[0][100] = -0.8;
+        waves[1][500] = 0.3;
+        resampler.process(&waves).unwrap();
+        assert_eq!(resampler.input_peaks(), &[0.0, 0.0]);
+        resampler.set_peak_metering(true);
+        resampler.process(&waves).unwrap();
+        assert_eq!(resampler.input_peaks(), &[0.8, 0.3]);
+        waves[1] = Vec::new();
+        resampler.process(&waves).unwrap();
+        assert_eq!(resampler.input_peaks(), &[0.8, 0.0]);
+    }
+
+    #[test]
+    fn filter_checksum() {
+        let resampler = FftFixedOut::<f64>::new(44100, 48000, 1024, 2, 2).unwrap();
+        let same = FftFixedOut::<f64>::new(44100, 48000, 1024, 2, 2).unwrap();
+        let other = FftFixedOut::<f64>::new(44100, 32000, 1024, 2, 2).unwrap();
+        assert_eq!(resampler.filter_checksum(), same.filter_checksum());
+        assert_ne!(resampler.filter_checksum(), other.filter_checksum());
+    }
+
+    #[test]
+    fn latency() {
+        let mut resampler = FftFixedIn::<f64>::new(44100, 48000, 1024, 2, 1).unwrap();
+        let latency = resampler.latency();
+        assert!((latency.seconds(44100.0) - latency.output_frames / 48000.0).abs() < 1.0e-12);
+        let waves = vec![(0..10000)
+            .map(|n| (0.05 * n as f64).sin())
+            .collect::<Vec<f64>>()];
+        let out = crate::process_all(&mut resampler, &waves).unwrap();
+        for (n, value) in out[0].iter().enumerate().skip(1000).take(4000) {
+            let expected = (0.05 * (n as f64 * 44100.0 / 48000.0 - latency.input_frames)).sin();
+            assert!((value - expected).abs() < 1.0e-6);
+        }
+        let resampler = FftFixedInOut::<f64>::new(44100, 48000, 1024, 1).unwrap();
+        let latency = resampler.latency();
+        assert_eq!(
+            latency.output_frames,
+            resampler.nbr_output_frames_next() as f64 / 2.0
+        );
+        assert_eq!(
+            latency.input_frames,
+            resampler.nbr_frames_needed() as f64 / 2.0
+        );
+    }
 }
@@ -0,0 +1,253 @@
+use crate::error::ResampleResult;
+use crate::{AliasingWarning, Resampler};
+use std::fmt;
+use std::time::{Duration, Instant};
+
+/// Processing time statistics, as returned by `TimedResampler::stats`.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct ProcessingStats {
+    /// Number of timed calls to "process".
+    pub nbr_calls: u64,
+    /// Duration of the last call.
+    pub last: Duration,
+    /// Average duration of all timed calls.
+    pub average: Duration,
+    /// Longest duration of any timed call.
+    pub max: Duration,
+    /// Number of output frames produced per second of processing time,
+    /// averaged over all timed calls. Zero if nothing has been timed yet.
+    pub frames_per_second: f64,
+}
+
+/// A wrapper that measures how long each call to "process" of the inner resampler takes.
+///
+/// This is meant for realtime applications that want to monitor their headroom,
+/// by comparing the time spent processing with the duration of the audio.
+/// The timing can be switched off with `set_timing`, and then adds no overhead
+/// apart from checking a flag.
+pub struct TimedResampler<T> {
+    resampler: Box<dyn Resampler<T>>,
+    enabled: bool,
+    nbr_calls: u64,
+    last: Duration,
+    total: Duration,
+    max: Duration,
+    total_frames: u64,
+}
+
+impl<T> TimedResampler<T> {
+    /// Wrap a resampler, with timing enabled.
+    pub fn new(resampler: Box<dyn Resampler<T>>) -> Self {
+        TimedResampler {
+            resampler,
+            enabled: true,
+            nbr_calls: 0,
+            last: Duration::from_secs(0),
+            total: Duration::from_secs(0),
+            max: Duration::from_secs(0),
+            total_frames: 0,
+        }
+    }
+
+    /// Get the inner resampler.
+    pub fn inner(&mut self) -> &mut dyn Resampler<T> {
+        self.resampler.as_mut()
+    }
+
+    /// Enable or disable the timing. The collected statistics are kept.
+    pub fn set_timing(&mut self, enabled: bool) {
+        trace!("Set timing to {}", enabled);
+        self.enabled = enabled;
+    }
+
+    /// Get the statistics collected so far.
+    pub fn stats(&self) -> ProcessingStats {
+        let (average, frames_per_second) = if self.nbr_calls > 0 {
+            let total_secs = self.total.as_secs_f64();
+            let frames_per_second = if total_secs > 0.0 {
+                self.total_frames as f64 / total_secs
+            } else {
+                0.0
+            };
+            // dividing by a u32 would truncate the number of calls of long running streams
+            let average = Duration::from_secs_f64(total_secs / self.nbr_calls as f64);
+            (average, frames_per_second)
+        } else {
+            (Duration::from_secs(0), 0.0)
+        };
+        ProcessingStats {
+            nbr_calls: self.nbr_calls,
+            last: self.last,
+            average,
+            max: self.max,
+            frames_per_second,
+        }
+    }
+
+    /// Clear the collected statistics.
+    pub fn reset_stats(&mut self) {
+        self.nbr_calls = 0;
+        self.last = Duration::from_secs(0);
+        self.total = Duration::from_secs(0);
+        self.max = Duration::from_secs(0);
+        self.total_frames = 0;
+    }
+
+    fn record(&mut self, elapsed: Duration, nbr_frames: usize) {
+        self.nbr_calls += 1;
+        self.last = elapsed;
+        self.total += elapsed;
+        if elapsed > self.max {
+            self.max = elapsed;
+        }
+        self.total_frames += nbr_frames as u64;
+    }
+}
+
+/// Shows the statistics, but not the inner resampler.
+impl<T> fmt::Debug for TimedResampler<T> {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        f.debug_struct("TimedResampler")
+            .field("enabled", &self.enabled)
+            .field("stats", &self.stats())
+            .finish()
+    }
+}
+
+impl<T> Resampler<T> for TimedResampler<T> {
+    fn process(&mut self, wave_in: &[Vec<T>]) -> ResampleResult<Vec<Vec<T>>> {
+        if !self.enabled {
+            return self.resampler.process(wave_in);
+        }
+        let start = Instant::now();
+        let wave_out = self.resampler.process(wave_in)?;
+        let elapsed = start.elapsed();
+        let nbr_frames = wave_out.iter().map(|chan| chan.len()).max().unwrap_or(0);
+        self.record(elapsed, nbr_frames);
+        Ok(wave_out)
+    }
+
+    fn process_with(
+        &mut self,
+        wave_in: &[Vec<T>],
+        f: &mut dyn FnMut(usize, &[T]),
+    ) -> ResampleResult<()> {
+        if !self.enabled {
+            return self.resampler.process_with(wave_in, f);
+        }
+        // the time spent in the closure is not the resampler's, so it is excluded
+        let mut nbr_frames = 0;
+        let mut in_closure = Duration::from_secs(0);
+        let start = Instant::now();
+        self.resampler.process_with(wave_in, &mut |chan, frames| {
+            let closure_start = Instant::now();
+            if frames.len() > nbr_frames {
+                nbr_frames = frames.len();
+            }
+            f(chan, frames);
+            in_closure += closure_start.elapsed();
+        })?;
+        let elapsed = start.elapsed();
+        let elapsed = if elapsed > in_closure {
+            elapsed - in_closure
+        } else {
+            Duration::from_secs(0)
+        };
+        self.record(elapsed, nbr_frames);
+        Ok(())
+    }
+
+    fn output_delay(&self) -> f64 {
+        self.resampler.output_delay()
+    }
+
+    /// The statistics are kept, and are cleared separately with "reset_stats".
+    fn reset(&mut self) {
+        self.resampler.reset();
+    }
+
+    /// The time spent flushing is not recorded, since it is not a regular chunk.
+    fn flush(&mut self) -> ResampleResult<Vec<Vec<T>>> {
+        self.resampler.flush()
+    }
+
+    fn nbr_frames_needed(&self) -> usize {
+        self.resampler.nbr_frames_needed()
+    }
+
+    fn nbr_output_frames_next(&self) -> usize {
+        self.resampler.nbr_output_frames_next()
+    }
+
+    fn set_resample_ratio(&mut self, new_ratio: f64) -> ResampleResult<()> {
+        self.resampler.set_resample_ratio(new_ratio)
+    }
+
+    fn set_resample_ratio_relative(&mut self, rel_ratio: f64) -> ResampleResult<()> {
+        self.resampler.set_resample_ratio_relative(rel_ratio)
+    }
+
+    fn aliasing_warning(&self) -> Option<AliasingWarning> {
+        self.resampler.aliasing_warning()
+    }
+
+    fn resample_ratio(&self) -> f64 {
+        self.resampler.resample_ratio()
+    }
+
+    fn resample_ratio_original(&self) -> f64 {
+        self.resampler.resample_ratio_original()
+    }
+
+    fn chunk_size(&self) -> usize {
+        self.resampler.chunk_size()
+    }
+
+    fn nbr_channels(&self) -> usize {
+        self.resampler.nbr_channels()
+    }
+
+    fn sinc_len(&self) -> Option<usize> {
+        self.resampler.sinc_len()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::timing::TimedResampler;
+    use crate::{FftFixedIn, Resampler};
+    use std::time::Duration;
+
+    #[test]
+    fn collect_stats() {
+        let mut resampler = TimedResampler::new(Box::new(
+            FftFixedIn::<f64>::new(44100, 48000, 1024, 2, 2).unwrap(),
+        ));
+        let mut reference = FftFixedIn::<f64>::new(44100, 48000, 1024, 2, 2).unwrap();
+        let waves = vec![vec![0.0; 1024]; 2];
+        let mut nbr_frames = 0;
+        for _ in 0..3 {
+            let output = resampler.process(&waves).unwrap();
+            assert_eq!(output, reference.process(&waves).unwrap());
+            nbr_frames += output[0].len();
+        }
+        resampler.process_with(&waves, &mut |_, _| {}).unwrap();
+        nbr_frames += reference.nbr_output_frames_next();
+        let stats = resampler.stats();
+        assert_eq!(stats.nbr_calls, 4);
+        assert!(stats.max >= stats.average);
+        assert!(stats.max >= stats.last);
+        assert!(stats.frames_per_second > 0.0);
+        let total = stats.average.as_secs_f64() * 4.0;
+        assert!((stats.frames_per_second * total / nbr_frames as f64 - 1.0).abs() < 0.01);
+
+        resampler.set_timing(false);
+        resampler.process(&waves).unwrap();
+        assert_eq!(resampler.stats().nbr_calls, 4);
+        resampler.reset_stats();
+        let stats = resampler.stats();
+        assert_eq!(stats.nbr_calls, 0);
+        assert_eq!(stats.max, Duration::from_secs(0));
+        assert_eq!(stats.frames_per_second, 0.0);
+    }
+}
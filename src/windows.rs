@@ -1,7 +1,7 @@
 use crate::Sample;
 
 /// Different window functions that can be used to window the sinc function.
-#[derive(Debug, Clone, Copy)]
+#[derive(Debug, Clone, PartialEq)]
 pub enum WindowFunction {
     /// Blackman. Intermediate rolloff and intermediate attenuation.
     Blackman,
@@ -15,6 +15,27 @@ pub enum WindowFunction {
     Hann,
     /// Squared Hann, slower rolloff and higher attenuation than simple Hann
     Hann2,
+    /// Nuttall, the four-term window with continuous first derivative.
+    /// Slow rolloff but very good attenuation.
+    Nuttall,
+    /// Blackman-Nuttall. Similar to Blackman-Harris, with slightly better attenuation.
+    BlackmanNuttall,
+    /// Flat top, five-term window with a very flat passband.
+    /// Very slow rolloff, mostly useful for matching designs that call for it.
+    FlatTop,
+    /// Gaussian window with the given standard deviation,
+    /// expressed relative to half the window length.
+    /// Smaller values give a narrower window, with slower rolloff but better attenuation.
+    /// Values between 0.3 and 0.5 are a good starting point.
+    /// The value must be a positive number, other values are rejected by the constructors of the sinc resamplers.
+    Gaussian(f64),
+    /// Use a precomputed window, for example designed in Matlab or SciPy.
+    /// The taps are used as-is, without any normalization.
+    /// The number of taps must be equal to the (rounded up) `sinc_len`,
+    /// in which case they are linearly interpolated to the oversampled length,
+    /// or to `sinc_len` multiplied by the oversampling factor, in which case they are used directly.
+    /// Other lengths are rejected by the constructors of the sinc resamplers.
+    CustomTaps(Vec<f64>),
 }
 
 /// Helper function. Standard Blackman-Harris window
@@ -60,6 +81,98 @@ where
     window
 }
 
+/// Helper function. Standard Nuttall window
+pub fn nuttall<T>(npoints: usize) -> Vec<T>
+where
+    T: Sample,
+{
+    trace!("Making a Nuttall windows with {} points", npoints);
+    let mut window = vec![T::zero(); npoints];
+    let pi2 = T::coerce(2.0) * T::PI;
+    let pi4 = T::coerce(4.0) * T::PI;
+    let pi6 = T::coerce(6.0) * T::PI;
+    let np_f = T::coerce(npoints);
+    let a = T::coerce(0.355768);
+    let b = T::coerce(0.487396);
+    let c = T::coerce(0.144232);
+    let d = T::coerce(0.012604);
+    for (x, item) in window.iter_mut().enumerate() {
+        let x_float = T::coerce(x);
+        *item = a - b * (pi2 * x_float / np_f).cos() + c * (pi4 * x_float / np_f).cos()
+            - d * (pi6 * x_float / np_f).cos();
+    }
+    window
+}
+
+/// Helper function. Standard Blackman-Nuttall window
+pub fn blackman_nuttall<T>(npoints: usize) -> Vec<T>
+where
+    T: Sample,
+{
+    trace!("Making a BlackmanNuttall windows with {} points", npoints);
+    let mut window = vec![T::zero(); npoints];
+    let pi2 = T::coerce(2.0) * T::PI;
+    let pi4 = T::coerce(4.0) * T::PI;
+    let pi6 = T::coerce(6.0) * T::PI;
+    let np_f = T::coerce(npoints);
+    let a = T::coerce(0.3635819);
+    let b = T::coerce(0.4891775);
+    let c = T::coerce(0.1365995);
+    let d = T::coerce(0.0106411);
+    for (x, item) in window.iter_mut().enumerate() {
+        let x_float = T::coerce(x);
+        *item = a - b * (pi2 * x_float / np_f).cos() + c * (pi4 * x_float / np_f).cos()
+            - d * (pi6 * x_float / np_f).cos();
+    }
+    window
+}
+
+/// Helper function. Flat top window, using the same coefficients as Matlab and SciPy
+pub fn flattop<T>(npoints: usize) -> Vec<T>
+where
+    T: Sample,
+{
+    trace!("Making a FlatTop windows with {} points", npoints);
+    let mut window = vec![T::zero(); npoints];
+    let pi2 = T::coerce(2.0) * T::PI;
+    let pi4 = T::coerce(4.0) * T::PI;
+    let pi6 = T::coerce(6.0) * T::PI;
+    let pi8 = T::coerce(8.0) * T::PI;
+    let np_f = T::coerce(npoints);
+    let a = T::coerce(0.21557895);
+    let b = T::coerce(0.41663158);
+    let c = T::coerce(0.277263158);
+    let d = T::coerce(0.083578947);
+    let e = T::coerce(0.006947368);
+    for (x, item) in window.iter_mut().enumerate() {
+        let x_float = T::coerce(x);
+        *item = a - b * (pi2 * x_float / np_f).cos() + c * (pi4 * x_float / np_f).cos()
+            - d * (pi6 * x_float / np_f).cos()
+            + e * (pi8 * x_float / np_f).cos();
+    }
+    window
+}
+
+/// Helper function. Gaussian window, with `sigma` relative to half the window length
+pub fn gaussian<T>(npoints: usize, sigma: f64) -> Vec<T>
+where
+    T: Sample,
+{
+    trace!(
+        "Making a Gaussian windows with {} points, sigma {}",
+        npoints,
+        sigma
+    );
+    let mut window = vec![T::zero(); npoints];
+    let half_np = T::coerce(npoints) / T::coerce(2.0);
+    let sigma = T::coerce(sigma) * half_np;
+    for (x, item) in window.iter_mut().enumerate() {
+        let x_rel = (T::coerce(x) - half_np) / sigma;
+        *item = (T::coerce(-0.5) * x_rel * x_rel).exp();
+    }
+    window
+}
+
 /// Standard Hann window
 pub fn hann<T>(npoints: usize) -> Vec<T>
 where
@@ -77,17 +190,51 @@ where
     window
 }
 
+/// Helper function. Custom window from a set of taps.
+/// If `npoints` is a multiple of the number of taps, the window is linearly interpolated
+/// between the taps, wrapping around at the end since the window is periodic.
+pub fn custom_taps<T>(npoints: usize, taps: &[f64]) -> Vec<T>
+where
+    T: Sample,
+{
+    trace!(
+        "Making a custom window with {} points from {} taps",
+        npoints,
+        taps.len()
+    );
+    let factor = npoints.checked_div(taps.len()).unwrap_or(0);
+    assert!(
+        factor > 0 && factor * taps.len() == npoints,
+        "Can't make a window with {} points from {} taps",
+        npoints,
+        taps.len()
+    );
+    let mut window = vec![T::zero(); npoints];
+    for (x, item) in window.iter_mut().enumerate() {
+        let idx = x / factor;
+        let frac = (x % factor) as f64 / factor as f64;
+        let next = taps[(idx + 1) % taps.len()];
+        *item = T::coerce((1.0 - frac) * taps[idx] + frac * next);
+    }
+    window
+}
+
 /// Make the selected window function
 pub fn make_window<T>(npoints: usize, windowfunc: WindowFunction) -> Vec<T>
 where
     T: Sample,
 {
-    let mut window = match windowfunc {
+    let mut window = match &windowfunc {
         WindowFunction::BlackmanHarris | WindowFunction::BlackmanHarris2 => {
             blackman_harris::<T>(npoints)
         }
         WindowFunction::Blackman | WindowFunction::Blackman2 => blackman::<T>(npoints),
         WindowFunction::Hann | WindowFunction::Hann2 => hann::<T>(npoints),
+        WindowFunction::Nuttall => nuttall::<T>(npoints),
+        WindowFunction::BlackmanNuttall => blackman_nuttall::<T>(npoints),
+        WindowFunction::FlatTop => flattop::<T>(npoints),
+        WindowFunction::Gaussian(sigma) => gaussian::<T>(npoints, *sigma),
+        WindowFunction::CustomTaps(taps) => custom_taps::<T>(npoints, taps),
     };
     match windowfunc {
         WindowFunction::Blackman2 | WindowFunction::BlackmanHarris2 | WindowFunction::Hann2 => {
@@ -102,8 +249,13 @@ where
 mod tests {
     use crate::windows::blackman;
     use crate::windows::blackman_harris;
+    use crate::windows::blackman_nuttall;
+    use crate::windows::custom_taps;
+    use crate::windows::flattop;
+    use crate::windows::gaussian;
     use crate::windows::hann;
     use crate::windows::make_window;
+    use crate::windows::nuttall;
     use crate::windows::WindowFunction;
 
     #[test]
@@ -134,6 +286,58 @@ mod tests {
         assert!(wnd2[7] > 0.000001);
     }
 
+    #[test]
+    fn test_nuttall() {
+        let wnd = nuttall::<f64>(16);
+        assert!((wnd[8] - 1.0).abs() < 0.000001);
+        assert!(wnd[0] < 0.001);
+        assert!(wnd[15] < 0.1);
+    }
+
+    #[test]
+    fn test_blackman_nuttall() {
+        let wnd = blackman_nuttall::<f64>(16);
+        assert!((wnd[8] - 1.0).abs() < 0.000001);
+        assert!(wnd[0] < 0.001);
+        assert!(wnd[15] < 0.1);
+    }
+
+    #[test]
+    fn test_flattop() {
+        let wnd = flattop::<f64>(16);
+        assert!((wnd[8] - 1.0).abs() < 0.000001);
+        assert!(wnd[0].abs() < 0.001);
+        assert!(wnd.iter().any(|v| *v < 0.0));
+    }
+
+    #[test]
+    fn test_gaussian() {
+        let wnd = gaussian::<f64>(16, 0.4);
+        assert!((wnd[8] - 1.0).abs() < 0.000001);
+        assert!((wnd[0] - (-0.5f64 / 0.16).exp()).abs() < 0.000001);
+        assert!((wnd[4] - wnd[12]).abs() < 0.000001);
+        let wide = make_window::<f64>(16, WindowFunction::Gaussian(0.8));
+        assert!(wide[2] > wnd[2]);
+    }
+
+    #[test]
+    fn test_custom_taps() {
+        let taps = hann::<f64>(16);
+        let wnd = custom_taps::<f64>(16, &taps);
+        assert_eq!(wnd, taps);
+        let wnd = make_window::<f64>(64, WindowFunction::CustomTaps(taps.clone()));
+        assert!((wnd[32] - taps[8]).abs() < 0.000001);
+        assert!((wnd[34] - 0.5 * (taps[8] + taps[9])).abs() < 0.000001);
+        assert!((wnd[62] - 0.5 * taps[15]).abs() < 0.000001);
+    }
+
+    #[test]
+    #[should_panic]
+    fn test_custom_taps_bad_length() {
+        let taps = hann::<f64>(15);
+        custom_taps::<f64>(64, &taps);
+    }
+
     #[test]
     fn test_hann() {
         let wnd = hann::<f64>(16);
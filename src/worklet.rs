@@ -0,0 +1,182 @@
+use crate::advisor::{suggest_parameters, Quality};
+use crate::error::ResampleResult;
+use crate::{Resampler, Sample, SincFixedOut};
+
+/// Number of frames in a render quantum of the Web Audio API.
+pub const RENDER_QUANTUM: usize = 128;
+
+/// A helper for resampling inside a Web Audio `AudioWorkletProcessor`,
+/// for example for bridging a 44.1 kHz source to a 48 kHz audio context.
+///
+/// The audio context calls the processor once per render quantum of 128 frames,
+/// while the resampler needs a varying number of input frames for each chunk.
+/// This helper keeps a small input queue in between. The input is added with `push_input`,
+/// in any block size, and each call to `fill_block` then produces one full render quantum,
+/// using a `SincFixedOut` with a chunk size of one quantum.
+/// If the queue does not hold enough frames, the block is filled with silence.
+///
+/// There is nothing browser specific in the helper itself,
+/// so it can be used the same way for other hosts with a fixed block size.
+#[derive(Debug)]
+pub struct QuantumResampler<T> {
+    resampler: SincFixedOut<T>,
+    queue: Vec<Vec<T>>,
+    chunk: Vec<Vec<T>>,
+    nbr_underruns: usize,
+}
+
+impl<T> QuantumResampler<T>
+where
+    T: Sample,
+{
+    /// Create a new helper for resampling from `fs_in` to `fs_out`,
+    /// using the parameters suggested for the given quality.
+    ///
+    /// # Errors
+    ///
+    /// The function returns `ResampleError::InvalidParameter` if a sample rate or the number of channels is zero.
+    pub fn new(
+        fs_in: usize,
+        fs_out: usize,
+        quality: Quality,
+        nbr_channels: usize,
+    ) -> ResampleResult<Self> {
+        let parameters = suggest_parameters(fs_in, fs_out, quality);
+        let resampler = SincFixedOut::new(
+            fs_out as f64 / fs_in as f64,
+            parameters,
+            RENDER_QUANTUM,
+            nbr_channels,
+        )?;
+        let capacity = 2 * resampler.nbr_frames_needed() + RENDER_QUANTUM;
+        debug!(
+            "Create new QuantumResampler, fs_in: {}, fs_out: {}, channels: {}",
+            fs_in, fs_out, nbr_channels
+        );
+        Ok(QuantumResampler {
+            resampler,
+            queue: vec![Vec::with_capacity(capacity); nbr_channels],
+            chunk: vec![Vec::with_capacity(capacity); nbr_channels],
+            nbr_underruns: 0,
+        })
+    }
+
+    /// Get the inner resampler, for example for adjusting the ratio to compensate for clock drift.
+    pub fn inner(&mut self) -> &mut SincFixedOut<T> {
+        &mut self.resampler
+    }
+
+    /// Add input frames to the queue. All channels must have the same number of frames.
+    ///
+    /// # Panics
+    ///
+    /// Panics if the number of channels doesn't match the helper,
+    /// or if the channels have different lengths.
+    pub fn push_input(&mut self, wave_in: &[Vec<T>]) {
+        assert_eq!(
+            wave_in.len(),
+            self.queue.len(),
+            "Wrong number of input channels"
+        );
+        let nbr_frames = wave_in.first().map(|chan| chan.len()).unwrap_or(0);
+        for (queue, chan) in self.queue.iter_mut().zip(wave_in.iter()) {
+            assert_eq!(
+                chan.len(),
+                nbr_frames,
+                "All channels must have the same length"
+            );
+            queue.extend_from_slice(chan);
+        }
+    }
+
+    /// Get the number of input frames waiting in the queue.
+    pub fn queued_frames(&self) -> usize {
+        self.queue.first().map(|queue| queue.len()).unwrap_or(0)
+    }
+
+    /// Get the number of input frames needed to fill the next block.
+    pub fn frames_needed(&self) -> usize {
+        self.resampler.nbr_frames_needed()
+    }
+
+    /// Get the number of blocks that were filled with silence because the queue ran empty.
+    pub fn nbr_underruns(&self) -> usize {
+        self.nbr_underruns
+    }
+
+    /// Fill one render quantum per channel in `block`.
+    /// Returns `false` if there was not enough input queued, and the block was filled with silence.
+    ///
+    /// # Panics
+    ///
+    /// Panics if the number of channels doesn't match the helper,
+    /// or if a channel is shorter than `RENDER_QUANTUM`.
+    pub fn fill_block(&mut self, block: &mut [Vec<T>]) -> bool {
+        assert_eq!(
+            block.len(),
+            self.queue.len(),
+            "Wrong number of output channels"
+        );
+        let frames_needed = self.resampler.nbr_frames_needed();
+        if self.queued_frames() < frames_needed {
+            trace!(
+                "Underrun, {} frames queued, {} needed",
+                self.queued_frames(),
+                frames_needed
+            );
+            self.nbr_underruns += 1;
+            for chan in block.iter_mut() {
+                for value in chan[..RENDER_QUANTUM].iter_mut() {
+                    *value = T::zero();
+                }
+            }
+            return false;
+        }
+        for (chunk, queue) in self.chunk.iter_mut().zip(self.queue.iter_mut()) {
+            chunk.clear();
+            chunk.extend(queue.drain(..frames_needed));
+        }
+        let wave_out = self.resampler.process(&self.chunk).unwrap();
+        for (chan, out) in block.iter_mut().zip(wave_out.iter()) {
+            chan[..RENDER_QUANTUM].copy_from_slice(out);
+        }
+        true
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::worklet::{QuantumResampler, RENDER_QUANTUM};
+    use crate::Quality;
+
+    #[test]
+    fn fill_blocks() {
+        let mut bridge = QuantumResampler::<f32>::new(44100, 48000, Quality::Medium, 2).unwrap();
+        let mut block = vec![vec![1.0; RENDER_QUANTUM]; 2];
+        assert!(!bridge.fill_block(&mut block));
+        assert!(block[1].iter().all(|value| *value == 0.0));
+        assert_eq!(bridge.nbr_underruns(), 1);
+
+        // push input one quantum at a time, whenever more is needed
+        let mut frame = 0;
+        let mut peak = 0.0f32;
+        for block_idx in 0..200 {
+            while bridge.queued_frames() < bridge.frames_needed() {
+                let quantum: Vec<f32> = (frame..frame + RENDER_QUANTUM)
+                    .map(|n| 0.5 * (n as f32 * 0.05).sin())
+                    .collect();
+                bridge.push_input(&[quantum.clone(), quantum]);
+                frame += RENDER_QUANTUM;
+            }
+            assert!(bridge.fill_block(&mut block));
+            if block_idx > 10 {
+                peak = block[0]
+                    .iter()
+                    .fold(peak, |peak, value| peak.max(value.abs()));
+            }
+        }
+        assert_eq!(bridge.nbr_underruns(), 1);
+        assert!((peak - 0.5).abs() < 0.01);
+        assert!(bridge.queued_frames() < RENDER_QUANTUM);
+    }
+}
@@ -0,0 +1,101 @@
+//! Check that the sinc resamplers, and the resampler of the `microfft` feature,
+//! don't allocate any memory while processing,
+//! by counting the allocations made through a global allocator.
+//! This file holds a single test, so that no other test allocates at the same time.
+
+extern crate rubato;
+
+#[cfg(feature = "microfft")]
+use rubato::MicroFftResampler;
+use rubato::{
+    InterpolationParameters, InterpolationType, Lfo, LfoWaveform, Resampler, Sample, SincFixedIn,
+    SincFixedOut, WindowFunction,
+};
+use std::alloc::{GlobalAlloc, Layout, System};
+use std::sync::atomic::{AtomicUsize, Ordering};
+
+static ALLOCATIONS: AtomicUsize = AtomicUsize::new(0);
+
+struct CountingAllocator;
+
+unsafe impl GlobalAlloc for CountingAllocator {
+    unsafe fn alloc(&self, layout: Layout) -> *mut u8 {
+        ALLOCATIONS.fetch_add(1, Ordering::SeqCst);
+        System.alloc(layout)
+    }
+
+    unsafe fn dealloc(&self, ptr: *mut u8, layout: Layout) {
+        System.dealloc(ptr, layout)
+    }
+}
+
+#[global_allocator]
+static GLOBAL: CountingAllocator = CountingAllocator;
+
+/// Process some chunks with "process_into_buffer", and return the number of allocations.
+fn count_allocations<T: Sample>(resampler: &mut dyn Resampler<T>, nbr_channels: usize) -> usize {
+    let mut wave_out = vec![vec![T::zero(); 2 * resampler.nbr_output_frames_next()]; nbr_channels];
+    let mut allocations = 0;
+    for chunk in 0..20 {
+        let nbr_frames = resampler.nbr_frames_needed();
+        let input: Vec<Vec<T>> = (0..nbr_channels)
+            .map(|chan| {
+                // some silent channels, to skip the interpolation of those
+                if chan % 5 == 4 {
+                    vec![T::zero(); nbr_frames]
+                } else {
+                    (0..nbr_frames)
+                        .map(|n| {
+                            T::coerce(((chunk * nbr_frames + n) as f64 * 0.01 * chan as f64).sin())
+                        })
+                        .collect()
+                }
+            })
+            .collect();
+        let before = ALLOCATIONS.load(Ordering::SeqCst);
+        resampler
+            .process_into_buffer(&input, &mut wave_out)
+            .unwrap();
+        allocations += ALLOCATIONS.load(Ordering::SeqCst) - before;
+    }
+    allocations
+}
+
+#[test]
+fn process_without_allocating() {
+    let params = InterpolationParameters {
+        sinc_len: 64,
+        f_cutoff: 0.95,
+        interpolation: InterpolationType::Cubic,
+        oversampling_factor: 16,
+        window: WindowFunction::BlackmanHarris2,
+    };
+    let lfo = Lfo::new(LfoWaveform::Sine, 2.0, 20.0, 44100.0);
+    // 10 channels, so that some are interpolated in channel lanes and some by the normal kernel
+    let nbr_channels = 10;
+
+    let mut fixed_in = SincFixedIn::<f64>::new(1.1, params.clone(), 256, nbr_channels).unwrap();
+    fixed_in.set_silence_threshold(Some(1.0e-6));
+    // the thread pool of the parallel feature allocates, so stay on this thread
+    fixed_in.set_parallel_threshold(usize::MAX);
+    assert_eq!(count_allocations(&mut fixed_in, nbr_channels), 0);
+    fixed_in.set_modulation(Some(lfo.clone()));
+    fixed_in.set_ratio_smoothing(100);
+    fixed_in.set_resample_ratio(1.15).unwrap();
+    assert_eq!(count_allocations(&mut fixed_in, nbr_channels), 0);
+
+    let mut fixed_out = SincFixedOut::<f64>::new(1.1, params, 256, nbr_channels).unwrap();
+    fixed_out.set_silence_threshold(Some(1.0e-6));
+    fixed_out.set_parallel_threshold(usize::MAX);
+    assert_eq!(count_allocations(&mut fixed_out, nbr_channels), 0);
+    fixed_out.set_modulation(Some(lfo));
+    fixed_out.set_ratio_smoothing(100);
+    fixed_out.set_resample_ratio(1.15).unwrap();
+    assert_eq!(count_allocations(&mut fixed_out, nbr_channels), 0);
+
+    #[cfg(feature = "microfft")]
+    {
+        let mut micro = MicroFftResampler::<256, 3>::new(16000, 48000).unwrap();
+        assert_eq!(count_allocations(&mut micro, 3), 0);
+    }
+}
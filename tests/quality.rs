@@ -0,0 +1,154 @@
+//! Regression tests for the resampling quality of the parameter presets.
+//! Each preset must reach a minimum signal to noise ratio, as measured by `measure_resampler_snr`
+//! with test tones just below its cutoff, where the anti-aliasing filter matters the most.
+//! When downsampling, each preset must also reach a minimum rejection, as measured by
+//! `measure_resampler_rejection` with test tones just above the output Nyquist frequency,
+//! that would alias if they were not removed. The rejection depends on the length
+//! of the sinc and the window, and is what separates the presets.
+
+extern crate rubato;
+
+use rubato::{
+    measure_resampler_rejection, measure_resampler_snr, suggest_parameters,
+    suggest_variable_parameters, FftFixedInOut, Quality, SincFixedIn, SincFixedOut,
+};
+
+/// Test frequencies for the fft resampler, relative to the input Nyquist frequency.
+const FFT_TEST_FREQS: [f64; 5] = [0.01, 0.1, 0.3, 0.6, 0.8];
+
+/// Test frequencies relative to the input Nyquist frequency, from 90% of the cutoff up to the cutoff.
+/// The cutoff of the filter is relative to the lower of the input and output Nyquist frequencies.
+fn near_cutoff(f_cutoff: f32, ratio: f64) -> [f64; 4] {
+    let cutoff = f_cutoff as f64 * ratio.min(1.0);
+    [0.9 * cutoff, 0.95 * cutoff, 0.98 * cutoff, cutoff]
+}
+
+fn fixed_snr(fs_in: usize, fs_out: usize, quality: Quality) -> f64 {
+    let params = suggest_parameters(fs_in, fs_out, quality);
+    let ratio = fs_out as f64 / fs_in as f64;
+    let freqs = near_cutoff(params.f_cutoff, ratio);
+    let mut resampler = SincFixedIn::<f64>::new(ratio, params, 1024, 1).unwrap();
+    measure_resampler_snr(&mut resampler, 1, &freqs).unwrap()
+}
+
+fn variable_snr(ratio: f64, quality: Quality) -> f64 {
+    let params = suggest_variable_parameters(quality);
+    let freqs = near_cutoff(params.f_cutoff, ratio);
+    let mut resampler = SincFixedOut::<f64>::new(ratio, params, 1024, 1).unwrap();
+    measure_resampler_snr(&mut resampler, 1, &freqs).unwrap()
+}
+
+/// Test frequencies above the output Nyquist frequency, relative to the input Nyquist frequency,
+/// when downsampling from 48000 to 44100 Hz. The output Nyquist frequency is at 0.91875.
+const FIXED_STOPBAND_FREQS: [f64; 4] = [0.93, 0.95, 0.97, 0.99];
+
+/// Test frequencies above the output Nyquist frequency, relative to the input Nyquist frequency,
+/// when downsampling with a ratio of 0.9.
+const VARIABLE_STOPBAND_FREQS: [f64; 4] = [0.92, 0.94, 0.96, 0.98];
+
+/// Minimum SNR in dB with a fixed ratio, a few dB below the measured values.
+/// The passband is equally clean for all presets.
+/// Measured: Low 162.0, Medium 162.2, High 161.9.
+const MIN_FIXED_SNR: f64 = 158.0;
+
+/// Minimum SNR in dB for each preset with a variable ratio, a few dB below the measured values.
+/// Measured: Low 153.1, Medium 161.5, High 162.9.
+fn min_variable_snr(quality: Quality) -> f64 {
+    match quality {
+        Quality::Low => 150.0,
+        Quality::Medium => 158.0,
+        Quality::High => 159.0,
+    }
+}
+
+/// Minimum rejection in dB for each preset with a fixed ratio, a few dB below the measured values.
+/// Measured: Low 65.4, Medium 102.7, High 148.1.
+fn min_fixed_rejection(quality: Quality) -> f64 {
+    match quality {
+        Quality::Low => 62.0,
+        Quality::Medium => 99.0,
+        Quality::High => 144.0,
+    }
+}
+
+/// Minimum rejection in dB for each preset with a variable ratio, a few dB below the measured values.
+/// Measured: Low 70.1, Medium 123.5, High 163.4.
+fn min_variable_rejection(quality: Quality) -> f64 {
+    match quality {
+        Quality::Low => 66.0,
+        Quality::Medium => 120.0,
+        Quality::High => 159.0,
+    }
+}
+
+#[test]
+fn fixed_presets() {
+    for quality in [Quality::Low, Quality::Medium, Quality::High].iter() {
+        for (fs_in, fs_out) in [(44100, 48000), (48000, 44100)].iter() {
+            let snr = fixed_snr(*fs_in, *fs_out, *quality);
+            assert!(
+                snr > MIN_FIXED_SNR,
+                "{:?} from {} to {}: {} dB",
+                quality,
+                fs_in,
+                fs_out,
+                snr
+            );
+        }
+    }
+}
+
+#[test]
+fn variable_presets() {
+    for quality in [Quality::Low, Quality::Medium, Quality::High].iter() {
+        let snr = variable_snr(1.0123, *quality);
+        assert!(
+            snr > min_variable_snr(*quality),
+            "{:?}: {} dB",
+            quality,
+            snr
+        );
+    }
+}
+
+#[test]
+fn fixed_rejection() {
+    for quality in [Quality::Low, Quality::Medium, Quality::High].iter() {
+        let params = suggest_parameters(48000, 44100, *quality);
+        let mut resampler = SincFixedIn::<f64>::new(44100.0 / 48000.0, params, 1024, 1).unwrap();
+        let rejection =
+            measure_resampler_rejection(&mut resampler, 1, &FIXED_STOPBAND_FREQS).unwrap();
+        assert!(
+            rejection > min_fixed_rejection(*quality),
+            "{:?}: {} dB",
+            quality,
+            rejection
+        );
+    }
+}
+
+#[test]
+fn variable_rejection() {
+    for quality in [Quality::Low, Quality::Medium, Quality::High].iter() {
+        let params = suggest_variable_parameters(*quality);
+        let mut resampler = SincFixedOut::<f64>::new(0.9, params, 1024, 1).unwrap();
+        let rejection =
+            measure_resampler_rejection(&mut resampler, 1, &VARIABLE_STOPBAND_FREQS).unwrap();
+        assert!(
+            rejection > min_variable_rejection(*quality),
+            "{:?}: {} dB",
+            quality,
+            rejection
+        );
+    }
+}
+
+#[test]
+fn fft_resampler() {
+    let mut resampler = FftFixedInOut::<f64>::new(44100, 48000, 1024, 1).unwrap();
+    let snr = measure_resampler_snr(&mut resampler, 1, &FFT_TEST_FREQS).unwrap();
+    assert!(snr > 150.0, "{} dB", snr);
+    let mut resampler = FftFixedInOut::<f64>::new(48000, 44100, 1024, 1).unwrap();
+    let rejection = measure_resampler_rejection(&mut resampler, 1, &FIXED_STOPBAND_FREQS).unwrap();
+    assert!(rejection > 160.0, "{} dB", rejection);
+}